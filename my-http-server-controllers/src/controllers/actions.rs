@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use hyper::Method;
 use my_http_server_core::{HttpContext, HttpFailResult, HttpOkResult};
@@ -46,6 +46,24 @@ pub trait HandleHttpRequest {
         http_route: &HttpRoute,
         ctx: &mut HttpContext,
     ) -> Result<HttpOkResult, HttpFailResult>;
+
+    /// Overrides the server-wide request timeout for this route — `request_timeout_ms` of
+    /// `#[http_route]`. `None` keeps the server-wide one.
+    fn get_request_timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Overrides the server-wide request body limit for this route — `max_body_size` of
+    /// `#[http_route]`. `None` keeps the server-wide one.
+    fn get_max_body_size(&self) -> Option<u64> {
+        None
+    }
+
+    /// Whether the response of this route may be compressed when the server compresses
+    /// responses — `compress_response` of `#[http_route]`. `None` leaves it to the server.
+    fn get_compress_response(&self) -> Option<bool> {
+        None
+    }
 }
 
 pub trait GetDescription {
@@ -147,6 +165,15 @@ impl HttpActions {
         for action in &self.actions {
             if action.http_route.is_my_path(&ctx.request.http_path) {
                 ctx.process_name = Some(action.http_route.route.clone());
+
+                if let Some(request_timeout) = action.handler.get_request_timeout() {
+                    ctx.set_request_timeout(Some(request_timeout));
+                }
+
+                if let Some(compress_response) = action.handler.get_compress_response() {
+                    ctx.set_response_compression(compress_response);
+                }
+
                 match authorization_map.is_authorized(
                     action,
                     &ctx.credentials,
@@ -154,6 +181,15 @@ impl HttpActions {
                     now,
                 ) {
                     super::AuthorizationResult::Allowed => {
+                        if let Some(max_body_size) = action.handler.get_max_body_size() {
+                            ctx.request.set_max_body_size(Some(max_body_size));
+                        }
+
+                        // Refused before the model is parsed — not a byte of the body is read.
+                        if let Err(err) = ctx.request.check_announced_body_size() {
+                            return Some(Err(err));
+                        }
+
                         return Some(action.handler.handle_request(&action.http_route, ctx).await);
                     }
                     super::AuthorizationResult::NotAuthenticated => {
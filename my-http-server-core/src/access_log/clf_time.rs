@@ -0,0 +1,89 @@
+use rust_extensions::date_time::DateTimeAsMicroseconds;
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+const MICROSECONDS_PER_SECOND: i64 = 1_000_000;
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// The time of the Common Log Format, in UTC: `10/Oct/2000:13:55:36 +0000`.
+pub(crate) fn format_clf_time(date_time: DateTimeAsMicroseconds) -> String {
+    let seconds = date_time
+        .unix_microseconds
+        .div_euclid(MICROSECONDS_PER_SECOND);
+
+    let days = seconds.div_euclid(SECONDS_PER_DAY);
+    let second_of_day = seconds.rem_euclid(SECONDS_PER_DAY);
+
+    let (year, month, day) = civil_from_days(days);
+
+    format!(
+        "{:02}/{}/{:04}:{:02}:{:02}:{:02} +0000",
+        day,
+        MONTHS[month as usize - 1],
+        year,
+        second_of_day / 3600,
+        second_of_day % 3600 / 60,
+        second_of_day % 60,
+    )
+}
+
+/// Days since 1970-01-01 -> (year, month, day) — Howard Hinnant's `civil_from_days`, the same
+/// one the compile date of the macros crate is made with.
+fn civil_from_days(days_since_epoch: i64) -> (i64, u32, u32) {
+    let shifted = days_since_epoch + 719_468;
+
+    let era = if shifted >= 0 {
+        shifted
+    } else {
+        shifted - 146_096
+    } / 146_097;
+    let day_of_era = shifted - era * 146_097;
+
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+
+    let year = year_of_era + era * 400;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+
+    let month_position = (5 * day_of_year + 2) / 153;
+
+    let day = (day_of_year - (153 * month_position + 2) / 5 + 1) as u32;
+
+    let month = if month_position < 10 {
+        month_position + 3
+    } else {
+        month_position - 9
+    } as u32;
+
+    let year = if month <= 2 { year + 1 } else { year };
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unix_epoch() {
+        assert_eq!(
+            format_clf_time(DateTimeAsMicroseconds::new(0)),
+            "01/Jan/1970:00:00:00 +0000"
+        );
+    }
+
+    #[test]
+    fn test_date_time() {
+        assert_eq!(
+            format_clf_time(DateTimeAsMicroseconds::new(1_755_681_342_123_456)),
+            "20/Aug/2025:09:15:42 +0000"
+        );
+
+        assert_eq!(
+            format_clf_time(DateTimeAsMicroseconds::new(1_709_164_800_000_000)),
+            "29/Feb/2024:00:00:00 +0000"
+        );
+    }
+}
@@ -0,0 +1,449 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use rust_extensions::Logger;
+
+use crate::{HttpRequestData, HttpRequestOutcome, HttpServerTechMiddleware, ResponseData};
+
+use super::clf_time::format_clf_time;
+
+const REDACTED: &str = "[REDACTED]";
+
+/// The headers whose values never make it into the log as they are, unless
+/// [`HttpAccessLogMiddleware::new`] is told otherwise.
+pub const DEFAULT_REDACTED_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+    "x-api-key",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpAccessLogFormat {
+    /// `127.0.0.1 - client-id [10/Oct/2000:13:55:36 +0000] "GET /path HTTP/1.1" 200 2326`
+    Common,
+    /// The Common one with the `Referer` and the `User-Agent` of the request after it.
+    Combined,
+    /// A JSON object per request with the [`HttpAccessLogField`]s picked with
+    /// [`HttpAccessLogMiddleware::with_fields`].
+    Json,
+}
+
+/// What a [`HttpAccessLogFormat::Json`] line has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpAccessLogField {
+    Time,
+    Ip,
+    ClientId,
+    /// See [`HttpContext::get_request_id`](crate::HttpContext::get_request_id).
+    RequestId,
+    Method,
+    Path,
+    Version,
+    StatusCode,
+    ResponseSize,
+    RequestSize,
+    Referer,
+    UserAgent,
+    ProcessName,
+    Duration,
+    Outcome,
+}
+
+impl HttpAccessLogField {
+    pub const ALL: &'static [HttpAccessLogField] = &[
+        Self::Time,
+        Self::Ip,
+        Self::ClientId,
+        Self::RequestId,
+        Self::Method,
+        Self::Path,
+        Self::Version,
+        Self::StatusCode,
+        Self::ResponseSize,
+        Self::RequestSize,
+        Self::Referer,
+        Self::UserAgent,
+        Self::ProcessName,
+        Self::Duration,
+        Self::Outcome,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Time => "time",
+            Self::Ip => "ip",
+            Self::ClientId => "client_id",
+            Self::RequestId => "request_id",
+            Self::Method => "method",
+            Self::Path => "path",
+            Self::Version => "version",
+            Self::StatusCode => "status_code",
+            Self::ResponseSize => "response_size",
+            Self::RequestSize => "request_size",
+            Self::Referer => "referer",
+            Self::UserAgent => "user_agent",
+            Self::ProcessName => "process_name",
+            Self::Duration => "duration_ms",
+            Self::Outcome => "outcome",
+        }
+    }
+}
+
+/// Writes a line per request through the [`Logger`] — as `write_info` of the `HttpAccessLog`
+/// process. Added with
+/// [`MyHttpServer::add_tech_middleware`](crate::MyHttpServer::add_tech_middleware), so the line
+/// is written after the response is, and the time it takes is not the client's.
+///
+/// ```ignore
+/// server.add_tech_middleware(Arc::new(
+///     HttpAccessLogMiddleware::new(logger.clone(), HttpAccessLogFormat::Combined)
+///         .with_headers(["x-forwarded-for"]),
+/// ));
+/// ```
+///
+/// The request headers picked with [`with_headers`](Self::with_headers) go into a line as they
+/// came — except the redacted ones: [`DEFAULT_REDACTED_HEADERS`] and those added with
+/// [`redact_header`](Self::redact_header). The `Referer` and the `User-Agent` are redacted the same
+/// way when asked to.
+pub struct HttpAccessLogMiddleware {
+    logger: Arc<dyn Logger + Send + Sync + 'static>,
+    format: HttpAccessLogFormat,
+    fields: Vec<HttpAccessLogField>,
+    headers: Vec<String>,
+    redacted_headers: Vec<String>,
+}
+
+impl HttpAccessLogMiddleware {
+    pub fn new(
+        logger: Arc<dyn Logger + Send + Sync + 'static>,
+        format: HttpAccessLogFormat,
+    ) -> Self {
+        Self {
+            logger,
+            format,
+            fields: HttpAccessLogField::ALL.to_vec(),
+            headers: Vec::new(),
+            redacted_headers: DEFAULT_REDACTED_HEADERS
+                .iter()
+                .map(|itm| itm.to_string())
+                .collect(),
+        }
+    }
+
+    /// The fields of a [`HttpAccessLogFormat::Json`] line, in this order. All of them by default.
+    /// The Common and the Combined formats have the fields they are defined with.
+    pub fn with_fields(mut self, fields: impl IntoIterator<Item = HttpAccessLogField>) -> Self {
+        self.fields = fields.into_iter().collect();
+        self
+    }
+
+    /// Request headers to write too: into the `headers` object of a JSON line, quoted one after
+    /// another at the end of a Common or a Combined one — `"-"` for a header the request has not.
+    pub fn with_headers<T: Into<String>>(mut self, headers: impl IntoIterator<Item = T>) -> Self {
+        self.headers = headers
+            .into_iter()
+            .map(|itm| itm.into().to_lowercase())
+            .collect();
+        self
+    }
+
+    /// Writes `[REDACTED]` instead of the value of the header.
+    pub fn redact_header(mut self, header: impl Into<String>) -> Self {
+        self.redacted_headers.push(header.into().to_lowercase());
+        self
+    }
+
+    /// Writes the values of all the headers as they are — [`DEFAULT_REDACTED_HEADERS`] included.
+    pub fn without_redaction(mut self) -> Self {
+        self.redacted_headers.clear();
+        self
+    }
+
+    pub fn format_line(&self, request: &HttpRequestData, response: &ResponseData) -> String {
+        match self.format {
+            HttpAccessLogFormat::Common => self.format_common(request, response, false),
+            HttpAccessLogFormat::Combined => self.format_common(request, response, true),
+            HttpAccessLogFormat::Json => self.format_json(request, response),
+        }
+    }
+
+    fn format_common(
+        &self,
+        request: &HttpRequestData,
+        response: &ResponseData,
+        combined: bool,
+    ) -> String {
+        let mut result = format!(
+            "{} - {} [{}] \"{} {} {:?}\" {} {}",
+            request.ip,
+            response
+                .client_id
+                .as_deref()
+                .map(escape)
+                .unwrap_or_else(|| "-".to_string()),
+            format_clf_time(request.started),
+            request.method,
+            escape(&request.path),
+            request.version,
+            response.status_code,
+            format_size(response.content_length as u64),
+        );
+
+        if combined {
+            for header in ["referer", "user-agent"] {
+                push_quoted(&mut result, self.get_header(request, header).as_deref());
+            }
+        }
+
+        for header in self.headers.iter() {
+            push_quoted(&mut result, self.get_header(request, header).as_deref());
+        }
+
+        result
+    }
+
+    fn format_json(&self, request: &HttpRequestData, response: &ResponseData) -> String {
+        let mut result = serde_json::Map::new();
+
+        for field in self.fields.iter() {
+            let value = match field {
+                HttpAccessLogField::Time => request.started.to_rfc3339().into(),
+                HttpAccessLogField::Ip => request.ip.as_str().into(),
+                HttpAccessLogField::ClientId => response.client_id.as_deref().into(),
+                HttpAccessLogField::RequestId => request.request_id.as_str().into(),
+                HttpAccessLogField::Method => request.method.as_str().into(),
+                HttpAccessLogField::Path => request.path.as_str().into(),
+                HttpAccessLogField::Version => format!("{:?}", request.version).into(),
+                HttpAccessLogField::StatusCode => response.status_code.into(),
+                HttpAccessLogField::ResponseSize => response.content_length.into(),
+                HttpAccessLogField::RequestSize => request.content_length.into(),
+                HttpAccessLogField::Referer => self.get_header(request, "referer").into(),
+                HttpAccessLogField::UserAgent => self.get_header(request, "user-agent").into(),
+                HttpAccessLogField::ProcessName => response.process_name.as_deref().into(),
+                HttpAccessLogField::Duration => (response.duration.as_secs_f64() * 1000.0).into(),
+                HttpAccessLogField::Outcome => format_outcome(response.outcome).into(),
+            };
+
+            result.insert(field.as_str().to_string(), value);
+        }
+
+        if !self.headers.is_empty() {
+            let mut headers = serde_json::Map::new();
+
+            for header in self.headers.iter() {
+                if let Some(value) = self.get_header(request, header) {
+                    headers.insert(header.to_string(), value.into());
+                }
+            }
+
+            result.insert("headers".to_string(), headers.into());
+        }
+
+        serde_json::Value::Object(result).to_string()
+    }
+
+    fn get_header(&self, request: &HttpRequestData, header: &str) -> Option<String> {
+        let value = request.headers.get(header)?;
+
+        if self.redacted_headers.iter().any(|itm| itm == header) {
+            return Some(REDACTED.to_string());
+        }
+
+        Some(String::from_utf8_lossy(value.as_bytes()).to_string())
+    }
+}
+
+#[async_trait]
+impl HttpServerTechMiddleware for HttpAccessLogMiddleware {
+    async fn got_result(&self, request: &HttpRequestData, http_result: &ResponseData) {
+        self.logger.write_info(
+            "HttpAccessLog".to_string(),
+            self.format_line(request, http_result),
+            None,
+        );
+    }
+}
+
+fn format_size(size: u64) -> String {
+    if size == 0 {
+        return "-".to_string();
+    }
+
+    size.to_string()
+}
+
+fn format_outcome(outcome: HttpRequestOutcome) -> &'static str {
+    match outcome {
+        HttpRequestOutcome::Handled => "handled",
+        HttpRequestOutcome::Panicked => "panicked",
+        HttpRequestOutcome::TimedOut => "timed_out",
+    }
+}
+
+fn push_quoted(result: &mut String, value: Option<&str>) {
+    result.push_str(" \"");
+    result.push_str(&escape(value.unwrap_or("-")));
+    result.push('"');
+}
+
+/// A value of a Common Log Format line can not break the line, nor close the quotes it is in —
+/// the way Apache escapes them.
+fn escape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            c if c.is_control() => result.push_str(&format!("\\x{:02x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use hyper::{HeaderMap, Method, Version};
+    use rust_extensions::date_time::DateTimeAsMicroseconds;
+
+    use super::*;
+    use crate::test_logger::NoLogger;
+
+    fn middleware(format: HttpAccessLogFormat) -> HttpAccessLogMiddleware {
+        HttpAccessLogMiddleware::new(Arc::new(NoLogger), format)
+    }
+
+    fn request() -> HttpRequestData {
+        let mut headers = HeaderMap::new();
+        headers.insert("user-agent", "curl/8.0 \"test\"".parse().unwrap());
+        headers.insert("authorization", "Bearer secret".parse().unwrap());
+        headers.insert("x-request-id", "abc".parse().unwrap());
+
+        HttpRequestData {
+            started: DateTimeAsMicroseconds::new(1_755_681_342_123_456),
+            method: Method::POST,
+            path: "/api/users".to_string(),
+            ip: "10.0.0.1".to_string(),
+            version: Version::HTTP_11,
+            user_agent: Some("curl/8.0 \"test\"".to_string()),
+            content_length: Some(12),
+            headers,
+            request_id: "abc".to_string(),
+        }
+    }
+
+    fn response() -> ResponseData {
+        ResponseData {
+            status_code: 201,
+            content_type: None,
+            content_length: 42,
+            has_error: false,
+            outcome: HttpRequestOutcome::Handled,
+            process_name: Some("/api/users".to_string()),
+            client_id: Some("client-1".to_string()),
+            duration: Duration::from_micros(1500),
+        }
+    }
+
+    #[test]
+    fn test_common() {
+        let line = middleware(HttpAccessLogFormat::Common).format_line(&request(), &response());
+
+        assert_eq!(
+            line,
+            "10.0.0.1 - client-1 [20/Aug/2025:09:15:42 +0000] \"POST /api/users HTTP/1.1\" 201 42"
+        );
+    }
+
+    #[test]
+    fn test_combined_escapes_quotes() {
+        let mut response = response();
+        response.client_id = None;
+        response.content_length = 0;
+
+        let line = middleware(HttpAccessLogFormat::Combined).format_line(&request(), &response);
+
+        assert_eq!(
+            line,
+            "10.0.0.1 - - [20/Aug/2025:09:15:42 +0000] \"POST /api/users HTTP/1.1\" 201 - \"-\" \"curl/8.0 \\\"test\\\"\""
+        );
+    }
+
+    #[test]
+    fn test_headers_are_redacted() {
+        let line = middleware(HttpAccessLogFormat::Common)
+            .with_headers(["Authorization", "X-Request-Id", "x-missing"])
+            .format_line(&request(), &response());
+
+        assert!(line.ends_with(" \"[REDACTED]\" \"abc\" \"-\""), "{}", line);
+
+        let line = middleware(HttpAccessLogFormat::Combined)
+            .redact_header("User-Agent")
+            .format_line(&request(), &response());
+
+        assert!(line.ends_with(" \"-\" \"[REDACTED]\""), "{}", line);
+
+        let line = middleware(HttpAccessLogFormat::Common)
+            .with_headers(["authorization"])
+            .without_redaction()
+            .format_line(&request(), &response());
+
+        assert!(line.ends_with(" \"Bearer secret\""), "{}", line);
+    }
+
+    #[test]
+    fn test_json_has_the_picked_fields() {
+        let line = middleware(HttpAccessLogFormat::Json)
+            .with_fields([
+                HttpAccessLogField::Method,
+                HttpAccessLogField::StatusCode,
+                HttpAccessLogField::ProcessName,
+                HttpAccessLogField::ClientId,
+                HttpAccessLogField::RequestId,
+                HttpAccessLogField::RequestSize,
+                HttpAccessLogField::Duration,
+                HttpAccessLogField::Outcome,
+            ])
+            .with_headers(["authorization", "x-request-id"])
+            .format_line(&request(), &response());
+
+        let json: serde_json::Value = serde_json::from_str(&line).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "method": "POST",
+                "status_code": 201,
+                "process_name": "/api/users",
+                "client_id": "client-1",
+                "request_id": "abc",
+                "request_size": 12,
+                "duration_ms": 1.5,
+                "outcome": "handled",
+                "headers": {
+                    "authorization": "[REDACTED]",
+                    "x-request-id": "abc",
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_json_has_all_fields_by_default() {
+        let line = middleware(HttpAccessLogFormat::Json).format_line(&request(), &response());
+
+        let json: serde_json::Value = serde_json::from_str(&line).unwrap();
+        let json = json.as_object().unwrap();
+
+        assert_eq!(json.len(), HttpAccessLogField::ALL.len());
+        assert_eq!(json["version"], "HTTP/1.1");
+        assert_eq!(json["user_agent"], "curl/8.0 \"test\"");
+        assert_eq!(json["referer"], serde_json::Value::Null);
+    }
+}
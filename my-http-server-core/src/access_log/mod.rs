@@ -0,0 +1,3 @@
+mod http_access_log_middleware;
+pub use http_access_log_middleware::*;
+mod clf_time;
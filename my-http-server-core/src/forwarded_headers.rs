@@ -0,0 +1,296 @@
+use crate::{
+    ForwardingHeaders, HttpRequestHeaders, TrustedProxies, FORWARDED_HEADER,
+    X_FORWARDED_FOR_HEADER, X_FORWARDED_HOST, X_FORWARDED_PROTO,
+};
+
+/// One hop of the forwarding headers: who a proxy got the request from, and how.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ForwardedHop<'s> {
+    /// `None` when the proxy only said how the request came in, not from where.
+    pub for_ip: Option<&'s str>,
+    pub proto: Option<&'s str>,
+    pub host: Option<&'s str>,
+}
+
+/// The hops of the request, the nearest proxy last. Taken only from the headers the proxies
+/// write: a header being there says nothing about who wrote it.
+pub(crate) fn read_forwarded_hops<'s>(
+    headers: &'s impl HttpRequestHeaders,
+    forwarding_headers: ForwardingHeaders,
+) -> Vec<ForwardedHop<'s>> {
+    match forwarding_headers {
+        ForwardingHeaders::XForwarded => read_x_forwarded_hops(headers),
+        ForwardingHeaders::Forwarded => match get_header(headers, FORWARDED_HEADER) {
+            Some(forwarded) => parse_forwarded(forwarded),
+            None => Vec::new(),
+        },
+    }
+}
+
+fn read_x_forwarded_hops<'s>(headers: &'s impl HttpRequestHeaders) -> Vec<ForwardedHop<'s>> {
+    let x_forwarded_for = split_list(get_header(headers, X_FORWARDED_FOR_HEADER));
+    let x_forwarded_proto = split_list(get_header(headers, X_FORWARDED_PROTO));
+    let x_forwarded_host = split_list(get_header(headers, X_FORWARDED_HOST));
+
+    if x_forwarded_for.is_empty() {
+        if x_forwarded_proto.is_empty() && x_forwarded_host.is_empty() {
+            return Vec::new();
+        }
+
+        return vec![ForwardedHop {
+            for_ip: None,
+            proto: x_forwarded_proto.last().copied(),
+            host: x_forwarded_host.last().copied(),
+        }];
+    }
+
+    // Proxies that append to every header keep the lists aligned. Otherwise only the value the
+    // nearest proxy left can be relied on.
+    let pick = |list: &[&'s str], index: usize| -> Option<&'s str> {
+        if list.len() == x_forwarded_for.len() {
+            return Some(list[index]);
+        }
+
+        list.last().copied()
+    };
+
+    x_forwarded_for
+        .iter()
+        .enumerate()
+        .map(|(index, for_ip)| ForwardedHop {
+            for_ip: Some(strip_port(for_ip)),
+            proto: pick(&x_forwarded_proto, index),
+            host: pick(&x_forwarded_host, index),
+        })
+        .collect()
+}
+
+/// Walks the hops from the right, skipping trusted proxies. The first hop that is not one is the
+/// client; if every hop is trusted, the leftmost is.
+pub(crate) fn find_client_hop(
+    hops: &[ForwardedHop],
+    trusted_proxies: &TrustedProxies,
+) -> Option<usize> {
+    if hops.is_empty() {
+        return None;
+    }
+
+    for (index, hop) in hops.iter().enumerate().rev() {
+        let is_trusted = match hop.for_ip {
+            Some(for_ip) => trusted_proxies.is_trusted_hop(for_ip),
+            None => false,
+        };
+
+        if !is_trusted {
+            return Some(index);
+        }
+    }
+
+    Some(0)
+}
+
+fn get_header<'s>(
+    headers: &'s impl HttpRequestHeaders,
+    header_name: &'static str,
+) -> Option<&'s str> {
+    headers.try_get_case_insensitive(header_name)?.as_str().ok()
+}
+
+fn split_list(value: Option<&str>) -> Vec<&str> {
+    match value {
+        Some(value) => value
+            .split(',')
+            .map(|itm| itm.trim())
+            .filter(|itm| !itm.is_empty())
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// RFC 7239: `for=192.0.2.60;proto=http;by=203.0.113.43, for="[2001:db8:cafe::17]:4711"`.
+fn parse_forwarded(value: &str) -> Vec<ForwardedHop<'_>> {
+    let mut result = Vec::new();
+
+    for element in value.split(',') {
+        let mut hop = ForwardedHop {
+            for_ip: None,
+            proto: None,
+            host: None,
+        };
+
+        for pair in element.split(';') {
+            let Some((key, value)) = pair.split_once('=') else {
+                continue;
+            };
+
+            let value = unquote(value.trim());
+
+            let key = key.trim();
+
+            if key.eq_ignore_ascii_case("for") {
+                hop.for_ip = Some(strip_port(value));
+            } else if key.eq_ignore_ascii_case("proto") {
+                hop.proto = Some(value);
+            } else if key.eq_ignore_ascii_case("host") {
+                hop.host = Some(value);
+            }
+        }
+
+        result.push(hop);
+    }
+
+    result
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|itm| itm.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+/// `[2001:db8::1]:4711` → `2001:db8::1`, `192.0.2.60:4711` → `192.0.2.60`. A bare IPv6 address
+/// has more than one colon and is left as is.
+fn strip_port(node: &str) -> &str {
+    if let Some(rest) = node.strip_prefix('[') {
+        return match rest.find(']') {
+            Some(end) => &rest[..end],
+            None => node,
+        };
+    }
+
+    match node.split_once(':') {
+        Some((ip, port)) if !port.contains(':') => ip,
+        _ => node,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::HeaderMap;
+
+    fn headers(items: &[(&'static str, &str)]) -> HeaderMap {
+        let mut result = HeaderMap::new();
+        for (name, value) in items {
+            result.insert(*name, value.parse().unwrap());
+        }
+        result
+    }
+
+    fn trusted() -> TrustedProxies {
+        TrustedProxies::new().add_cidr("10.0.0.0/8")
+    }
+
+    #[test]
+    fn x_forwarded_for_is_walked_from_the_right() {
+        let headers = headers(&[("x-forwarded-for", "6.6.6.6, 1.2.3.4, 10.0.0.5")]);
+
+        let hops = read_forwarded_hops(&headers, ForwardingHeaders::XForwarded);
+        let client = find_client_hop(&hops, &trusted()).unwrap();
+
+        assert_eq!(hops[client].for_ip, Some("1.2.3.4"));
+    }
+
+    #[test]
+    fn every_hop_trusted_gives_the_leftmost() {
+        let headers = headers(&[("x-forwarded-for", "10.0.0.1, 10.0.0.2")]);
+
+        let hops = read_forwarded_hops(&headers, ForwardingHeaders::XForwarded);
+
+        assert_eq!(find_client_hop(&hops, &trusted()), Some(0));
+    }
+
+    #[test]
+    fn x_forwarded_proto_aligned_with_the_chain() {
+        let headers = headers(&[
+            ("x-forwarded-for", "1.2.3.4, 10.0.0.5"),
+            ("x-forwarded-proto", "https, http"),
+        ]);
+
+        let hops = read_forwarded_hops(&headers, ForwardingHeaders::XForwarded);
+        let client = find_client_hop(&hops, &trusted()).unwrap();
+
+        assert_eq!(hops[client].proto, Some("https"));
+    }
+
+    #[test]
+    fn single_x_forwarded_proto_applies_to_every_hop() {
+        let headers = headers(&[
+            ("x-forwarded-for", "1.2.3.4, 10.0.0.5"),
+            ("x-forwarded-proto", "https"),
+            ("x-forwarded-host", "example.com"),
+        ]);
+
+        let hops = read_forwarded_hops(&headers, ForwardingHeaders::XForwarded);
+
+        assert_eq!(hops[0].proto, Some("https"));
+        assert_eq!(hops[0].host, Some("example.com"));
+    }
+
+    #[test]
+    fn x_forwarded_proto_without_for() {
+        let headers = headers(&[("x-forwarded-proto", "https")]);
+
+        let hops = read_forwarded_hops(&headers, ForwardingHeaders::XForwarded);
+
+        assert_eq!(hops.len(), 1);
+        assert_eq!(hops[0].for_ip, None);
+        assert_eq!(hops[0].proto, Some("https"));
+    }
+
+    #[test]
+    fn forwarded_header_when_the_proxies_write_it() {
+        let headers = headers(&[
+            (
+                "forwarded",
+                r#"for=6.6.6.6, for="[2001:db8:cafe::17]:4711";proto=https;host=example.com, for=10.0.0.5;proto=http"#,
+            ),
+            ("x-forwarded-for", "7.7.7.7"),
+        ]);
+
+        let hops = read_forwarded_hops(&headers, ForwardingHeaders::Forwarded);
+        let client = find_client_hop(&hops, &trusted()).unwrap();
+
+        assert_eq!(hops[client].for_ip, Some("2001:db8:cafe::17"));
+        assert_eq!(hops[client].proto, Some("https"));
+        assert_eq!(hops[client].host, Some("example.com"));
+    }
+
+    /// A proxy that appends to `X-Forwarded-For` passes the `Forwarded` of the client through.
+    #[test]
+    fn client_sent_forwarded_is_ignored_behind_an_x_forwarded_proxy() {
+        let headers = headers(&[("forwarded", "for=1.2.3.4"), ("x-forwarded-for", "6.6.6.6")]);
+
+        let hops = read_forwarded_hops(&headers, ForwardingHeaders::XForwarded);
+        let client = find_client_hop(&hops, &trusted()).unwrap();
+
+        assert_eq!(hops[client].for_ip, Some("6.6.6.6"));
+    }
+
+    #[test]
+    fn client_sent_x_forwarded_for_is_ignored_behind_a_forwarded_proxy() {
+        let headers = headers(&[("x-forwarded-for", "1.2.3.4")]);
+
+        let hops = read_forwarded_hops(&headers, ForwardingHeaders::Forwarded);
+
+        assert!(hops.is_empty());
+    }
+
+    #[test]
+    fn ports_are_stripped() {
+        assert_eq!(strip_port("192.0.2.60:4711"), "192.0.2.60");
+        assert_eq!(strip_port("[2001:db8::1]:4711"), "2001:db8::1");
+        assert_eq!(strip_port("2001:db8::1"), "2001:db8::1");
+        assert_eq!(strip_port("unknown"), "unknown");
+    }
+
+    #[test]
+    fn unknown_hop_is_not_trusted() {
+        let headers = headers(&[("forwarded", "for=unknown, for=10.0.0.5")]);
+
+        let hops = read_forwarded_hops(&headers, ForwardingHeaders::Forwarded);
+
+        assert_eq!(find_client_hop(&hops, &trusted()), Some(0));
+    }
+}
@@ -0,0 +1,122 @@
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use rust_extensions::ApplicationStates;
+
+use crate::HttpServerShutdown;
+
+use super::HttpHealthEndpoint;
+
+pub const DEFAULT_LIVENESS_PATH: &str = "/health/live";
+pub const DEFAULT_READINESS_PATH: &str = "/health/ready";
+pub const DEFAULT_HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Something the instance can not serve without — a database, a queue, a downstream service —
+/// asked on every readiness probe. `Err` says what is wrong; it is put into the readiness result
+/// as it is, so it should not carry secrets.
+#[async_trait]
+pub trait HttpHealthCheck {
+    async fn check(&self) -> Result<(), String>;
+}
+
+pub(crate) struct HttpHealthCheckItem {
+    pub name: String,
+    pub timeout: Duration,
+    pub check: Arc<dyn HttpHealthCheck + Send + Sync + 'static>,
+}
+
+/// The liveness and readiness endpoints of a server, given to it with
+/// [`MyHttpServer::set_health`](crate::MyHttpServer::set_health). Both answer `GET` with JSON and
+/// are put in front of all the middlewares, so they are reachable without whatever
+/// authentication those do.
+///
+/// - Liveness — `/health/live` — is `200` for as long as the server answers at all. It runs no
+///   checks: a database that is down is no reason for the orchestrator to restart the instance.
+/// - Readiness — `/health/ready` — is `200` once the application states report initialized, and
+///   only while every check passes; `503` before that, as soon as the application starts shutting
+///   down, and while any check fails or does not answer within its timeout. The checks run
+///   concurrently, and each one is listed with its status and latency.
+///
+/// For a load balancer to take the instance out of rotation before the listener closes, give it
+/// the time to notice with
+/// [`MyHttpServer::set_shutdown_delay`](crate::MyHttpServer::set_shutdown_delay).
+pub struct HttpHealth {
+    liveness_path: String,
+    readiness_path: String,
+    checks: Vec<HttpHealthCheckItem>,
+}
+
+impl HttpHealth {
+    pub fn new() -> Self {
+        Self {
+            liveness_path: DEFAULT_LIVENESS_PATH.to_string(),
+            readiness_path: DEFAULT_READINESS_PATH.to_string(),
+            checks: Vec::new(),
+        }
+    }
+
+    /// `/health/live` by default.
+    pub fn with_liveness_path(mut self, path: impl Into<String>) -> Self {
+        self.liveness_path = path.into();
+        self
+    }
+
+    /// `/health/ready` by default.
+    pub fn with_readiness_path(mut self, path: impl Into<String>) -> Self {
+        self.readiness_path = path.into();
+        self
+    }
+
+    /// A check the readiness depends on, with [`DEFAULT_HEALTH_CHECK_TIMEOUT`].
+    pub fn with_check(
+        mut self,
+        name: impl Into<String>,
+        check: Arc<dyn HttpHealthCheck + Send + Sync + 'static>,
+    ) -> Self {
+        self.add_check(name, DEFAULT_HEALTH_CHECK_TIMEOUT, check);
+        self
+    }
+
+    /// A check the readiness depends on. One that has not answered within `timeout` is reported
+    /// unhealthy — a probe is never kept waiting longer than its slowest timeout.
+    ///
+    /// Panics if a check with the same name is already added.
+    pub fn add_check(
+        &mut self,
+        name: impl Into<String>,
+        timeout: Duration,
+        check: Arc<dyn HttpHealthCheck + Send + Sync + 'static>,
+    ) {
+        let name = name.into();
+
+        if self.checks.iter().any(|itm| itm.name == name) {
+            panic!("Health check '{}' is already added", name);
+        }
+
+        self.checks.push(HttpHealthCheckItem {
+            name,
+            timeout,
+            check,
+        });
+    }
+
+    pub(crate) fn into_endpoint(
+        self,
+        app_states: Arc<dyn ApplicationStates + Send + Sync + 'static>,
+        shutdown: Arc<HttpServerShutdown>,
+    ) -> HttpHealthEndpoint {
+        HttpHealthEndpoint::new(
+            self.liveness_path,
+            self.readiness_path,
+            self.checks,
+            app_states,
+            shutdown,
+        )
+    }
+}
+
+impl Default for HttpHealth {
+    fn default() -> Self {
+        Self::new()
+    }
+}
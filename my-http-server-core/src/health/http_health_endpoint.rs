@@ -0,0 +1,313 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use hyper::Method;
+use rust_extensions::ApplicationStates;
+use serde::Serialize;
+
+use crate::{
+    HttpContext, HttpFailResult, HttpOkResult, HttpOutput, HttpResponseHeaders,
+    HttpServerMiddleware, HttpServerShutdown, WebContentType,
+};
+
+use super::HttpHealthCheckItem;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HttpHealthStatus {
+    Healthy,
+    Unhealthy,
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct HttpReadinessReport {
+    pub status: HttpHealthStatus,
+    pub initialized: bool,
+    pub shutting_down: bool,
+    pub checks: Vec<HttpHealthCheckReport>,
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct HttpHealthCheckReport {
+    pub name: String,
+    pub status: HttpHealthStatus,
+    pub latency_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct HttpLivenessReport {
+    status: HttpHealthStatus,
+}
+
+/// Answers `GET` of the liveness and readiness paths — put in front of the chain by
+/// [`MyHttpServer::set_health`](crate::MyHttpServer::set_health).
+pub(crate) struct HttpHealthEndpoint {
+    liveness_path: String,
+    readiness_path: String,
+    checks: Vec<HttpHealthCheckItem>,
+    app_states: Arc<dyn ApplicationStates + Send + Sync + 'static>,
+    shutdown: Arc<HttpServerShutdown>,
+}
+
+impl HttpHealthEndpoint {
+    pub fn new(
+        liveness_path: String,
+        readiness_path: String,
+        checks: Vec<HttpHealthCheckItem>,
+        app_states: Arc<dyn ApplicationStates + Send + Sync + 'static>,
+        shutdown: Arc<HttpServerShutdown>,
+    ) -> Self {
+        Self {
+            liveness_path,
+            readiness_path,
+            checks,
+            app_states,
+            shutdown,
+        }
+    }
+
+    /// The checks are not run while the application is not serving anyway — a probe of an
+    /// instance that is going away should not add load to the dependencies it checks.
+    pub async fn get_readiness(&self) -> HttpReadinessReport {
+        let initialized = self.app_states.is_initialized();
+        let shutting_down = self.app_states.is_shutting_down() || self.shutdown.is_started();
+
+        let checks = if initialized && !shutting_down {
+            futures::future::join_all(self.checks.iter().map(run_check)).await
+        } else {
+            Vec::new()
+        };
+
+        let healthy = initialized
+            && !shutting_down
+            && checks
+                .iter()
+                .all(|itm| itm.status == HttpHealthStatus::Healthy);
+
+        HttpReadinessReport {
+            status: if healthy {
+                HttpHealthStatus::Healthy
+            } else {
+                HttpHealthStatus::Unhealthy
+            },
+            initialized,
+            shutting_down,
+            checks,
+        }
+    }
+}
+
+#[async_trait]
+impl HttpServerMiddleware for HttpHealthEndpoint {
+    async fn handle_request(
+        &self,
+        ctx: &mut HttpContext,
+    ) -> Option<Result<HttpOkResult, HttpFailResult>> {
+        if ctx.request.method != Method::GET {
+            return None;
+        }
+
+        let path = ctx.request.http_path.as_str();
+
+        if path.eq_ignore_ascii_case(&self.liveness_path) {
+            ctx.process_name = Some(self.liveness_path.clone());
+
+            let report = HttpLivenessReport {
+                status: HttpHealthStatus::Healthy,
+            };
+
+            return Some(compile_result(report.status, &report));
+        }
+
+        if path.eq_ignore_ascii_case(&self.readiness_path) {
+            ctx.process_name = Some(self.readiness_path.clone());
+
+            let report = self.get_readiness().await;
+
+            return Some(compile_result(report.status, &report));
+        }
+
+        None
+    }
+}
+
+async fn run_check(item: &HttpHealthCheckItem) -> HttpHealthCheckReport {
+    let started = std::time::Instant::now();
+
+    let result = tokio::time::timeout(item.timeout, item.check.check()).await;
+
+    let (status, error) = match result {
+        Ok(Ok(())) => (HttpHealthStatus::Healthy, None),
+        Ok(Err(err)) => (HttpHealthStatus::Unhealthy, Some(err)),
+        Err(_) => (
+            HttpHealthStatus::Unhealthy,
+            Some(format!("Timed out after {:?}", item.timeout)),
+        ),
+    };
+
+    HttpHealthCheckReport {
+        name: item.name.clone(),
+        status,
+        latency_ms: started.elapsed().as_secs_f64() * 1000.0,
+        error,
+    }
+}
+
+/// Probes are not written to the telemetry: they come every few seconds from every load balancer
+/// and say nothing about the traffic.
+fn compile_result(
+    status: HttpHealthStatus,
+    report: &impl Serialize,
+) -> Result<HttpOkResult, HttpFailResult> {
+    let output = HttpOutput::Content {
+        status_code: match status {
+            HttpHealthStatus::Healthy => 200,
+            HttpHealthStatus::Unhealthy => 503,
+        },
+        headers: HttpResponseHeaders::new(WebContentType::Json.into()),
+        content: serde_json::to_vec(report).unwrap(),
+    };
+
+    match status {
+        HttpHealthStatus::Healthy => output.into_ok_result(false),
+        HttpHealthStatus::Unhealthy => Err(HttpFailResult::new(output, false, false)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicBool, AtomicI64, Ordering},
+        time::Duration,
+    };
+
+    use super::*;
+    use crate::test_logger::NoLogger;
+    use crate::{HttpHealth, HttpHealthCheck};
+
+    #[derive(Default)]
+    struct TestAppStates {
+        initialized: AtomicBool,
+        shutting_down: AtomicBool,
+    }
+
+    impl ApplicationStates for TestAppStates {
+        fn is_initialized(&self) -> bool {
+            self.initialized.load(Ordering::SeqCst)
+        }
+
+        fn is_shutting_down(&self) -> bool {
+            self.shutting_down.load(Ordering::SeqCst)
+        }
+    }
+
+    enum TestCheck {
+        Ok,
+        Fail,
+        Hang,
+    }
+
+    #[async_trait]
+    impl HttpHealthCheck for TestCheck {
+        async fn check(&self) -> Result<(), String> {
+            match self {
+                TestCheck::Ok => Ok(()),
+                TestCheck::Fail => Err("Connection refused".to_string()),
+                TestCheck::Hang => {
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    fn endpoint(health: HttpHealth, app_states: Arc<TestAppStates>) -> HttpHealthEndpoint {
+        let shutdown = Arc::new(HttpServerShutdown::new(
+            Arc::new(AtomicI64::new(0)),
+            Duration::from_secs(1),
+            Arc::new(NoLogger),
+        ));
+
+        health.into_endpoint(app_states, shutdown)
+    }
+
+    #[tokio::test]
+    async fn test_readiness_follows_the_application_states() {
+        let app_states = Arc::new(TestAppStates::default());
+        let endpoint = endpoint(
+            HttpHealth::new().with_check("db", Arc::new(TestCheck::Ok)),
+            app_states.clone(),
+        );
+
+        let report = endpoint.get_readiness().await;
+        assert_eq!(report.status, HttpHealthStatus::Unhealthy);
+        assert!(!report.initialized);
+        assert!(report.checks.is_empty());
+
+        app_states.initialized.store(true, Ordering::SeqCst);
+
+        let report = endpoint.get_readiness().await;
+        assert_eq!(report.status, HttpHealthStatus::Healthy);
+        assert_eq!(report.checks.len(), 1);
+        assert_eq!(report.checks[0].name, "db");
+
+        app_states.shutting_down.store(true, Ordering::SeqCst);
+
+        let report = endpoint.get_readiness().await;
+        assert_eq!(report.status, HttpHealthStatus::Unhealthy);
+        assert!(report.shutting_down);
+    }
+
+    #[tokio::test]
+    async fn test_readiness_lists_every_check() {
+        let app_states = Arc::new(TestAppStates::default());
+        app_states.initialized.store(true, Ordering::SeqCst);
+
+        let mut health = HttpHealth::new()
+            .with_check("db", Arc::new(TestCheck::Ok))
+            .with_check("queue", Arc::new(TestCheck::Fail));
+        health.add_check(
+            "downstream",
+            Duration::from_millis(50),
+            Arc::new(TestCheck::Hang),
+        );
+
+        let report = endpoint(health, app_states).get_readiness().await;
+
+        assert_eq!(report.status, HttpHealthStatus::Unhealthy);
+
+        let checks: Vec<_> = report
+            .checks
+            .iter()
+            .map(|itm| (itm.name.as_str(), itm.status, itm.error.as_deref()))
+            .collect();
+
+        assert_eq!(
+            checks,
+            vec![
+                ("db", HttpHealthStatus::Healthy, None),
+                (
+                    "queue",
+                    HttpHealthStatus::Unhealthy,
+                    Some("Connection refused")
+                ),
+                (
+                    "downstream",
+                    HttpHealthStatus::Unhealthy,
+                    Some("Timed out after 50ms")
+                ),
+            ]
+        );
+
+        assert!(report.checks[2].latency_ms >= 50.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Health check 'db' is already added")]
+    fn test_check_names_are_unique() {
+        HttpHealth::new()
+            .with_check("db", Arc::new(TestCheck::Ok))
+            .with_check("db", Arc::new(TestCheck::Ok));
+    }
+}
@@ -0,0 +1,4 @@
+mod http_health;
+pub use http_health::*;
+mod http_health_endpoint;
+pub(crate) use http_health_endpoint::*;
@@ -0,0 +1,43 @@
+use std::sync::Arc;
+
+use crate::{ProxyProtocolHeader, SocketAddress};
+
+/// What the accept loop learned about the connection before the first request came in. Built once
+/// per connection and shared by every request served on it.
+#[derive(Debug, Clone)]
+pub struct HttpConnectionInfo {
+    /// The name of the [`HttpListener`](crate::HttpListener) that accepted the connection.
+    pub listener_name: Arc<String>,
+    /// The client — as the balancer reported it when the listener expects the PROXY protocol.
+    pub addr: SocketAddress,
+    /// `Some` when the connection was accepted on a listener with
+    /// [`HttpListener::with_proxy_protocol`](crate::HttpListener::with_proxy_protocol).
+    pub proxy_protocol: Option<ProxyProtocolHeader>,
+    /// `Some` when the connection was accepted on a TLS listener and the handshake completed.
+    pub tls: Option<TlsConnectionInfo>,
+}
+
+impl HttpConnectionInfo {
+    pub fn new(listener_name: Arc<String>, addr: SocketAddress) -> Self {
+        Self {
+            listener_name,
+            addr,
+            proxy_protocol: None,
+            tls: None,
+        }
+    }
+
+    pub fn is_tls(&self) -> bool {
+        self.tls.is_some()
+    }
+}
+
+/// The outcome of the TLS handshake, as far as a middleware may care about it.
+#[derive(Debug, Clone, Default)]
+pub struct TlsConnectionInfo {
+    /// The SNI host name the client asked for — the one the certificate was picked by.
+    pub server_name: Option<String>,
+    /// The protocol agreed through ALPN: `h2` or `http/1.1`. `None` if the client did not offer
+    /// ALPN at all.
+    pub alpn_protocol: Option<String>,
+}
@@ -0,0 +1,254 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::{HttpConnectionRefusedReason, SocketAddress};
+
+/// What the accept loop does with a new connection once the server-wide limit is reached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionLimitMode {
+    /// Stop accepting until a connection closes. Clients queue up in the listen backlog of the OS.
+    Wait,
+    /// Accept and close the connection straight away.
+    Refuse,
+}
+
+/// Caps on the connections a [`MyHttpServer`](crate::MyHttpServer) keeps open. Set through
+/// [`MyHttpServer::set_connection_limits`](crate::MyHttpServer::set_connection_limits); nothing
+/// is limited by default.
+#[derive(Debug, Clone)]
+pub struct HttpConnectionLimits {
+    pub max_connections: Option<usize>,
+    pub when_limit_is_reached: ConnectionLimitMode,
+    /// Connections a single remote IP may keep open. A connection over the cap is always refused
+    /// — waiting would let one client hold up everybody else's accepts. Unix socket peers have no
+    /// IP and are not counted.
+    pub max_connections_per_ip: Option<usize>,
+}
+
+impl Default for HttpConnectionLimits {
+    fn default() -> Self {
+        Self {
+            max_connections: None,
+            when_limit_is_reached: ConnectionLimitMode::Wait,
+            max_connections_per_ip: None,
+        }
+    }
+}
+
+impl HttpConnectionLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_connections(
+        mut self,
+        max_connections: usize,
+        mode: ConnectionLimitMode,
+    ) -> Self {
+        self.max_connections = Some(max_connections);
+        self.when_limit_is_reached = mode;
+        self
+    }
+
+    pub fn with_max_connections_per_ip(mut self, max_connections_per_ip: usize) -> Self {
+        self.max_connections_per_ip = Some(max_connections_per_ip);
+        self
+    }
+}
+
+/// Enforces [`HttpConnectionLimits`] for every listener of a server.
+pub(crate) struct HttpConnectionsLimiter {
+    limits: HttpConnectionLimits,
+    semaphore: Option<Arc<Semaphore>>,
+    per_ip: Arc<Mutex<HashMap<IpAddr, usize>>>,
+}
+
+impl HttpConnectionsLimiter {
+    pub fn new(limits: HttpConnectionLimits) -> Self {
+        Self {
+            semaphore: limits
+                .max_connections
+                .map(|max_connections| Arc::new(Semaphore::new(max_connections))),
+            limits,
+            per_ip: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Called before `accept()`: in [`ConnectionLimitMode::Wait`] holds the accept loop until a
+    /// slot is free, and returns it. Resolves with `None` at once in every other case.
+    pub async fn wait_for_slot(&self) -> Option<OwnedSemaphorePermit> {
+        if self.limits.when_limit_is_reached != ConnectionLimitMode::Wait {
+            return None;
+        }
+
+        let semaphore = self.semaphore.as_ref()?;
+
+        Some(semaphore.clone().acquire_owned().await.unwrap())
+    }
+
+    /// Called after `accept()`: decides whether the connection may be served. The slot it returns
+    /// must live as long as the connection.
+    pub fn admit(
+        &self,
+        addr: &SocketAddress,
+        permit: Option<OwnedSemaphorePermit>,
+    ) -> Result<HttpConnectionSlot, HttpConnectionRefusedReason> {
+        let permit = match (permit, self.semaphore.as_ref()) {
+            (Some(permit), _) => Some(permit),
+            (None, Some(semaphore)) => match semaphore.clone().try_acquire_owned() {
+                Ok(permit) => Some(permit),
+                Err(_) => {
+                    return Err(HttpConnectionRefusedReason::TooManyConnections {
+                        limit: self.limits.max_connections.unwrap_or_default(),
+                    })
+                }
+            },
+            (None, None) => None,
+        };
+
+        let mut result = HttpConnectionSlot {
+            _permit: permit,
+            ip: None,
+            per_ip: self.per_ip.clone(),
+        };
+
+        let (Some(max_connections_per_ip), SocketAddress::Tcp(socket_addr)) =
+            (self.limits.max_connections_per_ip, addr)
+        else {
+            return Ok(result);
+        };
+
+        let ip = socket_addr.ip();
+
+        let mut per_ip = self.per_ip.lock().unwrap();
+        let connections = per_ip.entry(ip).or_default();
+
+        if *connections >= max_connections_per_ip {
+            return Err(HttpConnectionRefusedReason::TooManyConnectionsFromIp {
+                limit: max_connections_per_ip,
+            });
+        }
+
+        *connections += 1;
+        result.ip = Some(ip);
+
+        Ok(result)
+    }
+}
+
+/// The place a connection takes in the limits. Given back when dropped.
+pub(crate) struct HttpConnectionSlot {
+    _permit: Option<OwnedSemaphorePermit>,
+    ip: Option<IpAddr>,
+    per_ip: Arc<Mutex<HashMap<IpAddr, usize>>>,
+}
+
+impl Drop for HttpConnectionSlot {
+    fn drop(&mut self) {
+        let Some(ip) = self.ip else {
+            return;
+        };
+
+        let mut per_ip = self.per_ip.lock().unwrap();
+
+        if let Some(connections) = per_ip.get_mut(&ip) {
+            *connections -= 1;
+            if *connections == 0 {
+                per_ip.remove(&ip);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tcp(addr: &str) -> SocketAddress {
+        SocketAddress::Tcp(addr.parse().unwrap())
+    }
+
+    #[test]
+    fn per_ip_cap_refuses_only_the_ip_over_it() {
+        let limiter =
+            HttpConnectionsLimiter::new(HttpConnectionLimits::new().with_max_connections_per_ip(2));
+
+        let _first = limiter.admit(&tcp("10.0.0.1:1000"), None).unwrap();
+        let _second = limiter.admit(&tcp("10.0.0.1:1001"), None).unwrap();
+
+        assert!(matches!(
+            limiter.admit(&tcp("10.0.0.1:1002"), None),
+            Err(HttpConnectionRefusedReason::TooManyConnectionsFromIp { limit: 2 })
+        ));
+
+        assert!(limiter.admit(&tcp("10.0.0.2:1000"), None).is_ok());
+    }
+
+    #[test]
+    fn per_ip_slot_is_given_back_on_drop() {
+        let limiter =
+            HttpConnectionsLimiter::new(HttpConnectionLimits::new().with_max_connections_per_ip(1));
+
+        let slot = limiter.admit(&tcp("10.0.0.1:1000"), None).unwrap();
+        assert!(limiter.admit(&tcp("10.0.0.1:1001"), None).is_err());
+
+        drop(slot);
+
+        assert!(limiter.admit(&tcp("10.0.0.1:1002"), None).is_ok());
+    }
+
+    #[test]
+    fn unix_socket_peers_are_not_counted_per_ip() {
+        let limiter =
+            HttpConnectionsLimiter::new(HttpConnectionLimits::new().with_max_connections_per_ip(1));
+
+        let addr = SocketAddress::Unix(Arc::new("/tmp/test.sock".to_string()));
+
+        let _first = limiter.admit(&addr, None).unwrap();
+        assert!(limiter.admit(&addr, None).is_ok());
+    }
+
+    #[test]
+    fn refuse_mode_refuses_over_the_global_limit() {
+        let limiter = HttpConnectionsLimiter::new(
+            HttpConnectionLimits::new().with_max_connections(1, ConnectionLimitMode::Refuse),
+        );
+
+        let slot = limiter.admit(&tcp("10.0.0.1:1000"), None).unwrap();
+
+        assert!(matches!(
+            limiter.admit(&tcp("10.0.0.2:1000"), None),
+            Err(HttpConnectionRefusedReason::TooManyConnections { limit: 1 })
+        ));
+
+        drop(slot);
+
+        assert!(limiter.admit(&tcp("10.0.0.2:1000"), None).is_ok());
+    }
+
+    #[tokio::test]
+    async fn wait_mode_holds_the_accept_until_a_slot_is_free() {
+        let limiter = HttpConnectionsLimiter::new(
+            HttpConnectionLimits::new().with_max_connections(1, ConnectionLimitMode::Wait),
+        );
+
+        let permit = limiter.wait_for_slot().await;
+        let slot = limiter.admit(&tcp("10.0.0.1:1000"), permit).unwrap();
+
+        let waiting = tokio::time::timeout(
+            std::time::Duration::from_millis(50),
+            limiter.wait_for_slot(),
+        )
+        .await;
+        assert!(waiting.is_err());
+
+        drop(slot);
+
+        assert!(limiter.wait_for_slot().await.is_some());
+    }
+}
@@ -0,0 +1,131 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+};
+
+/// Values of any type a middleware hands to the middlewares after it and to the action — one per
+/// type, kept as they are: nothing is serialized the way
+/// [`HttpRequest::set_key_value`](crate::HttpRequest::set_key_value) has to.
+///
+/// ```ignore
+/// // An auth middleware
+/// ctx.extensions.insert(UserProfile { id, roles });
+///
+/// // An action
+/// let profile = ctx.extensions.get::<UserProfile>();
+/// ```
+///
+/// An action generated with `#[http_route]` can take a copy of one as an argument with
+/// `extension: T` — see
+/// [`HttpContext::get_required_extension`](crate::HttpContext::get_required_extension).
+#[derive(Default)]
+pub struct HttpContextExtensions {
+    values: Option<HashMap<TypeId, Box<dyn Any + Send + Sync>>>,
+}
+
+impl HttpContextExtensions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The value of the same type that was there before is given back.
+    pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) -> Option<T> {
+        self.values
+            .get_or_insert_with(HashMap::new)
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(downcast)
+    }
+
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.values
+            .as_ref()?
+            .get(&TypeId::of::<T>())?
+            .downcast_ref::<T>()
+    }
+
+    pub fn get_mut<T: Send + Sync + 'static>(&mut self) -> Option<&mut T> {
+        self.values
+            .as_mut()?
+            .get_mut(&TypeId::of::<T>())?
+            .downcast_mut::<T>()
+    }
+
+    pub fn remove<T: Send + Sync + 'static>(&mut self) -> Option<T> {
+        self.values
+            .as_mut()?
+            .remove(&TypeId::of::<T>())
+            .and_then(downcast)
+    }
+
+    pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+        self.get::<T>().is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.as_ref().map(|itm| itm.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.values = None;
+    }
+}
+
+fn downcast<T: 'static>(value: Box<dyn Any + Send + Sync>) -> Option<T> {
+    value.downcast::<T>().ok().map(|itm| *itm)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct UserProfile {
+        id: String,
+    }
+
+    #[test]
+    fn test_insert_get_remove_by_type() {
+        let mut extensions = HttpContextExtensions::new();
+        assert!(extensions.is_empty());
+        assert_eq!(extensions.get::<UserProfile>(), None);
+
+        let previous = extensions.insert(UserProfile {
+            id: "user-1".to_string(),
+        });
+        assert_eq!(previous, None);
+
+        extensions.insert(42u64);
+
+        assert_eq!(extensions.len(), 2);
+        assert_eq!(extensions.get::<UserProfile>().unwrap().id, "user-1");
+        assert_eq!(extensions.get::<u64>(), Some(&42));
+        assert_eq!(extensions.get::<u32>(), None);
+
+        *extensions.get_mut::<u64>().unwrap() += 1;
+        assert_eq!(extensions.get::<u64>(), Some(&43));
+
+        let removed = extensions.remove::<UserProfile>().unwrap();
+        assert_eq!(removed.id, "user-1");
+        assert!(!extensions.contains::<UserProfile>());
+        assert_eq!(extensions.len(), 1);
+    }
+
+    #[test]
+    fn test_insert_replaces_the_value_of_the_same_type() {
+        let mut extensions = HttpContextExtensions::new();
+
+        extensions.insert("first".to_string());
+        let previous = extensions.insert("second".to_string());
+
+        assert_eq!(previous.as_deref(), Some("first"));
+        assert_eq!(
+            extensions.get::<String>().map(|s| s.as_str()),
+            Some("second")
+        );
+        assert_eq!(extensions.len(), 1);
+    }
+}
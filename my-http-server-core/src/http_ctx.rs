@@ -1,28 +1,103 @@
 #[cfg(feature = "with-telemetry")]
 use my_telemetry::MyTelemetryContext;
-#[cfg(feature = "with-telemetry")]
-use rust_extensions::date_time::DateTimeAsMicroseconds;
 
-use crate::{HttpRequest, RequestCredentials};
+use std::time::Duration;
+
+use crate::{
+    HttpContextExtensions, HttpFailResult, HttpRequest, HttpRequestDeadline, HttpRequestId,
+    RequestCredentials,
+};
 
 pub struct HttpContext {
     pub request: HttpRequest,
+    /// What the telemetry of the request is written under — the process of
+    /// [`trace_context`](Self::trace_context).
     #[cfg(feature = "with-telemetry")]
     pub telemetry_context: MyTelemetryContext,
+    /// The W3C trace the request is part of — see [`HttpTraceContext`](crate::HttpTraceContext).
+    /// A call made on behalf of the request passes it on with
+    /// [`to_traceparent`](crate::HttpTraceContext::to_traceparent).
+    #[cfg(feature = "with-telemetry")]
+    pub trace_context: crate::HttpTraceContext,
     pub process_name: Option<String>,
     pub credentials: Option<Box<dyn RequestCredentials + Send + Sync + 'static>>,
+    /// What the middlewares hand to the action — see [`HttpContextExtensions`].
+    pub extensions: HttpContextExtensions,
+    pub(crate) request_deadline: HttpRequestDeadline,
+    pub(crate) response_compression: bool,
+    request_id: HttpRequestId,
 }
 
 impl HttpContext {
     pub fn new(request: HttpRequest) -> Self {
+        let request_id = request
+            .extensions()
+            .get::<HttpRequestId>()
+            .cloned()
+            .unwrap_or_else(HttpRequestId::generate);
+
+        #[cfg(feature = "with-telemetry")]
+        let trace_context = crate::HttpTraceContext::from_headers(request.data.headers());
+
         Self {
             request,
             credentials: None,
+            extensions: HttpContextExtensions::new(),
             #[cfg(feature = "with-telemetry")]
-            telemetry_context: MyTelemetryContext::Single(
-                DateTimeAsMicroseconds::now().unix_microseconds,
-            ),
+            telemetry_context: trace_context.to_telemetry_context(),
+            #[cfg(feature = "with-telemetry")]
+            trace_context,
             process_name: None,
+            request_deadline: HttpRequestDeadline::new(),
+            response_compression: true,
+            request_id,
+        }
+    }
+
+    /// The id of this request — see
+    /// [`MyHttpServer::set_request_id_header`](crate::MyHttpServer::set_request_id_header). It is
+    /// in every log line and telemetry event the server writes for the request, and in the header
+    /// of the response.
+    pub fn get_request_id(&self) -> &str {
+        self.request_id.as_str()
+    }
+
+    /// How long the request may take, counted from when it came in — see
+    /// [`MyHttpServer::set_request_timeout`](crate::MyHttpServer::set_request_timeout). A middleware
+    /// can change it while the request is being handled; `None` lets it run as long as it takes.
+    pub fn set_request_timeout(&mut self, timeout: Option<Duration>) {
+        self.request_deadline.set_timeout(timeout);
+    }
+
+    pub fn get_request_timeout(&self) -> Option<Duration> {
+        self.request_deadline.get_timeout()
+    }
+
+    /// Whether the response to this request may be compressed, when
+    /// [`MyHttpServer::set_response_compression`](crate::MyHttpServer::set_response_compression)
+    /// is on. The controllers turn it off for a route with `compress_response: false`.
+    pub fn set_response_compression(&mut self, value: bool) {
+        self.response_compression = value;
+    }
+
+    pub fn is_response_compression_enabled(&self) -> bool {
+        self.response_compression
+    }
+
+    /// A copy of the extension of type `T` for the action — what `extension: T` of
+    /// `#[http_route]` does before the action is called. The value stays in the context, so an
+    /// around middleware that runs the chain again hands it to the action again. A request that
+    /// reaches the action without it is answered with `500`: a middleware that was supposed to put
+    /// it there did not run.
+    pub fn get_required_extension<T: Clone + Send + Sync + 'static>(
+        &self,
+    ) -> Result<T, HttpFailResult> {
+        match self.extensions.get::<T>() {
+            Some(value) => Ok(value.clone()),
+            None => Err(HttpFailResult::as_fatal_error(format!(
+                "Request extension {} is not set",
+                std::any::type_name::<T>()
+            ))),
         }
     }
 }
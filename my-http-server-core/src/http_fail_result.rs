@@ -56,6 +56,11 @@ impl From<my_http_utils::http_input::HttpParseError> for HttpFailResult {
             NotSupportedContentType(msg) => HttpFailResult::as_not_supported_content_type(msg),
             Forbidden(msg) => HttpFailResult::as_forbidden(Some(msg)),
             Validation(msg) => HttpFailResult::as_validation_error(msg),
+            // The pump cut off a body that went over `max_body_size` — the same 413 a body read
+            // whole gets.
+            BodyStream(msg) if msg.starts_with(crate::BODY_TOO_LARGE_PREFIX) => {
+                HttpFailResult::as_payload_too_large(msg)
+            }
             // Streaming the body failed mid-flight: an aborted upload, a stream that was already
             // taken, or a size limit. The transfer broke on the client's side, so 400 — but unlike
             // an ordinary validation error it is worth seeing in the log.
@@ -123,6 +128,12 @@ impl HttpFailResult {
     pub fn as_not_supported_content_type(text: impl Into<String>) -> Self {
         HttpOutput::as_not_supported_content_type(text).into_http_fail_result(true, true)
     }
+
+    /// `413` — the request body is over the limit set by
+    /// [`MyHttpServer::set_max_body_size`](crate::MyHttpServer::set_max_body_size).
+    pub fn as_payload_too_large(text: impl Into<String>) -> Self {
+        Self::from((413u16, text.into()))
+    }
 }
 
 impl Into<HttpFailResult> for HttpOutput {
@@ -0,0 +1,40 @@
+use crate::{HttpFailResult, HttpOutput, HttpRequestData, HttpResponseHeaders, WebContentType};
+
+/// What a request whose handler panicked is answered with. Set through
+/// [`MyHttpServer::set_panic_result_factory`](crate::MyHttpServer::set_panic_result_factory);
+/// without one the answer is `500` with `Internal server error` as text.
+///
+/// The panic is written to the logger either way — `panic_message` is for a response that wants
+/// to say more, not for the log.
+pub trait HttpPanicResultFactory {
+    fn get_panic_result(&self, request: &HttpRequestData, panic_message: &str) -> HttpFailResult;
+}
+
+/// What a request no middleware answered is answered with. Set through
+/// [`MyHttpServer::set_not_found_result_factory`](crate::MyHttpServer::set_not_found_result_factory);
+/// without one the answer is `404` with `404 - Not Found` as text.
+pub trait HttpNotFoundResultFactory {
+    fn get_not_found_result(&self, request: &HttpRequestData) -> HttpFailResult;
+}
+
+/// What a request that comes in once the application is shutting down is answered with. Set
+/// through
+/// [`MyHttpServer::set_shutting_down_result_factory`](crate::MyHttpServer::set_shutting_down_result_factory);
+/// without one the answer is `502` with `Application is shutting down` as text.
+pub trait HttpShuttingDownResultFactory {
+    fn get_shutting_down_result(&self, request: &HttpRequestData) -> HttpFailResult;
+}
+
+pub(crate) fn get_default_not_found_result() -> HttpFailResult {
+    HttpFailResult::as_not_found("404 - Not Found".to_string(), false)
+}
+
+pub(crate) fn get_default_shutting_down_result() -> HttpFailResult {
+    let output = HttpOutput::Content {
+        status_code: 502,
+        headers: HttpResponseHeaders::new(WebContentType::Text.into()),
+        content: "Application is shutting down".as_bytes().to_vec(),
+    };
+
+    HttpFailResult::new(output, false, false)
+}
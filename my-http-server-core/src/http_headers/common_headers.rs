@@ -1,4 +1,5 @@
 pub const X_FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
 pub const X_FORWARDED_PROTO: &str = "X-Forwarded-Proto";
 pub const X_FORWARDED_HOST: &str = "X-Forwarded-Host";
+pub const FORWARDED_HEADER: &str = "forwarded";
 pub const CONTENT_TYPE_HEADER: &str = "Content-Type";
@@ -0,0 +1,91 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use crate::{HttpProtocol, ListenAddr, UnixSocketOptions};
+
+/// The name of the listener a [`MyHttpServer`](crate::MyHttpServer) is created with.
+pub const DEFAULT_LISTENER_NAME: &str = "default";
+
+/// One more address a [`MyHttpServer`](crate::MyHttpServer) accepts connections on, next to the
+/// one it was created with. Every listener feeds the same middleware chain; the name is what a
+/// middleware sees in [`HttpRequest::get_listener_name`](crate::HttpRequest::get_listener_name) —
+/// e.g. to serve admin routes on an internal port only.
+#[derive(Debug, Clone)]
+pub struct HttpListener {
+    pub name: String,
+    pub addr: ListenAddr,
+    /// `None` — whatever protocol the server is started with.
+    pub protocol: Option<HttpProtocol>,
+    pub unix_socket_options: UnixSocketOptions,
+    pub proxy_protocol: bool,
+    #[cfg(feature = "with-tls")]
+    pub tls: Option<crate::TlsSettings>,
+}
+
+impl HttpListener {
+    pub fn new(name: impl Into<String>, addr: ListenAddr) -> Self {
+        Self {
+            name: name.into(),
+            addr,
+            protocol: None,
+            unix_socket_options: UnixSocketOptions::default(),
+            proxy_protocol: false,
+            #[cfg(feature = "with-tls")]
+            tls: None,
+        }
+    }
+
+    pub fn tcp(name: impl Into<String>, addr: SocketAddr) -> Self {
+        Self::new(name, ListenAddr::Tcp(addr))
+    }
+
+    #[cfg(unix)]
+    pub fn unix_socket(name: impl Into<String>, unix_socket_addr: impl Into<String>) -> Self {
+        Self::new(name, ListenAddr::Unix(Arc::new(unix_socket_addr.into())))
+    }
+
+    /// A socket that is listening already — see [`take_listen_fds`](crate::take_listen_fds).
+    #[cfg(unix)]
+    pub fn from_listen_fd(name: impl Into<String>, fd: std::os::unix::io::RawFd) -> Self {
+        Self::new(name, ListenAddr::Fd(fd))
+    }
+
+    #[cfg(unix)]
+    pub fn from_std_tcp_listener(name: impl Into<String>, listener: std::net::TcpListener) -> Self {
+        use std::os::unix::io::IntoRawFd;
+        Self::from_listen_fd(name, listener.into_raw_fd())
+    }
+
+    #[cfg(unix)]
+    pub fn from_std_unix_listener(
+        name: impl Into<String>,
+        listener: std::os::unix::net::UnixListener,
+    ) -> Self {
+        use std::os::unix::io::IntoRawFd;
+        Self::from_listen_fd(name, listener.into_raw_fd())
+    }
+
+    pub fn with_protocol(mut self, protocol: HttpProtocol) -> Self {
+        self.protocol = Some(protocol);
+        self
+    }
+
+    pub fn with_unix_socket_options(mut self, unix_socket_options: UnixSocketOptions) -> Self {
+        self.unix_socket_options = unix_socket_options;
+        self
+    }
+
+    /// Expect every connection to start with a PROXY protocol v1 or v2 header — see
+    /// [`MyHttpServer::set_proxy_protocol`](crate::MyHttpServer::set_proxy_protocol).
+    pub fn with_proxy_protocol(mut self) -> Self {
+        self.proxy_protocol = true;
+        self
+    }
+
+    /// Terminate TLS on this listener only — the way
+    /// [`MyHttpServer::set_tls`](crate::MyHttpServer::set_tls) does for the default one.
+    #[cfg(feature = "with-tls")]
+    pub fn with_tls(mut self, tls: crate::TlsSettings) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+}
@@ -0,0 +1,120 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::sync::Mutex;
+
+use crate::{
+    HttpContext, HttpFailResult, HttpMiddlewareGroup, HttpNotFoundResultFactory, HttpOkResult,
+    HttpRequestData, HttpResolvedMiddleware, HttpServerMiddleware,
+};
+
+/// A middleware that wraps the rest of the chain: it gets the request first, decides whether —
+/// and how many times — to pass it on through [`HttpServerMiddlewareNext::run`], and gets the
+/// result back to change before it goes out. Adds response headers, times handlers, translates
+/// errors, retries.
+///
+/// Added with [`MyHttpServer::add_around_middleware`](crate::MyHttpServer::add_around_middleware)
+/// into the same chain as the [`HttpServerMiddleware`]s, in the order they are added.
+#[async_trait]
+pub trait HttpServerAroundMiddleware {
+    async fn handle_request(
+        &self,
+        ctx: &mut HttpContext,
+        next: HttpServerMiddlewareNext<'_>,
+    ) -> Result<HttpOkResult, HttpFailResult>;
+}
+
+/// One link of the middleware chain of a server.
+#[derive(Clone)]
+pub enum HttpServerMiddlewareItem {
+    /// Answers the request or passes it on — see [`HttpServerMiddleware`].
+    Middleware(Arc<dyn HttpServerMiddleware + Send + Sync + 'static>),
+    /// Wraps the rest of the chain — see [`HttpServerAroundMiddleware`].
+    Around(Arc<dyn HttpServerAroundMiddleware + Send + Sync + 'static>),
+    /// Middlewares for the requests under a path prefix — see [`HttpMiddlewareGroup`].
+    Group(HttpMiddlewareGroup),
+}
+
+/// The middlewares after the one being called, up to and including the `404` a request nobody
+/// answers gets. Those of groups the request is not under are skipped.
+pub struct HttpServerMiddlewareNext<'s> {
+    middlewares: &'s [HttpResolvedMiddleware],
+    client_id: &'s Mutex<Option<String>>,
+    not_found: &'s HttpNotFound<'s>,
+}
+
+/// What the end of the chain needs to answer a request nobody answered.
+pub(crate) struct HttpNotFound<'s> {
+    pub request_data: &'s HttpRequestData,
+    pub result_factory: Option<&'s (dyn HttpNotFoundResultFactory + Send + Sync + 'static)>,
+}
+
+impl<'s> HttpServerMiddlewareNext<'s> {
+    pub(crate) fn new(
+        middlewares: &'s [HttpResolvedMiddleware],
+        client_id: &'s Mutex<Option<String>>,
+        not_found: &'s HttpNotFound<'s>,
+    ) -> Self {
+        Self {
+            middlewares,
+            client_id,
+            not_found,
+        }
+    }
+
+    /// Passes the request on to the rest of the chain and gives back what it answered.
+    pub async fn run(&self, ctx: &mut HttpContext) -> Result<HttpOkResult, HttpFailResult> {
+        for (index, resolved) in self.middlewares.iter().enumerate() {
+            if let Some(scope) = resolved.scope.as_ref() {
+                if !scope.is_matching(&ctx.request.http_path, &ctx.request.method) {
+                    continue;
+                }
+            }
+
+            match &resolved.middleware {
+                HttpServerMiddlewareItem::Middleware(middleware) => {
+                    let result = middleware.handle_request(ctx).await;
+
+                    self.remember_client_id(ctx).await;
+
+                    if let Some(result) = result {
+                        return result;
+                    }
+                }
+                HttpServerMiddlewareItem::Around(middleware) => {
+                    let next = HttpServerMiddlewareNext {
+                        middlewares: &self.middlewares[index + 1..],
+                        client_id: self.client_id,
+                        not_found: self.not_found,
+                    };
+
+                    let result = middleware.handle_request(ctx, next).await;
+
+                    self.remember_client_id(ctx).await;
+
+                    return result;
+                }
+                HttpServerMiddlewareItem::Group(_) => {
+                    unreachable!("Middleware groups are resolved when the server starts")
+                }
+            }
+        }
+
+        match self.not_found.result_factory {
+            Some(factory) => Err(factory.get_not_found_result(self.not_found.request_data)),
+            None => Err(crate::get_default_not_found_result()),
+        }
+    }
+
+    /// The client the request was authenticated as, for the log and the telemetry of a request
+    /// that never makes it back with its context — one that panicked or timed out.
+    async fn remember_client_id(&self, ctx: &HttpContext) {
+        if let Some(credentials) = ctx.credentials.as_ref() {
+            let mut client_id = self.client_id.lock().await;
+
+            if client_id.is_none() {
+                *client_id = Some(credentials.get_id().to_string());
+            }
+        }
+    }
+}
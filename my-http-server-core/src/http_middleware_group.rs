@@ -0,0 +1,260 @@
+use std::sync::Arc;
+
+use hyper::Method;
+
+use crate::{HttpPath, HttpServerAroundMiddleware, HttpServerMiddleware, HttpServerMiddlewareItem};
+
+/// Middlewares that only see the requests under a path prefix — optionally of some methods only.
+/// Added to the server with
+/// [`MyHttpServer::add_middleware_group`](crate::MyHttpServer::add_middleware_group), or to
+/// another group with [`add_group`](Self::add_group): a nested group's prefix is relative to its
+/// parent's, and it only runs for the requests its parent runs for.
+///
+/// ```ignore
+/// let mut admin = HttpMiddlewareGroup::new("/api/admin");
+/// admin.add_middleware(Arc::new(AdminAuthMiddleware));
+///
+/// server.add_middleware_group(admin);
+/// server.add_middleware(Arc::new(controllers));
+/// ```
+///
+/// A group is a part of the server's chain, not a chain of its own: a request none of its
+/// middlewares answered goes on to what comes after the group, and an around middleware in a
+/// group wraps everything after it that the request gets to.
+#[derive(Clone)]
+pub struct HttpMiddlewareGroup {
+    prefix: HttpPath,
+    methods: Option<Vec<Method>>,
+    middlewares: Vec<HttpServerMiddlewareItem>,
+}
+
+impl HttpMiddlewareGroup {
+    /// Matched by whole segments and case-insensitively, the way routes are: `/api` covers
+    /// `/api` and `/API/users`, but not `/apis`.
+    pub fn new(prefix: &str) -> Self {
+        Self {
+            prefix: HttpPath::from_str(prefix),
+            methods: None,
+            middlewares: Vec::new(),
+        }
+    }
+
+    /// Only requests with one of `methods`. All methods by default.
+    pub fn with_methods(mut self, methods: impl IntoIterator<Item = Method>) -> Self {
+        self.methods = Some(methods.into_iter().collect());
+        self
+    }
+
+    pub fn add_middleware(
+        &mut self,
+        middleware: Arc<dyn HttpServerMiddleware + Send + Sync + 'static>,
+    ) {
+        self.middlewares
+            .push(HttpServerMiddlewareItem::Middleware(middleware));
+    }
+
+    pub fn add_around_middleware(
+        &mut self,
+        middleware: Arc<dyn HttpServerAroundMiddleware + Send + Sync + 'static>,
+    ) {
+        self.middlewares
+            .push(HttpServerMiddlewareItem::Around(middleware));
+    }
+
+    pub fn add_group(&mut self, group: HttpMiddlewareGroup) {
+        self.middlewares
+            .push(HttpServerMiddlewareItem::Group(group));
+    }
+}
+
+/// Which requests a middleware of a group runs for — its own group and every group above it.
+#[derive(Debug)]
+pub(crate) struct HttpMiddlewareScope {
+    prefix: HttpPath,
+    methods: Option<Vec<Method>>,
+}
+
+impl HttpMiddlewareScope {
+    fn nested(parent: Option<&HttpMiddlewareScope>, group: &HttpMiddlewareGroup) -> Self {
+        let Some(parent) = parent else {
+            return Self {
+                prefix: group.prefix.clone(),
+                methods: group.methods.clone(),
+            };
+        };
+
+        let prefix = if group.prefix.is_root() {
+            parent.prefix.clone()
+        } else {
+            HttpPath::from_string(format!(
+                "{}{}",
+                parent.prefix.as_str().trim_end_matches('/'),
+                group.prefix.as_str()
+            ))
+        };
+
+        let methods = match (&parent.methods, &group.methods) {
+            (Some(parent_methods), Some(methods)) => Some(
+                methods
+                    .iter()
+                    .filter(|itm| parent_methods.contains(itm))
+                    .cloned()
+                    .collect(),
+            ),
+            (Some(methods), None) | (None, Some(methods)) => Some(methods.clone()),
+            (None, None) => None,
+        };
+
+        Self { prefix, methods }
+    }
+
+    pub fn is_matching(&self, path: &HttpPath, method: &Method) -> bool {
+        if let Some(methods) = self.methods.as_ref() {
+            if !methods.contains(method) {
+                return false;
+            }
+        }
+
+        path.is_starting_with(&self.prefix)
+    }
+}
+
+/// A middleware of the server's chain with the groups it was added through resolved — done once,
+/// when the server starts.
+pub struct HttpResolvedMiddleware {
+    pub(crate) scope: Option<Arc<HttpMiddlewareScope>>,
+    pub(crate) middleware: HttpServerMiddlewareItem,
+}
+
+/// Flattens the groups of `middlewares` into one chain, every middleware tagged with the scope of
+/// the group it is in.
+pub(crate) fn resolve_middlewares(
+    middlewares: Vec<HttpServerMiddlewareItem>,
+) -> Vec<HttpResolvedMiddleware> {
+    let mut result = Vec::new();
+    resolve_into(&mut result, None, middlewares);
+    result
+}
+
+fn resolve_into(
+    result: &mut Vec<HttpResolvedMiddleware>,
+    scope: Option<Arc<HttpMiddlewareScope>>,
+    middlewares: Vec<HttpServerMiddlewareItem>,
+) {
+    for middleware in middlewares {
+        match middleware {
+            HttpServerMiddlewareItem::Group(group) => {
+                let group_scope = HttpMiddlewareScope::nested(scope.as_deref(), &group);
+                resolve_into(result, Some(Arc::new(group_scope)), group.middlewares);
+            }
+            middleware => result.push(HttpResolvedMiddleware {
+                scope: scope.clone(),
+                middleware,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::{HttpContext, HttpFailResult, HttpOkResult};
+
+    struct DummyMiddleware;
+
+    #[async_trait]
+    impl HttpServerMiddleware for DummyMiddleware {
+        async fn handle_request(
+            &self,
+            _ctx: &mut HttpContext,
+        ) -> Option<Result<HttpOkResult, HttpFailResult>> {
+            None
+        }
+    }
+
+    fn dummy() -> Arc<DummyMiddleware> {
+        Arc::new(DummyMiddleware)
+    }
+
+    fn is_matching(resolved: &HttpResolvedMiddleware, path: &str, method: Method) -> bool {
+        match resolved.scope.as_ref() {
+            Some(scope) => scope.is_matching(&HttpPath::from_str(path), &method),
+            None => true,
+        }
+    }
+
+    #[test]
+    fn groups_are_flattened_in_order() {
+        let mut group = HttpMiddlewareGroup::new("/api");
+        group.add_middleware(dummy());
+        group.add_middleware(dummy());
+
+        let resolved = resolve_middlewares(vec![
+            HttpServerMiddlewareItem::Middleware(dummy()),
+            HttpServerMiddlewareItem::Group(group),
+            HttpServerMiddlewareItem::Middleware(dummy()),
+        ]);
+
+        assert_eq!(resolved.len(), 4);
+        assert!(resolved[0].scope.is_none());
+        assert!(resolved[1].scope.is_some());
+        assert!(Arc::ptr_eq(
+            resolved[1].scope.as_ref().unwrap(),
+            resolved[2].scope.as_ref().unwrap()
+        ));
+        assert!(resolved[3].scope.is_none());
+    }
+
+    #[test]
+    fn prefix_is_matched_by_segments() {
+        let mut group = HttpMiddlewareGroup::new("/api/admin");
+        group.add_middleware(dummy());
+
+        let resolved = resolve_middlewares(vec![HttpServerMiddlewareItem::Group(group)]);
+
+        assert!(is_matching(&resolved[0], "/api/admin", Method::GET));
+        assert!(is_matching(&resolved[0], "/API/Admin/users", Method::GET));
+        assert!(!is_matching(
+            &resolved[0],
+            "/api/administrators",
+            Method::GET
+        ));
+        assert!(!is_matching(&resolved[0], "/api", Method::GET));
+    }
+
+    #[test]
+    fn nested_group_is_under_its_parent() {
+        let mut nested = HttpMiddlewareGroup::new("/admin");
+        nested.add_middleware(dummy());
+
+        let mut group = HttpMiddlewareGroup::new("/api/");
+        group.add_group(nested);
+
+        let resolved = resolve_middlewares(vec![HttpServerMiddlewareItem::Group(group)]);
+
+        assert_eq!(resolved.len(), 1);
+        assert!(is_matching(&resolved[0], "/api/admin/users", Method::GET));
+        assert!(!is_matching(&resolved[0], "/admin/users", Method::GET));
+    }
+
+    #[test]
+    fn methods_of_nested_groups_are_intersected() {
+        let mut nested = HttpMiddlewareGroup::new("/").with_methods([Method::POST, Method::PUT]);
+        nested.add_middleware(dummy());
+
+        let mut group = HttpMiddlewareGroup::new("/api").with_methods([Method::GET, Method::POST]);
+        group.add_middleware(dummy());
+        group.add_group(nested);
+
+        let resolved = resolve_middlewares(vec![HttpServerMiddlewareItem::Group(group)]);
+
+        assert!(is_matching(&resolved[0], "/api", Method::GET));
+        assert!(!is_matching(&resolved[0], "/api", Method::PUT));
+
+        assert!(is_matching(&resolved[1], "/api", Method::POST));
+        assert!(!is_matching(&resolved[1], "/api", Method::GET));
+        assert!(!is_matching(&resolved[1], "/api", Method::PUT));
+    }
+}
@@ -299,8 +299,11 @@ impl HttpResultBuilder {
             let body = match &self.output {
                 HttpOutput::Content { content, .. } => content,
                 HttpOutput::File { content, .. } => content,
-                HttpOutput::Empty | HttpOutput::Redirect { .. } => return self,
-                HttpOutput::Raw(_) => panic!("Can not compress raw response"),
+                // A raw response is compressed, if at all, by the server — see
+                // `MyHttpServer::set_response_compression`.
+                HttpOutput::Empty | HttpOutput::Redirect { .. } | HttpOutput::Raw(_) => {
+                    return self
+                }
             };
 
             if body.len() <= threshold {
@@ -390,6 +393,20 @@ mod tests {
         }
     }
 
+    #[test]
+    fn raw_is_noop() {
+        let (stream, _producer) = HttpOutput::as_stream(1);
+        let output = stream.get_result().unwrap().output;
+
+        let built = HttpResultBuilder { output }.with_compression(1).build();
+        match built {
+            HttpOutput::Raw(response) => {
+                assert!(response.headers().get("Content-Encoding").is_none());
+            }
+            other => panic!("expected Raw, got {:?}", other),
+        }
+    }
+
     #[test]
     fn body_below_threshold_is_unchanged() {
         let raw = vec![b'a'; 5 * 1024];
@@ -0,0 +1,147 @@
+use std::marker::PhantomData;
+
+use futures::channel::mpsc::SendError;
+use hyper::HeaderMap;
+use serde::Serialize;
+
+use crate::HttpOutputProducer;
+
+/// The `Content-Type` of an [`HttpOutput::as_ndjson`](crate::HttpOutput::as_ndjson) response.
+pub const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// What sending an item fails with.
+#[derive(Debug)]
+pub enum HttpNdJsonSendError {
+    /// The item could not be serialized. Nothing of it was sent, and the response goes on.
+    Serialize(serde_json::Error),
+    /// The response is gone and nothing more will reach the client.
+    Disconnected(SendError),
+}
+
+impl std::fmt::Display for HttpNdJsonSendError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HttpNdJsonSendError::Serialize(err) => write!(f, "Can not serialize the item: {}", err),
+            HttpNdJsonSendError::Disconnected(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for HttpNdJsonSendError {}
+
+/// Sends the items of an [`HttpOutput::as_ndjson`](crate::HttpOutput::as_ndjson) response, each
+/// as JSON on a line of its own — a client can parse every line as it arrives, without waiting
+/// for the whole array.
+pub struct HttpNdJsonProducer<T: Serialize> {
+    producer: HttpOutputProducer,
+    item: PhantomData<fn(&T)>,
+}
+
+impl<T: Serialize> HttpNdJsonProducer<T> {
+    pub(crate) fn new(producer: HttpOutputProducer) -> Self {
+        Self {
+            producer,
+            item: PhantomData,
+        }
+    }
+
+    pub async fn send(&mut self, item: &T) -> Result<(), HttpNdJsonSendError> {
+        let mut line = serde_json::to_vec(item).map_err(HttpNdJsonSendError::Serialize)?;
+        line.push(b'\n');
+
+        self.producer
+            .send(line)
+            .await
+            .map_err(HttpNdJsonSendError::Disconnected)
+    }
+
+    /// See [`HttpOutputProducer::send_trailers`].
+    pub async fn send_trailers(self, trailers: HeaderMap) -> Result<(), SendError> {
+        self.producer.send_trailers(trailers).await
+    }
+
+    /// See [`HttpOutputProducer::abort`].
+    pub async fn abort(self, reason: impl Into<String>) {
+        self.producer.abort(reason).await
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.producer.is_connected()
+    }
+
+    pub async fn disconnected(&mut self) {
+        self.producer.disconnected().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http_body_util::BodyExt;
+    use serde::Serialize;
+
+    use crate::HttpOutput;
+
+    #[derive(Serialize)]
+    struct Item {
+        id: u32,
+    }
+
+    #[tokio::test]
+    async fn test_item_per_line() {
+        let (output, mut producer) = HttpOutput::as_ndjson::<Item>(4);
+
+        let mut response = match output.get_result().unwrap().output {
+            HttpOutput::Raw(response) => response,
+            _ => panic!("Streamed response is expected to be raw"),
+        };
+
+        assert_eq!(
+            response.headers()["content-type"],
+            super::NDJSON_CONTENT_TYPE
+        );
+
+        producer.send(&Item { id: 1 }).await.unwrap();
+        producer.send(&Item { id: 2 }).await.unwrap();
+        drop(producer);
+
+        let body = response.body_mut().collect().await.unwrap().to_bytes();
+        assert_eq!(body.as_ref(), b"{\"id\":1}\n{\"id\":2}\n");
+    }
+
+    /// Fails to serialize when `broken` is set.
+    struct MaybeBroken {
+        broken: bool,
+    }
+
+    impl Serialize for MaybeBroken {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            if self.broken {
+                return Err(serde::ser::Error::custom("broken"));
+            }
+
+            serializer.serialize_u32(1)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_item_that_can_not_be_serialized_is_an_error() {
+        let (output, mut producer) = HttpOutput::as_ndjson::<MaybeBroken>(4);
+
+        let mut response = match output.get_result().unwrap().output {
+            HttpOutput::Raw(response) => response,
+            _ => panic!("Streamed response is expected to be raw"),
+        };
+
+        let err = producer
+            .send(&MaybeBroken { broken: true })
+            .await
+            .unwrap_err();
+        assert!(matches!(err, super::HttpNdJsonSendError::Serialize(_)));
+
+        producer.send(&MaybeBroken { broken: false }).await.unwrap();
+        drop(producer);
+
+        let body = response.body_mut().collect().await.unwrap().to_bytes();
+        assert_eq!(body.as_ref(), b"1\n");
+    }
+}
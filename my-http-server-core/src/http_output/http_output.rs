@@ -191,13 +191,53 @@ impl HttpOutput {
 
     pub fn as_stream(queue_size: usize) -> (HttpOutputAsStream, HttpOutputProducer) {
         let (tx, rx) = futures::channel::mpsc::channel(queue_size);
+        let (closed_tx, closed_rx) = futures::channel::oneshot::channel();
 
-        let output = HttpOutputAsStream::new(rx);
-        let producer = HttpOutputProducer::new(tx);
+        let output = HttpOutputAsStream::create(rx, closed_rx);
+        let producer = HttpOutputProducer::create(tx, closed_tx);
 
         (output, producer)
     }
 
+    /// A stream of `T`s as newline-delimited JSON, sent through the producer the way
+    /// [`as_stream`](Self::as_stream) sends bytes.
+    pub fn as_ndjson<T: Serialize>(
+        queue_size: usize,
+    ) -> (HttpOutputAsStream, HttpNdJsonProducer<T>) {
+        let (output, producer) = Self::as_stream(queue_size);
+
+        (
+            output.with_header("content-type", NDJSON_CONTENT_TYPE),
+            HttpNdJsonProducer::new(producer),
+        )
+    }
+
+    /// A Server-Sent Events response: the action returns the output and hands the producer to
+    /// whatever sends the events — a spawned task, typically, since the response only starts
+    /// going out once the action has returned. Up to `queue_size` events wait to be written.
+    ///
+    /// ```ignore
+    /// let (output, producer) = HttpOutput::as_sse(16);
+    /// let from_id = ctx.request.get_last_event_id().map(|id| id.to_string());
+    ///
+    /// tokio::spawn(async move {
+    ///     for (id, price) in prices.subscribe(from_id) {
+    ///         let event = HttpSseEvent::from_json(&price).with_event("price").with_id(id);
+    ///
+    ///         if producer.send(event).await.is_err() {
+    ///             break;
+    ///         }
+    ///     }
+    /// });
+    ///
+    /// output.get_result()
+    /// ```
+    pub fn as_sse(queue_size: usize) -> (HttpOutputAsSse, HttpSseProducer) {
+        let (tx, rx) = tokio::sync::mpsc::channel(queue_size);
+
+        (HttpOutputAsSse::new(rx), HttpSseProducer::new(tx))
+    }
+
     pub fn as_redirect(url: impl Into<String>, permanent: bool) -> HttpResultBuilder {
         let output = Self::Redirect {
             url: url.into(),
@@ -0,0 +1,231 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use bytes::Bytes;
+use hyper::body::{Body, Frame};
+use rust_extensions::StrOrString;
+use tokio::{sync::mpsc::Receiver, time::Sleep};
+
+use crate::{HttpFailResult, HttpOkResult};
+
+use super::sse_comment_to_bytes;
+
+/// How long a Server-Sent Events response stays silent before it sends a keep-alive comment —
+/// shorter than the idle timeouts proxies and load balancers commonly close connections at.
+pub const DEFAULT_SSE_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// A `text/event-stream` response — the half of [`HttpOutput::as_sse`](crate::HttpOutput::as_sse)
+/// the action returns, while the [`HttpSseProducer`](crate::HttpSseProducer) sends the events.
+///
+/// Goes out with `Cache-Control: no-cache` and `X-Accel-Buffering: no`, so neither caches nor
+/// nginx hold the events back.
+pub struct HttpOutputAsSse {
+    rx: Receiver<Bytes>,
+    headers: Vec<(String, String)>,
+    keep_alive: Option<Duration>,
+}
+
+impl HttpOutputAsSse {
+    pub(crate) fn new(rx: Receiver<Bytes>) -> Self {
+        Self {
+            rx,
+            headers: vec![
+                ("content-type".to_string(), "text/event-stream".to_string()),
+                ("cache-control".to_string(), "no-cache".to_string()),
+                ("x-accel-buffering".to_string(), "no".to_string()),
+            ],
+            keep_alive: Some(DEFAULT_SSE_KEEP_ALIVE),
+        }
+    }
+
+    pub fn with_header<'k, 'v>(
+        mut self,
+        key: impl Into<StrOrString<'k>>,
+        value: impl Into<StrOrString<'v>>,
+    ) -> Self {
+        let key = key.into();
+        let value = value.into();
+
+        self.headers.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// A comment goes out after `keep_alive` without an event. Besides keeping the connection
+    /// open, it is what finds a client that went away without closing it: the write fails, the
+    /// response is dropped and the producer learns it is disconnected. Defaults to
+    /// [`DEFAULT_SSE_KEEP_ALIVE`].
+    pub fn with_keep_alive(mut self, keep_alive: Duration) -> Self {
+        if keep_alive.is_zero() {
+            panic!("Server-sent events keep-alive interval can not be zero");
+        }
+
+        self.keep_alive = Some(keep_alive);
+        self
+    }
+
+    pub fn without_keep_alive(mut self) -> Self {
+        self.keep_alive = None;
+        self
+    }
+
+    pub fn get_result(self) -> Result<HttpOkResult, HttpFailResult> {
+        use http_body_util::BodyExt;
+
+        let body = SseBody {
+            rx: self.rx,
+            keep_alive: self.keep_alive.map(|interval| KeepAlive {
+                interval,
+                sleep: Box::pin(tokio::time::sleep(interval)),
+            }),
+        };
+
+        let mut builder = hyper::Response::builder();
+
+        for header in self.headers {
+            builder = builder.header(header.0, header.1);
+        }
+
+        let response = builder.body(body.boxed()).unwrap();
+
+        Ok(HttpOkResult {
+            write_telemetry: false,
+            #[cfg(feature = "with-telemetry")]
+            add_telemetry_tags: my_telemetry::TelemetryEventTagsBuilder::new(),
+            output: super::HttpOutput::Raw(response),
+        })
+    }
+}
+
+struct KeepAlive {
+    interval: Duration,
+    sleep: Pin<Box<Sleep>>,
+}
+
+impl KeepAlive {
+    fn restart(&mut self) {
+        let deadline = tokio::time::Instant::now() + self.interval;
+        self.sleep.as_mut().reset(deadline);
+    }
+}
+
+/// The events as they are sent, with a keep-alive comment whenever none came for a while. Ends
+/// when the producer is dropped; dropping it — hyper does when the client is gone — closes the
+/// producer's channel.
+struct SseBody {
+    rx: Receiver<Bytes>,
+    keep_alive: Option<KeepAlive>,
+}
+
+impl Body for SseBody {
+    type Data = Bytes;
+    type Error = String;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+
+        match this.rx.poll_recv(cx) {
+            Poll::Ready(Some(bytes)) => {
+                if let Some(keep_alive) = this.keep_alive.as_mut() {
+                    keep_alive.restart();
+                }
+
+                return Poll::Ready(Some(Ok(Frame::data(bytes))));
+            }
+            Poll::Ready(None) => return Poll::Ready(None),
+            Poll::Pending => {}
+        }
+
+        if let Some(keep_alive) = this.keep_alive.as_mut() {
+            if keep_alive.sleep.as_mut().poll(cx).is_ready() {
+                keep_alive.restart();
+                return Poll::Ready(Some(Ok(Frame::data(sse_comment_to_bytes("").into()))));
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http_body_util::BodyExt;
+
+    use crate::{HttpOutput, HttpSseEvent};
+
+    async fn next_frame(body: &mut my_hyper_utils::MyHttpResponse) -> Option<String> {
+        let frame = body.body_mut().frame().await?.unwrap();
+        Some(String::from_utf8(frame.into_data().unwrap().to_vec()).unwrap())
+    }
+
+    fn into_response(result: crate::HttpOkResult) -> my_hyper_utils::MyHttpResponse {
+        match result.output {
+            HttpOutput::Raw(response) => response,
+            _ => panic!("Server-sent events response is expected to be raw"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_events_and_headers() {
+        let (output, producer) = HttpOutput::as_sse(4);
+        let mut response = into_response(output.get_result().unwrap());
+
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+        assert_eq!(response.headers()["cache-control"], "no-cache");
+
+        producer
+            .send(HttpSseEvent::new("hello").with_event("greeting"))
+            .await
+            .unwrap();
+        producer.send_data("world").await.unwrap();
+        drop(producer);
+
+        assert_eq!(
+            next_frame(&mut response).await.unwrap(),
+            "event: greeting\ndata: hello\n\n"
+        );
+        assert_eq!(next_frame(&mut response).await.unwrap(), "data: world\n\n");
+        assert_eq!(next_frame(&mut response).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_keep_alive_while_idle() {
+        let keep_alive = std::time::Duration::from_millis(200);
+
+        let (output, producer) = HttpOutput::as_sse(4);
+        let mut response = into_response(output.with_keep_alive(keep_alive).get_result().unwrap());
+
+        let started = std::time::Instant::now();
+        assert_eq!(next_frame(&mut response).await.unwrap(), ":\n\n");
+        assert!(started.elapsed() >= keep_alive * 3 / 4);
+
+        // An event puts the next keep-alive off.
+        tokio::time::sleep(keep_alive / 2).await;
+        producer.send_data("x").await.unwrap();
+        assert_eq!(next_frame(&mut response).await.unwrap(), "data: x\n\n");
+
+        let started = std::time::Instant::now();
+        assert_eq!(next_frame(&mut response).await.unwrap(), ":\n\n");
+        assert!(started.elapsed() >= keep_alive * 3 / 4);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_response_disconnects_the_producer() {
+        let (output, producer) = HttpOutput::as_sse(4);
+        let response = into_response(output.get_result().unwrap());
+
+        assert!(producer.is_connected());
+
+        drop(response);
+
+        producer.disconnected().await;
+        assert!(!producer.is_connected());
+        assert!(producer.send_data("x").await.is_err());
+    }
+}
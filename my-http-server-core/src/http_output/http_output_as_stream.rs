@@ -1,20 +1,51 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
 use bytes::Bytes;
-use http_body_util::StreamBody;
+use futures::StreamExt;
+use hyper::body::{Body, Frame};
 use rust_extensions::StrOrString;
 
 use crate::{HttpFailResult, HttpOkResult};
 
+/// What goes from an [`HttpOutputProducer`](crate::HttpOutputProducer) to the response: a frame,
+/// or the reason the producer aborted the stream.
+pub(crate) type HttpOutputStreamItem = Result<hyper::body::Frame<Bytes>, String>;
+
 pub struct HttpOutputAsStream {
-    rx: futures::channel::mpsc::Receiver<Result<hyper::body::Frame<Bytes>, hyper::Error>>,
+    rx: OutputRx,
+    closed: Option<futures::channel::oneshot::Receiver<()>>,
     headers: Vec<(String, String)>,
 }
 
+/// The two kinds of channel an [`HttpOutputProducer`](crate::HttpOutputProducer) sends through.
+enum OutputRx {
+    Items(futures::channel::mpsc::Receiver<HttpOutputStreamItem>),
+    Frames(futures::channel::mpsc::Receiver<Result<hyper::body::Frame<Bytes>, hyper::Error>>),
+}
+
 impl HttpOutputAsStream {
+    /// The response for the frames an [`HttpOutputProducer::new`](crate::HttpOutputProducer::new)
+    /// sends into the other end of `rx`.
     pub fn new(
         rx: futures::channel::mpsc::Receiver<Result<hyper::body::Frame<Bytes>, hyper::Error>>,
     ) -> Self {
         Self {
-            rx,
+            rx: OutputRx::Frames(rx),
+            closed: None,
+            headers: Vec::new(),
+        }
+    }
+
+    pub(crate) fn create(
+        rx: futures::channel::mpsc::Receiver<HttpOutputStreamItem>,
+        closed: futures::channel::oneshot::Receiver<()>,
+    ) -> Self {
+        Self {
+            rx: OutputRx::Items(rx),
+            closed: Some(closed),
             headers: Vec::new(),
         }
     }
@@ -30,14 +61,22 @@ impl HttpOutputAsStream {
         self.headers.push((key.to_string(), value.to_string()));
         self
     }
+
+    /// Declares a trailer the producer is going to send with
+    /// [`send_trailers`](crate::HttpOutputProducer::send_trailers). HTTP/2 sends trailers either
+    /// way; HTTP/1.1 sends only the declared ones, and only to a client that asked for them with
+    /// `TE: trailers`.
+    pub fn with_trailer(self, name: &str) -> Self {
+        self.with_header("trailer", name)
+    }
+
     pub fn get_result(self) -> Result<HttpOkResult, HttpFailResult> {
         use http_body_util::BodyExt;
 
-        let rx = self.rx;
-
-        //   let stream_body = StreamBody::new(rx);
-        let stream_body = StreamBody::new(rx);
-        let boxed_body = stream_body.map_err(|e: hyper::Error| e.to_string()).boxed();
+        let body = StreamedBody {
+            rx: self.rx,
+            _closed: self.closed,
+        };
 
         let mut builder = hyper::Response::builder();
 
@@ -45,7 +84,7 @@ impl HttpOutputAsStream {
             builder = builder.header(header.0, header.1);
         }
 
-        let response = builder.body(boxed_body).unwrap();
+        let response = builder.body(body.boxed()).unwrap();
 
         Ok(HttpOkResult {
             write_telemetry: false,
@@ -55,3 +94,30 @@ impl HttpOutputAsStream {
         })
     }
 }
+
+/// The frames as the producer sends them. An abort is a body error: hyper resets the HTTP/2
+/// stream, and closes an HTTP/1.1 connection before the final chunk — either way the client
+/// can tell the body is not complete.
+struct StreamedBody {
+    rx: OutputRx,
+    /// Dropped with the body — hyper drops it when the client is gone — which is what
+    /// [`HttpOutputProducer::disconnected`](crate::HttpOutputProducer::disconnected) waits for.
+    _closed: Option<futures::channel::oneshot::Receiver<()>>,
+}
+
+impl Body for StreamedBody {
+    type Data = Bytes;
+    type Error = String;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        match &mut self.get_mut().rx {
+            OutputRx::Items(rx) => rx.poll_next_unpin(cx),
+            OutputRx::Frames(rx) => rx
+                .poll_next_unpin(cx)
+                .map(|itm| itm.map(|itm| itm.map_err(|err| err.to_string()))),
+        }
+    }
+}
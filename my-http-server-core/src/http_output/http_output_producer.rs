@@ -1,15 +1,47 @@
 use bytes::Bytes;
 use futures::{channel::mpsc::SendError, SinkExt};
+use hyper::HeaderMap;
 
+use super::HttpOutputStreamItem;
+
+/// Sends the body of an [`HttpOutput::as_stream`](crate::HttpOutput::as_stream) response. The
+/// body ends when the producer is dropped — or with
+/// [`send_trailers`](Self::send_trailers) or [`abort`](Self::abort), which take it.
 pub struct HttpOutputProducer {
-    tx: futures::channel::mpsc::Sender<Result<hyper::body::Frame<Bytes>, hyper::Error>>,
+    tx: ProducerTx,
+    closed: Option<futures::channel::oneshot::Sender<()>>,
+}
+
+/// The channel of [`HttpOutput::as_stream`](crate::HttpOutput::as_stream), or one the caller made
+/// for [`HttpOutputProducer::new`] — which has no way to carry the reason of an abort.
+enum ProducerTx {
+    Items(futures::channel::mpsc::Sender<HttpOutputStreamItem>),
+    Frames(futures::channel::mpsc::Sender<Result<hyper::body::Frame<Bytes>, hyper::Error>>),
 }
 
 impl HttpOutputProducer {
+    /// A producer for the other end of a channel handed to
+    /// [`HttpOutputAsStream::new`](crate::HttpOutputAsStream::new).
+    /// [`HttpOutput::as_stream`](crate::HttpOutput::as_stream) makes both with a channel of its
+    /// own, and only a producer made that way can [`abort`](Self::abort) with an error or tell
+    /// when the response is [gone](Self::disconnected).
     pub fn new(
         tx: futures::channel::mpsc::Sender<Result<hyper::body::Frame<Bytes>, hyper::Error>>,
     ) -> Self {
-        Self { tx }
+        Self {
+            tx: ProducerTx::Frames(tx),
+            closed: None,
+        }
+    }
+
+    pub(crate) fn create(
+        tx: futures::channel::mpsc::Sender<HttpOutputStreamItem>,
+        closed: futures::channel::oneshot::Sender<()>,
+    ) -> Self {
+        Self {
+            tx: ProducerTx::Items(tx),
+            closed: Some(closed),
+        }
     }
 
     pub async fn send(&mut self, bytes: Vec<u8>) -> Result<(), SendError> {
@@ -17,6 +49,135 @@ impl HttpOutputProducer {
 
         let frame = hyper::body::Frame::data(bytes);
 
-        self.tx.send(Ok(frame)).await
+        self.send_frame(frame).await
+    }
+
+    async fn send_frame(&mut self, frame: hyper::body::Frame<Bytes>) -> Result<(), SendError> {
+        match &mut self.tx {
+            ProducerTx::Items(tx) => tx.send(Ok(frame)).await,
+            ProducerTx::Frames(tx) => tx.send(Ok(frame)).await,
+        }
+    }
+
+    /// Ends the body with `trailers` — a checksum of what was sent, or the outcome of work that
+    /// was still going on when the headers went out. See
+    /// [`HttpOutputAsStream::with_trailer`](crate::HttpOutputAsStream::with_trailer) for what
+    /// HTTP/1.1 needs to pass them on.
+    pub async fn send_trailers(mut self, trailers: HeaderMap) -> Result<(), SendError> {
+        self.send_frame(hyper::body::Frame::trailers(trailers))
+            .await
+    }
+
+    /// Ends the body as failed, after whatever was sent before. The client sees an error — a
+    /// reset HTTP/2 stream, or an HTTP/1.1 connection closed before the final chunk — and not a
+    /// body that merely looks shorter than expected, as it would if the producer were dropped.
+    ///
+    /// A producer made with [`new`](Self::new) can only end the body.
+    pub async fn abort(mut self, reason: impl Into<String>) {
+        match &mut self.tx {
+            ProducerTx::Items(tx) => {
+                let _ = tx.send(Err(reason.into())).await;
+            }
+            ProducerTx::Frames(tx) => tx.close_channel(),
+        }
+    }
+
+    /// `false` once the response is gone — the client disconnected, or the server dropped the
+    /// response without sending it.
+    pub fn is_connected(&self) -> bool {
+        match &self.tx {
+            ProducerTx::Items(tx) => !tx.is_closed(),
+            ProducerTx::Frames(tx) => !tx.is_closed(),
+        }
+    }
+
+    /// Resolves once the response is gone, for a producer that waits long between sends to stop
+    /// waiting. Never resolves for a producer made with [`new`](Self::new) — a failed send is what
+    /// tells that one.
+    pub async fn disconnected(&mut self) {
+        match self.closed.as_mut() {
+            Some(closed) => closed.cancellation().await,
+            None => std::future::pending().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http_body_util::BodyExt;
+    use hyper::HeaderMap;
+
+    use crate::HttpOutput;
+
+    fn into_response(output: crate::HttpOutputAsStream) -> my_hyper_utils::MyHttpResponse {
+        match output.get_result().unwrap().output {
+            HttpOutput::Raw(response) => response,
+            _ => panic!("Streamed response is expected to be raw"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_trailers_follow_the_data() {
+        let (output, mut producer) = HttpOutput::as_stream(4);
+        let mut response = into_response(output.with_trailer("x-checksum"));
+
+        assert_eq!(response.headers()["trailer"], "x-checksum");
+
+        let mut trailers = HeaderMap::new();
+        trailers.insert("x-checksum", "abc".parse().unwrap());
+
+        producer.send(b"data".to_vec()).await.unwrap();
+        producer.send_trailers(trailers).await.unwrap();
+
+        let frame = response.body_mut().frame().await.unwrap().unwrap();
+        assert_eq!(frame.into_data().unwrap().as_ref(), b"data");
+
+        let frame = response.body_mut().frame().await.unwrap().unwrap();
+        assert_eq!(frame.into_trailers().unwrap()["x-checksum"], "abc");
+
+        assert!(response.body_mut().frame().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_abort_is_an_error_after_the_data() {
+        let (output, mut producer) = HttpOutput::as_stream(4);
+        let mut response = into_response(output);
+
+        producer.send(b"data".to_vec()).await.unwrap();
+        producer.abort("Export failed").await;
+
+        let frame = response.body_mut().frame().await.unwrap().unwrap();
+        assert_eq!(frame.into_data().unwrap().as_ref(), b"data");
+
+        let err = response.body_mut().frame().await.unwrap().unwrap_err();
+        assert_eq!(err, "Export failed");
+    }
+
+    #[tokio::test]
+    async fn test_producer_of_a_channel_of_its_own() {
+        let (tx, rx) = futures::channel::mpsc::channel(4);
+        let mut producer = crate::HttpOutputProducer::new(tx);
+        let mut response = into_response(crate::HttpOutputAsStream::new(rx));
+
+        producer.send(b"data".to_vec()).await.unwrap();
+        drop(producer);
+
+        let frame = response.body_mut().frame().await.unwrap().unwrap();
+        assert_eq!(frame.into_data().unwrap().as_ref(), b"data");
+        assert!(response.body_mut().frame().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_dropped_response_disconnects_the_producer() {
+        let (output, mut producer) = HttpOutput::as_stream(4);
+        let response = into_response(output);
+
+        assert!(producer.is_connected());
+
+        drop(response);
+
+        producer.disconnected().await;
+        assert!(!producer.is_connected());
+        assert!(producer.send(b"data".to_vec()).await.is_err());
     }
 }
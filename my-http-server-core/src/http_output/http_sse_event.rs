@@ -0,0 +1,190 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+/// One Server-Sent Event, as [`HttpSseProducer::send`](crate::HttpSseProducer::send) puts it on
+/// the wire:
+///
+/// ```text
+/// event: price
+/// id: 42
+/// retry: 5000
+/// data: first line
+/// data: second line
+///
+/// ```
+///
+/// Data with line breaks goes out as one `data:` line per line, and the browser joins them back
+/// with `\n`. A line break in `event` or `id` would end the field early, so they are dropped.
+#[derive(Debug, Clone, Default)]
+pub struct HttpSseEvent {
+    event: Option<String>,
+    id: Option<String>,
+    retry: Option<Duration>,
+    data: String,
+}
+
+impl HttpSseEvent {
+    pub fn new(data: impl Into<String>) -> Self {
+        Self {
+            data: data.into(),
+            ..Default::default()
+        }
+    }
+
+    /// `model` as JSON data.
+    pub fn from_json(model: &impl Serialize) -> Self {
+        Self::new(serde_json::to_string(model).unwrap())
+    }
+
+    /// The name an `EventSource` dispatches the event under — `message` if there is none.
+    pub fn with_event(mut self, event: impl Into<String>) -> Self {
+        self.event = Some(remove_line_breaks(event.into()));
+        self
+    }
+
+    /// What the browser sends back as `Last-Event-ID` when it reconnects — see
+    /// [`HttpRequest::get_last_event_id`](crate::HttpRequest::get_last_event_id).
+    pub fn with_id(mut self, id: impl Into<String>) -> Self {
+        let mut id = remove_line_breaks(id.into());
+        // A NULL makes the browser ignore the id altogether.
+        id.retain(|c| c != '\0');
+        self.id = Some(id);
+        self
+    }
+
+    /// How long the browser waits before it reconnects after the stream breaks.
+    pub fn with_retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let mut result = String::with_capacity(self.data.len() + 32);
+
+        if let Some(event) = self.event.as_ref() {
+            write_field(&mut result, "event", event);
+        }
+
+        if let Some(id) = self.id.as_ref() {
+            write_field(&mut result, "id", id);
+        }
+
+        if let Some(retry) = self.retry {
+            write_field(&mut result, "retry", &retry.as_millis().to_string());
+        }
+
+        for line in split_lines(&self.data) {
+            write_field(&mut result, "data", line);
+        }
+
+        result.push('\n');
+        result.into_bytes()
+    }
+}
+
+/// A comment line per line of `text` — ignored by the browser, but it keeps proxies from timing
+/// the connection out.
+pub(crate) fn sse_comment_to_bytes(text: &str) -> Vec<u8> {
+    let mut result = String::with_capacity(text.len() + 4);
+
+    for line in split_lines(text) {
+        write_field(&mut result, "", line);
+    }
+
+    result.push('\n');
+    result.into_bytes()
+}
+
+fn write_field(result: &mut String, name: &str, value: &str) {
+    result.push_str(name);
+    result.push(':');
+
+    if !value.is_empty() {
+        result.push(' ');
+        result.push_str(value);
+    }
+
+    result.push('\n');
+}
+
+/// Splits on `\r\n`, `\r` and `\n` — every line break the browser recognizes. Empty text is one
+/// empty line, so an event with no data still dispatches.
+fn split_lines(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = Some(text);
+
+    std::iter::from_fn(move || {
+        let text = rest?;
+
+        match text.find(['\r', '\n']) {
+            Some(pos) => {
+                let next = if text[pos..].starts_with("\r\n") {
+                    pos + 2
+                } else {
+                    pos + 1
+                };
+
+                rest = Some(&text[next..]);
+                Some(&text[..pos])
+            }
+            None => {
+                rest = None;
+                Some(text)
+            }
+        }
+    })
+}
+
+fn remove_line_breaks(mut value: String) -> String {
+    value.retain(|c| c != '\r' && c != '\n');
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn as_str(bytes: Vec<u8>) -> String {
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn test_data_only() {
+        let event = HttpSseEvent::new("hello");
+        assert_eq!(as_str(event.to_bytes()), "data: hello\n\n");
+
+        let event = HttpSseEvent::new("");
+        assert_eq!(as_str(event.to_bytes()), "data:\n\n");
+    }
+
+    #[test]
+    fn test_all_fields() {
+        let event = HttpSseEvent::new("first\nsecond\r\nthird\rfourth\n")
+            .with_event("price")
+            .with_id("42")
+            .with_retry(Duration::from_secs(5));
+
+        assert_eq!(
+            as_str(event.to_bytes()),
+            "event: price\nid: 42\nretry: 5000\ndata: first\ndata: second\ndata: third\ndata: fourth\ndata:\n\n"
+        );
+    }
+
+    #[test]
+    fn test_line_breaks_can_not_end_fields_early() {
+        let event = HttpSseEvent::new("x")
+            .with_event("pri\nce")
+            .with_id("4\r\n2\0");
+
+        assert_eq!(
+            as_str(event.to_bytes()),
+            "event: price\nid: 42\ndata: x\n\n"
+        );
+    }
+
+    #[test]
+    fn test_comment() {
+        assert_eq!(as_str(sse_comment_to_bytes("")), ":\n\n");
+        assert_eq!(as_str(sse_comment_to_bytes("a\nb")), ": a\n: b\n\n");
+    }
+}
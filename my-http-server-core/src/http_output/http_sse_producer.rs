@@ -0,0 +1,79 @@
+use bytes::Bytes;
+use tokio::sync::mpsc::Sender;
+
+use super::{sse_comment_to_bytes, HttpSseEvent};
+
+/// What sending an event fails with: the client is gone and nothing more will reach it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HttpSseDisconnected;
+
+impl std::fmt::Display for HttpSseDisconnected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Server-sent events client disconnected")
+    }
+}
+
+impl std::error::Error for HttpSseDisconnected {}
+
+/// Sends the events of an [`HttpOutput::as_sse`](crate::HttpOutput::as_sse) response. The
+/// response ends when the producer is dropped.
+///
+/// Sending waits while the queue is full, so a slow client slows the sender down instead of
+/// piling events up in memory. Once the client is gone every send fails — a loop that sends until
+/// an error stops by itself.
+pub struct HttpSseProducer {
+    tx: Sender<Bytes>,
+}
+
+impl HttpSseProducer {
+    pub(crate) fn new(tx: Sender<Bytes>) -> Self {
+        Self { tx }
+    }
+
+    pub async fn send(&self, event: HttpSseEvent) -> Result<(), HttpSseDisconnected> {
+        self.send_bytes(event.to_bytes()).await
+    }
+
+    /// An event with `data` alone, dispatched as `message`.
+    pub async fn send_data(&self, data: impl Into<String>) -> Result<(), HttpSseDisconnected> {
+        self.send(HttpSseEvent::new(data)).await
+    }
+
+    /// A comment — the browser ignores it. The response sends its own keep-alive comments; see
+    /// [`HttpOutputAsSse::with_keep_alive`](crate::HttpOutputAsSse::with_keep_alive).
+    pub async fn send_comment(&self, text: &str) -> Result<(), HttpSseDisconnected> {
+        self.send_bytes(sse_comment_to_bytes(text)).await
+    }
+
+    /// `false` once the response is gone — the client disconnected, or the server dropped the
+    /// response without sending it.
+    pub fn is_connected(&self) -> bool {
+        !self.tx.is_closed()
+    }
+
+    /// Resolves once the client is gone. For a producer that has nothing to send for long
+    /// stretches, to stop waiting for the next event:
+    ///
+    /// ```ignore
+    /// loop {
+    ///     tokio::select! {
+    ///         price = prices.recv() => {
+    ///             if producer.send(HttpSseEvent::from_json(&price)).await.is_err() {
+    ///                 return;
+    ///             }
+    ///         }
+    ///         _ = producer.disconnected() => return,
+    ///     }
+    /// }
+    /// ```
+    pub async fn disconnected(&self) {
+        self.tx.closed().await
+    }
+
+    async fn send_bytes(&self, bytes: Vec<u8>) -> Result<(), HttpSseDisconnected> {
+        self.tx
+            .send(bytes.into())
+            .await
+            .map_err(|_| HttpSseDisconnected)
+    }
+}
@@ -6,3 +6,11 @@ mod http_output_as_stream;
 pub use http_output_as_stream::*;
 mod http_response_headers;
 pub use http_response_headers::*;
+mod http_sse_event;
+pub use http_sse_event::*;
+mod http_sse_producer;
+pub use http_sse_producer::*;
+mod http_output_as_sse;
+pub use http_output_as_sse::*;
+mod http_ndjson_producer;
+pub use http_ndjson_producer::*;
@@ -0,0 +1,429 @@
+use std::io::Write;
+
+use crate::{ContentEncoding, HttpFailResult, BODY_TOO_LARGE_PREFIX};
+
+/// How many bytes a compressed request body may decode to, unless
+/// [`MyHttpServer::set_max_decompressed_body_size`](crate::MyHttpServer::set_max_decompressed_body_size)
+/// says otherwise.
+pub const DEFAULT_MAX_DECOMPRESSED_BODY_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Decodes a compressed request body piece by piece, as its frames come in — the pump hands the
+/// plaintext of each frame on straight away, the materialize path appends it.
+///
+/// Whatever comes out is held to `max_size`: a few kilobytes on the wire can inflate to gigabytes,
+/// and the limit is what stops it rather than the memory of the server.
+pub(crate) struct BodyDecoder {
+    encoding: ContentEncoding,
+    state: DecoderState,
+}
+
+enum DecoderState {
+    GZip(flate2::write::GzDecoder<DecodedSink>),
+    Deflate(DeflateDecoder),
+    Br(Box<brotli_decompressor::DecompressorWriter<DecodedSink>>),
+    Zstd(zstd::stream::zio::Writer<DecodedSink, zstd::stream::raw::Decoder<'static>>),
+}
+
+/// Why a body could not be decoded.
+#[derive(Debug)]
+pub(crate) enum BodyDecodeError {
+    TooLarge(u64),
+    Corrupted(String),
+    /// The server could not set a decoder up — nothing to do with what the client sent.
+    DecoderUnavailable(String),
+}
+
+impl BodyDecodeError {
+    pub fn into_reason(self) -> String {
+        match self {
+            BodyDecodeError::TooLarge(max_size) => format!(
+                "{} {} bytes once decompressed",
+                BODY_TOO_LARGE_PREFIX, max_size
+            ),
+            BodyDecodeError::Corrupted(reason) => reason,
+            BodyDecodeError::DecoderUnavailable(reason) => reason,
+        }
+    }
+
+    pub fn into_fail_result(self) -> HttpFailResult {
+        match self {
+            BodyDecodeError::TooLarge(_) => {
+                HttpFailResult::as_payload_too_large(self.into_reason())
+            }
+            BodyDecodeError::Corrupted(_) => HttpFailResult::from((400u16, self.into_reason())),
+            BodyDecodeError::DecoderUnavailable(_) => {
+                HttpFailResult::as_fatal_error(self.into_reason())
+            }
+        }
+    }
+}
+
+impl BodyDecoder {
+    /// `None` for a body that is not compressed — there is nothing to decode.
+    pub fn new(
+        encoding: ContentEncoding,
+        max_size: Option<u64>,
+    ) -> Result<Option<Self>, BodyDecodeError> {
+        let sink = DecodedSink {
+            buffer: Vec::new(),
+            total: 0,
+            max_size,
+            exceeded: false,
+        };
+
+        let state = match encoding {
+            ContentEncoding::None => return Ok(None),
+            ContentEncoding::GZip => DecoderState::GZip(flate2::write::GzDecoder::new(sink)),
+            ContentEncoding::Deflate => DecoderState::Deflate(DeflateDecoder::new(sink)),
+            ContentEncoding::Br => DecoderState::Br(Box::new(
+                brotli_decompressor::DecompressorWriter::new(sink, 4096),
+            )),
+            ContentEncoding::Zstd => {
+                let decoder = zstd::stream::raw::Decoder::new().map_err(|err| {
+                    BodyDecodeError::DecoderUnavailable(format!(
+                        "Can not create zstd decoder. Err: {}",
+                        err
+                    ))
+                })?;
+                DecoderState::Zstd(zstd::stream::zio::Writer::new(sink, decoder))
+            }
+        };
+
+        Ok(Some(Self { encoding, state }))
+    }
+
+    /// Feeds the next piece of the compressed body in, returns the plaintext it decoded to — which
+    /// may be nothing yet.
+    pub fn decode(&mut self, chunk: &[u8]) -> Result<Vec<u8>, BodyDecodeError> {
+        let result = match &mut self.state {
+            DecoderState::GZip(decoder) => decoder.write_all(chunk),
+            DecoderState::Deflate(decoder) => decoder.write_all(chunk),
+            DecoderState::Br(decoder) => decoder.write_all(chunk),
+            DecoderState::Zstd(decoder) => decoder.write_all(chunk),
+        };
+
+        self.take_decoded(result)
+    }
+
+    /// The body is over: returns the rest of the plaintext, or an error if the compressed stream
+    /// was cut short.
+    pub fn finish(&mut self) -> Result<Vec<u8>, BodyDecodeError> {
+        let result = match &mut self.state {
+            DecoderState::GZip(decoder) => decoder.try_finish(),
+            DecoderState::Deflate(decoder) => decoder.finish(),
+            DecoderState::Br(decoder) => decoder.close(),
+            DecoderState::Zstd(decoder) => decoder.finish(),
+        };
+
+        self.take_decoded(result)
+    }
+
+    fn take_decoded(&mut self, result: std::io::Result<()>) -> Result<Vec<u8>, BodyDecodeError> {
+        let sink = match &mut self.state {
+            DecoderState::GZip(decoder) => decoder.get_mut(),
+            DecoderState::Deflate(decoder) => &mut decoder.sink,
+            DecoderState::Br(decoder) => decoder.get_mut(),
+            DecoderState::Zstd(decoder) => decoder.writer_mut(),
+        };
+
+        if sink.exceeded {
+            return Err(BodyDecodeError::TooLarge(sink.max_size.unwrap_or_default()));
+        }
+
+        if let Err(err) = result {
+            return Err(BodyDecodeError::Corrupted(format!(
+                "Can not decompress request body using {:?} method: {}",
+                self.encoding, err
+            )));
+        }
+
+        Ok(std::mem::take(&mut sink.buffer))
+    }
+}
+
+/// Decodes a whole body at once.
+pub(crate) fn decode_body(
+    encoding: ContentEncoding,
+    body: &[u8],
+    max_size: Option<u64>,
+) -> Result<Vec<u8>, BodyDecodeError> {
+    let Some(mut decoder) = BodyDecoder::new(encoding, max_size)? else {
+        return Ok(body.to_vec());
+    };
+
+    let mut result = decoder.decode(body)?;
+    result.extend_from_slice(&decoder.finish()?);
+    Ok(result)
+}
+
+/// Where the decoders put the plaintext. Refuses to take more than `max_size` bytes in total —
+/// the decoder stops on that error there and then.
+struct DecodedSink {
+    buffer: Vec<u8>,
+    total: u64,
+    max_size: Option<u64>,
+    exceeded: bool,
+}
+
+impl Write for DecodedSink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.total += buf.len() as u64;
+
+        if let Some(max_size) = self.max_size {
+            if self.total > max_size {
+                self.exceeded = true;
+                return Err(std::io::Error::other("decompressed body is too large"));
+            }
+        }
+
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// `Content-Encoding: deflate` is meant to be a zlib stream, but clients sending a bare deflate
+/// stream under that name are common enough — the first two bytes tell which one it is.
+///
+/// Driven by hand rather than through `flate2::write::ZlibDecoder`, which can not tell a stream
+/// that ended from one that was cut short.
+struct DeflateDecoder {
+    decompress: Option<flate2::Decompress>,
+    head: Vec<u8>,
+    ended: bool,
+    sink: DecodedSink,
+}
+
+impl DeflateDecoder {
+    fn new(sink: DecodedSink) -> Self {
+        Self {
+            decompress: None,
+            head: Vec::new(),
+            ended: false,
+            sink,
+        }
+    }
+
+    fn write_all(&mut self, chunk: &[u8]) -> std::io::Result<()> {
+        if self.decompress.is_none() {
+            self.head.extend_from_slice(chunk);
+
+            if self.head.len() < 2 {
+                return Ok(());
+            }
+
+            let is_zlib = self.head[0] & 0x0F == 8
+                && (u16::from(self.head[0]) << 8 | u16::from(self.head[1])) % 31 == 0;
+
+            self.decompress = Some(flate2::Decompress::new(is_zlib));
+
+            let head = std::mem::take(&mut self.head);
+            return self.inflate(&head);
+        }
+
+        self.inflate(chunk)
+    }
+
+    fn inflate(&mut self, mut input: &[u8]) -> std::io::Result<()> {
+        let decompress = self.decompress.as_mut().unwrap();
+        let mut buffer = [0u8; 1024 * 16];
+
+        loop {
+            if self.ended {
+                if input.is_empty() {
+                    return Ok(());
+                }
+
+                return Err(std::io::Error::other("data after the end of the stream"));
+            }
+
+            let total_in = decompress.total_in();
+            let total_out = decompress.total_out();
+
+            let status = decompress
+                .decompress(input, &mut buffer, flate2::FlushDecompress::None)
+                .map_err(std::io::Error::other)?;
+
+            let consumed = (decompress.total_in() - total_in) as usize;
+            let produced = (decompress.total_out() - total_out) as usize;
+
+            input = &input[consumed..];
+            self.sink.write_all(&buffer[..produced])?;
+
+            if status == flate2::Status::StreamEnd {
+                self.ended = true;
+                continue;
+            }
+
+            if consumed == 0 && produced == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    fn finish(&mut self) -> std::io::Result<()> {
+        if self.decompress.is_none() {
+            let head = std::mem::take(&mut self.head);
+            self.decompress = Some(flate2::Decompress::new(false));
+            self.inflate(&head)?;
+        }
+
+        if self.ended {
+            return Ok(());
+        }
+
+        Err(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            "the stream ended before it was complete",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    const TEXT: &[u8] = b"The quick brown fox jumps over the lazy dog. The quick brown fox jumps over the lazy dog.";
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn zlib(data: &[u8]) -> Vec<u8> {
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn raw_deflate(data: &[u8]) -> Vec<u8> {
+        let mut encoder =
+            flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    /// There is no brotli encoder around — a stream of one uncompressed meta-block is still a valid
+    /// brotli stream.
+    fn brotli_uncompressed(data: &[u8]) -> Vec<u8> {
+        // WBITS, ISLAST, MNIBBLES = 4, MLEN - 1, ISUNCOMPRESSED — padded to the byte.
+        let header = ((data.len() as u32 - 1) << 4) | (1 << 20);
+
+        let mut result = header.to_le_bytes()[..3].to_vec();
+        result.extend_from_slice(data);
+        // ISLAST, ISLASTEMPTY.
+        result.push(0x03);
+        result
+    }
+
+    fn zstd(data: &[u8]) -> Vec<u8> {
+        zstd::stream::encode_all(data, 3).unwrap()
+    }
+
+    /// Feeds the body in pieces of `piece` bytes — the way frames arrive.
+    fn decode_in_pieces(
+        encoding: ContentEncoding,
+        body: &[u8],
+        piece: usize,
+        max_size: Option<u64>,
+    ) -> Result<Vec<u8>, BodyDecodeError> {
+        let mut decoder = BodyDecoder::new(encoding, max_size)?.unwrap();
+
+        let mut result = Vec::new();
+        for chunk in body.chunks(piece) {
+            result.extend_from_slice(&decoder.decode(chunk)?);
+        }
+        result.extend_from_slice(&decoder.finish()?);
+
+        Ok(result)
+    }
+
+    #[test]
+    fn gzip_in_pieces() {
+        let result = decode_in_pieces(ContentEncoding::GZip, &gzip(TEXT), 7, None).unwrap();
+        assert_eq!(result, TEXT);
+    }
+
+    #[test]
+    fn zlib_deflate_in_pieces() {
+        let result = decode_in_pieces(ContentEncoding::Deflate, &zlib(TEXT), 1, None).unwrap();
+        assert_eq!(result, TEXT);
+    }
+
+    #[test]
+    fn raw_deflate_in_pieces() {
+        let result =
+            decode_in_pieces(ContentEncoding::Deflate, &raw_deflate(TEXT), 5, None).unwrap();
+        assert_eq!(result, TEXT);
+    }
+
+    #[test]
+    fn br_in_pieces() {
+        let result =
+            decode_in_pieces(ContentEncoding::Br, &brotli_uncompressed(TEXT), 4, None).unwrap();
+        assert_eq!(result, TEXT);
+    }
+
+    #[test]
+    fn zstd_in_pieces() {
+        let result = decode_in_pieces(ContentEncoding::Zstd, &zstd(TEXT), 3, None).unwrap();
+        assert_eq!(result, TEXT);
+    }
+
+    #[test]
+    fn truncated_streams_are_errors() {
+        for (encoding, body) in [
+            (ContentEncoding::GZip, gzip(TEXT)),
+            (ContentEncoding::Deflate, zlib(TEXT)),
+            (ContentEncoding::Br, brotli_uncompressed(TEXT)),
+            (ContentEncoding::Zstd, zstd(TEXT)),
+        ] {
+            let truncated = &body[..body.len() - 4];
+
+            let result = decode_in_pieces(encoding, truncated, 16, None);
+
+            assert!(
+                matches!(result, Err(BodyDecodeError::Corrupted(_))),
+                "{:?}: {:?}",
+                encoding,
+                result
+            );
+        }
+    }
+
+    #[test]
+    fn decompression_bomb_is_stopped_at_the_limit() {
+        let bomb = gzip(&vec![0u8; 10 * 1024 * 1024]);
+        assert!(bomb.len() < 64 * 1024);
+
+        let result = decode_in_pieces(ContentEncoding::GZip, &bomb, 4096, Some(1024 * 1024));
+
+        assert!(matches!(result, Err(BodyDecodeError::TooLarge(1048576))));
+    }
+
+    #[test]
+    fn too_large_is_a_413() {
+        let result = BodyDecodeError::TooLarge(100).into_fail_result();
+        assert_eq!(result.output.get_status_code(), 413);
+    }
+
+    #[test]
+    fn unavailable_decoder_is_a_500() {
+        let result =
+            BodyDecodeError::DecoderUnavailable("no memory".to_string()).into_fail_result();
+        assert_eq!(result.output.get_status_code(), 500);
+    }
+
+    #[test]
+    fn garbage_is_an_error() {
+        let result = decode_body(ContentEncoding::GZip, b"definitely not gzip", None);
+        assert!(matches!(result, Err(BodyDecodeError::Corrupted(_))));
+    }
+}
@@ -2,7 +2,8 @@
 //!
 //! my-http-utils owns the channel and the reading half and knows nothing about transports; the
 //! whole "where do the bytes come from" half lives here: pull DATA frames off
-//! `hyper::body::Incoming` and pour them into the sending half.
+//! [`HttpRequestBodySource`] — hyper's `Incoming`, or a body made in memory — and pour them into
+//! the sending half.
 //!
 //! [`next_data_frame`] is *the* body-reading primitive of this crate — both the pump below and the
 //! materialize-it-whole path ([`crate::HttpRequestBody::get_http_request_body`]) go through it, so
@@ -12,7 +13,10 @@
 
 use my_http_utils::http_input::{HttpBodyAsStream, HttpBodyStreamSender, HttpParseError};
 
-use crate::HttpRequestBody;
+use crate::{
+    BodyDecodeError, BodyDecoder, ContentEncoding, HttpFailResult, HttpRequestBody,
+    HttpRequestBodySource,
+};
 
 /// What the request promised about its body, and how to tell whether it kept the promise.
 ///
@@ -41,7 +45,63 @@ pub struct BodyExpectations {
     pub read_timeout: Option<std::time::Duration>,
 }
 
+/// What the server holds a body to besides the promises of the request: how large it may be and
+/// how it is decoded. The default — no limits, no decoding — is how a body was always read.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BodyReadOptions {
+    /// The most bytes the body may have. A body that announces more is refused before anything is
+    /// read; one that does not say (chunked, HTTP/2) is cut off as soon as it goes over.
+    ///
+    /// `None` (the default) takes whatever the client sends. Set it via
+    /// `MyHttpServer::set_max_body_size`, or per route with `max_body_size` of `#[http_route]`.
+    pub max_size: Option<u64>,
+    /// The `Content-Encoding` of the body. Both ways of reading it decode it frame by frame, so a
+    /// handler only ever sees plaintext — `Content-Length` and `max_size` count the bytes on the
+    /// wire.
+    pub content_encoding: ContentEncoding,
+    /// The most bytes a compressed body may decode to — what stops a decompression bomb. Set it
+    /// via `MyHttpServer::set_max_decompressed_body_size`.
+    pub max_decoded_size: Option<u64>,
+}
+
+/// How every "body is too large" message starts — what turns a failed stream into a `413` once it
+/// reaches the action as an error.
+pub(crate) const BODY_TOO_LARGE_PREFIX: &str = "Request body exceeds the limit of";
+
+impl BodyReadOptions {
+    /// The decoder for a compressed body, `None` for a plain one.
+    pub(crate) fn create_decoder(&self) -> Result<Option<BodyDecoder>, BodyDecodeError> {
+        BodyDecoder::new(self.content_encoding, self.max_decoded_size)
+    }
+
+    /// Why a body of `size` bytes can not be accepted, or `None` when it is within the limit.
+    pub fn too_large_reason(&self, size: u64) -> Option<String> {
+        let max_size = self.max_size?;
+
+        if size <= max_size {
+            return None;
+        }
+
+        Some(format!("{} {} bytes", BODY_TOO_LARGE_PREFIX, max_size))
+    }
+
+    /// Refuses a body whose `Content-Length` is over the limit — before a byte of it is read.
+    pub fn check_announced_size(
+        &self,
+        expectations: &BodyExpectations,
+    ) -> Result<(), HttpFailResult> {
+        match expectations
+            .content_length
+            .and_then(|itm| self.too_large_reason(itm))
+        {
+            Some(reason) => Err(HttpFailResult::as_payload_too_large(reason)),
+            None => Ok(()),
+        }
+    }
+}
+
 impl BodyExpectations {
+
     /// Why the body that just ended is NOT complete, or `None` when it is.
     ///
     /// `end_stream_seen` is hyper's own `Body::is_end_stream`, **latched** — sampled after every
@@ -105,7 +165,7 @@ impl EndStreamWatch {
     }
 
     /// Sample the flag. Call after every frame, and once more when the body ends.
-    pub fn sample(&mut self, incoming: &hyper::body::Incoming) {
+    pub fn sample(&mut self, incoming: &HttpRequestBodySource) {
         self.0 |= hyper::body::Body::is_end_stream(incoming);
     }
 
@@ -126,7 +186,7 @@ impl Default for EndStreamWatch {
 /// an empty chunk is not an end-of-body marker, and letting one through would make
 /// `Ok(Some(vec![]))` look like "almost done" to a consumer.
 pub async fn next_data_frame(
-    incoming: &mut hyper::body::Incoming,
+    incoming: &mut HttpRequestBodySource,
 ) -> Result<Option<bytes::Bytes>, hyper::Error> {
     use http_body_util::BodyExt;
 
@@ -157,7 +217,7 @@ pub struct BodyReadTimeout;
 /// The timeout wraps the wait for *one* frame and is restarted for the next one, so it only ever
 /// fires on a client that has stopped sending — never on a slow but progressing upload.
 pub async fn next_data_frame_with_timeout(
-    incoming: &mut hyper::body::Incoming,
+    incoming: &mut HttpRequestBodySource,
     timeout: Option<std::time::Duration>,
 ) -> Result<Result<Option<bytes::Bytes>, hyper::Error>, BodyReadTimeout> {
     let Some(timeout) = timeout else {
@@ -179,11 +239,19 @@ pub async fn next_data_frame_with_timeout(
 pub fn spawn_body_pump(
     body: HttpRequestBody,
     expectations: BodyExpectations,
+    options: BodyReadOptions,
     buffer: usize,
 ) -> HttpBodyAsStream {
-    let (sender, stream) = HttpBodyAsStream::create(buffer, expectations.content_length);
+    // The announced length is that of the compressed body — the handler is told nothing rather
+    // than a length the plaintext will not have.
+    let content_length = match options.content_encoding {
+        ContentEncoding::None => expectations.content_length,
+        _ => None,
+    };
 
-    tokio::spawn(pump(body, sender, expectations));
+    let (sender, stream) = HttpBodyAsStream::create(buffer, content_length);
+
+    tokio::spawn(pump(body, sender, expectations, options));
 
     stream
 }
@@ -192,6 +260,7 @@ async fn pump(
     body: HttpRequestBody,
     sender: HttpBodyStreamSender,
     expectations: BodyExpectations,
+    options: BodyReadOptions,
 ) {
     match body {
         HttpRequestBody::Incoming { mut incoming, .. } => {
@@ -206,6 +275,15 @@ async fn pump(
 
             let mut delivered: u64 = 0;
             let mut end_stream = EndStreamWatch::new();
+            let mut decoder = match options.create_decoder() {
+                Ok(decoder) => decoder,
+                Err(err) => {
+                    sender
+                        .send_error(HttpParseError::BodyStream(err.into_reason()))
+                        .await;
+                    return;
+                }
+            };
 
             loop {
                 let frame = tokio::select! {
@@ -238,11 +316,37 @@ async fn pump(
                 match frame {
                     Ok(Some(data)) => {
                         delivered += data.len() as u64;
+
+                        // Dropping `incoming` on return is what stops the client: hyper closes
+                        // the HTTP/1 connection, resets the HTTP/2 stream.
+                        if let Some(reason) = options.too_large_reason(delivered) {
+                            sender.send_error(HttpParseError::BodyStream(reason)).await;
+                            return;
+                        }
+
                         // Before the send: a full channel parks us here, and a reset arriving
                         // during that wait would erase the flag.
                         end_stream.sample(&incoming);
 
-                        if !sender.send_chunk(data.into()).await {
+                        let data: Vec<u8> = match decoder.as_mut() {
+                            Some(decoder) => match decoder.decode(&data) {
+                                Ok(decoded) => decoded,
+                                Err(err) => {
+                                    sender
+                                        .send_error(HttpParseError::BodyStream(err.into_reason()))
+                                        .await;
+                                    return;
+                                }
+                            },
+                            None => data.into(),
+                        };
+
+                        // A compressed frame may not decode to anything yet.
+                        if data.is_empty() {
+                            continue;
+                        }
+
+                        if !sender.send_chunk(data).await {
                             // Reader is gone.
                             return;
                         }
@@ -260,6 +364,22 @@ async fn pump(
                             return;
                         }
 
+                        if let Some(decoder) = decoder.as_mut() {
+                            match decoder.finish() {
+                                Ok(rest) => {
+                                    if !rest.is_empty() && !sender.send_chunk(rest).await {
+                                        return;
+                                    }
+                                }
+                                Err(err) => {
+                                    sender
+                                        .send_error(HttpParseError::BodyStream(err.into_reason()))
+                                        .await;
+                                    return;
+                                }
+                            }
+                        }
+
                         // The ONLY place `finish` may be called: every other exit out of this
                         // loop leaves the flag unset, which is what turns a half-delivered body
                         // into an error on the reading side instead of a silent truncation.
@@ -1,14 +1,15 @@
-use std::io::Read;
-
 use hyper::body::Bytes;
 
-use crate::HttpFailResult;
+use crate::{BodyDecodeError, HttpFailResult};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum ContentEncoding {
+    #[default]
     None,
     GZip,
+    Deflate,
     Br,
+    Zstd,
 }
 
 impl ContentEncoding {
@@ -18,104 +19,134 @@ impl ContentEncoding {
             None => return Ok(Self::None),
         };
 
+        let header_value = header_value.trim();
+
+        if header_value.is_empty() || header_value.eq_ignore_ascii_case("identity") {
+            return Ok(Self::None);
+        }
+
         if header_value.eq_ignore_ascii_case("gzip") {
             return Ok(Self::GZip);
         }
 
+        if header_value.eq_ignore_ascii_case("deflate") {
+            return Ok(Self::Deflate);
+        }
+
         if header_value.eq_ignore_ascii_case("br") {
             return Ok(Self::Br);
         }
 
-        Err(HttpFailResult::as_validation_error(format!(
-            "Unsupported content encoding: {}",
-            header_value
+        if header_value.eq_ignore_ascii_case("zstd") {
+            return Ok(Self::Zstd);
+        }
+
+        // A body in an encoding the server can not decode can not be read at all.
+        Err(HttpFailResult::from((
+            415u16,
+            format!("Unsupported content encoding: {}", header_value),
         )))
     }
+
+    /// Decodes a whole body already in memory, up to
+    /// [`DEFAULT_MAX_DECOMPRESSED_BODY_SIZE`](crate::DEFAULT_MAX_DECOMPRESSED_BODY_SIZE). The
+    /// server decodes request bodies itself, under the limit set with
+    /// `MyHttpServer::set_max_decompressed_body_size`, so there is no need to call this.
     pub fn decompress_if_needed(&self, body: Bytes) -> Result<Vec<u8>, HttpFailResult> {
-        let body: Vec<_> = body.into();
-        match self {
-            ContentEncoding::None => Ok(body.into()),
-            ContentEncoding::GZip => match decompress_gzip(body.as_slice()) {
-                Some(result) => return Ok(result),
-                None => {
-                    return self.decompress_fall_back(body.as_slice());
-                }
-            },
-            ContentEncoding::Br => match decompress_br(body.as_slice()) {
-                Some(result) => return Ok(result),
-                None => {
-                    return self.decompress_fall_back(body.as_slice());
-                }
-            },
+        match decode_capped(*self, body.as_ref()) {
+            Ok(result) => Ok(result),
+            Err(BodyDecodeError::Corrupted(reason)) => {
+                self.decompress_fall_back(body.as_ref(), reason)
+            }
+            Err(err) => Err(err.into_fail_result()),
         }
     }
 
-    fn decompress_fall_back(&self, body: &[u8]) -> Result<Vec<u8>, HttpFailResult> {
-        match self {
-            ContentEncoding::None => {}
-            ContentEncoding::GZip => {
-                if let Some(body) = decompress_br(body) {
-                    return Ok(body);
-                }
+    /// Clients mix up `gzip` and `br`, so a body that does not decode as one is tried as the
+    /// other.
+    fn decompress_fall_back(&self, body: &[u8], reason: String) -> Result<Vec<u8>, HttpFailResult> {
+        let fall_back = match self {
+            ContentEncoding::GZip => ContentEncoding::Br,
+            ContentEncoding::Br => ContentEncoding::GZip,
+            ContentEncoding::None | ContentEncoding::Deflate | ContentEncoding::Zstd => {
+                return Err(BodyDecodeError::Corrupted(reason).into_fail_result());
             }
-            ContentEncoding::Br => {
-                if let Some(body) = decompress_gzip(body) {
-                    return Ok(body);
-                }
-            }
-        }
+        };
 
-        Err(HttpFailResult::as_fatal_error(format!(
-            "Can not decompress body using {:?} method",
-            self
-        )))
+        match decode_capped(fall_back, body) {
+            Ok(result) => Ok(result),
+            Err(BodyDecodeError::Corrupted(_)) => Err(HttpFailResult::as_fatal_error(format!(
+                "Can not decompress body using {:?} method",
+                self
+            ))),
+            Err(err) => Err(err.into_fail_result()),
+        }
     }
 }
 
-fn decompress_gzip(body: &[u8]) -> Option<Vec<u8>> {
-    let mut decompressor = flate2::read::GzDecoder::new(body);
-
-    let mut result = Vec::new();
-    let mut buffer = [0u8; 1024 * 4];
-
-    loop {
-        let read_amount = decompressor.read(&mut buffer);
-
-        if read_amount.is_err() {
-            return None;
-        }
-
-        let read_amount = read_amount.unwrap();
-
-        if read_amount == 0 {
-            return Some(result);
-        }
-
-        result.extend_from_slice(&buffer[..read_amount]);
-    }
+fn decode_capped(encoding: ContentEncoding, body: &[u8]) -> Result<Vec<u8>, BodyDecodeError> {
+    crate::decode_body(
+        encoding,
+        body,
+        Some(crate::DEFAULT_MAX_DECOMPRESSED_BODY_SIZE),
+    )
 }
 
-fn decompress_br(body: &[u8]) -> Option<Vec<u8>> {
-    use brotli_decompressor::Decompressor;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_values() {
+        assert_eq!(ContentEncoding::new(None).unwrap(), ContentEncoding::None);
+        assert_eq!(
+            ContentEncoding::new(Some("identity")).unwrap(),
+            ContentEncoding::None
+        );
+        assert_eq!(
+            ContentEncoding::new(Some("GZIP")).unwrap(),
+            ContentEncoding::GZip
+        );
+        assert_eq!(
+            ContentEncoding::new(Some("deflate")).unwrap(),
+            ContentEncoding::Deflate
+        );
+        assert_eq!(
+            ContentEncoding::new(Some(" zstd ")).unwrap(),
+            ContentEncoding::Zstd
+        );
+    }
 
-    let mut decompressor = Decompressor::new(body, 4096);
+    #[test]
+    fn unsupported_encoding_is_a_415() {
+        let err = ContentEncoding::new(Some("compress")).unwrap_err();
+        assert_eq!(err.output.get_status_code(), 415);
+    }
 
-    let mut result = Vec::new();
-    let mut buffer = [0u8; 1024 * 8];
+    fn gzip(body: &[u8]) -> Bytes {
+        use std::io::Write;
 
-    loop {
-        let read_amount = decompressor.read(&mut buffer);
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        encoder.write_all(body).unwrap();
+        encoder.finish().unwrap().into()
+    }
 
-        if read_amount.is_err() {
-            return None;
-        }
+    #[test]
+    fn gzip_body_labelled_br_falls_back() {
+        let result = ContentEncoding::Br
+            .decompress_if_needed(gzip(b"hello"))
+            .unwrap();
 
-        let read_amount = read_amount.unwrap();
+        assert_eq!(result, b"hello");
+    }
 
-        if read_amount == 0 {
-            return Some(result);
-        }
+    #[test]
+    fn gzip_bomb_is_a_413() {
+        let body = vec![0u8; crate::DEFAULT_MAX_DECOMPRESSED_BODY_SIZE as usize + 1];
 
-        result.extend_from_slice(&buffer[..read_amount]);
+        let err = ContentEncoding::GZip
+            .decompress_if_needed(gzip(&body))
+            .unwrap_err();
+        assert_eq!(err.output.get_status_code(), 413);
     }
 }
@@ -3,9 +3,10 @@ use std::{collections::HashMap, net::SocketAddr, sync::Arc};
 use my_http_utils::http_input::{HttpBodyAsStream, BODY_STREAM_DEFAULT_BUFFER};
 
 use crate::{
-    http_headers::*, CookiesReader, HttpFailResult, HttpPath, HttpPathReader,
-    HttpRequestBodyContent, HttpRequestHeaders, MyHyperHttpRequest, QueryStringReader, RequestData,
-    RequestIp,
+    find_client_hop, http_headers::*, read_forwarded_hops, CookiesReader, ForwardedHop,
+    HttpConnectionInfo, HttpFailResult, HttpPath, HttpPathReader, HttpRequestBodyContent,
+    HttpRequestBodySource, HttpRequestHeaders, MyHyperHttpRequest, QueryStringReader, RequestData,
+    RequestIp, TrustedProxies,
 };
 
 use hyper::{Method, Uri};
@@ -35,16 +36,19 @@ impl SocketAddress {
 pub struct HttpRequest {
     pub data: RequestData,
     pub addr: SocketAddress,
+    /// The connection this request came in on — shared by every request served on it.
+    pub connection: Arc<HttpConnectionInfo>,
     pub content_type_header: Option<String>,
     key_values: Option<HashMap<String, Vec<u8>>>,
+    trusted_proxies: Option<Arc<TrustedProxies>>,
     pub method: Method,
     pub http_path: HttpPath,
 }
 
 impl HttpRequest {
     pub fn new(
-        req: hyper::Request<hyper::body::Incoming>,
-        addr: SocketAddress,
+        req: hyper::Request<impl Into<HttpRequestBodySource>>,
+        connection: Arc<HttpConnectionInfo>,
     ) -> Result<Self, HttpFailResult> {
         let method = req.method().clone();
 
@@ -52,8 +56,10 @@ impl HttpRequest {
 
         let result = Self {
             data: RequestData::new(req)?,
-            addr,
+            addr: connection.addr.clone(),
+            connection,
             key_values: None,
+            trusted_proxies: None,
             content_type_header: None,
             method,
             http_path,
@@ -96,6 +102,49 @@ impl HttpRequest {
         self.data.set_body_read_timeout(timeout);
     }
 
+    /// The most bytes the body may have. Set from
+    /// [`MyHttpServer::set_max_body_size`](crate::MyHttpServer::set_max_body_size); the controllers
+    /// change it for a route with `max_body_size` of its own, and any middleware may do the same
+    /// before the body is read.
+    pub fn set_max_body_size(&mut self, max_body_size: Option<u64>) {
+        self.data.set_max_body_size(max_body_size);
+    }
+
+    pub fn get_max_body_size(&self) -> Option<u64> {
+        self.data.get_max_body_size()
+    }
+
+    /// The most bytes a compressed body may decode to. Set from
+    /// [`MyHttpServer::set_max_decompressed_body_size`](crate::MyHttpServer::set_max_decompressed_body_size);
+    /// a middleware may change it before the body is read.
+    pub fn set_max_decompressed_body_size(&mut self, max_decompressed_body_size: Option<u64>) {
+        self.data
+            .set_max_decompressed_body_size(max_decompressed_body_size);
+    }
+
+    pub fn get_max_decompressed_body_size(&self) -> Option<u64> {
+        self.data.get_max_decompressed_body_size()
+    }
+
+    /// `413` if the client announced a body over [the limit](Self::set_max_body_size) — so it can
+    /// be refused before the handler runs. The body-reading methods check the same on their own.
+    pub fn check_announced_body_size(&self) -> Result<(), HttpFailResult> {
+        self.data.check_announced_body_size()
+    }
+
+    /// Which peers the forwarding headers are taken from. Set from
+    /// [`MyHttpServer::set_trusted_proxies`](crate::MyHttpServer::set_trusted_proxies); with
+    /// `None` they are taken from anybody, as they always were.
+    pub fn set_trusted_proxies(&mut self, trusted_proxies: Option<Arc<TrustedProxies>>) {
+        self.trusted_proxies = trusted_proxies;
+    }
+
+    /// What [`set_trusted_proxies`](Self::set_trusted_proxies) was given — for code that answers
+    /// IP questions about this request after it has been taken apart, such as a web socket upgrade.
+    pub fn get_trusted_proxies(&self) -> Option<&Arc<TrustedProxies>> {
+        self.trusted_proxies.as_ref()
+    }
+
     /// Takes the body as a stream of chunks, with the default channel capacity. Used by the
     /// generated action code for a `#[http_body_as_stream]` model.
     pub fn take_body_stream(&mut self) -> Result<HttpBodyAsStream, HttpFailResult> {
@@ -111,7 +160,9 @@ impl HttpRequest {
         self.data.take_body_stream(buffer)
     }
 
-    pub fn take_my_hyper_http_request(&mut self) -> MyHyperHttpRequest {
+    /// The request as hyper has it — what a web socket upgrade needs. Fails once the body is
+    /// taken, and for a body the in-process test client streams.
+    pub fn take_my_hyper_http_request(&mut self) -> Result<MyHyperHttpRequest, HttpFailResult> {
         self.data.take_my_hyper_http_request()
     }
 
@@ -119,19 +170,63 @@ impl HttpRequest {
         HttpPathReader::new(self.data.uri().path())
     }
 
+    /// The name of the listener this request came in on — [`DEFAULT_LISTENER_NAME`](crate::DEFAULT_LISTENER_NAME)
+    /// unless the server was given more listeners through
+    /// [`MyHttpServer::add_listener`](crate::MyHttpServer::add_listener).
+    pub fn get_listener_name(&self) -> &str {
+        self.connection.listener_name.as_str()
+    }
+
     pub fn get_ip<'s>(&'s self) -> RequestIp<'s> {
-        RequestIp::new(&self.addr, self.get_headers())
+        match self.trusted_proxies.as_ref() {
+            Some(trusted_proxies) => {
+                RequestIp::new_with_trusted_proxies(&self.addr, self.get_headers(), trusted_proxies)
+            }
+            None => RequestIp::new(&self.addr, self.get_headers()),
+        }
+    }
+
+    /// The hop of the forwarding headers that describes the client — only when the peer is a
+    /// trusted proxy.
+    fn get_trusted_client_hop(&self, trusted_proxies: &TrustedProxies) -> Option<ForwardedHop<'_>> {
+        if !trusted_proxies.is_trusted_peer(&self.addr) {
+            return None;
+        }
+
+        let hops = read_forwarded_hops(
+            self.data.headers(),
+            trusted_proxies.get_forwarding_headers(),
+        );
+        let client = find_client_hop(&hops, trusted_proxies)?;
+
+        Some(hops[client])
     }
 
     pub fn get_host(&self) -> &str {
+        if let Some(trusted_proxies) = self.trusted_proxies.as_ref() {
+            if let Some(host) = self
+                .get_trusted_client_hop(trusted_proxies)
+                .and_then(|hop| hop.host)
+            {
+                return host;
+            }
+        }
+
         // HTTP/1 sends the host in the `Host` header — check it first.
         if let Some(value) = self.data.headers().try_get_case_insensitive("host") {
             return value.as_str().unwrap();
         }
 
-        // Behind a reverse proxy the original host may come in X-Forwarded-Host.
-        if let Some(value) = self.data.headers().try_get_case_insensitive(X_FORWARDED_HOST) {
-            return value.as_str().unwrap();
+        // Behind a reverse proxy the original host may come in X-Forwarded-Host. With trusted
+        // proxies set it has been looked at above already — and only believed from them.
+        if self.trusted_proxies.is_none() {
+            if let Some(value) = self
+                .data
+                .headers()
+                .try_get_case_insensitive(X_FORWARDED_HOST)
+            {
+                return value.as_str().unwrap();
+            }
         }
 
         // HTTP/2 carries the host in the `:authority` pseudo-header (surfaced by hyper as the URI
@@ -160,6 +255,19 @@ impl HttpRequest {
         self.data.headers()
     }
 
+    /// The `Last-Event-ID` a browser's `EventSource` sends when it reconnects: the id of the last
+    /// event it got, for an [`HttpOutput::as_sse`](crate::HttpOutput::as_sse) action to resume
+    /// the stream after.
+    pub fn get_last_event_id(&self) -> Option<&str> {
+        let value = self.data.headers().get("last-event-id")?.to_str().ok()?;
+
+        if value.is_empty() {
+            return None;
+        }
+
+        Some(value)
+    }
+
     pub fn get_uri(&self) -> &Uri {
         self.data.uri()
     }
@@ -179,6 +287,17 @@ impl HttpRequest {
     }
 
     pub fn get_scheme(&self) -> &str {
+        if let Some(trusted_proxies) = self.trusted_proxies.as_ref() {
+            if let Some(proto) = self
+                .get_trusted_client_hop(trusted_proxies)
+                .and_then(|hop| hop.proto)
+            {
+                return proto;
+            }
+
+            return self.get_connection_scheme();
+        }
+
         let x_forwarded_proto = self
             .data
             .headers()
@@ -190,13 +309,26 @@ impl HttpRequest {
             }
         }
 
+        self.get_connection_scheme()
+    }
+
+    /// The scheme as this server saw it, forwarding headers aside.
+    fn get_connection_scheme(&self) -> &str {
         let scheme = self.data.uri().scheme();
 
         match scheme {
             Some(scheme) => {
                 return scheme.as_str();
             }
-            None => "http",
+            None => {
+                // An HTTP/1 request line carries no scheme — but the connection knows whether it
+                // is TLS.
+                if self.connection.is_tls() {
+                    "https"
+                } else {
+                    "http"
+                }
+            }
         }
     }
 
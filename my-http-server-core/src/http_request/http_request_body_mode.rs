@@ -2,12 +2,13 @@ use my_http_utils::http_input::HttpBodyAsStream;
 
 use crate::{
     next_data_frame_with_timeout, spawn_body_pump, BodyContentType, BodyExpectations,
-    ContentEncoding, HttpFailResult, HttpRequestBodyContent,
+    BodyReadOptions, HttpFailResult, HttpRequestBodyContent, HttpRequestBodySource,
 };
 
-/// The request body, kept **lazy**: it holds hyper's `Incoming` and is not turned into bytes until
-/// something actually asks for them. There are two ways to ask, and both go through the same
-/// frame-by-frame primitive ([`next_data_frame`]):
+/// The request body, kept **lazy**: it holds the [`HttpRequestBodySource`] — hyper's `Incoming`
+/// for a request off the wire — and is not turned into bytes until something actually asks for
+/// them. There are two ways to ask, and both go through the same frame-by-frame primitive
+/// ([`next_data_frame`]):
 ///
 /// * materialize it whole — [`get_http_request_body`](Self::get_http_request_body) /
 ///   [`into_http_request_body`](Self::into_http_request_body), used for deserialization,
@@ -16,7 +17,7 @@ use crate::{
 ///   `#[http_body_as_stream]` model.
 pub enum HttpRequestBody {
     Incoming {
-        incoming: Option<hyper::body::Incoming>,
+        incoming: Option<HttpRequestBodySource>,
         content_type: BodyContentType,
     },
     Full(HttpRequestBodyContent),
@@ -26,15 +27,28 @@ impl HttpRequestBody {
     pub async fn get_http_request_body(
         &mut self,
         expectations: BodyExpectations,
+    ) -> Result<&HttpRequestBodyContent, HttpFailResult> {
+        self.get_http_request_body_with_options(expectations, BodyReadOptions::default())
+            .await
+    }
+
+    /// [`get_http_request_body`](Self::get_http_request_body), held to the limits and decoded as
+    /// `options` say.
+    pub async fn get_http_request_body_with_options(
+        &mut self,
+        expectations: BodyExpectations,
+        options: BodyReadOptions,
     ) -> Result<&HttpRequestBodyContent, HttpFailResult> {
         match self {
             HttpRequestBody::Incoming {
                 incoming,
                 content_type,
             } => {
+                options.check_announced_size(&expectations)?;
+
                 let mut take = incoming.take().unwrap();
 
-                let bytes = read_bytes(ContentEncoding::None, &mut take, expectations).await?;
+                let bytes = read_bytes(&mut take, expectations, options).await?;
                 let body = HttpRequestBodyContent::new(bytes, content_type.clone())?;
                 *self = HttpRequestBody::Full(body);
             }
@@ -52,14 +66,27 @@ impl HttpRequestBody {
     pub async fn into_http_request_body(
         self,
         expectations: BodyExpectations,
+    ) -> Result<HttpRequestBodyContent, HttpFailResult> {
+        self.into_http_request_body_with_options(expectations, BodyReadOptions::default())
+            .await
+    }
+
+    /// [`into_http_request_body`](Self::into_http_request_body), held to the limits and decoded as
+    /// `options` say.
+    pub async fn into_http_request_body_with_options(
+        self,
+        expectations: BodyExpectations,
+        options: BodyReadOptions,
     ) -> Result<HttpRequestBodyContent, HttpFailResult> {
         match self {
             HttpRequestBody::Incoming {
                 mut incoming,
                 content_type,
             } => {
+                options.check_announced_size(&expectations)?;
+
                 let mut take = incoming.take().unwrap();
-                let bytes = read_bytes(ContentEncoding::None, &mut take, expectations).await?;
+                let bytes = read_bytes(&mut take, expectations, options).await?;
                 let body = HttpRequestBodyContent::new(bytes, content_type)?;
                 return Ok(body);
             }
@@ -74,21 +101,38 @@ impl HttpRequestBody {
         expectations: BodyExpectations,
         buffer: usize,
     ) -> HttpBodyAsStream {
-        spawn_body_pump(self, expectations, buffer)
+        self.into_body_stream_with_options(expectations, BodyReadOptions::default(), buffer)
+    }
+
+    /// [`into_body_stream`](Self::into_body_stream), held to the limits and decoded as `options`
+    /// say.
+    pub fn into_body_stream_with_options(
+        self,
+        expectations: BodyExpectations,
+        options: BodyReadOptions,
+        buffer: usize,
+    ) -> HttpBodyAsStream {
+        spawn_body_pump(self, expectations, options, buffer)
     }
 }
 
 /// Materializes a whole body through the same frame loop the stream pump uses — and holds it to
 /// the same completeness rule. Both matter: a truncated upload must not reach a `#[http_body_raw]`
 /// action, or a middleware that reads the body, looking like the whole thing.
+///
+/// A compressed body is decoded as it comes in, so the limit on what it decodes to stops a
+/// decompression bomb before it is all in memory.
 async fn read_bytes(
-    body_compression: ContentEncoding,
-    incoming: &mut hyper::body::Incoming,
+    incoming: &mut HttpRequestBodySource,
     expectations: BodyExpectations,
+    options: BodyReadOptions,
 ) -> Result<Vec<u8>, HttpFailResult> {
     let mut result: Vec<u8> = Vec::new();
     let mut delivered: u64 = 0;
     let mut end_stream = crate::EndStreamWatch::new();
+    let mut decoder = options
+        .create_decoder()
+        .map_err(|err| err.into_fail_result())?;
 
     loop {
         let frame = next_data_frame_with_timeout(incoming, expectations.read_timeout).await;
@@ -109,8 +153,21 @@ async fn read_bytes(
         };
 
         delivered += chunk.len() as u64;
+
+        if let Some(reason) = options.too_large_reason(delivered) {
+            return Err(HttpFailResult::as_payload_too_large(reason));
+        }
+
         end_stream.sample(incoming);
 
+        if let Some(decoder) = decoder.as_mut() {
+            let decoded = decoder
+                .decode(&chunk)
+                .map_err(|err| err.into_fail_result())?;
+            result.extend_from_slice(&decoded);
+            continue;
+        }
+
         if result.is_empty() {
             // The common single-frame case moves the buffer instead of copying it.
             result = chunk.into();
@@ -125,5 +182,10 @@ async fn read_bytes(
         return Err(HttpFailResult::from((400u16, reason)));
     }
 
-    body_compression.decompress_if_needed(result.into())
+    if let Some(decoder) = decoder.as_mut() {
+        let decoded = decoder.finish().map_err(|err| err.into_fail_result())?;
+        result.extend_from_slice(&decoded);
+    }
+
+    Ok(result)
 }
@@ -0,0 +1,152 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+#[cfg(feature = "test-client")]
+use futures::Stream;
+use hyper::body::{Body, Frame, SizeHint};
+
+use crate::{HttpFailResult, MyHyperHttpRequest};
+
+/// Where the bytes of a request body come from: hyper's `Incoming` for a request off the wire, or
+/// a body made in memory — what the in-process test client of the `test-client` feature sends,
+/// and only with that feature on.
+///
+/// Both are read through the same frame loop, so a test body is held to the same limits,
+/// timeouts and completeness rules as a real one: a stream that ends short of the
+/// `Content-Length` it was sent with is an incomplete body, just as a dropped connection is.
+pub struct HttpRequestBodySource(BodySource);
+
+enum BodySource {
+    Incoming(hyper::body::Incoming),
+    #[cfg(feature = "test-client")]
+    Full(Option<Bytes>),
+    #[cfg(feature = "test-client")]
+    Stream {
+        stream: Pin<Box<dyn Stream<Item = Bytes> + Send + Sync>>,
+        ended: bool,
+    },
+}
+
+#[cfg(feature = "test-client")]
+impl HttpRequestBodySource {
+    pub fn empty() -> Self {
+        Self(BodySource::Full(None))
+    }
+
+    pub fn from_bytes(bytes: impl Into<Bytes>) -> Self {
+        let bytes = bytes.into();
+
+        if bytes.is_empty() {
+            return Self::empty();
+        }
+
+        Self(BodySource::Full(Some(bytes)))
+    }
+
+    /// A body that arrives chunk by chunk, as `stream` yields them; it ends when `stream` does.
+    pub fn from_stream(stream: impl Stream<Item = Bytes> + Send + Sync + 'static) -> Self {
+        Self(BodySource::Stream {
+            stream: Box::pin(stream),
+            ended: false,
+        })
+    }
+}
+
+impl HttpRequestBodySource {
+    /// Fails for a body that is being made in memory chunk by chunk — hyper has no request type
+    /// to carry it in.
+    pub(crate) fn into_hyper_request(
+        self,
+        parts: hyper::http::request::Parts,
+    ) -> Result<MyHyperHttpRequest, HttpFailResult> {
+        match self.0 {
+            BodySource::Incoming(incoming) => Ok(MyHyperHttpRequest::Incoming(
+                hyper::Request::from_parts(parts, incoming),
+            )),
+            #[cfg(feature = "test-client")]
+            BodySource::Full(bytes) => {
+                let body = http_body_util::Full::new(bytes.unwrap_or_default());
+                Ok(MyHyperHttpRequest::Full(hyper::Request::from_parts(
+                    parts, body,
+                )))
+            }
+            #[cfg(feature = "test-client")]
+            BodySource::Stream { .. } => Err(HttpFailResult::as_fatal_error(
+                "A streamed request body can not be handed over as a hyper request".to_string(),
+            )),
+        }
+    }
+}
+
+impl From<hyper::body::Incoming> for HttpRequestBodySource {
+    fn from(src: hyper::body::Incoming) -> Self {
+        Self(BodySource::Incoming(src))
+    }
+}
+
+#[cfg(feature = "test-client")]
+impl From<Vec<u8>> for HttpRequestBodySource {
+    fn from(src: Vec<u8>) -> Self {
+        Self::from_bytes(src)
+    }
+}
+
+#[cfg(feature = "test-client")]
+impl From<Bytes> for HttpRequestBodySource {
+    fn from(src: Bytes) -> Self {
+        Self::from_bytes(src)
+    }
+}
+
+impl Body for HttpRequestBodySource {
+    type Data = Bytes;
+    type Error = hyper::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        match &mut self.get_mut().0 {
+            BodySource::Incoming(incoming) => Pin::new(incoming).poll_frame(cx),
+            #[cfg(feature = "test-client")]
+            BodySource::Full(bytes) => Poll::Ready(bytes.take().map(|itm| Ok(Frame::data(itm)))),
+            #[cfg(feature = "test-client")]
+            BodySource::Stream { stream, ended } => {
+                match std::task::ready!(stream.as_mut().poll_next(cx)) {
+                    Some(chunk) => Poll::Ready(Some(Ok(Frame::data(chunk)))),
+                    None => {
+                        *ended = true;
+                        Poll::Ready(None)
+                    }
+                }
+            }
+        }
+    }
+
+    /// An in-memory body has ended once it has been read to the end — the way a stream that got
+    /// its END_STREAM has, so it passes the HTTP/2 completeness check too.
+    fn is_end_stream(&self) -> bool {
+        match &self.0 {
+            BodySource::Incoming(incoming) => incoming.is_end_stream(),
+            #[cfg(feature = "test-client")]
+            BodySource::Full(bytes) => bytes.is_none(),
+            #[cfg(feature = "test-client")]
+            BodySource::Stream { ended, .. } => *ended,
+        }
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        match &self.0 {
+            BodySource::Incoming(incoming) => incoming.size_hint(),
+            #[cfg(feature = "test-client")]
+            BodySource::Full(bytes) => {
+                SizeHint::with_exact(bytes.as_ref().map(|itm| itm.len() as u64).unwrap_or(0))
+            }
+            #[cfg(feature = "test-client")]
+            BodySource::Stream { .. } => SizeHint::default(),
+        }
+    }
+}
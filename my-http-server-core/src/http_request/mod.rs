@@ -10,9 +10,13 @@ mod http_path_reader;
 pub use http_path_reader::*;
 mod content_encoding;
 pub use content_encoding::*;
+mod body_decoder;
+pub use body_decoder::*;
 mod http_request_body_mode;
 pub use http_request_body_mode::*;
 mod body_stream_pump;
 pub use body_stream_pump::*;
 mod my_hyper_http_request;
 pub use my_hyper_http_request::*;
+mod http_request_body_source;
+pub use http_request_body_source::*;
@@ -5,7 +5,8 @@ use hyper::Uri;
 use my_http_utils::http_input::HttpBodyAsStream;
 
 use crate::{
-    BodyContentType, BodyExpectations, HttpFailResult, HttpRequestBody, HttpRequestBodyContent,
+    BodyContentType, BodyExpectations, BodyReadOptions, ContentEncoding, HttpFailResult,
+    HttpRequestBody, HttpRequestBodyContent, HttpRequestBodySource, HttpRequestHeaders,
     MyHyperHttpRequest,
 };
 
@@ -13,10 +14,14 @@ pub struct RequestData {
     parts: hyper::http::request::Parts,
     body: Option<HttpRequestBody>,
     body_read_timeout: Option<std::time::Duration>,
+    max_body_size: Option<u64>,
+    max_decompressed_body_size: Option<u64>,
 }
 
 impl RequestData {
-    pub fn new(req: hyper::Request<hyper::body::Incoming>) -> Result<Self, HttpFailResult> {
+    pub fn new(
+        req: hyper::Request<impl Into<HttpRequestBodySource>>,
+    ) -> Result<Self, HttpFailResult> {
         let (parts, incoming) = req.into_parts();
 
         let content_type = match parts.headers.get("content-type") {
@@ -34,23 +39,29 @@ impl RequestData {
         let content_type = BodyContentType::from_content_type(content_type)?;
 
         let body = HttpRequestBody::Incoming {
-            incoming: Some(incoming),
+            incoming: Some(incoming.into()),
             content_type,
         };
         let result = Self {
             parts,
             body: Some(body),
             body_read_timeout: None,
+            max_body_size: None,
+            max_decompressed_body_size: None,
         };
 
         Ok(result)
     }
 
     pub async fn get_body(&mut self) -> Result<&HttpRequestBodyContent, HttpFailResult> {
+        let options = self.body_read_options()?;
         let expectations = self.body_expectations();
 
         match self.body.as_mut() {
-            Some(body) => body.get_http_request_body(expectations).await,
+            Some(body) => {
+                body.get_http_request_body_with_options(expectations, options)
+                    .await
+            }
             None => {
                 panic!("Body is removed and can not be accessed")
             }
@@ -58,10 +69,15 @@ impl RequestData {
     }
 
     pub async fn receive_body(&mut self) -> Result<HttpRequestBodyContent, HttpFailResult> {
+        let options = self.body_read_options()?;
         let expectations = self.body_expectations();
 
         match self.body.take() {
-            Some(body) => return body.into_http_request_body(expectations).await,
+            Some(body) => {
+                return body
+                    .into_http_request_body_with_options(expectations, options)
+                    .await
+            }
             None => {
                 panic!("Body is taken by some middleware before")
             }
@@ -71,14 +87,17 @@ impl RequestData {
     /// Takes the body as a stream of chunks. Like [`receive_body`](Self::receive_body) it *takes*
     /// the body, so afterwards `get_body` / `take_my_hyper_http_request` behave exactly as they do
     /// after any other middleware consumed it.
-    pub fn take_body_stream(
-        &mut self,
-        buffer: usize,
-    ) -> Result<HttpBodyAsStream, HttpFailResult> {
-        let expectations = self.body_expectations();
+    pub fn take_body_stream(&mut self, buffer: usize) -> Result<HttpBodyAsStream, HttpFailResult> {
+        if self.body.is_some() {
+            self.check_announced_body_size()?;
+        }
+
+        let options = self.body_read_options()?;
 
         match self.body.take() {
-            Some(body) => Ok(body.into_body_stream(expectations, buffer)),
+            Some(body) => {
+                Ok(body.into_body_stream_with_options(self.body_expectations(), options, buffer))
+            }
             None => Err(HttpFailResult::as_fatal_error(
                 "Body is taken by some middleware before".to_string(),
             )),
@@ -95,10 +114,51 @@ impl RequestData {
         }
     }
 
+    /// The limits the body is held to and how it is decoded. `415` for a `Content-Encoding` the
+    /// server can not decode.
+    fn body_read_options(&self) -> Result<BodyReadOptions, HttpFailResult> {
+        let mut result = self.wire_read_options();
+        result.content_encoding = self.parts.headers.get_content_encoding()?;
+        Ok(result)
+    }
+
+    /// The same, as far as the bytes on the wire go — before anything is known about decoding
+    /// them.
+    fn wire_read_options(&self) -> BodyReadOptions {
+        BodyReadOptions {
+            max_size: self.max_body_size,
+            content_encoding: ContentEncoding::None,
+            max_decoded_size: self.max_decompressed_body_size,
+        }
+    }
+
+    /// `413` if the client announced a body over the limit. A body without an announced length
+    /// passes here and is held to the limit while it is read.
+    pub fn check_announced_body_size(&self) -> Result<(), HttpFailResult> {
+        self.wire_read_options()
+            .check_announced_size(&self.body_expectations())
+    }
+
     pub fn set_body_read_timeout(&mut self, timeout: Option<std::time::Duration>) {
         self.body_read_timeout = timeout;
     }
 
+    pub fn set_max_body_size(&mut self, max_body_size: Option<u64>) {
+        self.max_body_size = max_body_size;
+    }
+
+    pub fn get_max_body_size(&self) -> Option<u64> {
+        self.max_body_size
+    }
+
+    pub fn set_max_decompressed_body_size(&mut self, max_decompressed_body_size: Option<u64>) {
+        self.max_decompressed_body_size = max_decompressed_body_size;
+    }
+
+    pub fn get_max_decompressed_body_size(&self) -> Option<u64> {
+        self.max_decompressed_body_size
+    }
+
     /// `Content-Length` when the client sent a valid one. `None` for a chunked body — and for a
     /// malformed header, which is not worth failing the request over: the length is a hint here,
     /// the body is read frame by frame either way.
@@ -129,28 +189,34 @@ impl RequestData {
         &mut self.parts.extensions
     }
 
-    pub fn take_my_hyper_http_request(&mut self) -> MyHyperHttpRequest {
+    pub fn take_my_hyper_http_request(&mut self) -> Result<MyHyperHttpRequest, HttpFailResult> {
         match self.body.take() {
             Some(body) => match body {
-                HttpRequestBody::Incoming { mut incoming, .. } => {
-                    let result =
-                        hyper::Request::from_parts(self.parts.clone(), incoming.take().unwrap());
-
-                    return MyHyperHttpRequest::Incoming(result);
-                }
+                HttpRequestBody::Incoming { mut incoming, .. } => incoming
+                    .take()
+                    .unwrap()
+                    .into_hyper_request(self.parts.clone()),
                 HttpRequestBody::Full(body) => {
                     let body = body.as_slice().to_vec();
 
                     let body = http_body_util::Full::new(bytes::Bytes::from(body));
 
-                    let req = hyper::Request::from_parts(self.parts.clone(), body);
+                    let mut parts = self.parts.clone();
 
-                    MyHyperHttpRequest::Full(req)
+                    // The body was decoded when it was read — the headers must not say otherwise.
+                    if parts.headers.contains_key(http::header::CONTENT_ENCODING) {
+                        parts.headers.remove(http::header::CONTENT_ENCODING);
+                        parts.headers.remove(http::header::CONTENT_LENGTH);
+                    }
+
+                    let req = hyper::Request::from_parts(parts, body);
+
+                    Ok(MyHyperHttpRequest::Full(req))
                 }
             },
-            None => {
-                panic!("Body is taken by some middleware before")
-            }
+            None => Err(HttpFailResult::as_fatal_error(
+                "Body is taken by some middleware before".to_string(),
+            )),
         }
     }
 }
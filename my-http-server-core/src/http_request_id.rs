@@ -0,0 +1,98 @@
+use hyper::header::{HeaderMap, HeaderName};
+
+pub const DEFAULT_REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// An id a client or a proxy sends that is longer than this is replaced with one of our own —
+/// it ends up in every log line of the request.
+pub const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// The id of a request — taken from the header set by
+/// [`MyHttpServer::set_request_id_header`](crate::MyHttpServer::set_request_id_header) or
+/// generated. Besides [`HttpContext::get_request_id`](crate::HttpContext::get_request_id), it is
+/// kept in the extensions of the hyper request, which is where the web sockets upgraded from the
+/// request pick it up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpRequestId(String);
+
+impl HttpRequestId {
+    /// A new random id — a v4 UUID.
+    pub fn generate() -> Self {
+        Self(uuid::Uuid::new_v4().to_string())
+    }
+
+    /// The id the request came with in `header`, if it is one we can log and echo back as is:
+    /// not empty, not longer than [`MAX_REQUEST_ID_LEN`], and visible ASCII only. A new one
+    /// otherwise.
+    pub fn from_headers(headers: &HeaderMap, header: &HeaderName) -> Self {
+        let incoming = headers
+            .get(header)
+            .and_then(|itm| itm.to_str().ok())
+            .filter(|itm| is_valid_request_id(itm));
+
+        match incoming {
+            Some(incoming) => Self(incoming.to_string()),
+            None => Self::generate(),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+fn is_valid_request_id(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= MAX_REQUEST_ID_LEN
+        && value.bytes().all(|b| b.is_ascii_graphic())
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::header::HeaderValue;
+
+    use super::*;
+
+    fn headers_with(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            DEFAULT_REQUEST_ID_HEADER,
+            HeaderValue::from_str(value).unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn test_takes_incoming_id() {
+        let header = HeaderName::from_static(DEFAULT_REQUEST_ID_HEADER);
+
+        let id = HttpRequestId::from_headers(&headers_with("abc-123"), &header);
+
+        assert_eq!(id.as_str(), "abc-123");
+    }
+
+    #[test]
+    fn test_generates_id_when_missing_or_invalid() {
+        let header = HeaderName::from_static(DEFAULT_REQUEST_ID_HEADER);
+
+        let generated = HttpRequestId::from_headers(&HeaderMap::new(), &header);
+        assert_eq!(generated.as_str().len(), 36);
+
+        for invalid in ["", "with space", &"a".repeat(MAX_REQUEST_ID_LEN + 1)] {
+            let id = HttpRequestId::from_headers(&headers_with(invalid), &header);
+            assert_ne!(id.as_str(), invalid);
+            assert_eq!(id.as_str().len(), 36);
+        }
+    }
+
+    #[test]
+    fn test_custom_header_name() {
+        let header = HeaderName::from_static("x-correlation-id");
+
+        let mut headers = headers_with("ignored");
+        headers.insert(header.clone(), HeaderValue::from_static("corr-1"));
+
+        let id = HttpRequestId::from_headers(&headers, &header);
+
+        assert_eq!(id.as_str(), "corr-1");
+    }
+}
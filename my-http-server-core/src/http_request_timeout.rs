@@ -0,0 +1,144 @@
+use std::time::Duration;
+
+use tokio::{sync::watch, time::Instant};
+
+use crate::{HttpFailResult, HttpOutput, HttpRequestData, HttpResponseHeaders, WebContentType};
+
+/// What a request that ran out of time is answered with. Set through
+/// [`MyHttpServer::set_request_timeout_result_factory`](crate::MyHttpServer::set_request_timeout_result_factory);
+/// without one the answer is `504 Gateway Timeout`.
+pub trait HttpRequestTimeoutResultFactory {
+    fn get_request_timeout_result(
+        &self,
+        request: &HttpRequestData,
+        timeout: Duration,
+    ) -> HttpFailResult;
+}
+
+impl HttpFailResult {
+    /// `504` — the handler waited on something that did not answer in time.
+    pub fn as_gateway_timeout(text: impl Into<String>) -> Self {
+        Self::as_timeout(504, text)
+    }
+
+    /// `503` — for a client that should back off and retry later.
+    pub fn as_service_unavailable(text: impl Into<String>) -> Self {
+        Self::as_timeout(503, text)
+    }
+
+    fn as_timeout(status_code: u16, text: impl Into<String>) -> Self {
+        let output = HttpOutput::Content {
+            status_code,
+            headers: HttpResponseHeaders::new(WebContentType::Text.into()),
+            content: text.into().into_bytes(),
+        };
+
+        Self::new(output, true, true)
+    }
+}
+
+pub(crate) fn get_default_request_timeout_result(timeout: Duration) -> HttpFailResult {
+    HttpFailResult::as_gateway_timeout(format!("Request is not handled within {:?}", timeout))
+}
+
+/// The deadline of one request, counted from when it came in. A middleware can move it once it
+/// knows what the request is — the controllers do it for a route with a timeout of its own.
+pub(crate) struct HttpRequestDeadline {
+    started: Instant,
+    timeout: watch::Sender<Option<Duration>>,
+}
+
+impl HttpRequestDeadline {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            timeout: watch::Sender::new(None),
+        }
+    }
+
+    pub fn set_timeout(&self, timeout: Option<Duration>) {
+        self.timeout.send_replace(timeout);
+    }
+
+    pub fn get_timeout(&self) -> Option<Duration> {
+        *self.timeout.borrow()
+    }
+
+    /// Resolves with the timeout once the request is past its deadline. Never resolves while there
+    /// is none — or once the request is done with its context.
+    pub fn wait_expired(&self) -> impl std::future::Future<Output = Duration> + Send + 'static {
+        let started = self.started;
+        let mut receiver = self.timeout.subscribe();
+
+        async move {
+            loop {
+                let timeout = *receiver.borrow_and_update();
+
+                let changed = match timeout {
+                    Some(timeout) => {
+                        tokio::select! {
+                            _ = tokio::time::sleep_until(started + timeout) => return timeout,
+                            changed = receiver.changed() => changed,
+                        }
+                    }
+                    None => receiver.changed().await,
+                };
+
+                if changed.is_err() {
+                    std::future::pending::<()>().await;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn expires_after_timeout() {
+        let deadline = HttpRequestDeadline::new();
+        deadline.set_timeout(Some(Duration::from_millis(20)));
+
+        let result = tokio::time::timeout(Duration::from_secs(1), deadline.wait_expired()).await;
+
+        assert_eq!(result.unwrap(), Duration::from_millis(20));
+    }
+
+    #[tokio::test]
+    async fn no_timeout_never_expires() {
+        let deadline = HttpRequestDeadline::new();
+
+        let result = tokio::time::timeout(Duration::from_millis(50), deadline.wait_expired()).await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn timeout_can_be_moved_while_waiting() {
+        let deadline = HttpRequestDeadline::new();
+        deadline.set_timeout(Some(Duration::from_millis(20)));
+
+        let expired = deadline.wait_expired();
+
+        deadline.set_timeout(Some(Duration::from_secs(60)));
+
+        let result = tokio::time::timeout(Duration::from_millis(100), expired).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn timeout_can_be_shortened_while_waiting() {
+        let deadline = HttpRequestDeadline::new();
+        deadline.set_timeout(Some(Duration::from_secs(60)));
+
+        let expired = tokio::spawn(deadline.wait_expired());
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        deadline.set_timeout(Some(Duration::from_millis(30)));
+
+        let result = tokio::time::timeout(Duration::from_secs(1), expired).await;
+        assert_eq!(result.unwrap().unwrap(), Duration::from_millis(30));
+    }
+}
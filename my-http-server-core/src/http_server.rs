@@ -2,12 +2,12 @@ use futures::FutureExt;
 use hyper::server::conn::http1;
 use hyper::service::service_fn;
 use hyper::StatusCode;
-use std::panic::AssertUnwindSafe;
 use hyper_util::rt::{TokioExecutor, TokioIo};
 use hyper_util::server::conn::auto;
 #[cfg(feature = "with-telemetry")]
 use my_telemetry::TelemetryEventTagsBuilder;
 use rust_extensions::date_time::DateTimeAsMicroseconds;
+use std::panic::AssertUnwindSafe;
 
 use rust_extensions::{ApplicationStates, Logger};
 
@@ -18,7 +18,15 @@ use tokio::sync::Mutex;
 use std::sync::Arc;
 
 use crate::{
-    HttpContext, HttpFailResult, HttpRequest, HttpServerMiddleware, HttpServerMiddlewares,
+    serve_until_shutdown, HttpConnectionInfo, HttpConnectionLimits, HttpConnectionSlot,
+    HttpConnectionsLimiter, HttpContext, HttpFailResult, HttpHealth, HttpListener,
+    HttpMiddlewareGroup, HttpNotFound, HttpNotFoundResultFactory, HttpPanicResultFactory,
+    HttpRequest, HttpRequestBodySource, HttpRequestOutcome, HttpRequestTimeoutResultFactory,
+    HttpServerAroundMiddleware, HttpServerMetrics, HttpServerMiddleware, HttpServerMiddlewareItem,
+    HttpServerMiddlewareNext, HttpServerMiddlewares, HttpServerShutdown,
+    HttpShuttingDownResultFactory, ResponseCompression, ResponseEncoding, TrustedProxies,
+    UnixSocketOptions, DEFAULT_GRACEFUL_SHUTDOWN_TIMEOUT, DEFAULT_LISTENER_NAME,
+    DEFAULT_MAX_DECOMPRESSED_BODY_SIZE, DEFAULT_REQUEST_ID_HEADER,
 };
 use crate::{HttpOkResult, SocketAddress};
 
@@ -38,40 +46,207 @@ impl HttpConnectionsCounter {
     }
 }
 
+/// What a started [`MyHttpServer`] hands back to control it while it runs.
+#[derive(Clone)]
+pub struct HttpServerHandle {
+    shutdown: Arc<HttpServerShutdown>,
+    #[cfg(feature = "with-tls")]
+    tls_certificates: HashMap<String, crate::TlsCertificatesHandle>,
+    #[cfg(unix)]
+    listen_fds: crate::ListenFdsRegistry,
+}
+
+impl HttpServerHandle {
+    /// Stops the server gracefully and resolves once it is down: no new connections are accepted,
+    /// requests in flight are allowed to finish within the timeout set by
+    /// [`MyHttpServer::set_graceful_shutdown_timeout`], and whatever is still open after that is
+    /// closed.
+    ///
+    /// The same sequence starts on its own when the application states report shutting down —
+    /// awaiting this is then how `main` waits for the drain to complete.
+    pub async fn shutdown(&self) {
+        self.shutdown.shutdown().await;
+    }
+
+    /// Swaps the certificates of the TLS listener the server was created with at runtime. `None`
+    /// if that listener does not terminate TLS.
+    #[cfg(feature = "with-tls")]
+    pub fn get_tls_certificates(&self) -> Option<&crate::TlsCertificatesHandle> {
+        self.get_listener_tls_certificates(DEFAULT_LISTENER_NAME)
+    }
+
+    /// Same, for a listener added through [`MyHttpServer::add_listener`].
+    #[cfg(feature = "with-tls")]
+    pub fn get_listener_tls_certificates(
+        &self,
+        listener_name: &str,
+    ) -> Option<&crate::TlsCertificatesHandle> {
+        self.tls_certificates.get(listener_name)
+    }
+
+    /// The sockets the listeners accept on, each named after its listener — what
+    /// [`spawn_with_listen_fds`](crate::spawn_with_listen_fds) hands to the next version of the
+    /// binary. A listener shows up once its socket is bound, and the descriptors are only valid
+    /// until the server has shut down.
+    #[cfg(unix)]
+    pub fn get_listen_fds(&self) -> Vec<crate::ListenFd> {
+        self.listen_fds.get()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum ListenAddr {
     Tcp(SocketAddr),
     Unix(Arc<String>),
+    /// A TCP or Unix socket that is bound and listening already — passed by systemd, by the
+    /// previous version of the binary, or bound by the application itself. The server takes
+    /// ownership of the descriptor.
+    #[cfg(unix)]
+    Fd(std::os::unix::io::RawFd),
+}
+
+/// How a listener speaks HTTP on the connections it accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpProtocol {
+    /// HTTP/1 or HTTP/2, detected per connection — by ALPN on a TLS listener, by the connection
+    /// preface otherwise.
+    Auto,
+    Http1,
+    Http2,
+}
+
+impl HttpProtocol {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HttpProtocol::Auto => "auto h1/h2",
+            HttpProtocol::Http1 => "h1 only",
+            HttpProtocol::Http2 => "h2",
+        }
+    }
+
+    /// What a TLS listener offers through ALPN, most preferred first.
+    pub fn get_alpn_protocols(&self) -> Vec<Vec<u8>> {
+        match self {
+            HttpProtocol::Auto => vec![b"h2".to_vec(), b"http/1.1".to_vec()],
+            HttpProtocol::Http1 => vec![b"http/1.1".to_vec()],
+            HttpProtocol::Http2 => vec![b"h2".to_vec()],
+        }
+    }
 }
 
 pub struct MyHttpServer {
     pub addr: ListenAddr,
-    middlewares: Option<Vec<Arc<dyn HttpServerMiddleware + Send + Sync + 'static>>>,
+    listeners: Vec<HttpListener>,
+    middlewares: Option<Vec<HttpServerMiddlewareItem>>,
     tech_middlewares: Option<Vec<Arc<dyn HttpServerTechMiddleware + Send + Sync + 'static>>>,
     connections: Arc<AtomicI64>,
     body_read_timeout: Option<std::time::Duration>,
+    max_body_size: Option<u64>,
+    max_decompressed_body_size: u64,
+    graceful_shutdown_timeout: std::time::Duration,
+    shutdown_delay: std::time::Duration,
+    connection_limits: HttpConnectionLimits,
+    unix_socket_options: UnixSocketOptions,
+    proxy_protocol: bool,
+    trusted_proxies: Option<Arc<TrustedProxies>>,
+    request_timeout: Option<std::time::Duration>,
+    request_timeout_result_factory:
+        Option<Arc<dyn HttpRequestTimeoutResultFactory + Send + Sync + 'static>>,
+    panic_result_factory: Option<Arc<dyn HttpPanicResultFactory + Send + Sync + 'static>>,
+    not_found_result_factory: Option<Arc<dyn HttpNotFoundResultFactory + Send + Sync + 'static>>,
+    shutting_down_result_factory:
+        Option<Arc<dyn HttpShuttingDownResultFactory + Send + Sync + 'static>>,
+    shutting_down_retry_after: Option<std::time::Duration>,
+    response_compression: Option<Arc<ResponseCompression>>,
+    metrics: Option<HttpServerMetrics>,
+    health: Option<HttpHealth>,
+    request_id_header: hyper::header::HeaderName,
+    #[cfg(feature = "with-telemetry")]
+    trace_response: bool,
+    #[cfg(feature = "with-tls")]
+    tls: Option<crate::TlsSettings>,
 }
 
 impl MyHttpServer {
     pub fn new(addr: SocketAddr) -> Self {
-        Self {
-            addr: ListenAddr::Tcp(addr),
-            middlewares: Some(Vec::new()),
-            tech_middlewares: Some(Vec::new()),
-            connections: Arc::new(AtomicI64::new(0)),
-            body_read_timeout: None,
-        }
+        Self::create(ListenAddr::Tcp(addr))
     }
 
+    /// A path, or `@name` for the Linux abstract namespace — see [`UnixSocketOptions`].
     #[cfg(unix)]
     pub fn new_as_unix_socket(unix_socket_addr: impl Into<String>) -> Self {
+        Self::create(ListenAddr::Unix(Arc::new(unix_socket_addr.into())))
+    }
+
+    /// Serve a listening socket the process got instead of binding one — see
+    /// [`take_listen_fds`](crate::take_listen_fds).
+    #[cfg(unix)]
+    pub fn new_from_listen_fd(fd: std::os::unix::io::RawFd) -> Self {
+        Self::create(ListenAddr::Fd(fd))
+    }
+
+    #[cfg(unix)]
+    pub fn new_from_std_tcp_listener(listener: std::net::TcpListener) -> Self {
+        use std::os::unix::io::IntoRawFd;
+        Self::new_from_listen_fd(listener.into_raw_fd())
+    }
+
+    #[cfg(unix)]
+    pub fn new_from_std_unix_listener(listener: std::os::unix::net::UnixListener) -> Self {
+        use std::os::unix::io::IntoRawFd;
+        Self::new_from_listen_fd(listener.into_raw_fd())
+    }
+
+    fn create(addr: ListenAddr) -> Self {
         Self {
-            addr: ListenAddr::Unix(Arc::new(unix_socket_addr.into())),
+            addr,
+            listeners: Vec::new(),
             middlewares: Some(Vec::new()),
             tech_middlewares: Some(Vec::new()),
             connections: Arc::new(AtomicI64::new(0)),
             body_read_timeout: None,
+            max_body_size: None,
+            max_decompressed_body_size: DEFAULT_MAX_DECOMPRESSED_BODY_SIZE,
+            graceful_shutdown_timeout: DEFAULT_GRACEFUL_SHUTDOWN_TIMEOUT,
+            shutdown_delay: std::time::Duration::ZERO,
+            connection_limits: HttpConnectionLimits::default(),
+            unix_socket_options: UnixSocketOptions::default(),
+            proxy_protocol: false,
+            trusted_proxies: None,
+            request_timeout: None,
+            request_timeout_result_factory: None,
+            panic_result_factory: None,
+            not_found_result_factory: None,
+            shutting_down_result_factory: None,
+            shutting_down_retry_after: None,
+            response_compression: None,
+            metrics: None,
+            health: None,
+            request_id_header: hyper::header::HeaderName::from_static(DEFAULT_REQUEST_ID_HEADER),
+            #[cfg(feature = "with-telemetry")]
+            trace_response: false,
+            #[cfg(feature = "with-tls")]
+            tls: None,
+        }
+    }
+
+    /// Accept connections on one more address — another port, a Unix socket — with the same
+    /// middlewares, limits and shutdown as the address the server was created with.
+    pub fn add_listener(&mut self, listener: HttpListener) {
+        if self.middlewares.is_none() {
+            panic!("You can not add listener after starting server");
         }
+
+        if listener.name == DEFAULT_LISTENER_NAME
+            || self.listeners.iter().any(|itm| itm.name == listener.name)
+        {
+            panic!(
+                "Http listener with name '{}' is already added",
+                listener.name
+            );
+        }
+
+        self.listeners.push(listener);
     }
 
     /// Stop waiting on a client that has gone quiet mid-body.
@@ -86,6 +261,198 @@ impl MyHttpServer {
         self.body_read_timeout = Some(timeout);
     }
 
+    /// The most bytes a request body may have. A request that announces more in `Content-Length`
+    /// is answered with `413` before any of its body is read; a chunked or HTTP/2 body without a
+    /// length is cut off — and answered with `413` — as soon as it goes over. A body taken as a
+    /// stream is held to the same limit by the pump that fills it.
+    ///
+    /// A route can have a limit of its own (`max_body_size` of `#[http_route]`), and any
+    /// middleware can change it through
+    /// [`HttpRequest::set_max_body_size`](crate::HttpRequest::set_max_body_size) before the body
+    /// is read. Off by default.
+    pub fn set_max_body_size(&mut self, max_body_size: u64) {
+        self.max_body_size = Some(max_body_size);
+    }
+
+    /// The most bytes a compressed request body may decode to. A body sent with
+    /// `Content-Encoding` — gzip, deflate, br or zstd — is decoded as it is read, so actions and
+    /// streamed bodies see plaintext; one that inflates past this limit is cut off and answered
+    /// with `413`, whatever [the limit on the body](Self::set_max_body_size) itself says.
+    ///
+    /// Defaults to [`DEFAULT_MAX_DECOMPRESSED_BODY_SIZE`].
+    pub fn set_max_decompressed_body_size(&mut self, max_decompressed_body_size: u64) {
+        self.max_decompressed_body_size = max_decompressed_body_size;
+    }
+
+    /// How long a shutdown waits for requests in flight before it closes the connections they
+    /// are on. Defaults to [`DEFAULT_GRACEFUL_SHUTDOWN_TIMEOUT`].
+    pub fn set_graceful_shutdown_timeout(&mut self, timeout: std::time::Duration) {
+        self.graceful_shutdown_timeout = timeout;
+    }
+
+    /// How long the server keeps accepting and serving after the application states report
+    /// shutting down, before it stops accepting connections — the time a load balancer needs to
+    /// see the readiness go unhealthy (see [`set_health`](Self::set_health)) and stop sending
+    /// requests here. Does not apply to [`HttpServerHandle::shutdown`]. None by default.
+    pub fn set_shutdown_delay(&mut self, delay: std::time::Duration) {
+        self.shutdown_delay = delay;
+    }
+
+    /// Caps on open connections, enforced when they are accepted — before a single byte of a
+    /// request is read. Refused connections are reported to the tech middlewares through
+    /// [`HttpServerTechMiddleware::connection_refused`] — the place to count them — and written to
+    /// the logger at debug level, so a flood of them does not flood the log.
+    pub fn set_connection_limits(&mut self, connection_limits: HttpConnectionLimits) {
+        self.connection_limits = connection_limits;
+    }
+
+    /// Permissions, ownership and stale-file handling of the socket file, when the server was
+    /// created with [`MyHttpServer::new_as_unix_socket`].
+    pub fn set_unix_socket_options(&mut self, unix_socket_options: UnixSocketOptions) {
+        self.unix_socket_options = unix_socket_options;
+    }
+
+    /// Put this server's listener behind an L4 balancer that speaks the HAProxy PROXY protocol.
+    /// Every connection must then start with a v1 or v2 header; one that does not is closed. The
+    /// client address from the header becomes the request's `addr` — and what the connection
+    /// limits count — and the whole header is kept in `ctx.request.connection.proxy_protocol`.
+    ///
+    /// Only turn it on when the listener can be reached through the balancer alone — anybody who
+    /// can connect directly can claim any address.
+    pub fn set_proxy_protocol(&mut self, proxy_protocol: bool) {
+        self.proxy_protocol = proxy_protocol;
+    }
+
+    /// The reverse proxies in front of this server. Once set, the forwarding headers they write —
+    /// see [`ForwardingHeaders`](crate::ForwardingHeaders) — are only taken from them, which is
+    /// what [`HttpRequest::get_ip`](crate::HttpRequest::get_ip),
+    /// [`get_scheme`](crate::HttpRequest::get_scheme) and [`get_host`](crate::HttpRequest::get_host)
+    /// — and the IP checks of the controllers built on them — answer from.
+    ///
+    /// Until it is set, the headers are believed whoever sends them, as they always were.
+    pub fn set_trusted_proxies(&mut self, trusted_proxies: TrustedProxies) {
+        self.trusted_proxies = Some(Arc::new(trusted_proxies));
+    }
+
+    /// How long a request may take from the moment it comes in until the middleware chain answers.
+    /// A request past it has the chain cancelled — whatever the handler was awaiting is dropped —
+    /// and is answered with a `504`, or what
+    /// [`set_request_timeout_result_factory`](Self::set_request_timeout_result_factory) gives.
+    /// Tech middlewares see it as [`HttpRequestOutcome::TimedOut`](crate::HttpRequestOutcome).
+    ///
+    /// A route can have a timeout of its own (`request_timeout_ms` of `#[http_route]`), and any
+    /// middleware can move the deadline through
+    /// [`HttpContext::set_request_timeout`](crate::HttpContext::set_request_timeout).
+    ///
+    /// Off by default. A streamed response is not covered once the chain has returned it.
+    pub fn set_request_timeout(&mut self, timeout: std::time::Duration) {
+        self.request_timeout = Some(timeout);
+    }
+
+    /// Answer timed out requests with something other than `504` — e.g.
+    /// [`HttpFailResult::as_service_unavailable`].
+    pub fn set_request_timeout_result_factory(
+        &mut self,
+        factory: Arc<dyn HttpRequestTimeoutResultFactory + Send + Sync + 'static>,
+    ) {
+        self.request_timeout_result_factory = Some(factory);
+    }
+
+    /// Answer requests whose handler panicked with something other than a plain-text `500` —
+    /// e.g. the JSON error envelope of the API.
+    pub fn set_panic_result_factory(
+        &mut self,
+        factory: Arc<dyn HttpPanicResultFactory + Send + Sync + 'static>,
+    ) {
+        self.panic_result_factory = Some(factory);
+    }
+
+    /// Answer requests no middleware answered with something other than a plain-text `404`.
+    pub fn set_not_found_result_factory(
+        &mut self,
+        factory: Arc<dyn HttpNotFoundResultFactory + Send + Sync + 'static>,
+    ) {
+        self.not_found_result_factory = Some(factory);
+    }
+
+    /// Answer requests that come in once the application is shutting down with something other
+    /// than a plain-text `502` — e.g. [`HttpFailResult::as_service_unavailable`].
+    pub fn set_shutting_down_result_factory(
+        &mut self,
+        factory: Arc<dyn HttpShuttingDownResultFactory + Send + Sync + 'static>,
+    ) {
+        self.shutting_down_result_factory = Some(factory);
+    }
+
+    /// Send `Retry-After` with the response to requests that come in once the application is
+    /// shutting down — how long a client should wait before it tries again, by when the new
+    /// instance is expected to be up. In whole seconds, at least one.
+    pub fn set_shutting_down_retry_after(&mut self, retry_after: std::time::Duration) {
+        self.shutting_down_retry_after = Some(retry_after);
+    }
+
+    /// Compress responses with the encoding the client asks for in `Accept-Encoding` — br, zstd
+    /// or gzip, see [`ResponseCompression`]. Streamed responses are compressed as they are sent.
+    ///
+    /// A route can opt out with `compress_response: false` of `#[http_route]`, and any middleware
+    /// through [`HttpContext::set_response_compression`](crate::HttpContext::set_response_compression).
+    /// Off by default.
+    pub fn set_response_compression(&mut self, response_compression: ResponseCompression) {
+        self.response_compression = Some(Arc::new(response_compression));
+    }
+
+    /// Count requests, their latency and sizes, the requests in flight, the open connections and
+    /// the panics into `metrics`, and answer `GET /metrics` with them — see [`HttpServerMetrics`].
+    ///
+    /// The endpoint is put in front of all the middlewares, so it is reachable without whatever
+    /// authentication they do: give the metrics a path of their own with
+    /// [`HttpServerMetrics::with_path`], or expose them on another listener or server with
+    /// [`HttpServerMetrics::without_endpoint`] and [`HttpServerMetrics::render`].
+    pub fn set_metrics(&mut self, metrics: HttpServerMetrics) {
+        self.metrics = Some(metrics);
+    }
+
+    /// Answer `GET /health/live` and `GET /health/ready` — see [`HttpHealth`]. The readiness
+    /// follows the application states the server is started with.
+    pub fn set_health(&mut self, health: HttpHealth) {
+        self.health = Some(health);
+    }
+
+    /// The header a request brings its id in and gets it back in — `X-Request-Id` by default. A
+    /// request without one, or with one that is too long or not printable, gets a generated id.
+    /// The id is kept in [`HttpContext::get_request_id`], written into every log line and
+    /// telemetry event of the request, passed to the tech middlewares, and given to the web
+    /// sockets upgraded from the request.
+    ///
+    /// Panics if `header` is not a valid header name.
+    pub fn set_request_id_header(&mut self, header: &str) {
+        self.request_id_header = match hyper::header::HeaderName::try_from(header) {
+            Ok(header) => header,
+            Err(err) => panic!("Invalid request id header name '{}': {}", header, err),
+        };
+    }
+
+    /// Answer every request with a `traceresponse` header — the trace it was handled in and the
+    /// span of this server, see [`HttpTraceContext`](crate::HttpTraceContext). The trace itself is
+    /// always taken from `traceparent`; this only tells the caller about it. Off by default.
+    #[cfg(feature = "with-telemetry")]
+    pub fn set_trace_response(&mut self, trace_response: bool) {
+        self.trace_response = trace_response;
+    }
+
+    /// Terminate TLS on this server's listener. Certificates are read from their PEM files when
+    /// the server starts; a file that can not be loaded stops the start the same way a port that
+    /// can not be bound does. Afterwards they can be swapped without a restart through
+    /// [`HttpServerHandle::get_tls_certificates`], or picked up from disk automatically with
+    /// [`TlsSettings::watch_files`](crate::TlsSettings::watch_files).
+    ///
+    /// Requests served over TLS report `https` from
+    /// [`HttpRequest::get_scheme`](crate::HttpRequest::get_scheme) on their own.
+    #[cfg(feature = "with-tls")]
+    pub fn set_tls(&mut self, tls: crate::TlsSettings) {
+        self.tls = Some(tls);
+    }
+
     pub fn add_middleware(
         &mut self,
         middleware: Arc<dyn HttpServerMiddleware + Send + Sync + 'static>,
@@ -93,7 +460,37 @@ impl MyHttpServer {
         if self.middlewares.is_none() {
             panic!("You can not add middleware after starting server");
         }
-        self.middlewares.as_mut().unwrap().push(middleware);
+        self.middlewares
+            .as_mut()
+            .unwrap()
+            .push(HttpServerMiddlewareItem::Middleware(middleware));
+    }
+
+    /// Adds a middleware that wraps the rest of the chain — the middlewares added after it — and
+    /// can change what they answer. See [`HttpServerAroundMiddleware`].
+    pub fn add_around_middleware(
+        &mut self,
+        middleware: Arc<dyn HttpServerAroundMiddleware + Send + Sync + 'static>,
+    ) {
+        if self.middlewares.is_none() {
+            panic!("You can not add middleware after starting server");
+        }
+        self.middlewares
+            .as_mut()
+            .unwrap()
+            .push(HttpServerMiddlewareItem::Around(middleware));
+    }
+
+    /// Adds middlewares that only run for the requests under the prefix of `group` — see
+    /// [`HttpMiddlewareGroup`]. They take their place in the chain where the group is added.
+    pub fn add_middleware_group(&mut self, group: HttpMiddlewareGroup) {
+        if self.middlewares.is_none() {
+            panic!("You can not add middleware after starting server");
+        }
+        self.middlewares
+            .as_mut()
+            .unwrap()
+            .push(HttpServerMiddlewareItem::Group(group));
     }
 
     pub fn add_tech_middleware(
@@ -116,200 +513,531 @@ impl MyHttpServer {
         &mut self,
         app_states: Arc<dyn ApplicationStates + Send + Sync + 'static>,
         logger: Arc<dyn Logger + Send + Sync + 'static>,
-    ) {
-        let middlewares = self.middlewares.take();
-
-        if middlewares.is_none() {
-            panic!("You can not start HTTP server two times");
-        }
+    ) -> HttpServerHandle {
+        self.start_with_protocol(HttpProtocol::Auto, app_states, logger)
+    }
 
-        logger.write_info(
-            "Starting Http Server".to_string(),
-            format!("Http server (auto h1/h2) starts at: {:?}", self.addr),
-            None,
-        );
+    /// Starts server in HTTP/1 only mode. Use it when auto h1/h2 negotiation is not desired.
+    pub fn start_h1(
+        &mut self,
+        app_states: Arc<dyn ApplicationStates + Send + Sync + 'static>,
+        logger: Arc<dyn Logger + Send + Sync + 'static>,
+    ) -> HttpServerHandle {
+        self.start_with_protocol(HttpProtocol::Http1, app_states, logger)
+    }
 
-        let http_server_middlewares = HttpServerMiddlewares {
-            middlewares: middlewares.unwrap(),
-            tech_middlewares: self.tech_middlewares.take().unwrap(),
-            body_read_timeout: self.body_read_timeout,
-        };
+    pub fn start_h2(
+        &mut self,
+        app_states: Arc<dyn ApplicationStates + Send + Sync + 'static>,
+        logger: Arc<dyn Logger + Send + Sync + 'static>,
+    ) -> HttpServerHandle {
+        self.start_with_protocol(HttpProtocol::Http2, app_states, logger)
+    }
 
-        let connections = self.connections.clone();
-        match &self.addr {
-            ListenAddr::Tcp(socket_addr) => {
-                let socket_addr = socket_addr.clone();
-                tokio::spawn(start_http_auto(
-                    socket_addr,
-                    Arc::new(http_server_middlewares),
-                    app_states,
-                    logger,
-                    connections,
-                ));
-            }
-            ListenAddr::Unix(unix_socket_addr) => {
-                let unix_socket_addr = unix_socket_addr.clone();
-                tokio::spawn(start_http_auto_unix_socket(
-                    unix_socket_addr,
-                    Arc::new(http_server_middlewares),
-                    app_states,
-                    logger,
-                    connections,
-                ));
-            }
-        }
+    pub fn start_auto(
+        &mut self,
+        app_states: Arc<dyn ApplicationStates + Send + Sync + 'static>,
+        logger: Arc<dyn Logger + Send + Sync + 'static>,
+    ) -> HttpServerHandle {
+        self.start_with_protocol(HttpProtocol::Auto, app_states, logger)
     }
 
-    /// Starts server in HTTP/1 only mode. Use it when auto h1/h2 negotiation is not desired.
-    pub fn start_h1(
+    /// Runs requests through everything this server is configured with — middlewares, limits,
+    /// timeouts, compression, the results of [panics](Self::set_panic_result_factory) and
+    /// [unmatched requests](Self::set_not_found_result_factory) — without binding a socket. See
+    /// [`HttpTestClient`](crate::HttpTestClient).
+    ///
+    /// Takes the middlewares the way starting the server does: a server can not be both started
+    /// and tested.
+    #[cfg(feature = "test-client")]
+    pub fn create_test_client(
         &mut self,
         app_states: Arc<dyn ApplicationStates + Send + Sync + 'static>,
         logger: Arc<dyn Logger + Send + Sync + 'static>,
-    ) {
+    ) -> crate::HttpTestClient {
+        let shutdown = self.create_shutdown(logger.clone());
+        let http_server_middlewares = self.take_http_server_middlewares(&app_states, &shutdown);
+
+        crate::HttpTestClient::new(http_server_middlewares, logger, shutdown)
+    }
+
+    fn create_shutdown(
+        &self,
+        logger: Arc<dyn Logger + Send + Sync + 'static>,
+    ) -> Arc<HttpServerShutdown> {
+        Arc::new(
+            HttpServerShutdown::new(
+                self.connections.clone(),
+                self.graceful_shutdown_timeout,
+                logger,
+            )
+            .with_delay(self.shutdown_delay),
+        )
+    }
+
+    /// Everything the requests are handled with. Panics the second time: the middlewares are moved
+    /// into the result.
+    fn take_http_server_middlewares(
+        &mut self,
+        app_states: &Arc<dyn ApplicationStates + Send + Sync + 'static>,
+        shutdown: &Arc<HttpServerShutdown>,
+    ) -> Arc<HttpServerMiddlewares> {
         let middlewares = self.middlewares.take();
 
         if middlewares.is_none() {
             panic!("You can not start HTTP server two times");
         }
 
-        logger.write_info(
-            "Starting Http Server".to_string(),
-            format!("Http server (h1 only) starts at: {:?}", self.addr),
-            None,
-        );
+        let mut middlewares = middlewares.unwrap();
+        let mut tech_middlewares = self.tech_middlewares.take().unwrap();
 
-        let http_server_middlewares = HttpServerMiddlewares {
-            middlewares: middlewares.unwrap(),
-            tech_middlewares: self.tech_middlewares.take().unwrap(),
-            body_read_timeout: self.body_read_timeout,
-        };
+        if let Some(health) = self.health.take() {
+            let endpoint = health.into_endpoint(app_states.clone(), shutdown.clone());
+            middlewares.insert(0, HttpServerMiddlewareItem::Middleware(Arc::new(endpoint)));
+        }
 
-        let connections = self.connections.clone();
-        match &self.addr {
-            ListenAddr::Tcp(socket_addr) => {
-                let socket_addr = socket_addr.clone();
-                tokio::spawn(start_http_1(
-                    socket_addr,
-                    Arc::new(http_server_middlewares),
-                    app_states,
-                    logger,
-                    connections,
-                ));
-            }
-            ListenAddr::Unix(unix_socket_addr) => {
-                let unix_socket_addr = unix_socket_addr.clone();
-                tokio::spawn(start_http_1_unix_socket(
-                    unix_socket_addr,
-                    Arc::new(http_server_middlewares),
-                    app_states,
-                    logger,
-                    connections,
-                ));
+        if let Some(metrics) = self.metrics.as_ref() {
+            metrics.add_http_connections_counter(self.get_http_connections_counter());
+
+            if let Some(endpoint) = metrics.get_endpoint() {
+                middlewares.insert(0, HttpServerMiddlewareItem::Middleware(endpoint));
             }
+
+            tech_middlewares.push(Arc::new(crate::HttpMetricsRecorder::new(metrics.clone())));
         }
+
+        Arc::new(HttpServerMiddlewares {
+            middlewares: crate::resolve_middlewares(middlewares),
+            tech_middlewares,
+            body_read_timeout: self.body_read_timeout,
+            max_body_size: self.max_body_size,
+            max_decompressed_body_size: self.max_decompressed_body_size,
+            trusted_proxies: self.trusted_proxies.clone(),
+            request_timeout: self.request_timeout,
+            request_timeout_result_factory: self.request_timeout_result_factory.clone(),
+            panic_result_factory: self.panic_result_factory.clone(),
+            not_found_result_factory: self.not_found_result_factory.clone(),
+            shutting_down_result_factory: self.shutting_down_result_factory.clone(),
+            shutting_down_retry_after: self.shutting_down_retry_after,
+            response_compression: self.response_compression.clone(),
+            metrics: self.metrics.clone(),
+            request_id_header: self.request_id_header.clone(),
+            #[cfg(feature = "with-telemetry")]
+            trace_response: self.trace_response,
+        })
     }
 
-    pub fn start_h2(
+    fn start_with_protocol(
         &mut self,
+        protocol: HttpProtocol,
         app_states: Arc<dyn ApplicationStates + Send + Sync + 'static>,
         logger: Arc<dyn Logger + Send + Sync + 'static>,
-    ) {
-        let middlewares = self.middlewares.take();
+    ) -> HttpServerHandle {
+        let shutdown = self.create_shutdown(logger.clone());
+        let http_server_middlewares = self.take_http_server_middlewares(&app_states, &shutdown);
+
+        shutdown.spawn_app_states_watcher(app_states.clone());
+
+        let connections_limiter =
+            Arc::new(HttpConnectionsLimiter::new(self.connection_limits.clone()));
+
+        #[allow(unused_mut)]
+        let mut result = HttpServerHandle {
+            shutdown: shutdown.clone(),
+            #[cfg(feature = "with-tls")]
+            tls_certificates: HashMap::new(),
+            #[cfg(unix)]
+            listen_fds: crate::ListenFdsRegistry::default(),
+        };
 
-        let tech_middlewares = self.tech_middlewares.take();
-        if middlewares.is_none() {
-            panic!("You can not start HTTP2 server two times");
-        }
+        let default_listener = HttpListener {
+            name: DEFAULT_LISTENER_NAME.to_string(),
+            addr: self.addr.clone(),
+            protocol: Some(protocol),
+            unix_socket_options: self.unix_socket_options.clone(),
+            proxy_protocol: self.proxy_protocol,
+            #[cfg(feature = "with-tls")]
+            tls: self.tls.clone(),
+        };
 
-        logger.write_info(
-            "Starting Http2 Server".to_string(),
-            format!("Http2 server starts at: {:?}", self.addr),
-            None,
-        );
+        let listeners = std::iter::once(default_listener).chain(self.listeners.drain(..));
 
-        let http_server_middlewares = HttpServerMiddlewares {
-            middlewares: middlewares.unwrap(),
-            tech_middlewares: tech_middlewares.unwrap(),
-            body_read_timeout: self.body_read_timeout,
-        };
+        for listener in listeners {
+            let listener_protocol = listener.protocol.unwrap_or(protocol);
+
+            let mut listener_ctx = HttpListenerContext::new(
+                Arc::new(listener.name.clone()),
+                listener_protocol,
+                http_server_middlewares.clone(),
+                logger.clone(),
+                self.connections.clone(),
+                connections_limiter.clone(),
+                shutdown.clone(),
+            );
+
+            listener_ctx.proxy_protocol = listener.proxy_protocol;
 
-        let connections = self.connections.clone();
-
-        match &self.addr {
-            ListenAddr::Tcp(socket_addr) => {
-                let socket_addr = socket_addr.clone();
-                tokio::spawn(start_http_2(
-                    socket_addr,
-                    Arc::new(http_server_middlewares),
-                    app_states,
-                    logger,
-                    connections,
-                ));
+            #[cfg(unix)]
+            {
+                listener_ctx.listen_fds = result.listen_fds.clone();
             }
-            ListenAddr::Unix(_) => {
-                panic!("Unix socket does not support Http2 yet");
+
+            #[cfg(feature = "with-tls")]
+            if let Some(tls) = listener.tls.as_ref() {
+                match tls.build_server_config(listener_protocol) {
+                    Ok((server_config, tls_certificates)) => {
+                        listener_ctx.tls_acceptor =
+                            Some(tokio_rustls::TlsAcceptor::from(server_config));
+
+                        if let Some(interval) = tls.watch_files_interval {
+                            tokio::spawn(crate::watch_tls_files(
+                                tls_certificates.clone(),
+                                interval,
+                                app_states.clone(),
+                                logger.clone(),
+                            ));
+                        }
+
+                        result
+                            .tls_certificates
+                            .insert(listener.name.clone(), tls_certificates);
+                    }
+                    Err(err) => {
+                        let err = format!(
+                            "Can not start https server at {:?}. Err: {}",
+                            listener.addr, err
+                        );
+                        eprintln!("{}", err);
+                        panic!("{}", err);
+                    }
+                }
+            }
+
+            logger.write_info(
+                "Starting Http Server".to_string(),
+                format!(
+                    "Http{} server ({}{}) starts at: {:?}. Listener: {}",
+                    if listener_ctx.is_tls() { "s" } else { "" },
+                    listener_protocol.as_str(),
+                    if listener_ctx.proxy_protocol {
+                        ", PROXY protocol"
+                    } else {
+                        ""
+                    },
+                    listener.addr,
+                    listener.name
+                ),
+                None,
+            );
+
+            let listener_ctx = Arc::new(listener_ctx);
+
+            match listener.addr {
+                ListenAddr::Tcp(socket_addr) => {
+                    tokio::spawn(run_tcp_listener(socket_addr, listener_ctx));
+                }
+                ListenAddr::Unix(unix_socket_addr) => {
+                    tokio::spawn(run_unix_listener(
+                        unix_socket_addr,
+                        listener.unix_socket_options,
+                        listener_ctx,
+                    ));
+                }
+                #[cfg(unix)]
+                ListenAddr::Fd(fd) => {
+                    tokio::spawn(run_fd_listener(fd, listener_ctx));
+                }
             }
         }
+
+        result
     }
+}
 
-    pub fn start_auto(
-        &mut self,
+/// Everything an accept loop hands over to the connections it accepts.
+struct HttpListenerContext {
+    listener_name: Arc<String>,
+    protocol: HttpProtocol,
+    http_server_middlewares: Arc<HttpServerMiddlewares>,
+    logger: Arc<dyn Logger + Send + Sync + 'static>,
+    connections: Arc<AtomicI64>,
+    connections_limiter: Arc<HttpConnectionsLimiter>,
+    shutdown: Arc<HttpServerShutdown>,
+    proxy_protocol: bool,
+    #[cfg(unix)]
+    listen_fds: crate::ListenFdsRegistry,
+    #[cfg(feature = "with-tls")]
+    tls_acceptor: Option<tokio_rustls::TlsAcceptor>,
+}
+
+impl HttpListenerContext {
+    fn new(
+        listener_name: Arc<String>,
+        protocol: HttpProtocol,
+        http_server_middlewares: Arc<HttpServerMiddlewares>,
+        logger: Arc<dyn Logger + Send + Sync + 'static>,
+        connections: Arc<AtomicI64>,
+        connections_limiter: Arc<HttpConnectionsLimiter>,
+        shutdown: Arc<HttpServerShutdown>,
+    ) -> Self {
+        Self {
+            listener_name,
+            protocol,
+            http_server_middlewares,
+            logger,
+            connections,
+            connections_limiter,
+            shutdown,
+            proxy_protocol: false,
+            #[cfg(unix)]
+            listen_fds: crate::ListenFdsRegistry::default(),
+            #[cfg(feature = "with-tls")]
+            tls_acceptor: None,
+        }
+    }
+
+    /// A listener that is a server on its own — what the `start_http_*` functions run.
+    fn new_standalone(
+        protocol: HttpProtocol,
+        http_server_middlewares: Arc<HttpServerMiddlewares>,
         app_states: Arc<dyn ApplicationStates + Send + Sync + 'static>,
         logger: Arc<dyn Logger + Send + Sync + 'static>,
-    ) {
-        let middlewares = self.middlewares.take();
-        let tech_middlewares = self.tech_middlewares.take();
+        connections: Arc<AtomicI64>,
+    ) -> Self {
+        let shutdown = Arc::new(HttpServerShutdown::new(
+            connections.clone(),
+            DEFAULT_GRACEFUL_SHUTDOWN_TIMEOUT,
+            logger.clone(),
+        ));
+
+        shutdown.spawn_app_states_watcher(app_states);
+
+        Self::new(
+            Arc::new(DEFAULT_LISTENER_NAME.to_string()),
+            protocol,
+            http_server_middlewares,
+            logger,
+            connections,
+            Arc::new(HttpConnectionsLimiter::new(HttpConnectionLimits::default())),
+            shutdown,
+        )
+    }
 
-        if middlewares.is_none() {
-            panic!("You can not start HTTP server two times");
-        }
+    /// Applies the connection limits to a just accepted connection. `None` means it was refused:
+    /// the refusal is already reported, and dropping the stream closes it.
+    fn admit_connection(
+        &self,
+        addr: &SocketAddress,
+        permit: Option<tokio::sync::OwnedSemaphorePermit>,
+    ) -> Option<HttpConnectionSlot> {
+        let reason = match self.connections_limiter.admit(addr, permit) {
+            Ok(slot) => return Some(slot),
+            Err(reason) => reason,
+        };
 
-        logger.write_info(
-            "Starting Http Server (auto h1/h2)".to_string(),
-            format!("Http server (auto) starts at: {:?}", self.addr),
-            None,
+        let mut ctx = HashMap::new();
+        ctx.insert("ip".to_string(), addr.ip_as_string());
+
+        self.logger.write_debug_info(
+            "HttpConnectionLimits".to_string(),
+            format!("Connection is refused. Reason: {:?}", reason),
+            Some(ctx),
         );
 
-        let http_server_middlewares = HttpServerMiddlewares {
-            middlewares: middlewares.unwrap(),
-            tech_middlewares: tech_middlewares.unwrap(),
-            body_read_timeout: self.body_read_timeout,
-        };
+        if !self.http_server_middlewares.tech_middlewares.is_empty() {
+            let http_server_middlewares = self.http_server_middlewares.clone();
 
-        let connections = self.connections.clone();
-
-        match &self.addr {
-            ListenAddr::Tcp(socket_addr) => {
-                let socket_addr = socket_addr.clone();
-                tokio::spawn(start_http_auto(
-                    socket_addr,
-                    Arc::new(http_server_middlewares),
-                    app_states,
-                    logger,
-                    connections,
-                ));
-            }
-            ListenAddr::Unix(unix_socket_addr) => {
-                let unix_socket_addr = unix_socket_addr.clone();
-                tokio::spawn(start_http_auto_unix_socket(
-                    unix_socket_addr,
-                    Arc::new(http_server_middlewares),
-                    app_states,
-                    logger,
-                    connections,
-                ));
-            }
+            let connection_refused = HttpConnectionRefusedData {
+                refused: DateTimeAsMicroseconds::now(),
+                ip: addr.ip_as_string(),
+                reason,
+            };
+
+            tokio::spawn(async move {
+                for tech_middleware in &http_server_middlewares.tech_middlewares {
+                    tech_middleware
+                        .connection_refused(&connection_refused)
+                        .await;
+                }
+            });
+        }
+
+        None
+    }
+
+    fn is_tls(&self) -> bool {
+        #[cfg(feature = "with-tls")]
+        {
+            self.tls_acceptor.is_some()
+        }
+        #[cfg(not(feature = "with-tls"))]
+        {
+            false
         }
     }
 }
 
-pub async fn start_http_1(
-    addr: SocketAddr,
+pub async fn start_http_1(
+    addr: SocketAddr,
+    http_server_middlewares: Arc<HttpServerMiddlewares>,
+    app_states: Arc<dyn ApplicationStates + Send + Sync + 'static>,
+    logger: Arc<dyn Logger + Send + Sync + 'static>,
+    connections: Arc<AtomicI64>,
+) {
+    let listener_ctx = HttpListenerContext::new_standalone(
+        HttpProtocol::Http1,
+        http_server_middlewares,
+        app_states,
+        logger,
+        connections,
+    );
+
+    run_tcp_listener(addr, Arc::new(listener_ctx)).await;
+}
+
+#[cfg(not(unix))]
+pub async fn start_http_1_unix_socket(
+    unix_socket: Arc<String>,
+    http_server_middlewares: Arc<HttpServerMiddlewares>,
+    app_states: Arc<dyn ApplicationStates + Send + Sync + 'static>,
+    logger: Arc<dyn Logger + Send + Sync + 'static>,
+    connections: Arc<AtomicI64>,
+) {
+    panic!("Unix socket is not supported by OS");
+}
+
+#[cfg(unix)]
+pub async fn start_http_1_unix_socket(
+    unix_socket: Arc<String>,
+    http_server_middlewares: Arc<HttpServerMiddlewares>,
+    app_states: Arc<dyn ApplicationStates + Send + Sync + 'static>,
+    logger: Arc<dyn Logger + Send + Sync + 'static>,
+    connections: Arc<AtomicI64>,
+) {
+    let listener_ctx = HttpListenerContext::new_standalone(
+        HttpProtocol::Http1,
+        http_server_middlewares,
+        app_states,
+        logger,
+        connections,
+    );
+
+    run_unix_listener(
+        unix_socket,
+        UnixSocketOptions::default(),
+        Arc::new(listener_ctx),
+    )
+    .await;
+}
+
+pub async fn start_http_2(
+    addr: SocketAddr,
+    http_server_middlewares: Arc<HttpServerMiddlewares>,
+    app_states: Arc<dyn ApplicationStates + Send + Sync + 'static>,
+    logger: Arc<dyn Logger + Send + Sync + 'static>,
+    connections: Arc<AtomicI64>,
+) {
+    let listener_ctx = HttpListenerContext::new_standalone(
+        HttpProtocol::Http2,
+        http_server_middlewares,
+        app_states,
+        logger,
+        connections,
+    );
+
+    run_tcp_listener(addr, Arc::new(listener_ctx)).await;
+}
+
+#[cfg(not(unix))]
+pub async fn start_http_2_unix_socket(
+    _unix_socket: Arc<String>,
+    _http_server_middlewares: Arc<HttpServerMiddlewares>,
+    _app_states: Arc<dyn ApplicationStates + Send + Sync + 'static>,
+    _logger: Arc<dyn Logger + Send + Sync + 'static>,
+    _connections: Arc<AtomicI64>,
+) {
+    panic!("Unix socket is not supported by OS");
+}
+
+/// HTTP/2 with prior knowledge (h2c) over a Unix socket.
+#[cfg(unix)]
+pub async fn start_http_2_unix_socket(
+    unix_socket: Arc<String>,
+    http_server_middlewares: Arc<HttpServerMiddlewares>,
+    app_states: Arc<dyn ApplicationStates + Send + Sync + 'static>,
+    logger: Arc<dyn Logger + Send + Sync + 'static>,
+    connections: Arc<AtomicI64>,
+) {
+    let listener_ctx = HttpListenerContext::new_standalone(
+        HttpProtocol::Http2,
+        http_server_middlewares,
+        app_states,
+        logger,
+        connections,
+    );
+
+    run_unix_listener(
+        unix_socket,
+        UnixSocketOptions::default(),
+        Arc::new(listener_ctx),
+    )
+    .await;
+}
+
+pub async fn start_http_auto(
+    addr: SocketAddr,
+    http_server_middlewares: Arc<HttpServerMiddlewares>,
+    app_states: Arc<dyn ApplicationStates + Send + Sync + 'static>,
+    logger: Arc<dyn Logger + Send + Sync + 'static>,
+    connections: Arc<AtomicI64>,
+) {
+    let listener_ctx = HttpListenerContext::new_standalone(
+        HttpProtocol::Auto,
+        http_server_middlewares,
+        app_states,
+        logger,
+        connections,
+    );
+
+    run_tcp_listener(addr, Arc::new(listener_ctx)).await;
+}
+
+#[cfg(not(unix))]
+pub async fn start_http_auto_unix_socket(
+    _unix_socket: Arc<String>,
+    _http_server_middlewares: Arc<HttpServerMiddlewares>,
+    _app_states: Arc<dyn ApplicationStates + Send + Sync + 'static>,
+    _logger: Arc<dyn Logger + Send + Sync + 'static>,
+    _connections: Arc<AtomicI64>,
+) {
+    panic!("Unix socket is not supported by OS");
+}
+
+#[cfg(unix)]
+pub async fn start_http_auto_unix_socket(
+    unix_socket: Arc<String>,
     http_server_middlewares: Arc<HttpServerMiddlewares>,
     app_states: Arc<dyn ApplicationStates + Send + Sync + 'static>,
     logger: Arc<dyn Logger + Send + Sync + 'static>,
     connections: Arc<AtomicI64>,
 ) {
+    let listener_ctx = HttpListenerContext::new_standalone(
+        HttpProtocol::Auto,
+        http_server_middlewares,
+        app_states,
+        logger,
+        connections,
+    );
+
+    run_unix_listener(
+        unix_socket,
+        UnixSocketOptions::default(),
+        Arc::new(listener_ctx),
+    )
+    .await;
+}
+
+async fn run_tcp_listener(addr: SocketAddr, listener_ctx: Arc<HttpListenerContext>) {
     let listener = tokio::net::TcpListener::bind(addr).await;
 
     if let Err(err) = &listener {
@@ -318,78 +1046,54 @@ pub async fn start_http_1(
         panic!("{}", err);
     }
 
-    let listener = listener.unwrap();
+    serve_tcp_listener(listener.unwrap(), listener_ctx).await;
+}
+
+async fn serve_tcp_listener(
+    listener: tokio::net::TcpListener,
+    listener_ctx: Arc<HttpListenerContext>,
+) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::io::AsRawFd;
+        listener_ctx
+            .listen_fds
+            .register(listener_ctx.listener_name.as_str(), listener.as_raw_fd());
+    }
 
-    let mut http1 = http1::Builder::new();
-    http1.keep_alive(true);
     loop {
-        if app_states.is_shutting_down() {
-            break;
-        }
-
-        let (stream, socket_addr) = listener.accept().await.unwrap();
-
-        connections.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-
-        let io = TokioIo::new(stream);
-        let http_server_middlewares = http_server_middlewares.clone();
-        let logger: Arc<dyn Logger + Send + Sync> = logger.clone();
-        let app_states = app_states.clone();
-
-        let http_server_middlewares = http_server_middlewares.clone();
-        let logger = logger.clone();
-        let socket_addr = socket_addr.clone();
-        let app_states = app_states.clone();
-
-        let connection = http1
-            .serve_connection(
-                io,
-                service_fn(move |req| {
-                    let resp = handle_requests(
-                        req,
-                        http_server_middlewares.clone(),
-                        SocketAddress::Tcp(socket_addr.clone()),
-                        logger.clone(),
-                        app_states.is_shutting_down(),
-                    );
+        let permit = tokio::select! {
+            permit = listener_ctx.connections_limiter.wait_for_slot() => permit,
+            _ = listener_ctx.shutdown.wait_started() => break,
+        };
 
-                    resp
-                }),
-            )
-            .with_upgrades();
+        let (stream, socket_addr) = tokio::select! {
+            accepted = listener.accept() => accepted.unwrap(),
+            _ = listener_ctx.shutdown.wait_started() => break,
+        };
 
-        let connections_clone = connections.clone();
-        tokio::task::spawn(async move {
-            if let Err(_err) = connection.await {
-                //println!("Error serving connection: {:?}", err);
-            }
+        let addr = SocketAddress::Tcp(socket_addr);
 
-            connections_clone.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
-        });
+        dispatch_accepted_connection(stream, addr, permit, &listener_ctx);
     }
 }
 
 #[cfg(not(unix))]
-pub async fn start_http_1_unix_socket(
-    unix_socket: Arc<String>,
-    http_server_middlewares: Arc<HttpServerMiddlewares>,
-    app_states: Arc<dyn ApplicationStates + Send + Sync + 'static>,
-    logger: Arc<dyn Logger + Send + Sync + 'static>,
-    connections: Arc<AtomicI64>,
+async fn run_unix_listener(
+    _unix_socket: Arc<String>,
+    _unix_socket_options: UnixSocketOptions,
+    _listener_ctx: Arc<HttpListenerContext>,
 ) {
     panic!("Unix socket is not supported by OS");
 }
 
 #[cfg(unix)]
-pub async fn start_http_1_unix_socket(
+async fn run_unix_listener(
     unix_socket: Arc<String>,
-    http_server_middlewares: Arc<HttpServerMiddlewares>,
-    app_states: Arc<dyn ApplicationStates + Send + Sync + 'static>,
-    logger: Arc<dyn Logger + Send + Sync + 'static>,
-    connections: Arc<AtomicI64>,
+    unix_socket_options: UnixSocketOptions,
+    listener_ctx: Arc<HttpListenerContext>,
 ) {
-    let _ = tokio::fs::remove_file(unix_socket.as_str()).await;
-    let listener = tokio::net::UnixListener::bind(unix_socket.as_str());
+    let listener = crate::bind_unix_listener(unix_socket.as_str(), &unix_socket_options);
 
     if let Err(err) = &listener {
         let err = format!(
@@ -400,330 +1104,621 @@ pub async fn start_http_1_unix_socket(
         panic!("{}", err);
     }
 
-    let listener = listener.unwrap();
+    serve_unix_listener(listener.unwrap(), listener_ctx).await;
+}
+
+#[cfg(unix)]
+async fn serve_unix_listener(
+    listener: tokio::net::UnixListener,
+    listener_ctx: Arc<HttpListenerContext>,
+) {
+    {
+        use std::os::unix::io::AsRawFd;
+        listener_ctx
+            .listen_fds
+            .register(listener_ctx.listener_name.as_str(), listener.as_raw_fd());
+    }
 
-    let mut http1 = http1::Builder::new();
-    http1.keep_alive(true);
     loop {
-        if app_states.is_shutting_down() {
-            break;
-        }
-
-        let (stream, socket_addr) = listener.accept().await.unwrap();
-
-        connections.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-
-        let io = TokioIo::new(stream);
-        let http_server_middlewares = http_server_middlewares.clone();
-        let logger: Arc<dyn Logger + Send + Sync> = logger.clone();
-        let app_states = app_states.clone();
-
-        let http_server_middlewares = http_server_middlewares.clone();
-        let logger = logger.clone();
-        let socket_addr = SocketAddress::Unix(Arc::new(format!("{:?}", socket_addr)));
-        let app_states = app_states.clone();
-
-        let connection = http1
-            .serve_connection(
-                io,
-                service_fn(move |req| {
-                    let resp = handle_requests(
-                        req,
-                        http_server_middlewares.clone(),
-                        socket_addr.clone(),
-                        logger.clone(),
-                        app_states.is_shutting_down(),
-                    );
+        let permit = tokio::select! {
+            permit = listener_ctx.connections_limiter.wait_for_slot() => permit,
+            _ = listener_ctx.shutdown.wait_started() => break,
+        };
 
-                    resp
-                }),
-            )
-            .with_upgrades();
+        let (stream, socket_addr) = tokio::select! {
+            accepted = listener.accept() => accepted.unwrap(),
+            _ = listener_ctx.shutdown.wait_started() => break,
+        };
 
-        let connections_clone = connections.clone();
-        tokio::task::spawn(async move {
-            if let Err(_err) = connection.await {
-                //println!("Error serving connection: {:?}", err);
-            }
+        let addr = SocketAddress::Unix(Arc::new(format!("{:?}", socket_addr)));
 
-            connections_clone.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
-        });
+        dispatch_accepted_connection(stream, addr, permit, &listener_ctx);
     }
 }
 
-pub async fn start_http_2(
-    addr: SocketAddr,
-    http_server_middlewares: Arc<HttpServerMiddlewares>,
-    app_states: Arc<dyn ApplicationStates + Send + Sync + 'static>,
-    logger: Arc<dyn Logger + Send + Sync + 'static>,
-    connections: Arc<AtomicI64>,
-) {
-    let listener = tokio::net::TcpListener::bind(addr).await;
+/// Adopts a socket that is listening already. Whether it is a TCP or a Unix one is asked from the
+/// socket itself.
+#[cfg(unix)]
+async fn run_fd_listener(fd: std::os::unix::io::RawFd, listener_ctx: Arc<HttpListenerContext>) {
+    match adopt_listen_fd(fd) {
+        Ok(AdoptedListener::Tcp(listener)) => serve_tcp_listener(listener, listener_ctx).await,
+        Ok(AdoptedListener::Unix(listener)) => serve_unix_listener(listener, listener_ctx).await,
+        Err(err) => {
+            let err = format!("Can not start http server at fd {}. Err: {:?}", fd, err);
+            eprintln!("{}", err);
+            panic!("{}", err);
+        }
+    }
+}
 
-    if let Err(err) = &listener {
-        panic!("Error starting h2 server at {}. Err: {:?}", addr, err);
+#[cfg(unix)]
+enum AdoptedListener {
+    Tcp(tokio::net::TcpListener),
+    Unix(tokio::net::UnixListener),
+}
+
+#[cfg(unix)]
+fn adopt_listen_fd(fd: std::os::unix::io::RawFd) -> std::io::Result<AdoptedListener> {
+    use std::os::unix::io::{FromRawFd, IntoRawFd};
+
+    let listener = unsafe { std::net::TcpListener::from_raw_fd(fd) };
+
+    // Reading the address of a Unix socket as an IP one fails — without touching the descriptor.
+    if listener.local_addr().is_ok() {
+        listener.set_nonblocking(true)?;
+        return Ok(AdoptedListener::Tcp(tokio::net::TcpListener::from_std(
+            listener,
+        )?));
     }
 
-    let listener = listener.unwrap();
+    let listener = unsafe { std::os::unix::net::UnixListener::from_raw_fd(listener.into_raw_fd()) };
+    listener.local_addr()?;
+    listener.set_nonblocking(true)?;
+
+    Ok(AdoptedListener::Unix(tokio::net::UnixListener::from_std(
+        listener,
+    )?))
+}
 
-    let mut http2_builder = hyper::server::conn::http2::Builder::new(TokioExecutor::new());
-    http2_builder.enable_connect_protocol();
-    let http2_builder = Arc::new(http2_builder);
+fn dispatch_accepted_connection<TStream>(
+    stream: TStream,
+    addr: SocketAddress,
+    permit: Option<tokio::sync::OwnedSemaphorePermit>,
+    listener_ctx: &Arc<HttpListenerContext>,
+) where
+    TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Unpin + 'static,
+{
+    // Behind a balancer the peer is the balancer — the limits can only be applied once the
+    // header has said who the client is. The connection counts from accept all the same, so a
+    // shutdown waits for it like for any other.
+    if listener_ctx.proxy_protocol {
+        listener_ctx
+            .connections
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+
+        tokio::spawn(serve_proxied_connection(
+            stream,
+            addr,
+            permit,
+            listener_ctx.clone(),
+        ));
+        return;
+    }
 
-    loop {
-        if app_states.is_shutting_down() {
-            break;
-        }
+    let Some(slot) = listener_ctx.admit_connection(&addr, permit) else {
+        return;
+    };
 
-        let (stream, socket_addr) = listener.accept().await.unwrap();
+    listener_ctx
+        .connections
+        .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+
+    tokio::spawn(serve_accepted_connection(
+        stream,
+        addr,
+        None,
+        slot,
+        listener_ctx.clone(),
+    ));
+}
 
-        connections.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+/// A balancer that connects and never sends the header must not hold the connection forever.
+const PROXY_PROTOCOL_HEADER_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+async fn serve_proxied_connection<TStream>(
+    mut stream: TStream,
+    peer_addr: SocketAddress,
+    permit: Option<tokio::sync::OwnedSemaphorePermit>,
+    listener_ctx: Arc<HttpListenerContext>,
+) where
+    TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Unpin + 'static,
+{
+    let Some((addr, header, slot)) =
+        admit_proxied_connection(&mut stream, peer_addr, permit, &listener_ctx).await
+    else {
+        listener_ctx
+            .connections
+            .fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+        return;
+    };
 
-        let io = TokioIo::new(stream);
-        let http_server_middlewares = http_server_middlewares.clone();
-        let logger: Arc<dyn Logger + Send + Sync> = logger.clone();
-        let app_states = app_states.clone();
+    serve_accepted_connection(stream, addr, Some(header), slot, listener_ctx).await;
+}
 
-        let http_server_middlewares = http_server_middlewares.clone();
-        let logger = logger.clone();
-        let socket_addr = socket_addr.clone();
-        let app_states = app_states.clone();
+/// Reads the PROXY protocol header and applies the connection limits to the client it names.
+/// `None` means the connection is to be closed.
+async fn admit_proxied_connection<TStream>(
+    stream: &mut TStream,
+    peer_addr: SocketAddress,
+    permit: Option<tokio::sync::OwnedSemaphorePermit>,
+    listener_ctx: &HttpListenerContext,
+) -> Option<(
+    SocketAddress,
+    crate::ProxyProtocolHeader,
+    HttpConnectionSlot,
+)>
+where
+    TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Unpin + 'static,
+{
+    let header = tokio::time::timeout(
+        PROXY_PROTOCOL_HEADER_TIMEOUT,
+        crate::read_proxy_protocol_header(stream),
+    );
+
+    // No request is in flight before the header, so a shutdown does not wait for it.
+    let header = tokio::select! {
+        header = header => header,
+        _ = listener_ctx.shutdown.wait_started() => return None,
+    };
 
-        let builder = http2_builder.clone();
+    let header = match header {
+        Ok(Ok(header)) => header,
+        Ok(Err(err)) => {
+            listener_ctx.logger.write_debug_info(
+                "ProxyProtocol".to_string(),
+                format!(
+                    "Connection from {} is closed. Err: {}",
+                    peer_addr.to_string(),
+                    err
+                ),
+                None,
+            );
+            return None;
+        }
+        Err(_) => {
+            listener_ctx.logger.write_debug_info(
+                "ProxyProtocol".to_string(),
+                format!(
+                    "Connection from {} did not send PROXY protocol header in time",
+                    peer_addr.to_string()
+                ),
+                None,
+            );
+            return None;
+        }
+    };
 
-        let connection = builder.serve_connection(
-            io,
-            service_fn(move |req| {
-                let resp = handle_requests(
-                    req,
-                    http_server_middlewares.clone(),
-                    SocketAddress::Tcp(socket_addr.clone()),
-                    logger.clone(),
-                    app_states.is_shutting_down(),
-                );
-                resp
-            }),
-        );
+    let addr = match header.source {
+        Some(source) => SocketAddress::Tcp(source),
+        None => peer_addr,
+    };
 
-        let connections_clone = connections.clone();
-        tokio::task::spawn(async move {
-            if let Err(_err) = connection.await {
-                //println!("Error serving connection: {:?}", err);
-            }
+    let slot = listener_ctx.admit_connection(&addr, permit)?;
 
-            connections_clone.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
-        });
-    }
+    Some((addr, header, slot))
 }
 
-pub async fn start_http_auto(
-    addr: SocketAddr,
-    http_server_middlewares: Arc<HttpServerMiddlewares>,
-    app_states: Arc<dyn ApplicationStates + Send + Sync + 'static>,
-    logger: Arc<dyn Logger + Send + Sync + 'static>,
-    connections: Arc<AtomicI64>,
-) {
-    let listener = tokio::net::TcpListener::bind(addr).await;
+/// Everything after `accept()`: TLS (when the listener has it), then HTTP, then the connection
+/// counter goes back down.
+async fn serve_accepted_connection<TStream>(
+    stream: TStream,
+    addr: SocketAddress,
+    proxy_protocol: Option<crate::ProxyProtocolHeader>,
+    _slot: HttpConnectionSlot,
+    listener_ctx: Arc<HttpListenerContext>,
+) where
+    TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Unpin + 'static,
+{
+    let mut connection = HttpConnectionInfo::new(listener_ctx.listener_name.clone(), addr);
+    connection.proxy_protocol = proxy_protocol;
+
+    #[cfg(feature = "with-tls")]
+    let stream = match listener_ctx.tls_acceptor.as_ref() {
+        Some(tls_acceptor) => {
+            serve_tls_connection(stream, connection, tls_acceptor, &listener_ctx).await;
+
+            listener_ctx
+                .connections
+                .fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+            return;
+        }
+        None => stream,
+    };
 
-    if let Err(err) = &listener {
-        let err = format!("Can not start http server at {}. Err: {:?}", addr, err);
-        eprintln!("{}", err);
-        panic!("{}", err);
-    }
+    serve_connection(stream, listener_ctx.protocol, connection, &listener_ctx).await;
 
-    let listener = listener.unwrap();
+    listener_ctx
+        .connections
+        .fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+}
 
-    let mut auto_builder = auto::Builder::new(TokioExecutor::new());
-    auto_builder.http1().keep_alive(true);
-    auto_builder.http2().enable_connect_protocol();
-    let auto_builder = Arc::new(auto_builder);
+/// A client that connects and never finishes the handshake must not hold the connection forever.
+#[cfg(feature = "with-tls")]
+const TLS_HANDSHAKE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+#[cfg(feature = "with-tls")]
+async fn serve_tls_connection<TStream>(
+    stream: TStream,
+    mut connection: HttpConnectionInfo,
+    tls_acceptor: &tokio_rustls::TlsAcceptor,
+    listener_ctx: &HttpListenerContext,
+) where
+    TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Unpin + 'static,
+{
+    // A connection still in the handshake has no request in flight, so a shutdown drops it at
+    // once instead of holding the drain up until the handshake times out.
+    let handshake = tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, tls_acceptor.accept(stream));
+
+    let handshake = tokio::select! {
+        handshake = handshake => handshake,
+        _ = listener_ctx.shutdown.wait_started() => return,
+    };
 
-    loop {
-        if app_states.is_shutting_down() {
-            break;
-        }
-
-        let (stream, socket_addr) = listener.accept().await.unwrap();
-
-        connections.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-
-        let io = TokioIo::new(stream);
-        let http_server_middlewares = http_server_middlewares.clone();
-        let logger: Arc<dyn Logger + Send + Sync> = logger.clone();
-        let app_states = app_states.clone();
-        let builder = auto_builder.clone();
-        let connections_clone = connections.clone();
-
-        tokio::task::spawn(async move {
-            let connection = builder.serve_connection_with_upgrades(
-                io,
-                service_fn(move |req| {
-                    let resp = handle_requests(
-                        req,
-                        http_server_middlewares.clone(),
-                        SocketAddress::Tcp(socket_addr.clone()),
-                        logger.clone(),
-                        app_states.is_shutting_down(),
-                    );
-                    resp
-                }),
+    let stream = match handshake {
+        Ok(Ok(stream)) => stream,
+        Ok(Err(err)) => {
+            listener_ctx.logger.write_debug_info(
+                "TlsHandshake".to_string(),
+                format!(
+                    "Tls handshake with {} failed. Err: {}",
+                    connection.addr.to_string(),
+                    err
+                ),
+                None,
             );
+            return;
+        }
+        Err(_) => {
+            listener_ctx.logger.write_debug_info(
+                "TlsHandshake".to_string(),
+                format!(
+                    "Tls handshake with {} timed out",
+                    connection.addr.to_string()
+                ),
+                None,
+            );
+            return;
+        }
+    };
 
-            if let Err(_err) = connection.await {
-                //println!("Error serving connection: {:?}", err);
-            }
+    let (_, tls_connection) = stream.get_ref();
 
-            connections_clone.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
-        });
-    }
-}
+    let alpn_protocol = tls_connection
+        .alpn_protocol()
+        .map(|itm| String::from_utf8_lossy(itm).to_string());
 
-#[cfg(not(unix))]
-pub async fn start_http_auto_unix_socket(
-    _unix_socket: Arc<String>,
-    _http_server_middlewares: Arc<HttpServerMiddlewares>,
-    _app_states: Arc<dyn ApplicationStates + Send + Sync + 'static>,
-    _logger: Arc<dyn Logger + Send + Sync + 'static>,
-    _connections: Arc<AtomicI64>,
-) {
-    panic!("Unix socket is not supported by OS");
-}
+    // ALPN already settled the protocol — no need to sniff the preface.
+    let protocol = match alpn_protocol.as_deref() {
+        Some("h2") => HttpProtocol::Http2,
+        Some("http/1.1") => HttpProtocol::Http1,
+        _ => listener_ctx.protocol,
+    };
 
-#[cfg(unix)]
-pub async fn start_http_auto_unix_socket(
-    unix_socket: Arc<String>,
-    http_server_middlewares: Arc<HttpServerMiddlewares>,
-    app_states: Arc<dyn ApplicationStates + Send + Sync + 'static>,
-    logger: Arc<dyn Logger + Send + Sync + 'static>,
-    connections: Arc<AtomicI64>,
-) {
-    let _ = tokio::fs::remove_file(unix_socket.as_str()).await;
-    let listener = tokio::net::UnixListener::bind(unix_socket.as_str());
+    connection.tls = Some(crate::TlsConnectionInfo {
+        server_name: tls_connection.server_name().map(|itm| itm.to_string()),
+        alpn_protocol,
+    });
 
-    if let Err(err) = &listener {
-        let err = format!(
-            "Can not start http server at {}. Err: {:?}",
-            unix_socket, err
-        );
-        eprintln!("{}", err);
-        panic!("{}", err);
-    }
+    serve_connection(stream, protocol, connection, listener_ctx).await;
+}
 
-    let listener = listener.unwrap();
+async fn serve_connection<TStream>(
+    stream: TStream,
+    protocol: HttpProtocol,
+    connection: HttpConnectionInfo,
+    listener_ctx: &HttpListenerContext,
+) where
+    TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Unpin + 'static,
+{
+    let io = TokioIo::new(stream);
+
+    let connection = Arc::new(connection);
+    let http_server_middlewares = listener_ctx.http_server_middlewares.clone();
+    let logger = listener_ctx.logger.clone();
+    let shutdown = listener_ctx.shutdown.clone();
+
+    // A graceful shutdown lets requests already read finish normally; only the ones that race
+    // the force-close get the "shutting down" answer.
+    let service = service_fn(move |req| {
+        handle_requests(
+            req,
+            http_server_middlewares.clone(),
+            connection.clone(),
+            logger.clone(),
+            shutdown.is_force_closing(),
+        )
+    });
 
-    let mut auto_builder = auto::Builder::new(TokioExecutor::new());
-    auto_builder.http1().keep_alive(true);
-    auto_builder.http2().enable_connect_protocol();
-    let auto_builder = Arc::new(auto_builder);
+    match protocol {
+        HttpProtocol::Http1 => {
+            let mut http1 = http1::Builder::new();
+            http1.keep_alive(true);
 
-    loop {
-        if app_states.is_shutting_down() {
-            break;
-        }
-
-        let (stream, socket_addr) = listener.accept().await.unwrap();
-
-        connections.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-
-        let io = TokioIo::new(stream);
-        let http_server_middlewares = http_server_middlewares.clone();
-        let logger: Arc<dyn Logger + Send + Sync> = logger.clone();
-        let socket_addr = SocketAddress::Unix(Arc::new(format!("{:?}", socket_addr)));
-        let app_states = app_states.clone();
-        let builder = auto_builder.clone();
-        let connections_clone = connections.clone();
-
-        tokio::task::spawn(async move {
-            let connection = builder.serve_connection_with_upgrades(
-                io,
-                service_fn(move |req| {
-                    let resp = handle_requests(
-                        req,
-                        http_server_middlewares.clone(),
-                        socket_addr.clone(),
-                        logger.clone(),
-                        app_states.is_shutting_down(),
-                    );
-                    resp
-                }),
-            );
+            let connection = http1.serve_connection(io, service).with_upgrades();
+            tokio::pin!(connection);
 
-            if let Err(_err) = connection.await {
-                //println!("Error serving connection: {:?}", err);
-            }
+            serve_until_shutdown(
+                connection.as_mut(),
+                |connection| connection.graceful_shutdown(),
+                &listener_ctx.shutdown,
+            )
+            .await;
+        }
+        HttpProtocol::Http2 => {
+            let mut http2 = hyper::server::conn::http2::Builder::new(TokioExecutor::new());
+            http2.enable_connect_protocol();
+
+            let connection = http2.serve_connection(io, service);
+            tokio::pin!(connection);
 
-            connections_clone.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
-        });
+            serve_until_shutdown(
+                connection.as_mut(),
+                |connection| connection.graceful_shutdown(),
+                &listener_ctx.shutdown,
+            )
+            .await;
+        }
+        HttpProtocol::Auto => {
+            let mut auto_builder = auto::Builder::new(TokioExecutor::new());
+            auto_builder.http1().keep_alive(true);
+            auto_builder.http2().enable_connect_protocol();
+
+            let connection = auto_builder.serve_connection_with_upgrades(io, service);
+            tokio::pin!(connection);
+
+            serve_until_shutdown(
+                connection.as_mut(),
+                |connection| connection.graceful_shutdown(),
+                &listener_ctx.shutdown,
+            )
+            .await;
+        }
     }
 }
 
+/// Handles one request — what every connection the server accepts calls for each of its requests.
+/// The body comes from hyper, or from memory for the in-process test client of the `test-client`
+/// feature.
 pub async fn handle_requests(
-    req: hyper::Request<hyper::body::Incoming>,
+    req: hyper::Request<impl Into<HttpRequestBodySource>>,
     http_server_middlewares: Arc<HttpServerMiddlewares>,
-    addr: SocketAddress,
+    connection: Arc<HttpConnectionInfo>,
     logger: Arc<dyn Logger + Send + Sync + 'static>,
     app_is_shutting_down: bool,
 ) -> hyper::Result<my_hyper_utils::MyHttpResponse> {
-    if app_is_shutting_down {
-        return compile_app_is_shutting_down_http_response();
-    }
+    let mut req: hyper::Request<HttpRequestBodySource> = req.map(Into::into);
+    let started = std::time::Instant::now();
+
+    let _in_flight = http_server_middlewares
+        .metrics
+        .as_ref()
+        .map(|itm| itm.start_request());
+
+    let response_compression = match http_server_middlewares.response_compression.as_ref() {
+        Some(settings) if req.method() != hyper::Method::HEAD => {
+            let accept_encoding = req
+                .headers()
+                .get(hyper::header::ACCEPT_ENCODING)
+                .and_then(|itm| itm.to_str().ok());
+
+            let encoding =
+                crate::negotiate_response_encoding(accept_encoding, settings.get_encodings());
+
+            Some((settings.clone(), encoding))
+        }
+        _ => None,
+    };
+
+    let version = req.version();
+    let user_agent = req
+        .headers()
+        .get(hyper::header::USER_AGENT)
+        .and_then(|itm| itm.to_str().ok())
+        .map(|itm| itm.to_string());
+    let content_length = req
+        .headers()
+        .get(hyper::header::CONTENT_LENGTH)
+        .and_then(|itm| itm.to_str().ok())
+        .and_then(|itm| itm.parse::<u64>().ok());
+    // No copy for a server without anything that reads them.
+    let headers = if http_server_middlewares.needs_request_headers() {
+        req.headers().clone()
+    } else {
+        hyper::HeaderMap::new()
+    };
+
+    let request_id = crate::HttpRequestId::from_headers(
+        req.headers(),
+        &http_server_middlewares.request_id_header,
+    );
+    req.extensions_mut().insert(request_id.clone());
 
-    let mut req = HttpRequest::new(req, addr).unwrap();
+    let mut req = HttpRequest::new(req, connection).unwrap();
     req.set_body_read_timeout(http_server_middlewares.body_read_timeout);
+    req.set_max_body_size(http_server_middlewares.max_body_size);
+    req.set_max_decompressed_body_size(Some(http_server_middlewares.max_decompressed_body_size));
+    req.set_trusted_proxies(http_server_middlewares.trusted_proxies.clone());
 
     let method = req.method.clone();
     let mut request_ctx = HttpContext::new(req);
+    request_ctx.set_request_timeout(http_server_middlewares.request_timeout);
+    let request_deadline_expired = request_ctx.request_deadline.wait_expired();
 
     #[cfg(feature = "with-telemetry")]
     let ctx = request_ctx.telemetry_context.clone();
 
+    let echo_headers = EchoHeaders {
+        request_id_header: http_server_middlewares.request_id_header.clone(),
+        request_id: request_id.as_str().to_string(),
+        #[cfg(feature = "with-telemetry")]
+        trace_response: if http_server_middlewares.trace_response {
+            Some(request_ctx.trace_context.to_traceparent())
+        } else {
+            None
+        },
+    };
+
     let request_data = Arc::new(HttpRequestData {
         method,
         path: request_ctx.request.get_path().to_string(),
         ip: request_ctx.request.get_ip().get_real_ip().to_string(),
         started: DateTimeAsMicroseconds::now(),
+        version,
+        user_agent,
+        content_length,
+        headers,
+        request_id: request_id.as_str().to_string(),
     });
 
+    if app_is_shutting_down {
+        return Ok(
+            echo_headers.add_to(compile_app_is_shutting_down_http_response(
+                &http_server_middlewares,
+                &request_data,
+            )),
+        );
+    }
+
     let client_id: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(None));
 
     let client_id_spawned = client_id.clone();
     let http_server_middlewares_cloned = http_server_middlewares.clone();
+    let request_data_spawned = request_data.clone();
     let flow_execution_future = AssertUnwindSafe(async move {
-        let mut credentials_assigned = false;
+        let not_found = HttpNotFound {
+            request_data: &request_data_spawned,
+            result_factory: http_server_middlewares_cloned
+                .not_found_result_factory
+                .as_deref(),
+        };
+
+        let http_result = HttpServerMiddlewareNext::new(
+            &http_server_middlewares_cloned.middlewares,
+            &client_id_spawned,
+            &not_found,
+        )
+        .run(&mut request_ctx)
+        .await;
+
+        MiddleWareFlowResult {
+            http_context: request_ctx,
+            http_result,
+        }
+    })
+    .catch_unwind();
+
+    let flow_execution_result = tokio::select! {
+        result = flow_execution_future => Ok(result),
+        timeout = request_deadline_expired => Err(timeout),
+    };
+
+    let flow_execution_result = match flow_execution_result {
+        Ok(flow_execution_result) => flow_execution_result,
+        Err(timeout) => {
+            let fail_result = match http_server_middlewares
+                .request_timeout_result_factory
+                .as_ref()
+            {
+                Some(factory) => factory.get_request_timeout_result(&request_data, timeout),
+                None => crate::get_default_request_timeout_result(timeout),
+            };
+
+            #[cfg(feature = "with-telemetry")]
+            let mut fail_result = fail_result;
+
+            let client_id = client_id.lock().await.take();
+
+            if !http_server_middlewares.tech_middlewares.is_empty() {
+                let response_data = ResponseData {
+                    status_code: fail_result.output.get_status_code(),
+                    content_type: fail_result
+                        .output
+                        .get_content_type_as_str()
+                        .map(|itm| itm.to_string()),
+                    content_length: fail_result.output.get_content_size(),
+                    has_error: true,
+                    outcome: HttpRequestOutcome::TimedOut,
+                    process_name: None,
+                    client_id: client_id.clone(),
+                    duration: started.elapsed(),
+                };
+                let request_data = request_data.clone();
+                tokio::spawn(async move {
+                    for middleware in http_server_middlewares.tech_middlewares.iter() {
+                        middleware.got_result(&request_data, &response_data).await;
+                    }
+                });
+            }
 
-        for middleware in http_server_middlewares_cloned.middlewares.iter() {
-            let result = middleware.handle_request(&mut request_ctx).await;
+            if fail_result.write_to_log {
+                let mut ctx = HashMap::new();
+                ctx.insert("path".to_string(), request_data.path.to_string());
+                ctx.insert("method".to_string(), request_data.method.to_string());
+                ctx.insert("ip".to_string(), request_data.ip.to_string());
+                ctx.insert(
+                    "request_id".to_string(),
+                    request_data.request_id.to_string(),
+                );
 
-            if let Some(credentials) = request_ctx.credentials.as_ref() {
-                if !credentials_assigned {
-                    credentials_assigned = true;
-                    let mut client_id_access = client_id_spawned.lock().await;
-                    *client_id_access = Some(credentials.get_id().to_string());
+                if let Some(client_id) = client_id.as_ref() {
+                    ctx.insert("client_id".to_string(), client_id.to_string());
                 }
+
+                logger.write_warning(
+                    "HttpRequest".to_string(),
+                    format!("Request timed out after {:?}", timeout),
+                    Some(ctx),
+                );
             }
 
-            if let Some(http_result) = result {
-                return MiddleWareFlowResult {
-                    http_context: request_ctx,
-                    http_result: http_result,
-                };
+            #[cfg(feature = "with-telemetry")]
+            if fail_result.write_telemetry {
+                let mut tags = fail_result
+                    .add_telemetry_tags
+                    .take_tags()
+                    .add_ip(request_data.ip.to_string())
+                    .add("request_id", request_data.request_id.to_string());
+
+                if let Some(client_id) = client_id.as_ref() {
+                    tags = tags.add("client_id", client_id.to_string());
+                }
+
+                my_telemetry::TELEMETRY_INTERFACE.write_fail(
+                    &ctx,
+                    request_data.started,
+                    format!("[{}]{}", request_data.method, request_data.path),
+                    format!("Timeout: {:?}", timeout),
+                    tags.into(),
+                );
             }
-        }
 
-        MiddleWareFlowResult {
-            http_context: request_ctx,
-            http_result: Err(HttpFailResult::as_not_found(
-                "404 - Not Found".to_string(),
-                false,
-            )),
+            return Ok(echo_headers.add_to(fail_result.output.into()));
         }
-    })
-    .catch_unwind();
+    };
 
-    let flow_execution_result = match flow_execution_future.await {
+    let flow_execution_result = match flow_execution_result {
         Ok(flow_execution_result) => {
             if http_server_middlewares.tech_middlewares.len() > 0 {
-                let response_data = ResponseData::from(&flow_execution_result.http_result);
+                let http_context = &flow_execution_result.http_context;
+
+                let mut response_data = ResponseData::from(&flow_execution_result.http_result);
+                response_data.process_name = http_context.process_name.clone();
+                response_data.client_id = http_context
+                    .credentials
+                    .as_ref()
+                    .map(|itm| itm.get_id().to_string());
+                response_data.duration = started.elapsed();
+
                 let request_data = request_data.clone();
                 tokio::spawn(async move {
                     for middleware in http_server_middlewares.tech_middlewares.iter() {
@@ -743,29 +1738,49 @@ pub async fn handle_requests(
                 "panic".to_string()
             };
 
+            let client_id = client_id.lock().await.take();
+
+            let panic_result = http_server_middlewares
+                .panic_result_factory
+                .as_ref()
+                .map(|factory| factory.get_panic_result(&request_data, &panic_msg));
+
             let request_data = Arc::new(request_data);
             let request_data_cloned = request_data.clone();
+            let response_data = ResponseData {
+                status_code: match panic_result.as_ref() {
+                    Some(fail_result) => fail_result.output.get_status_code(),
+                    None => PANIC_HTTP_CODE.as_u16(),
+                },
+                content_type: match panic_result.as_ref() {
+                    Some(fail_result) => fail_result
+                        .output
+                        .get_content_type_as_str()
+                        .map(|itm| itm.to_string()),
+                    None => Some("text/plain".into()),
+                },
+                content_length: match panic_result.as_ref() {
+                    Some(fail_result) => fail_result.output.get_content_size(),
+                    None => 0,
+                },
+                has_error: true,
+                outcome: HttpRequestOutcome::Panicked,
+                process_name: None,
+                client_id: client_id.clone(),
+                duration: started.elapsed(),
+            };
             tokio::spawn(async move {
                 for middleware in http_server_middlewares.tech_middlewares.iter() {
                     middleware
-                        .got_result(
-                            &request_data_cloned,
-                            &ResponseData {
-                                status_code: PANIC_HTTP_CODE.as_u16(),
-                                content_type: Some("text/plain".into()),
-                                content_length: 0,
-                                has_error: true,
-                            },
-                        )
+                        .got_result(&request_data_cloned, &response_data)
                         .await;
                 }
             });
-
-            let client_id = client_id.lock().await.take();
             #[cfg(feature = "with-telemetry")]
             {
-                let mut tags =
-                    TelemetryEventTagsBuilder::new().add_ip(request_data.ip.as_str().to_string());
+                let mut tags = TelemetryEventTagsBuilder::new()
+                    .add_ip(request_data.ip.as_str().to_string())
+                    .add("request_id", request_data.request_id.to_string());
 
                 if let Some(client_id) = client_id.as_ref() {
                     tags = tags.add("client_id", client_id.to_string());
@@ -784,6 +1799,10 @@ pub async fn handle_requests(
             ctx.insert("path".to_string(), request_data.path.to_string());
             ctx.insert("method".to_string(), request_data.method.to_string());
             ctx.insert("ip".to_string(), request_data.ip.to_string());
+            ctx.insert(
+                "request_id".to_string(),
+                request_data.request_id.to_string(),
+            );
 
             if let Some(client_id) = client_id.as_ref() {
                 ctx.insert("client_id".to_string(), client_id.to_string());
@@ -795,10 +1814,21 @@ pub async fn handle_requests(
                 Some(ctx),
             );
 
-            return Ok((PANIC_HTTP_CODE, "Internal server error").to_my_http_response());
+            let response = match panic_result {
+                Some(fail_result) => fail_result.output.into(),
+                None => (PANIC_HTTP_CODE, "Internal server error").to_my_http_response(),
+            };
+
+            return Ok(echo_headers.add_to(response));
         }
     };
 
+    let response_compression = response_compression.filter(|_| {
+        flow_execution_result
+            .http_context
+            .is_response_compression_enabled()
+    });
+
     match flow_execution_result.http_result {
         Ok(ok_result) => {
             #[cfg(feature = "with-telemetry")]
@@ -810,7 +1840,8 @@ pub async fn handle_requests(
                     let mut tags = ok_result
                         .add_telemetry_tags
                         .take_tags()
-                        .add_ip(request_data.ip.to_string());
+                        .add_ip(request_data.ip.to_string())
+                        .add("request_id", request_data.request_id.to_string());
 
                     if let Some(credentials) = &flow_execution_result.http_context.credentials {
                         tags = tags.add("client_id", credentials.get_id().to_string());
@@ -834,7 +1865,10 @@ pub async fn handle_requests(
                 }
             }
 
-            Ok(ok_result.output.into())
+            Ok(echo_headers.add_to(compress_response(
+                response_compression,
+                ok_result.output.into(),
+            )))
         }
         Err(err_result) => {
             #[cfg(feature = "with-telemetry")]
@@ -853,6 +1887,10 @@ pub async fn handle_requests(
                             .to_string(),
                     );
                     ctx.insert("ip".to_string(), request_data.ip.to_string());
+                    ctx.insert(
+                        "request_id".to_string(),
+                        request_data.request_id.to_string(),
+                    );
                     ctx.insert(
                         "httpCode".to_string(),
                         err_result.output.get_status_code().to_string(),
@@ -877,7 +1915,8 @@ pub async fn handle_requests(
                     let mut tags = err_result
                         .add_telemetry_tags
                         .take_tags()
-                        .add_ip(request_data.ip.to_string());
+                        .add_ip(request_data.ip.to_string())
+                        .add("request_id", request_data.request_id.to_string());
 
                     if let Some(credentials) = &flow_execution_result.http_context.credentials {
                         tags = tags.add("client_id".to_string(), credentials.get_id().to_string());
@@ -901,8 +1940,60 @@ pub async fn handle_requests(
                 }
             }
 
-            Ok(err_result.output.into())
+            Ok(echo_headers.add_to(compress_response(
+                response_compression,
+                err_result.output.into(),
+            )))
+        }
+    }
+}
+
+fn compress_response(
+    response_compression: Option<(Arc<ResponseCompression>, Option<ResponseEncoding>)>,
+    response: my_hyper_utils::MyHttpResponse,
+) -> my_hyper_utils::MyHttpResponse {
+    match response_compression {
+        Some((settings, encoding)) => settings.compress(response, encoding),
+        None => response,
+    }
+}
+
+/// What a response is sent with whichever way the request ended — unless the action has set the
+/// header itself.
+struct EchoHeaders {
+    request_id_header: hyper::header::HeaderName,
+    request_id: String,
+    #[cfg(feature = "with-telemetry")]
+    trace_response: Option<String>,
+}
+
+impl EchoHeaders {
+    fn add_to(
+        &self,
+        mut response: my_hyper_utils::MyHttpResponse,
+    ) -> my_hyper_utils::MyHttpResponse {
+        add_header_if_missing(&mut response, &self.request_id_header, &self.request_id);
+
+        #[cfg(feature = "with-telemetry")]
+        if let Some(trace_response) = self.trace_response.as_ref() {
+            add_header_if_missing(
+                &mut response,
+                &hyper::header::HeaderName::from_static(crate::TRACERESPONSE_HEADER),
+                trace_response,
+            );
         }
+
+        response
+    }
+}
+
+fn add_header_if_missing(
+    response: &mut my_hyper_utils::MyHttpResponse,
+    header: &hyper::header::HeaderName,
+    value: &str,
+) {
+    if let Ok(value) = hyper::header::HeaderValue::from_str(value) {
+        response.headers_mut().entry(header).or_insert(value);
     }
 }
 
@@ -911,8 +2002,27 @@ pub struct MiddleWareFlowResult {
     pub http_result: Result<HttpOkResult, HttpFailResult>,
 }
 
-fn compile_app_is_shutting_down_http_response() -> hyper::Result<my_hyper_utils::MyHttpResponse> {
-    let builder = hyper::Response::builder().status(502);
-    let content = "Application is shutting down";
-    hyper::Result::Ok((builder, content.as_bytes().to_vec()).to_my_http_response())
+fn compile_app_is_shutting_down_http_response(
+    http_server_middlewares: &HttpServerMiddlewares,
+    request_data: &HttpRequestData,
+) -> my_hyper_utils::MyHttpResponse {
+    let fail_result = match http_server_middlewares
+        .shutting_down_result_factory
+        .as_ref()
+    {
+        Some(factory) => factory.get_shutting_down_result(request_data),
+        None => crate::get_default_shutting_down_result(),
+    };
+
+    let mut response: my_hyper_utils::MyHttpResponse = fail_result.output.into();
+
+    if let Some(retry_after) = http_server_middlewares.shutting_down_retry_after {
+        add_header_if_missing(
+            &mut response,
+            &hyper::header::RETRY_AFTER,
+            &retry_after.as_secs().max(1).to_string(),
+        );
+    }
+
+    response
 }
@@ -1,13 +1,67 @@
 use std::sync::Arc;
 
-use crate::{HttpServerMiddleware, HttpServerTechMiddleware};
+use crate::{
+    HttpNotFoundResultFactory, HttpPanicResultFactory, HttpRequestTimeoutResultFactory,
+    HttpResolvedMiddleware, HttpServerMetrics, HttpServerTechMiddleware,
+    HttpShuttingDownResultFactory, ResponseCompression, TrustedProxies,
+};
 
 pub struct HttpServerMiddlewares {
-    pub middlewares: Vec<Arc<dyn HttpServerMiddleware + Send + Sync + 'static>>,
+    pub middlewares: Vec<HttpResolvedMiddleware>,
     pub tech_middlewares: Vec<Arc<dyn HttpServerTechMiddleware + Send + Sync + 'static>>,
     /// Idle timeout for reading a request body — see
     /// [`BodyExpectations::read_timeout`](crate::BodyExpectations::read_timeout). Set through
     /// [`MyHttpServer::set_body_read_timeout`](crate::MyHttpServer::set_body_read_timeout);
     /// `None` waits forever, which is what this server has always done.
     pub body_read_timeout: Option<std::time::Duration>,
+    /// Set through [`MyHttpServer::set_max_body_size`](crate::MyHttpServer::set_max_body_size).
+    /// `None` takes bodies of any size.
+    pub max_body_size: Option<u64>,
+    /// Set through
+    /// [`MyHttpServer::set_max_decompressed_body_size`](crate::MyHttpServer::set_max_decompressed_body_size).
+    pub max_decompressed_body_size: u64,
+    /// Set through [`MyHttpServer::set_trusted_proxies`](crate::MyHttpServer::set_trusted_proxies).
+    /// `None` takes the forwarding headers from any peer, the way this server always has.
+    pub trusted_proxies: Option<Arc<TrustedProxies>>,
+    /// Set through [`MyHttpServer::set_request_timeout`](crate::MyHttpServer::set_request_timeout).
+    /// `None` lets a request run as long as its handler takes.
+    pub request_timeout: Option<std::time::Duration>,
+    pub request_timeout_result_factory:
+        Option<Arc<dyn HttpRequestTimeoutResultFactory + Send + Sync + 'static>>,
+    pub panic_result_factory: Option<Arc<dyn HttpPanicResultFactory + Send + Sync + 'static>>,
+    pub not_found_result_factory:
+        Option<Arc<dyn HttpNotFoundResultFactory + Send + Sync + 'static>>,
+    pub shutting_down_result_factory:
+        Option<Arc<dyn HttpShuttingDownResultFactory + Send + Sync + 'static>>,
+    /// Set through
+    /// [`MyHttpServer::set_shutting_down_retry_after`](crate::MyHttpServer::set_shutting_down_retry_after).
+    pub shutting_down_retry_after: Option<std::time::Duration>,
+    /// Set through
+    /// [`MyHttpServer::set_response_compression`](crate::MyHttpServer::set_response_compression).
+    /// `None` sends responses the way the actions built them.
+    pub response_compression: Option<Arc<ResponseCompression>>,
+    /// Set through [`MyHttpServer::set_metrics`](crate::MyHttpServer::set_metrics) — here for the
+    /// requests in flight; what they are answered with is counted by a tech middleware.
+    pub metrics: Option<HttpServerMetrics>,
+    /// Set through
+    /// [`MyHttpServer::set_request_id_header`](crate::MyHttpServer::set_request_id_header) — the
+    /// request id is read from it and echoed back in it.
+    pub request_id_header: hyper::header::HeaderName,
+    /// Set through
+    /// [`MyHttpServer::set_trace_response`](crate::MyHttpServer::set_trace_response).
+    #[cfg(feature = "with-telemetry")]
+    pub trace_response: bool,
+}
+
+impl HttpServerMiddlewares {
+    /// Whether anything is handed [`HttpRequestData`](crate::HttpRequestData) that may read its
+    /// headers — a tech middleware, or a factory of the timeout, panic, not-found or
+    /// shutting-down result. Without one the headers are not copied.
+    pub fn needs_request_headers(&self) -> bool {
+        !self.tech_middlewares.is_empty()
+            || self.request_timeout_result_factory.is_some()
+            || self.panic_result_factory.is_some()
+            || self.not_found_result_factory.is_some()
+            || self.shutting_down_result_factory.is_some()
+    }
 }
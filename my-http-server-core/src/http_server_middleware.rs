@@ -1,6 +1,8 @@
 use crate::{HttpContext, HttpFailResult, HttpOkResult};
+use std::time::Duration;
+
 use async_trait::async_trait;
-use hyper::Method;
+use hyper::{HeaderMap, Method, Version};
 use rust_extensions::date_time::DateTimeAsMicroseconds;
 
 #[async_trait]
@@ -11,40 +13,130 @@ pub trait HttpServerMiddleware {
     ) -> Option<Result<HttpOkResult, HttpFailResult>>;
 }
 
+/// What the tech middlewares and the fallback result factories are told about a request. Built
+/// by the server; [`HttpRequestData::new`] is there for tests of such middlewares.
 pub struct HttpRequestData {
     pub started: DateTimeAsMicroseconds,
     pub method: Method,
     pub path: String,
     pub ip: String,
+    /// `HTTP/1.1`, `HTTP/2.0`...
+    pub version: Version,
+    pub user_agent: Option<String>,
+    /// The size of the body as the client announced it in `Content-Length` — `None` for a chunked
+    /// body or one without any.
+    pub content_length: Option<u64>,
+    /// The headers the request came with — everything, secrets included: whoever writes them
+    /// anywhere has to redact them the way [`HttpAccessLogMiddleware`](crate::HttpAccessLogMiddleware)
+    /// does. Copied only when a tech middleware or a result factory is set, empty otherwise.
+    pub headers: HeaderMap,
+    /// See [`HttpContext::get_request_id`](crate::HttpContext::get_request_id).
+    pub request_id: String,
 }
 
+/// How a request was answered, for the tech middlewares. Built by the server;
+/// [`ResponseData::new`] is there for tests of such middlewares.
 pub struct ResponseData {
     pub status_code: u16,
     pub content_type: Option<String>,
     pub content_length: usize,
     pub has_error: bool,
+    pub outcome: HttpRequestOutcome,
+    /// What the request was handled as — [`HttpContext::process_name`]: the route of the action
+    /// for the controllers. Only known for a request the chain finished with.
+    pub process_name: Option<String>,
+    /// The id of the credentials the request was authenticated with.
+    pub client_id: Option<String>,
+    /// From the moment the request came in till the chain answered it — the body of a streamed
+    /// response may still be going out.
+    pub duration: Duration,
+}
+
+/// How the middleware chain finished with a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpRequestOutcome {
+    /// A middleware answered — successfully or with an error result.
+    Handled,
+    /// A middleware panicked; the client got a `500`.
+    Panicked,
+    /// The request ran past its deadline and the chain was cancelled — see
+    /// [`MyHttpServer::set_request_timeout`](crate::MyHttpServer::set_request_timeout).
+    TimedOut,
+}
+
+impl HttpRequestData {
+    /// The rest is left empty: `HTTP/1.1`, no user agent, no body, no headers, no request id.
+    pub fn new(started: DateTimeAsMicroseconds, method: Method, path: String, ip: String) -> Self {
+        Self {
+            started,
+            method,
+            path,
+            ip,
+            version: Version::HTTP_11,
+            user_agent: None,
+            content_length: None,
+            headers: HeaderMap::new(),
+            request_id: String::new(),
+        }
+    }
 }
 
 impl ResponseData {
+    /// A request a middleware [handled](HttpRequestOutcome::Handled), with no process name, no
+    /// client id and no duration.
+    pub fn new(
+        status_code: u16,
+        content_type: Option<String>,
+        content_length: usize,
+        has_error: bool,
+    ) -> Self {
+        Self {
+            status_code,
+            content_type,
+            content_length,
+            has_error,
+            outcome: HttpRequestOutcome::Handled,
+            process_name: None,
+            client_id: None,
+            duration: Duration::ZERO,
+        }
+    }
+
     pub fn from(result: &Result<HttpOkResult, HttpFailResult>) -> Self {
         let output = match result {
             Ok(ok) => &ok.output,
             Err(err) => &err.output,
         };
 
-        Self {
-            status_code: output.get_status_code(),
-            content_type: output
-                .get_content_type_as_str()
-                .map(|itm| itm.to_string().into()),
-
-            content_length: output.get_content_size(),
-            has_error: false,
-        }
+        Self::new(
+            output.get_status_code(),
+            output.get_content_type_as_str().map(|itm| itm.to_string()),
+            output.get_content_size(),
+            false,
+        )
     }
 }
 
+/// Why the accept loop closed a connection without serving it.
+#[derive(Debug, Clone, Copy)]
+pub enum HttpConnectionRefusedReason {
+    /// The server-wide limit of open connections is reached.
+    TooManyConnections { limit: usize },
+    /// The remote IP already has as many connections open as it is allowed.
+    TooManyConnectionsFromIp { limit: usize },
+}
+
+pub struct HttpConnectionRefusedData {
+    pub refused: DateTimeAsMicroseconds,
+    pub ip: String,
+    pub reason: HttpConnectionRefusedReason,
+}
+
 #[async_trait]
 pub trait HttpServerTechMiddleware {
     async fn got_result(&self, request: &HttpRequestData, http_result: &ResponseData);
+
+    /// A connection was closed at accept time because of
+    /// [`HttpConnectionLimits`](crate::HttpConnectionLimits). No request was read from it.
+    async fn connection_refused(&self, _connection: &HttpConnectionRefusedData) {}
 }
@@ -0,0 +1,234 @@
+use std::{
+    sync::{atomic::AtomicI64, Arc},
+    time::Duration,
+};
+
+use rust_extensions::{ApplicationStates, Logger};
+use tokio::sync::watch;
+
+pub const DEFAULT_GRACEFUL_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+const PROCESS_NAME: &str = "HttpServerShutdown";
+
+/// How long the force-closed connections get to notice. A connection task that is never polled
+/// again, or a counter that missed a decrement, must not keep the shutdown from completing.
+const FORCE_CLOSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The shutdown sequence of one [`MyHttpServer`](crate::MyHttpServer), shared by its accept loops
+/// and every connection they spawned:
+///
+/// 1. accept loops stop at once;
+/// 2. every live connection is told to finish gracefully — HTTP/1 closes after the response in
+///    flight, HTTP/2 sends `GOAWAY` and lets the open streams complete;
+/// 3. once the connections are gone, or the timeout is up, whatever is still open is dropped.
+pub struct HttpServerShutdown {
+    graceful: watch::Sender<bool>,
+    force_close: watch::Sender<bool>,
+    finished: watch::Sender<bool>,
+    connections: Arc<AtomicI64>,
+    timeout: Duration,
+    delay: Duration,
+    logger: Arc<dyn Logger + Send + Sync + 'static>,
+}
+
+impl HttpServerShutdown {
+    pub fn new(
+        connections: Arc<AtomicI64>,
+        timeout: Duration,
+        logger: Arc<dyn Logger + Send + Sync + 'static>,
+    ) -> Self {
+        Self {
+            graceful: watch::Sender::new(false),
+            force_close: watch::Sender::new(false),
+            finished: watch::Sender::new(false),
+            connections,
+            timeout,
+            delay: Duration::ZERO,
+            logger,
+        }
+    }
+
+    /// How long the server goes on serving after the application states report shutting down,
+    /// before the shutdown sequence starts. See
+    /// [`MyHttpServer::set_shutdown_delay`](crate::MyHttpServer::set_shutdown_delay).
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    pub fn is_started(&self) -> bool {
+        *self.graceful.borrow()
+    }
+
+    pub fn is_force_closing(&self) -> bool {
+        *self.force_close.borrow()
+    }
+
+    /// Resolves once the shutdown has started — accept loops stop, connections begin to wind down.
+    pub async fn wait_started(&self) {
+        let mut receiver = self.graceful.subscribe();
+        let _ = receiver.wait_for(|started| *started).await;
+    }
+
+    /// Resolves once the drain timeout is up and the connections still open are to be dropped.
+    pub async fn wait_force_close(&self) {
+        let mut receiver = self.force_close.subscribe();
+        let _ = receiver.wait_for(|force_close| *force_close).await;
+    }
+
+    /// Runs the shutdown sequence and resolves when it is complete. Safe to call more than once —
+    /// a second caller just waits for the sequence the first one started.
+    pub async fn shutdown(&self) {
+        let started_here = self.graceful.send_if_modified(|started| {
+            if *started {
+                return false;
+            }
+            *started = true;
+            true
+        });
+
+        if !started_here {
+            let mut receiver = self.finished.subscribe();
+            let _ = receiver.wait_for(|finished| *finished).await;
+            return;
+        }
+
+        self.logger.write_info(
+            PROCESS_NAME.to_string(),
+            format!(
+                "Http server stopped accepting connections. Draining {} connection(s) for up to {:?}",
+                self.get_connections_amount(),
+                self.timeout
+            ),
+            None,
+        );
+
+        let deadline = tokio::time::Instant::now() + self.timeout;
+
+        while self.get_connections_amount() > 0 && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+
+        let left_open = self.get_connections_amount();
+
+        if left_open > 0 {
+            self.logger.write_warning(
+                PROCESS_NAME.to_string(),
+                format!(
+                    "Drain timeout {:?} is up. Force closing {} connection(s)",
+                    self.timeout, left_open
+                ),
+                None,
+            );
+
+            self.force_close.send_replace(true);
+
+            // Dropping a connection is immediate, but its task still has to be polled to notice.
+            let deadline = tokio::time::Instant::now() + FORCE_CLOSE_TIMEOUT;
+
+            while self.get_connections_amount() > 0 && tokio::time::Instant::now() < deadline {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+
+            let abandoned = self.get_connections_amount();
+
+            if abandoned > 0 {
+                self.logger.write_warning(
+                    PROCESS_NAME.to_string(),
+                    format!(
+                        "{} connection(s) did not close after being force closed. They are abandoned",
+                        abandoned
+                    ),
+                    None,
+                );
+            }
+        }
+
+        self.logger.write_info(
+            PROCESS_NAME.to_string(),
+            "Http server is stopped".to_string(),
+            None,
+        );
+
+        self.finished.send_replace(true);
+    }
+
+    /// Starts the shutdown on its own when the application begins to shut down, so a service that
+    /// only flips its [`ApplicationStates`] still gets its requests drained. With a delay the
+    /// server keeps accepting for that long first — unless the shutdown is started directly.
+    pub fn spawn_app_states_watcher(
+        self: &Arc<Self>,
+        app_states: Arc<dyn ApplicationStates + Send + Sync + 'static>,
+    ) {
+        let shutdown = self.clone();
+        tokio::spawn(async move {
+            while !app_states.is_shutting_down() {
+                if shutdown.is_started() {
+                    return;
+                }
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+
+            if !shutdown.delay.is_zero() {
+                shutdown.logger.write_info(
+                    PROCESS_NAME.to_string(),
+                    format!(
+                        "Application is shutting down. Http server keeps serving for {:?} before it stops accepting connections",
+                        shutdown.delay
+                    ),
+                    None,
+                );
+
+                tokio::select! {
+                    _ = tokio::time::sleep(shutdown.delay) => {}
+                    _ = shutdown.wait_started() => {}
+                }
+            }
+
+            shutdown.shutdown().await;
+        });
+    }
+
+    fn get_connections_amount(&self) -> i64 {
+        self.connections.load(std::sync::atomic::Ordering::SeqCst)
+    }
+}
+
+/// Drives a hyper connection to completion, switching it to graceful mode when the shutdown starts
+/// and dropping it if it is still open when the drain timeout is up.
+pub(crate) async fn serve_until_shutdown<TConnection: std::future::Future>(
+    mut connection: std::pin::Pin<&mut TConnection>,
+    graceful_shutdown: impl FnOnce(std::pin::Pin<&mut TConnection>),
+    shutdown: &HttpServerShutdown,
+) {
+    tokio::select! {
+        _ = connection.as_mut() => return,
+        _ = shutdown.wait_started() => {}
+    }
+
+    graceful_shutdown(connection.as_mut());
+
+    tokio::select! {
+        _ = connection.as_mut() => {}
+        _ = shutdown.wait_force_close() => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_logger::NoLogger;
+
+    #[tokio::test]
+    async fn test_connection_that_never_closes_does_not_hang_the_shutdown() {
+        let connections = Arc::new(AtomicI64::new(1));
+
+        let shutdown =
+            HttpServerShutdown::new(connections, Duration::from_millis(10), Arc::new(NoLogger));
+
+        let result = tokio::time::timeout(Duration::from_secs(5), shutdown.shutdown()).await;
+
+        assert!(result.is_ok());
+        assert!(shutdown.is_force_closing());
+    }
+}
@@ -0,0 +1,323 @@
+use hyper::header::HeaderMap;
+use my_telemetry::MyTelemetryContext;
+use rust_extensions::date_time::DateTimeAsMicroseconds;
+
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+pub const TRACESTATE_HEADER: &str = "tracestate";
+pub const TRACERESPONSE_HEADER: &str = "traceresponse";
+
+const TRACE_FLAG_SAMPLED: u8 = 0x01;
+
+/// The [W3C Trace Context](https://www.w3.org/TR/trace-context/) of a request: the trace it is
+/// part of, taken from the `traceparent` and `tracestate` it came with — or a new trace, when it
+/// came without them or with ones that do not parse.
+///
+/// The server handles the request as a span of its own — [`get_span_id`](Self::get_span_id) — so
+/// a call made on behalf of the request passes [`to_traceparent`](Self::to_traceparent) and
+/// [`get_trace_state`](Self::get_trace_state) on, and the service it calls becomes a child of this
+/// one.
+///
+/// The telemetry the server writes keeps using [`MyTelemetryContext`], which has a number for a
+/// process rather than a trace id: it is the last 8 bytes of the trace id, so every service of a
+/// trace writes its telemetry under the same process. A new trace is started with the timestamp
+/// the process used to be — see [`to_telemetry_context`](Self::to_telemetry_context).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpTraceContext {
+    trace_id: [u8; 16],
+    parent_id: Option<[u8; 8]>,
+    span_id: [u8; 8],
+    trace_flags: u8,
+    trace_state: Option<String>,
+}
+
+impl HttpTraceContext {
+    /// A new trace, sampled, with this server's span as its root.
+    pub fn new_root() -> Self {
+        let mut trace_id = [0u8; 16];
+        trace_id[..8].copy_from_slice(&random_bytes()[..8]);
+        trace_id[8..].copy_from_slice(
+            &DateTimeAsMicroseconds::now()
+                .unix_microseconds
+                .to_be_bytes(),
+        );
+
+        Self {
+            trace_id,
+            parent_id: None,
+            span_id: new_span_id(),
+            trace_flags: TRACE_FLAG_SAMPLED,
+            trace_state: None,
+        }
+    }
+
+    /// From `traceparent` and `tracestate` of `headers`; a new root if `traceparent` is missing or
+    /// malformed — `tracestate` is dropped along with it then, as the spec says.
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let trace_parent = headers
+            .get(TRACEPARENT_HEADER)
+            .and_then(|itm| itm.to_str().ok());
+
+        let trace_state = headers
+            .get_all(TRACESTATE_HEADER)
+            .iter()
+            .filter_map(|itm| itm.to_str().ok())
+            .map(|itm| itm.trim())
+            .filter(|itm| !itm.is_empty())
+            .collect::<Vec<_>>();
+
+        let trace_state = if trace_state.is_empty() {
+            None
+        } else {
+            Some(trace_state.join(","))
+        };
+
+        trace_parent
+            .and_then(|trace_parent| Self::parse(trace_parent, trace_state))
+            .unwrap_or_else(Self::new_root)
+    }
+
+    /// `None` if `trace_parent` is not a valid `traceparent`. Versions after `00` are read the
+    /// way the spec asks: the `00` fields, whatever follows them ignored.
+    pub fn parse(trace_parent: &str, trace_state: Option<String>) -> Option<Self> {
+        let trace_parent = trace_parent.trim();
+
+        if trace_parent.len() < 55 || !trace_parent.is_ascii() {
+            return None;
+        }
+
+        let version = parse_hex::<1>(&trace_parent[0..2])?;
+
+        match version[0] {
+            0xff => return None,
+            0x00 if trace_parent.len() != 55 => return None,
+            _ if trace_parent.len() > 55 && &trace_parent[55..56] != "-" => return None,
+            _ => {}
+        }
+
+        if &trace_parent[2..3] != "-"
+            || &trace_parent[35..36] != "-"
+            || &trace_parent[52..53] != "-"
+        {
+            return None;
+        }
+
+        let trace_id = parse_hex::<16>(&trace_parent[3..35])?;
+        let parent_id = parse_hex::<8>(&trace_parent[36..52])?;
+        let trace_flags = parse_hex::<1>(&trace_parent[53..55])?[0];
+
+        if trace_id.iter().all(|b| *b == 0) || parent_id.iter().all(|b| *b == 0) {
+            return None;
+        }
+
+        Some(Self {
+            trace_id,
+            parent_id: Some(parent_id),
+            span_id: new_span_id(),
+            trace_flags,
+            trace_state,
+        })
+    }
+
+    /// 32 lowercase hex digits.
+    pub fn get_trace_id(&self) -> String {
+        to_hex(&self.trace_id)
+    }
+
+    /// The span of the caller — `None` for a trace this server started.
+    pub fn get_parent_id(&self) -> Option<String> {
+        self.parent_id.as_ref().map(|itm| to_hex(itm))
+    }
+
+    /// The span of this server handling the request.
+    pub fn get_span_id(&self) -> String {
+        to_hex(&self.span_id)
+    }
+
+    pub fn get_trace_flags(&self) -> u8 {
+        self.trace_flags
+    }
+
+    pub fn is_sampled(&self) -> bool {
+        self.trace_flags & TRACE_FLAG_SAMPLED != 0
+    }
+
+    /// As it came in `tracestate` — passed on untouched.
+    pub fn get_trace_state(&self) -> Option<&str> {
+        self.trace_state.as_deref()
+    }
+
+    /// The `traceparent` a call made on behalf of the request sends — this server's span as the
+    /// parent. The same value is what `traceresponse` echoes.
+    pub fn to_traceparent(&self) -> String {
+        format!(
+            "00-{}-{}-{:02x}",
+            self.get_trace_id(),
+            self.get_span_id(),
+            self.trace_flags
+        )
+    }
+
+    /// The number of the process the telemetry of the request is written under — the last 8 bytes
+    /// of the trace id, without the sign bit.
+    pub fn get_process_id(&self) -> i64 {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&self.trace_id[8..]);
+        i64::from_be_bytes(bytes) & i64::MAX
+    }
+
+    pub fn to_telemetry_context(&self) -> MyTelemetryContext {
+        MyTelemetryContext::Single(self.get_process_id())
+    }
+}
+
+fn random_bytes() -> [u8; 16] {
+    *uuid::Uuid::new_v4().as_bytes()
+}
+
+fn new_span_id() -> [u8; 8] {
+    let mut result = [0u8; 8];
+    result.copy_from_slice(&random_bytes()[8..]);
+    result
+}
+
+/// Lowercase only — the spec does not allow uppercase hex in `traceparent`.
+fn parse_hex<const N: usize>(src: &str) -> Option<[u8; N]> {
+    let src = src.as_bytes();
+
+    if src.len() != N * 2 {
+        return None;
+    }
+
+    let mut result = [0u8; N];
+
+    for (i, pair) in src.chunks(2).enumerate() {
+        result[i] = (hex_digit(pair[0])? << 4) | hex_digit(pair[1])?;
+    }
+
+    Some(result)
+}
+
+fn hex_digit(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'a'..=b'f' => Some(c - b'a' + 10),
+        _ => None,
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    let mut result = String::with_capacity(bytes.len() * 2);
+
+    for b in bytes {
+        result.push_str(&format!("{:02x}", b));
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::header::HeaderValue;
+
+    use super::*;
+
+    const TRACE_PARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[test]
+    fn test_parse_traceparent() {
+        let trace_context =
+            HttpTraceContext::parse(TRACE_PARENT, Some("congo=t61rcWkgMzE".to_string())).unwrap();
+
+        assert_eq!(
+            trace_context.get_trace_id(),
+            "4bf92f3577b34da6a3ce929d0e0e4736"
+        );
+        assert_eq!(
+            trace_context.get_parent_id().as_deref(),
+            Some("00f067aa0ba902b7")
+        );
+        assert_ne!(trace_context.get_span_id(), "00f067aa0ba902b7");
+        assert!(trace_context.is_sampled());
+        assert_eq!(trace_context.get_trace_state(), Some("congo=t61rcWkgMzE"));
+
+        let trace_parent = trace_context.to_traceparent();
+        assert!(trace_parent.starts_with("00-4bf92f3577b34da6a3ce929d0e0e4736-"));
+        assert!(trace_parent.ends_with("-01"));
+        assert_eq!(trace_parent.len(), 55);
+
+        assert_eq!(trace_context.get_process_id(), 0x23ce929d0e0e4736);
+    }
+
+    #[test]
+    fn test_malformed_traceparent() {
+        for trace_parent in [
+            "",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+            "00_4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+        ] {
+            assert!(
+                HttpTraceContext::parse(trace_parent, None).is_none(),
+                "{}",
+                trace_parent
+            );
+        }
+    }
+
+    #[test]
+    fn test_future_version_is_read_as_00() {
+        let trace_context = HttpTraceContext::parse(
+            "cc-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00-what-comes-next",
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            trace_context.get_trace_id(),
+            "4bf92f3577b34da6a3ce929d0e0e4736"
+        );
+        assert!(!trace_context.is_sampled());
+    }
+
+    #[test]
+    fn test_from_headers_falls_back_to_new_root() {
+        let mut headers = HeaderMap::new();
+        headers.insert(TRACEPARENT_HEADER, HeaderValue::from_static("garbage"));
+        headers.insert(TRACESTATE_HEADER, HeaderValue::from_static("congo=1"));
+
+        let trace_context = HttpTraceContext::from_headers(&headers);
+
+        assert_eq!(trace_context.get_parent_id(), None);
+        assert_eq!(trace_context.get_trace_state(), None);
+        assert!(trace_context.is_sampled());
+    }
+
+    #[test]
+    fn test_from_headers_joins_tracestate() {
+        let mut headers = HeaderMap::new();
+        headers.insert(TRACEPARENT_HEADER, HeaderValue::from_static(TRACE_PARENT));
+        headers.append(TRACESTATE_HEADER, HeaderValue::from_static("congo=1"));
+        headers.append(TRACESTATE_HEADER, HeaderValue::from_static("rojo=2"));
+
+        let trace_context = HttpTraceContext::from_headers(&headers);
+
+        assert_eq!(trace_context.get_trace_state(), Some("congo=1,rojo=2"));
+    }
+
+    #[test]
+    fn test_new_root_keeps_the_timestamp_as_the_process_id() {
+        let before = DateTimeAsMicroseconds::now().unix_microseconds;
+        let trace_context = HttpTraceContext::new_root();
+        let after = DateTimeAsMicroseconds::now().unix_microseconds;
+
+        let process_id = trace_context.get_process_id();
+        assert!(process_id >= before && process_id <= after);
+
+        let restored = HttpTraceContext::parse(&trace_context.to_traceparent(), None).unwrap();
+        assert_eq!(restored.get_process_id(), process_id);
+    }
+}
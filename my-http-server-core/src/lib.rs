@@ -1,11 +1,17 @@
 pub mod errors;
 mod http_ctx;
+mod http_context_extensions;
 mod http_fail_result;
 
 mod http_ok_result;
 mod http_path;
 
 mod http_server_middleware;
+mod http_middleware_chain;
+mod http_middleware_group;
+mod access_log;
+mod metrics;
+mod health;
 
 mod request_credentials;
 //mod request_flow;
@@ -13,8 +19,31 @@ mod request_ip;
 
 mod http_server;
 mod http_server_data;
+mod http_server_shutdown;
+mod http_request_timeout;
+mod http_fallback_results;
+mod http_request_id;
+#[cfg(feature = "test-client")]
+mod test_client;
+#[cfg(test)]
+mod test_logger;
+#[cfg(feature = "with-telemetry")]
+mod http_trace_context;
+
+mod http_connection_info;
+mod http_connection_limits;
+mod http_listener;
+#[cfg(feature = "with-tls")]
+mod tls;
+mod unix_socket_options;
+mod proxy_protocol;
+mod trusted_proxies;
+mod forwarded_headers;
+#[cfg(unix)]
+mod listen_fds;
 
 mod web_content_type;
+mod response_compression;
 
 // ── The value / reader / conversion / field-type layer is owned by my-http-utils (the same lib
 // fl-url and other clients use), so a `#[derive(MyHttpInput)]` model compiles on both sides.
@@ -35,6 +64,7 @@ pub use my_http_utils::http_input::{
 };
 
 pub use http_ctx::HttpContext;
+pub use http_context_extensions::*;
 pub use http_fail_result::HttpFailResult;
 pub use http_ok_result::*;
 pub use http_path::HttpPath;
@@ -42,12 +72,38 @@ pub use http_path::HttpPath;
 pub use http_server::*;
 
 pub use http_server_middleware::*;
+pub use http_middleware_chain::*;
+pub use http_middleware_group::*;
+pub use access_log::*;
+pub use metrics::*;
+pub use health::*;
 pub use request_credentials::*;
 //pub use request_flow::HttpServerRequestFlow;
 pub use request_ip::*;
 pub use web_content_type::WebContentType;
+pub use response_compression::*;
 
 pub use http_server_data::*;
+pub use http_server_shutdown::*;
+pub use http_request_timeout::*;
+pub use http_fallback_results::*;
+pub use http_request_id::*;
+#[cfg(feature = "test-client")]
+pub use test_client::*;
+#[cfg(feature = "with-telemetry")]
+pub use http_trace_context::*;
+
+pub use http_connection_info::*;
+pub use http_connection_limits::*;
+pub use http_listener::*;
+#[cfg(feature = "with-tls")]
+pub use tls::*;
+pub use unix_socket_options::*;
+pub use proxy_protocol::*;
+pub use trusted_proxies::*;
+pub(crate) use forwarded_headers::*;
+#[cfg(unix)]
+pub use listen_fds::*;
 
 mod http_request;
 pub use http_request::*;
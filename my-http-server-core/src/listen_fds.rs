@@ -0,0 +1,285 @@
+use std::os::unix::io::RawFd;
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// The first descriptor systemd socket activation passes — `0..=2` are stdin, stdout and stderr.
+pub const SD_LISTEN_FDS_START: RawFd = 3;
+
+const LISTEN_PID_ENV: &str = "LISTEN_PID";
+const LISTEN_FDS_ENV: &str = "LISTEN_FDS";
+const LISTEN_FDNAMES_ENV: &str = "LISTEN_FDNAMES";
+/// Set by [`spawn_with_listen_fds`] in place of `LISTEN_PID`, which it can not know before the
+/// child exists: the sockets are for the process whose parent has this pid.
+const LISTEN_PARENT_PID_ENV: &str = "LISTEN_PARENT_PID";
+
+static LISTEN_FDS_TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// A listening socket a process got from its parent instead of binding it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListenFd {
+    pub fd: RawFd,
+    /// `FileDescriptorName=` of the systemd socket unit, or the name of the
+    /// [`HttpListener`](crate::HttpListener) the socket belonged to when it was handed over by
+    /// [`spawn_with_listen_fds`].
+    pub name: Option<String>,
+}
+
+/// Takes the listening sockets passed through the systemd socket activation protocol —
+/// `LISTEN_FDS`, `LISTEN_FDNAMES` and `LISTEN_PID` — which is also how [`spawn_with_listen_fds`]
+/// hands them to a child. Empty if the process was started some other way, or the variables name
+/// another process.
+///
+/// Only the first call returns the sockets. The environment is left as it is — changing it is not
+/// safe while other threads may read it — but the sockets are made close-on-exec, and a process
+/// this one starts is not the one the variables name. Serve a socket with
+/// [`MyHttpServer::new_from_listen_fd`](crate::MyHttpServer::new_from_listen_fd) or
+/// [`HttpListener::from_listen_fd`](crate::HttpListener::from_listen_fd).
+pub fn take_listen_fds() -> Vec<ListenFd> {
+    if LISTEN_FDS_TAKEN.swap(true, Ordering::SeqCst) {
+        return Vec::new();
+    }
+
+    let result = parse_listen_fds(
+        ListenFdsEnv {
+            listen_pid: std::env::var(LISTEN_PID_ENV).ok().as_deref(),
+            listen_parent_pid: std::env::var(LISTEN_PARENT_PID_ENV).ok().as_deref(),
+            listen_fds: std::env::var(LISTEN_FDS_ENV).ok().as_deref(),
+            listen_fdnames: std::env::var(LISTEN_FDNAMES_ENV).ok().as_deref(),
+        },
+        std::process::id(),
+        std::os::unix::process::parent_id(),
+    );
+
+    for listen_fd in &result {
+        unsafe {
+            let flags = libc::fcntl(listen_fd.fd, libc::F_GETFD);
+            if flags >= 0 {
+                libc::fcntl(listen_fd.fd, libc::F_SETFD, flags | libc::FD_CLOEXEC);
+            }
+        }
+    }
+
+    result
+}
+
+/// Starts `command` with `listen_fds` as its descriptors `3..`, announced the way systemd does
+/// it — the child picks them up with [`take_listen_fds`]. Pass it
+/// [`HttpServerHandle::get_listen_fds`](crate::HttpServerHandle::get_listen_fds) to roll a new
+/// binary without refusing a single connection: both processes accept on the same sockets until
+/// the parent calls [`HttpServerHandle::shutdown`](crate::HttpServerHandle::shutdown) and
+/// drains.
+///
+/// The child has to take the sockets before this process exits: they are announced for the child
+/// of this process, and an orphan gets another parent.
+///
+/// The descriptors of the parent are left as they are.
+pub fn spawn_with_listen_fds(
+    command: &mut Command,
+    listen_fds: &[ListenFd],
+) -> std::io::Result<Child> {
+    let names: Vec<&str> = listen_fds
+        .iter()
+        .map(|itm| itm.name.as_deref().unwrap_or("unknown"))
+        .collect();
+
+    command.env_remove(LISTEN_PID_ENV);
+    command.env(LISTEN_PARENT_PID_ENV, std::process::id().to_string());
+    command.env(LISTEN_FDS_ENV, listen_fds.len().to_string());
+    command.env(LISTEN_FDNAMES_ENV, names.join(":"));
+
+    let source_fds: Vec<RawFd> = listen_fds.iter().map(|itm| itm.fd).collect();
+    let mut temp_fds: Vec<RawFd> = Vec::with_capacity(source_fds.len());
+
+    // Runs in the child between fork and exec, where nothing may allocate: `temp_fds` has its
+    // capacity already.
+    unsafe {
+        command.pre_exec(move || {
+            let first_free_fd = SD_LISTEN_FDS_START + source_fds.len() as RawFd;
+
+            // Moving the sockets to 3.. directly could overwrite one that is still to be moved.
+            // Copies above the target range are out of the way — and close on exec.
+            temp_fds.clear();
+            for fd in &source_fds {
+                let temp_fd = libc::fcntl(*fd, libc::F_DUPFD_CLOEXEC, first_free_fd);
+                if temp_fd < 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                temp_fds.push(temp_fd);
+            }
+
+            // dup2 clears close-on-exec on the copy it makes — these are the ones the child gets.
+            for (index, temp_fd) in temp_fds.iter().enumerate() {
+                if libc::dup2(*temp_fd, SD_LISTEN_FDS_START + index as RawFd) < 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+            }
+
+            Ok(())
+        });
+    }
+
+    command.spawn()
+}
+
+struct ListenFdsEnv<'s> {
+    listen_pid: Option<&'s str>,
+    listen_parent_pid: Option<&'s str>,
+    listen_fds: Option<&'s str>,
+    listen_fdnames: Option<&'s str>,
+}
+
+fn parse_listen_fds(env: ListenFdsEnv, pid: u32, parent_pid: u32) -> Vec<ListenFd> {
+    let parse_pid = |value: &str| value.trim().parse::<u32>().ok();
+
+    // Without a pid to match the variables may have been meant for another process — inherited,
+    // say, from the parent the sockets were passed to.
+    let is_for_this_process = match (env.listen_pid, env.listen_parent_pid) {
+        (Some(listen_pid), _) => parse_pid(listen_pid) == Some(pid),
+        (None, Some(listen_parent_pid)) => parse_pid(listen_parent_pid) == Some(parent_pid),
+        (None, None) => false,
+    };
+
+    if !is_for_this_process {
+        return Vec::new();
+    }
+
+    let amount = match env
+        .listen_fds
+        .and_then(|itm| itm.trim().parse::<RawFd>().ok())
+    {
+        Some(amount) if amount > 0 => amount,
+        _ => return Vec::new(),
+    };
+
+    let mut names = env.listen_fdnames.map(|itm| itm.split(':'));
+
+    (0..amount)
+        .map(|index| ListenFd {
+            fd: SD_LISTEN_FDS_START + index,
+            name: names
+                .as_mut()
+                .and_then(|itm| itm.next())
+                .filter(|itm| !itm.is_empty())
+                .map(|itm| itm.to_string()),
+        })
+        .collect()
+}
+
+/// The sockets the accept loops of a server listen on, for
+/// [`HttpServerHandle::get_listen_fds`](crate::HttpServerHandle::get_listen_fds).
+#[derive(Clone, Default)]
+pub(crate) struct ListenFdsRegistry {
+    items: Arc<Mutex<Vec<ListenFd>>>,
+}
+
+impl ListenFdsRegistry {
+    pub fn register(&self, listener_name: &str, fd: RawFd) {
+        self.items.lock().unwrap().push(ListenFd {
+            fd,
+            name: Some(listener_name.to_string()),
+        });
+    }
+
+    pub fn get(&self) -> Vec<ListenFd> {
+        self.items.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env<'s>(
+        listen_pid: Option<&'s str>,
+        listen_parent_pid: Option<&'s str>,
+        listen_fds: Option<&'s str>,
+        listen_fdnames: Option<&'s str>,
+    ) -> ListenFdsEnv<'s> {
+        ListenFdsEnv {
+            listen_pid,
+            listen_parent_pid,
+            listen_fds,
+            listen_fdnames,
+        }
+    }
+
+    #[test]
+    fn fds_with_names() {
+        let result = parse_listen_fds(env(Some("42"), None, Some("2"), Some("http:admin")), 42, 1);
+
+        assert_eq!(
+            result,
+            vec![
+                ListenFd {
+                    fd: 3,
+                    name: Some("http".to_string())
+                },
+                ListenFd {
+                    fd: 4,
+                    name: Some("admin".to_string())
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn fds_of_another_process_are_ignored() {
+        assert!(parse_listen_fds(env(Some("41"), None, Some("1"), None), 42, 1).is_empty());
+    }
+
+    #[test]
+    fn missing_pid_is_refused() {
+        assert!(parse_listen_fds(env(None, None, Some("1"), None), 42, 1).is_empty());
+    }
+
+    #[test]
+    fn fds_handed_over_by_the_parent() {
+        let result = parse_listen_fds(env(None, Some("7"), Some("1"), None), 42, 7);
+
+        assert_eq!(result, vec![ListenFd { fd: 3, name: None }]);
+    }
+
+    /// A process the child starts inherits the variables, but is not the child of that parent.
+    #[test]
+    fn fds_handed_over_to_another_child_are_ignored() {
+        assert!(parse_listen_fds(env(None, Some("7"), Some("1"), None), 43, 42).is_empty());
+    }
+
+    #[test]
+    fn nothing_passed() {
+        assert!(parse_listen_fds(env(None, None, None, None), 42, 1).is_empty());
+        assert!(parse_listen_fds(env(Some("42"), None, Some("0"), None), 42, 1).is_empty());
+        assert!(parse_listen_fds(env(Some("42"), None, Some("x"), None), 42, 1).is_empty());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn child_gets_fds_from_three() {
+        use std::os::unix::io::AsRawFd;
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let listen_fds = vec![ListenFd {
+            fd: listener.as_raw_fd(),
+            name: Some("http".to_string()),
+        }];
+
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg("readlink /proc/self/fd/3 | grep -q '^socket:' && echo \"$LISTEN_FDS $LISTEN_FDNAMES $LISTEN_PARENT_PID\"")
+            .stdout(std::process::Stdio::piped());
+
+        let output = spawn_with_listen_fds(&mut command, &listen_fds)
+            .unwrap()
+            .wait_with_output()
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout).trim(),
+            format!("1 http {}", std::process::id())
+        );
+    }
+}
@@ -0,0 +1,48 @@
+use std::sync::{
+    atomic::{AtomicI64, Ordering},
+    Arc,
+};
+
+/// A number that goes up and down — the connections of a kind that are open right now. Clones
+/// share the value: one is kept by whoever counts, another is given to
+/// [`HttpServerMetrics::add_connections_gauge`](crate::HttpServerMetrics::add_connections_gauge).
+#[derive(Debug, Clone, Default)]
+pub struct HttpMetricsGauge {
+    value: Arc<AtomicI64>,
+}
+
+impl HttpMetricsGauge {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn inc(&self) {
+        self.value.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn dec(&self) {
+        self.value.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> i64 {
+        self.value.load(Ordering::Relaxed)
+    }
+
+    /// Up till the guard is dropped.
+    pub fn inc_scoped(&self) -> HttpMetricsGaugeGuard {
+        self.inc();
+        HttpMetricsGaugeGuard {
+            gauge: self.clone(),
+        }
+    }
+}
+
+pub struct HttpMetricsGaugeGuard {
+    gauge: HttpMetricsGauge,
+}
+
+impl Drop for HttpMetricsGaugeGuard {
+    fn drop(&mut self) {
+        self.gauge.dec();
+    }
+}
@@ -0,0 +1,78 @@
+use async_trait::async_trait;
+use hyper::Method;
+
+use crate::{
+    HttpConnectionRefusedData, HttpContext, HttpFailResult, HttpOkResult, HttpOutput,
+    HttpRequestData, HttpResponseHeaders, HttpServerMiddleware, HttpServerTechMiddleware,
+    ResponseData, WebContentType,
+};
+
+use super::HttpServerMetrics;
+
+const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Answers `GET` of the metrics path — put in front of the chain by
+/// [`MyHttpServer::set_metrics`](crate::MyHttpServer::set_metrics).
+pub(crate) struct HttpMetricsEndpoint {
+    path: String,
+    metrics: HttpServerMetrics,
+}
+
+impl HttpMetricsEndpoint {
+    pub fn new(path: String, metrics: HttpServerMetrics) -> Self {
+        Self { path, metrics }
+    }
+}
+
+#[async_trait]
+impl HttpServerMiddleware for HttpMetricsEndpoint {
+    async fn handle_request(
+        &self,
+        ctx: &mut HttpContext,
+    ) -> Option<Result<HttpOkResult, HttpFailResult>> {
+        if ctx.request.method != Method::GET
+            || !ctx
+                .request
+                .http_path
+                .as_str()
+                .eq_ignore_ascii_case(&self.path)
+        {
+            return None;
+        }
+
+        ctx.process_name = Some(self.path.clone());
+
+        let output = HttpOutput::Content {
+            status_code: 200,
+            headers: HttpResponseHeaders::new(Some(WebContentType::Raw(
+                PROMETHEUS_CONTENT_TYPE.to_string(),
+            ))),
+            content: self.metrics.render().into_bytes(),
+        };
+
+        Some(output.into_ok_result(false))
+    }
+}
+
+/// Counts what the server answered — added to its tech middlewares by
+/// [`MyHttpServer::set_metrics`](crate::MyHttpServer::set_metrics).
+pub(crate) struct HttpMetricsRecorder {
+    metrics: HttpServerMetrics,
+}
+
+impl HttpMetricsRecorder {
+    pub fn new(metrics: HttpServerMetrics) -> Self {
+        Self { metrics }
+    }
+}
+
+#[async_trait]
+impl HttpServerTechMiddleware for HttpMetricsRecorder {
+    async fn got_result(&self, request: &HttpRequestData, http_result: &ResponseData) {
+        self.metrics.record(request, http_result);
+    }
+
+    async fn connection_refused(&self, _connection: &HttpConnectionRefusedData) {
+        self.metrics.connection_refused();
+    }
+}
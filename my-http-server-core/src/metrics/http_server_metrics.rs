@@ -0,0 +1,579 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use hyper::Method;
+
+use crate::{
+    HttpConnectionsCounter, HttpRequestData, HttpRequestOutcome, HttpServerMiddleware, ResponseData,
+};
+
+use super::{
+    metrics_histogram::{write_header, write_sample, MetricsHistogram},
+    HttpMetricsEndpoint, HttpMetricsGauge, HttpMetricsGaugeGuard,
+};
+
+pub const DEFAULT_METRICS_PATH: &str = "/metrics";
+
+/// The default buckets of the Prometheus client libraries, in seconds.
+pub const DEFAULT_LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+pub const DEFAULT_SIZE_BUCKETS: &[f64] = &[
+    100.0,
+    1_000.0,
+    10_000.0,
+    100_000.0,
+    1_000_000.0,
+    10_000_000.0,
+];
+
+/// The route label of the requests no action was matched for. Their paths are not labels: every
+/// scanner probing for `/wp-admin` would add one more time series otherwise.
+pub const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// The method label of the requests with a method that is not a standard one — hyper takes any
+/// token as a method, so a client could otherwise add a time series per request.
+pub const OTHER_METHOD: &str = "OTHER";
+
+/// Prometheus metrics of a server: what it answered and how fast, per method, route and status
+/// class, the requests in flight, the open connections, the panics. Given to the server with
+/// [`MyHttpServer::set_metrics`](crate::MyHttpServer::set_metrics), which also answers
+/// `GET /metrics` with them in the Prometheus text format.
+///
+/// The route of a request is the one of the action that handled it — `/api/users/{id}`, not
+/// `/api/users/42`: [`HttpContext::process_name`](crate::HttpContext::process_name), which the
+/// controllers set.
+///
+/// Clones share what they count, so one can be kept to render the metrics elsewhere — see
+/// [`render`](Self::render) — or to add the gauges of WebSocket, SignalR and Socket.IO
+/// connections with [`add_connections_gauge`](Self::add_connections_gauge).
+#[derive(Clone)]
+pub struct HttpServerMetrics {
+    path: Option<String>,
+    latency_buckets: Arc<Vec<f64>>,
+    size_buckets: Arc<Vec<f64>>,
+    state: Arc<HttpServerMetricsState>,
+}
+
+#[derive(Default)]
+struct HttpServerMetricsState {
+    requests: Mutex<BTreeMap<RequestLabels, RequestMetrics>>,
+    in_flight: HttpMetricsGauge,
+    panics: AtomicU64,
+    refused_connections: AtomicU64,
+    http_connections: Mutex<Vec<HttpConnectionsCounter>>,
+    connections_gauges: Mutex<Vec<(String, HttpMetricsGauge)>>,
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct RequestLabels {
+    method: &'static str,
+    route: String,
+    status: &'static str,
+}
+
+struct RequestMetrics {
+    count: u64,
+    duration: MetricsHistogram,
+    request_size: MetricsHistogram,
+    response_size: MetricsHistogram,
+}
+
+impl HttpServerMetrics {
+    pub fn new() -> Self {
+        Self {
+            path: Some(DEFAULT_METRICS_PATH.to_string()),
+            latency_buckets: Arc::new(DEFAULT_LATENCY_BUCKETS.to_vec()),
+            size_buckets: Arc::new(DEFAULT_SIZE_BUCKETS.to_vec()),
+            state: Arc::default(),
+        }
+    }
+
+    /// Where the server answers with the metrics. `/metrics` by default.
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// The server does not answer with the metrics — they are exposed some other way, e.g. by
+    /// [`render`](Self::render) on a server that is not reachable from outside.
+    pub fn without_endpoint(mut self) -> Self {
+        self.path = None;
+        self
+    }
+
+    /// The buckets of the request duration histogram, in seconds. To be set before the metrics
+    /// are given to the server.
+    pub fn with_latency_buckets(mut self, buckets: impl IntoIterator<Item = f64>) -> Self {
+        self.latency_buckets = Arc::new(sorted(buckets));
+        self
+    }
+
+    /// The buckets of the request and response size histograms, in bytes. To be set before the
+    /// metrics are given to the server.
+    pub fn with_size_buckets(mut self, buckets: impl IntoIterator<Item = f64>) -> Self {
+        self.size_buckets = Arc::new(sorted(buckets));
+        self
+    }
+
+    /// Exposes `gauge` as `http_server_realtime_connections{kind="..."}` — the
+    /// `get_connections_gauge` of the WebSocket, SignalR and Socket.IO middlewares.
+    pub fn add_connections_gauge(&self, kind: impl Into<String>, gauge: HttpMetricsGauge) {
+        self.state
+            .connections_gauges
+            .lock()
+            .unwrap()
+            .push((kind.into(), gauge));
+    }
+
+    pub(crate) fn add_http_connections_counter(&self, counter: HttpConnectionsCounter) {
+        self.state.http_connections.lock().unwrap().push(counter);
+    }
+
+    pub(crate) fn get_endpoint(
+        &self,
+    ) -> Option<Arc<dyn HttpServerMiddleware + Send + Sync + 'static>> {
+        let path = self.path.as_ref()?;
+        Some(Arc::new(HttpMetricsEndpoint::new(
+            path.clone(),
+            self.clone(),
+        )))
+    }
+
+    pub(crate) fn start_request(&self) -> HttpMetricsGaugeGuard {
+        self.state.in_flight.inc_scoped()
+    }
+
+    pub(crate) fn connection_refused(&self) {
+        self.state
+            .refused_connections
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record(&self, request: &HttpRequestData, response: &ResponseData) {
+        if response.outcome == HttpRequestOutcome::Panicked {
+            self.state.panics.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let labels = RequestLabels {
+            method: get_method_label(&request.method),
+            route: response
+                .process_name
+                .clone()
+                .unwrap_or_else(|| UNMATCHED_ROUTE.to_string()),
+            status: get_status_class(response.status_code),
+        };
+
+        let mut requests = self.state.requests.lock().unwrap();
+
+        let metrics = requests.entry(labels).or_insert_with(|| RequestMetrics {
+            count: 0,
+            duration: MetricsHistogram::new(self.latency_buckets.clone()),
+            request_size: MetricsHistogram::new(self.size_buckets.clone()),
+            response_size: MetricsHistogram::new(self.size_buckets.clone()),
+        });
+
+        metrics.count += 1;
+        metrics.duration.observe(response.duration.as_secs_f64());
+        metrics
+            .response_size
+            .observe(response.content_length as f64);
+
+        if let Some(content_length) = request.content_length {
+            metrics.request_size.observe(content_length as f64);
+        }
+    }
+
+    /// The metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        {
+            let requests = self.state.requests.lock().unwrap();
+
+            write_header(
+                &mut out,
+                "http_server_requests_total",
+                "counter",
+                "Requests the server answered.",
+            );
+            for (labels, metrics) in requests.iter() {
+                write_sample(
+                    &mut out,
+                    "http_server_requests_total",
+                    &labels.as_slice(),
+                    None,
+                    metrics.count,
+                );
+            }
+
+            write_header(
+                &mut out,
+                "http_server_request_duration_seconds",
+                "histogram",
+                "From the moment a request came in till it was answered.",
+            );
+            for (labels, metrics) in requests.iter() {
+                metrics.duration.write(
+                    &mut out,
+                    "http_server_request_duration_seconds",
+                    &labels.as_slice(),
+                );
+            }
+
+            write_header(
+                &mut out,
+                "http_server_request_size_bytes",
+                "histogram",
+                "Bodies of the requests, as announced by Content-Length.",
+            );
+            for (labels, metrics) in requests.iter() {
+                metrics.request_size.write(
+                    &mut out,
+                    "http_server_request_size_bytes",
+                    &labels.as_slice(),
+                );
+            }
+
+            write_header(
+                &mut out,
+                "http_server_response_size_bytes",
+                "histogram",
+                "Bodies of the responses; 0 for a streamed one.",
+            );
+            for (labels, metrics) in requests.iter() {
+                metrics.response_size.write(
+                    &mut out,
+                    "http_server_response_size_bytes",
+                    &labels.as_slice(),
+                );
+            }
+        }
+
+        write_header(
+            &mut out,
+            "http_server_requests_in_flight",
+            "gauge",
+            "Requests being handled right now.",
+        );
+        write_sample(
+            &mut out,
+            "http_server_requests_in_flight",
+            &[],
+            None,
+            self.state.in_flight.get(),
+        );
+
+        let open_connections: i64 = self
+            .state
+            .http_connections
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|itm| itm.get_connections_amount())
+            .sum();
+
+        write_header(
+            &mut out,
+            "http_server_open_connections",
+            "gauge",
+            "HTTP connections open right now.",
+        );
+        write_sample(
+            &mut out,
+            "http_server_open_connections",
+            &[],
+            None,
+            open_connections,
+        );
+
+        write_header(
+            &mut out,
+            "http_server_refused_connections_total",
+            "counter",
+            "Connections closed at accept time because of the connection limits.",
+        );
+        write_sample(
+            &mut out,
+            "http_server_refused_connections_total",
+            &[],
+            None,
+            self.state.refused_connections.load(Ordering::Relaxed),
+        );
+
+        write_header(
+            &mut out,
+            "http_server_panics_total",
+            "counter",
+            "Requests a middleware panicked on.",
+        );
+        write_sample(
+            &mut out,
+            "http_server_panics_total",
+            &[],
+            None,
+            self.state.panics.load(Ordering::Relaxed),
+        );
+
+        let connections_gauges = self.state.connections_gauges.lock().unwrap();
+
+        if !connections_gauges.is_empty() {
+            write_header(
+                &mut out,
+                "http_server_realtime_connections",
+                "gauge",
+                "WebSocket, SignalR and Socket.IO connections open right now.",
+            );
+            for (kind, gauge) in connections_gauges.iter() {
+                write_sample(
+                    &mut out,
+                    "http_server_realtime_connections",
+                    &[("kind", kind.as_str())],
+                    None,
+                    gauge.get(),
+                );
+            }
+        }
+
+        out
+    }
+}
+
+impl Default for HttpServerMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RequestLabels {
+    fn as_slice(&self) -> [(&str, &str); 3] {
+        [
+            ("method", self.method),
+            ("route", self.route.as_str()),
+            ("status", self.status),
+        ]
+    }
+}
+
+fn get_method_label(method: &Method) -> &'static str {
+    match *method {
+        Method::GET => "GET",
+        Method::POST => "POST",
+        Method::PUT => "PUT",
+        Method::PATCH => "PATCH",
+        Method::DELETE => "DELETE",
+        Method::HEAD => "HEAD",
+        Method::OPTIONS => "OPTIONS",
+        Method::CONNECT => "CONNECT",
+        Method::TRACE => "TRACE",
+        _ => OTHER_METHOD,
+    }
+}
+
+fn get_status_class(status_code: u16) -> &'static str {
+    match status_code / 100 {
+        1 => "1xx",
+        2 => "2xx",
+        3 => "3xx",
+        4 => "4xx",
+        5 => "5xx",
+        _ => "unknown",
+    }
+}
+
+fn sorted(buckets: impl IntoIterator<Item = f64>) -> Vec<f64> {
+    let mut result: Vec<f64> = buckets.into_iter().collect();
+    result.sort_by(|a, b| a.total_cmp(b));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use hyper::{HeaderMap, Method, Version};
+    use rust_extensions::date_time::DateTimeAsMicroseconds;
+
+    use super::*;
+
+    fn request(content_length: Option<u64>) -> HttpRequestData {
+        HttpRequestData {
+            started: DateTimeAsMicroseconds::now(),
+            method: Method::GET,
+            path: "/api/users/42".to_string(),
+            ip: "127.0.0.1".to_string(),
+            version: Version::HTTP_11,
+            user_agent: None,
+            content_length,
+            headers: HeaderMap::new(),
+            request_id: "request-1".to_string(),
+        }
+    }
+
+    fn response(
+        status_code: u16,
+        process_name: Option<&str>,
+        outcome: HttpRequestOutcome,
+    ) -> ResponseData {
+        ResponseData {
+            status_code,
+            content_type: None,
+            content_length: 500,
+            has_error: false,
+            outcome,
+            process_name: process_name.map(|itm| itm.to_string()),
+            client_id: None,
+            duration: Duration::from_millis(30),
+        }
+    }
+
+    #[test]
+    fn test_requests_are_labelled_by_route_template_and_status_class() {
+        let metrics = HttpServerMetrics::new();
+
+        for status_code in [200, 201, 404] {
+            metrics.record(
+                &request(None),
+                &response(
+                    status_code,
+                    Some("/api/users/{id}"),
+                    HttpRequestOutcome::Handled,
+                ),
+            );
+        }
+
+        metrics.record(
+            &request(None),
+            &response(404, None, HttpRequestOutcome::Handled),
+        );
+
+        let rendered = metrics.render();
+
+        assert!(rendered.contains(
+            "http_server_requests_total{method=\"GET\",route=\"/api/users/{id}\",status=\"2xx\"} 2\n"
+        ));
+        assert!(rendered.contains(
+            "http_server_requests_total{method=\"GET\",route=\"/api/users/{id}\",status=\"4xx\"} 1\n"
+        ));
+        assert!(rendered.contains(
+            "http_server_requests_total{method=\"GET\",route=\"unmatched\",status=\"4xx\"} 1\n"
+        ));
+        assert!(!rendered.contains("/api/users/42"));
+    }
+
+    #[test]
+    fn test_non_standard_methods_share_one_label() {
+        let metrics = HttpServerMetrics::new();
+
+        for method in ["PURGE", "X-RANDOM-1", "X-RANDOM-2"] {
+            let mut request = request(None);
+            request.method = Method::from_bytes(method.as_bytes()).unwrap();
+
+            metrics.record(&request, &response(404, None, HttpRequestOutcome::Handled));
+        }
+
+        let rendered = metrics.render();
+
+        assert!(rendered.contains(
+            "http_server_requests_total{method=\"OTHER\",route=\"unmatched\",status=\"4xx\"} 3\n"
+        ));
+        assert!(!rendered.contains("PURGE"));
+    }
+
+    #[test]
+    fn test_histogram_buckets_are_cumulative() {
+        let metrics = HttpServerMetrics::new()
+            .with_latency_buckets([0.1, 0.01])
+            .with_size_buckets([1000.0, 100.0]);
+
+        metrics.record(
+            &request(Some(50)),
+            &response(200, Some("/a"), HttpRequestOutcome::Handled),
+        );
+
+        let rendered = metrics.render();
+
+        let labels = "method=\"GET\",route=\"/a\",status=\"2xx\"";
+
+        for expected in [
+            format!(
+                "http_server_request_duration_seconds_bucket{{{},le=\"0.01\"}} 0\n",
+                labels
+            ),
+            format!(
+                "http_server_request_duration_seconds_bucket{{{},le=\"0.1\"}} 1\n",
+                labels
+            ),
+            format!(
+                "http_server_request_duration_seconds_bucket{{{},le=\"+Inf\"}} 1\n",
+                labels
+            ),
+            format!(
+                "http_server_request_duration_seconds_count{{{}}} 1\n",
+                labels
+            ),
+            format!(
+                "http_server_request_size_bytes_bucket{{{},le=\"100\"}} 1\n",
+                labels
+            ),
+            format!(
+                "http_server_response_size_bytes_bucket{{{},le=\"100\"}} 0\n",
+                labels
+            ),
+            format!(
+                "http_server_response_size_bytes_bucket{{{},le=\"1000\"}} 1\n",
+                labels
+            ),
+            format!("http_server_response_size_bytes_sum{{{}}} 500\n", labels),
+        ] {
+            assert!(rendered.contains(&expected), "{}\n{}", expected, rendered);
+        }
+    }
+
+    #[test]
+    fn test_panics_and_gauges() {
+        let metrics = HttpServerMetrics::new();
+
+        metrics.record(
+            &request(None),
+            &response(500, None, HttpRequestOutcome::Panicked),
+        );
+
+        let web_sockets = HttpMetricsGauge::new();
+        metrics.add_connections_gauge("websocket", web_sockets.clone());
+        web_sockets.inc();
+        web_sockets.inc();
+
+        let in_flight = metrics.start_request();
+
+        let rendered = metrics.render();
+
+        assert!(rendered.contains("http_server_panics_total 1\n"));
+        assert!(rendered.contains("http_server_requests_in_flight 1\n"));
+        assert!(rendered.contains("http_server_realtime_connections{kind=\"websocket\"} 2\n"));
+
+        drop(in_flight);
+
+        assert!(metrics
+            .render()
+            .contains("http_server_requests_in_flight 0\n"));
+    }
+
+    #[test]
+    fn test_label_values_are_escaped() {
+        let metrics = HttpServerMetrics::new();
+
+        metrics.record(
+            &request(None),
+            &response(200, Some("/a\"b\\c"), HttpRequestOutcome::Handled),
+        );
+
+        assert!(metrics.render().contains("route=\"/a\\\"b\\\\c\""));
+    }
+}
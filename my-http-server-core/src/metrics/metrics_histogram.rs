@@ -0,0 +1,100 @@
+use std::{fmt::Write, sync::Arc};
+
+/// Cumulative the way Prometheus wants its buckets: an observation is counted in every bucket it
+/// fits in, not only in the smallest one.
+pub(crate) struct MetricsHistogram {
+    bounds: Arc<Vec<f64>>,
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl MetricsHistogram {
+    pub fn new(bounds: Arc<Vec<f64>>) -> Self {
+        Self {
+            buckets: vec![0; bounds.len()],
+            bounds,
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    pub fn observe(&mut self, value: f64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(self.bounds.iter()) {
+            if value <= *bound {
+                *bucket += 1;
+            }
+        }
+
+        self.sum += value;
+        self.count += 1;
+    }
+
+    pub fn write(&self, out: &mut String, name: &str, labels: &[(&str, &str)]) {
+        for (bucket, bound) in self.buckets.iter().zip(self.bounds.iter()) {
+            write_sample(
+                out,
+                &format!("{}_bucket", name),
+                labels,
+                Some(&bound.to_string()),
+                *bucket,
+            );
+        }
+
+        write_sample(
+            out,
+            &format!("{}_bucket", name),
+            labels,
+            Some("+Inf"),
+            self.count,
+        );
+        write_sample(out, &format!("{}_sum", name), labels, None, self.sum);
+        write_sample(out, &format!("{}_count", name), labels, None, self.count);
+    }
+}
+
+pub(crate) fn write_header(out: &mut String, name: &str, metric_type: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, metric_type);
+}
+
+pub(crate) fn write_sample(
+    out: &mut String,
+    name: &str,
+    labels: &[(&str, &str)],
+    le: Option<&str>,
+    value: impl std::fmt::Display,
+) {
+    out.push_str(name);
+
+    let labels = labels.iter().copied().chain(le.map(|le| ("le", le)));
+
+    let mut first = true;
+
+    for (key, label_value) in labels {
+        out.push(if first { '{' } else { ',' });
+        first = false;
+
+        out.push_str(key);
+        out.push_str("=\"");
+        escape_label_value(out, label_value);
+        out.push('"');
+    }
+
+    if !first {
+        out.push('}');
+    }
+
+    let _ = writeln!(out, " {}", value);
+}
+
+fn escape_label_value(out: &mut String, value: &str) {
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            c => out.push(c),
+        }
+    }
+}
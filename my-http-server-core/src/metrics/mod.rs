@@ -0,0 +1,7 @@
+mod http_server_metrics;
+pub use http_server_metrics::*;
+mod http_metrics_gauge;
+pub use http_metrics_gauge::*;
+mod http_metrics_middleware;
+mod metrics_histogram;
+pub(crate) use http_metrics_middleware::*;
@@ -0,0 +1,4 @@
+mod proxy_protocol_header;
+pub use proxy_protocol_header::*;
+mod proxy_protocol_reader;
+pub(crate) use proxy_protocol_reader::*;
@@ -0,0 +1,128 @@
+use std::net::SocketAddr;
+
+pub const PP2_TYPE_ALPN: u8 = 0x01;
+pub const PP2_TYPE_AUTHORITY: u8 = 0x02;
+pub const PP2_TYPE_CRC32C: u8 = 0x03;
+pub const PP2_TYPE_NOOP: u8 = 0x04;
+pub const PP2_TYPE_UNIQUE_ID: u8 = 0x05;
+pub const PP2_TYPE_SSL: u8 = 0x20;
+pub const PP2_SUBTYPE_SSL_VERSION: u8 = 0x21;
+pub const PP2_SUBTYPE_SSL_CN: u8 = 0x22;
+pub const PP2_SUBTYPE_SSL_CIPHER: u8 = 0x23;
+pub const PP2_SUBTYPE_SSL_SIG_ALG: u8 = 0x24;
+pub const PP2_SUBTYPE_SSL_KEY_ALG: u8 = 0x25;
+pub const PP2_TYPE_NETNS: u8 = 0x30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyProtocolVersion {
+    V1,
+    V2,
+}
+
+/// One type-length-value field of a PROXY protocol v2 header, as the balancer sent it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyProtocolTlv {
+    pub kind: u8,
+    pub value: Vec<u8>,
+}
+
+/// What the balancer in front of a [`HttpListener`](crate::HttpListener) with
+/// [`with_proxy_protocol`](crate::HttpListener::with_proxy_protocol) said about the connection.
+/// Available to middlewares as `ctx.request.connection.proxy_protocol`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyProtocolHeader {
+    pub version: ProxyProtocolVersion,
+    /// The client the balancer accepted the connection from. `None` when the balancer speaks for
+    /// itself (a v2 `LOCAL` command, e.g. its health checks) or the address is not an IP one —
+    /// the request then keeps the address of the balancer.
+    pub source: Option<SocketAddr>,
+    /// The address the client connected to on the balancer.
+    pub destination: Option<SocketAddr>,
+    /// Only v2 carries them.
+    pub tlvs: Vec<ProxyProtocolTlv>,
+}
+
+impl ProxyProtocolHeader {
+    pub fn get_tlv(&self, kind: u8) -> Option<&[u8]> {
+        self.tlvs
+            .iter()
+            .find(|itm| itm.kind == kind)
+            .map(|itm| itm.value.as_slice())
+    }
+
+    /// The SNI host name the client sent to the balancer.
+    pub fn get_authority(&self) -> Option<&str> {
+        std::str::from_utf8(self.get_tlv(PP2_TYPE_AUTHORITY)?).ok()
+    }
+
+    /// The protocol the client agreed with the balancer through ALPN.
+    pub fn get_alpn(&self) -> Option<&str> {
+        std::str::from_utf8(self.get_tlv(PP2_TYPE_ALPN)?).ok()
+    }
+
+    pub fn get_unique_id(&self) -> Option<&[u8]> {
+        self.get_tlv(PP2_TYPE_UNIQUE_ID)
+    }
+
+    /// The TLS the balancer terminated. `None` if it did not send the `PP2_TYPE_SSL` field.
+    pub fn get_ssl(&self) -> Option<ProxyProtocolSsl> {
+        ProxyProtocolSsl::parse(self.get_tlv(PP2_TYPE_SSL)?)
+    }
+}
+
+/// The `PP2_TYPE_SSL` field: how the client connected to the balancer.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProxyProtocolSsl {
+    /// Bit field: `0x01` — the client connected over TLS, `0x02` — it presented a certificate on
+    /// this connection, `0x04` — it presented one at least once in this TLS session.
+    pub client: u8,
+    /// `0` if the client certificate was verified.
+    pub verify: u32,
+    pub version: Option<String>,
+    /// Common name of the client certificate.
+    pub common_name: Option<String>,
+    pub cipher: Option<String>,
+    pub sig_alg: Option<String>,
+    pub key_alg: Option<String>,
+}
+
+impl ProxyProtocolSsl {
+    pub fn is_tls(&self) -> bool {
+        self.client & 0x01 != 0
+    }
+
+    pub fn has_client_certificate(&self) -> bool {
+        self.client & 0x06 != 0
+    }
+
+    pub fn is_client_certificate_verified(&self) -> bool {
+        self.has_client_certificate() && self.verify == 0
+    }
+
+    fn parse(value: &[u8]) -> Option<Self> {
+        if value.len() < 5 {
+            return None;
+        }
+
+        let mut result = Self {
+            client: value[0],
+            verify: u32::from_be_bytes([value[1], value[2], value[3], value[4]]),
+            ..Default::default()
+        };
+
+        for tlv in crate::parse_tlvs(&value[5..]).ok()? {
+            let text = String::from_utf8_lossy(&tlv.value).to_string();
+
+            match tlv.kind {
+                PP2_SUBTYPE_SSL_VERSION => result.version = Some(text),
+                PP2_SUBTYPE_SSL_CN => result.common_name = Some(text),
+                PP2_SUBTYPE_SSL_CIPHER => result.cipher = Some(text),
+                PP2_SUBTYPE_SSL_SIG_ALG => result.sig_alg = Some(text),
+                PP2_SUBTYPE_SSL_KEY_ALG => result.key_alg = Some(text),
+                _ => {}
+            }
+        }
+
+        Some(result)
+    }
+}
@@ -0,0 +1,367 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::{ProxyProtocolHeader, ProxyProtocolTlv, ProxyProtocolVersion};
+
+const V2_SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+
+/// The longest v1 line the spec allows, `\r\n` included.
+const V1_MAX_LENGTH: usize = 107;
+
+/// Reads the PROXY protocol header off the start of the stream — v1 or v2, whichever it is — and
+/// not a byte more, so the stream can go on to TLS or HTTP as if the header had never been there.
+pub(crate) async fn read_proxy_protocol_header<TStream: AsyncRead + Unpin>(
+    stream: &mut TStream,
+) -> Result<ProxyProtocolHeader, String> {
+    // Both versions are at least this long: v2 has a 16-byte fixed part, the shortest v1 line is
+    // `PROXY UNKNOWN\r\n`.
+    let mut prefix = [0u8; 12];
+    stream
+        .read_exact(&mut prefix)
+        .await
+        .map_err(|err| format!("Can not read PROXY protocol header. Err: {}", err))?;
+
+    if prefix == V2_SIGNATURE {
+        let mut fixed = [0u8; 4];
+        stream
+            .read_exact(&mut fixed)
+            .await
+            .map_err(|err| format!("Can not read PROXY protocol v2 header. Err: {}", err))?;
+
+        let mut payload = vec![0u8; u16::from_be_bytes([fixed[2], fixed[3]]) as usize];
+        stream
+            .read_exact(&mut payload)
+            .await
+            .map_err(|err| format!("Can not read PROXY protocol v2 addresses. Err: {}", err))?;
+
+        return parse_v2(fixed[0], fixed[1], &payload);
+    }
+
+    if !prefix.starts_with(b"PROXY ") {
+        return Err("Connection does not start with a PROXY protocol header".to_string());
+    }
+
+    let mut line = prefix.to_vec();
+
+    while !line.ends_with(b"\r\n") {
+        if line.len() >= V1_MAX_LENGTH {
+            return Err(format!(
+                "PROXY protocol v1 header is longer than {} bytes",
+                V1_MAX_LENGTH
+            ));
+        }
+
+        // One byte at a time: whatever follows the line belongs to the client.
+        let next = stream
+            .read_u8()
+            .await
+            .map_err(|err| format!("Can not read PROXY protocol v1 header. Err: {}", err))?;
+
+        line.push(next);
+    }
+
+    parse_v1(&line)
+}
+
+pub(crate) fn parse_v1(line: &[u8]) -> Result<ProxyProtocolHeader, String> {
+    let line = std::str::from_utf8(line)
+        .ok()
+        .and_then(|itm| itm.strip_suffix("\r\n"))
+        .ok_or_else(|| "PROXY protocol v1 header is not a text line".to_string())?;
+
+    let parts: Vec<&str> = line.split(' ').collect();
+
+    let mut result = ProxyProtocolHeader {
+        version: ProxyProtocolVersion::V1,
+        source: None,
+        destination: None,
+        tlvs: Vec::new(),
+    };
+
+    match parts.get(1).copied() {
+        Some("UNKNOWN") => return Ok(result),
+        Some("TCP4") | Some("TCP6") => {}
+        _ => return Err(format!("Invalid PROXY protocol v1 header: {}", line)),
+    }
+
+    if parts.len() != 6 {
+        return Err(format!("Invalid PROXY protocol v1 header: {}", line));
+    }
+
+    let is_v4 = parts[1] == "TCP4";
+
+    let parse_addr = |ip: &str, port: &str| -> Result<SocketAddr, String> {
+        let ip: IpAddr = ip
+            .parse()
+            .map_err(|_| format!("Invalid address in PROXY protocol v1 header: {}", line))?;
+
+        if ip.is_ipv4() != is_v4 {
+            return Err(format!(
+                "Address family mismatch in PROXY protocol v1 header: {}",
+                line
+            ));
+        }
+
+        let port: u16 = port
+            .parse()
+            .map_err(|_| format!("Invalid port in PROXY protocol v1 header: {}", line))?;
+
+        Ok(SocketAddr::new(ip, port))
+    };
+
+    result.source = Some(parse_addr(parts[2], parts[4])?);
+    result.destination = Some(parse_addr(parts[3], parts[5])?);
+
+    Ok(result)
+}
+
+pub(crate) fn parse_v2(
+    version_command: u8,
+    family: u8,
+    payload: &[u8],
+) -> Result<ProxyProtocolHeader, String> {
+    if version_command >> 4 != 2 {
+        return Err(format!(
+            "Unsupported PROXY protocol version {}",
+            version_command >> 4
+        ));
+    }
+
+    let is_local = match version_command & 0x0F {
+        0x00 => true,
+        0x01 => false,
+        command => return Err(format!("Unknown PROXY protocol v2 command {}", command)),
+    };
+
+    let (addresses_len, addresses) = match family >> 4 {
+        // AF_UNSPEC
+        0x00 => (0, None),
+        // AF_INET
+        0x01 => (12, parse_inet_addresses(payload)),
+        // AF_INET6
+        0x02 => (36, parse_inet6_addresses(payload)),
+        // AF_UNIX — no IP to put into the request.
+        0x03 => (216, None),
+        family => {
+            return Err(format!(
+                "Unknown PROXY protocol v2 address family {}",
+                family
+            ))
+        }
+    };
+
+    if payload.len() < addresses_len {
+        return Err("PROXY protocol v2 header is shorter than its addresses".to_string());
+    }
+
+    let mut result = ProxyProtocolHeader {
+        version: ProxyProtocolVersion::V2,
+        source: None,
+        destination: None,
+        tlvs: parse_tlvs(&payload[addresses_len..])?,
+    };
+
+    // A LOCAL connection is the balancer's own — its addresses mean nothing.
+    if !is_local {
+        if let Some((source, destination)) = addresses {
+            result.source = Some(source);
+            result.destination = Some(destination);
+        }
+    }
+
+    Ok(result)
+}
+
+fn parse_inet_addresses(payload: &[u8]) -> Option<(SocketAddr, SocketAddr)> {
+    let payload = payload.get(..12)?;
+
+    let source = Ipv4Addr::new(payload[0], payload[1], payload[2], payload[3]);
+    let destination = Ipv4Addr::new(payload[4], payload[5], payload[6], payload[7]);
+
+    Some((
+        SocketAddr::new(source.into(), u16::from_be_bytes([payload[8], payload[9]])),
+        SocketAddr::new(
+            destination.into(),
+            u16::from_be_bytes([payload[10], payload[11]]),
+        ),
+    ))
+}
+
+fn parse_inet6_addresses(payload: &[u8]) -> Option<(SocketAddr, SocketAddr)> {
+    let payload = payload.get(..36)?;
+
+    let source: [u8; 16] = payload[0..16].try_into().ok()?;
+    let destination: [u8; 16] = payload[16..32].try_into().ok()?;
+
+    Some((
+        SocketAddr::new(
+            Ipv6Addr::from(source).into(),
+            u16::from_be_bytes([payload[32], payload[33]]),
+        ),
+        SocketAddr::new(
+            Ipv6Addr::from(destination).into(),
+            u16::from_be_bytes([payload[34], payload[35]]),
+        ),
+    ))
+}
+
+pub(crate) fn parse_tlvs(mut data: &[u8]) -> Result<Vec<ProxyProtocolTlv>, String> {
+    let mut result = Vec::new();
+
+    while !data.is_empty() {
+        if data.len() < 3 {
+            return Err("Truncated PROXY protocol v2 TLV".to_string());
+        }
+
+        let kind = data[0];
+        let len = u16::from_be_bytes([data[1], data[2]]) as usize;
+
+        let value = data
+            .get(3..3 + len)
+            .ok_or_else(|| "Truncated PROXY protocol v2 TLV".to_string())?;
+
+        result.push(ProxyProtocolTlv {
+            kind,
+            value: value.to_vec(),
+        });
+
+        data = &data[3 + len..];
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PP2_SUBTYPE_SSL_CN, PP2_SUBTYPE_SSL_VERSION, PP2_TYPE_AUTHORITY, PP2_TYPE_SSL};
+
+    fn tlv(kind: u8, value: &[u8]) -> Vec<u8> {
+        let mut result = vec![kind];
+        result.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        result.extend_from_slice(value);
+        result
+    }
+
+    fn v2_header(version_command: u8, family: u8, payload: &[u8]) -> Vec<u8> {
+        let mut result = V2_SIGNATURE.to_vec();
+        result.push(version_command);
+        result.push(family);
+        result.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+        result.extend_from_slice(payload);
+        result
+    }
+
+    #[tokio::test]
+    async fn v1_tcp4_header_is_read_and_the_rest_is_left_in_the_stream() {
+        let mut stream: &[u8] = b"PROXY TCP4 192.168.0.1 10.0.0.1 56324 443\r\nGET / HTTP/1.1\r\n";
+
+        let header = read_proxy_protocol_header(&mut stream).await.unwrap();
+
+        assert_eq!(header.version, ProxyProtocolVersion::V1);
+        assert_eq!(header.source, Some("192.168.0.1:56324".parse().unwrap()));
+        assert_eq!(header.destination, Some("10.0.0.1:443".parse().unwrap()));
+        assert_eq!(stream, b"GET / HTTP/1.1\r\n");
+    }
+
+    #[test]
+    fn v1_tcp6_header() {
+        let header = parse_v1(b"PROXY TCP6 2001:db8::1 2001:db8::2 4000 80\r\n").unwrap();
+
+        assert_eq!(header.source, Some("[2001:db8::1]:4000".parse().unwrap()));
+    }
+
+    #[test]
+    fn v1_unknown_header_has_no_addresses() {
+        let header = parse_v1(b"PROXY UNKNOWN\r\n").unwrap();
+
+        assert_eq!(header.source, None);
+        assert_eq!(header.destination, None);
+    }
+
+    #[test]
+    fn v1_header_with_mismatched_family_is_rejected() {
+        assert!(parse_v1(b"PROXY TCP4 2001:db8::1 10.0.0.1 4000 80\r\n").is_err());
+    }
+
+    #[tokio::test]
+    async fn plain_http_is_rejected() {
+        let mut stream: &[u8] = b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n";
+
+        assert!(read_proxy_protocol_header(&mut stream).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn v1_header_without_line_end_is_rejected() {
+        let mut line = b"PROXY TCP4 ".to_vec();
+        line.extend_from_slice(&[b'1'; 200]);
+        let mut stream: &[u8] = &line;
+
+        assert!(read_proxy_protocol_header(&mut stream).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn v2_inet_header_with_tlvs() {
+        let mut ssl = vec![0x01, 0, 0, 0, 0];
+        ssl.extend(tlv(PP2_SUBTYPE_SSL_VERSION, b"TLSv1.3"));
+        ssl.extend(tlv(PP2_SUBTYPE_SSL_CN, b"client"));
+
+        let mut payload = vec![192, 168, 0, 1, 10, 0, 0, 1];
+        payload.extend_from_slice(&56324u16.to_be_bytes());
+        payload.extend_from_slice(&443u16.to_be_bytes());
+        payload.extend(tlv(PP2_TYPE_AUTHORITY, b"example.com"));
+        payload.extend(tlv(PP2_TYPE_SSL, &ssl));
+
+        let mut data = v2_header(0x21, 0x11, &payload);
+        data.extend_from_slice(b"GET /");
+        let mut stream: &[u8] = &data;
+
+        let header = read_proxy_protocol_header(&mut stream).await.unwrap();
+
+        assert_eq!(header.version, ProxyProtocolVersion::V2);
+        assert_eq!(header.source, Some("192.168.0.1:56324".parse().unwrap()));
+        assert_eq!(header.get_authority(), Some("example.com"));
+
+        let ssl = header.get_ssl().unwrap();
+        assert!(ssl.is_tls());
+        assert_eq!(ssl.version.as_deref(), Some("TLSv1.3"));
+        assert_eq!(ssl.common_name.as_deref(), Some("client"));
+
+        assert_eq!(stream, b"GET /");
+    }
+
+    #[test]
+    fn v2_inet6_header() {
+        let mut payload = Vec::new();
+        payload.extend_from_slice(&"2001:db8::1".parse::<Ipv6Addr>().unwrap().octets());
+        payload.extend_from_slice(&"2001:db8::2".parse::<Ipv6Addr>().unwrap().octets());
+        payload.extend_from_slice(&4000u16.to_be_bytes());
+        payload.extend_from_slice(&80u16.to_be_bytes());
+
+        let header = parse_v2(0x21, 0x21, &payload).unwrap();
+
+        assert_eq!(header.source, Some("[2001:db8::1]:4000".parse().unwrap()));
+        assert_eq!(
+            header.destination,
+            Some("[2001:db8::2]:80".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn v2_local_command_keeps_no_addresses() {
+        let payload = [127, 0, 0, 1, 127, 0, 0, 1, 0, 80, 0, 80];
+
+        let header = parse_v2(0x20, 0x11, &payload).unwrap();
+
+        assert_eq!(header.source, None);
+    }
+
+    #[test]
+    fn v2_truncated_tlv_is_rejected() {
+        let mut payload = vec![192, 168, 0, 1, 10, 0, 0, 1, 0, 80, 0, 80];
+        payload.extend_from_slice(&[PP2_TYPE_AUTHORITY, 0, 10, b'a']);
+
+        assert!(parse_v2(0x21, 0x11, &payload).is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{HttpRequestHeaders, SocketAddress};
+use crate::{HttpRequestHeaders, SocketAddress, TrustedProxies};
 
 pub enum RequestIp<'s> {
     SingleIp(String),
@@ -19,6 +19,32 @@ impl<'s> RequestIp<'s> {
         return RequestIp::SingleIp(addr.ip_as_string());
     }
 
+    /// Same, but the forwarding headers are only believed when `addr` is a trusted proxy, and the
+    /// chain is cut at the first hop from the right that is not one — see [`TrustedProxies`]. The
+    /// chain this returns starts with the client.
+    pub fn new_with_trusted_proxies(
+        addr: &SocketAddress,
+        headers: &'s impl HttpRequestHeaders,
+        trusted_proxies: &TrustedProxies,
+    ) -> Self {
+        if !trusted_proxies.is_trusted_peer(addr) {
+            return RequestIp::SingleIp(addr.ip_as_string());
+        }
+
+        let hops = crate::read_forwarded_hops(headers, trusted_proxies.get_forwarding_headers());
+
+        let Some(client) = crate::find_client_hop(&hops, trusted_proxies) else {
+            return RequestIp::SingleIp(addr.ip_as_string());
+        };
+
+        // The proxies told how the request came in, but not from where.
+        if hops[client].for_ip.is_none() {
+            return RequestIp::SingleIp(addr.ip_as_string());
+        }
+
+        RequestIp::Forwarded(hops[client..].iter().filter_map(|hop| hop.for_ip).collect())
+    }
+
     pub fn create_as_single_ip(addr: SocketAddress) -> Self {
         Self::SingleIp(addr.ip_as_string())
     }
@@ -62,3 +88,68 @@ fn extract_ip(src: &mut String) {
     }
 }
  */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::HeaderMap;
+
+    fn headers(x_forwarded_for: &str) -> HeaderMap {
+        let mut result = HeaderMap::new();
+        result.insert("x-forwarded-for", x_forwarded_for.parse().unwrap());
+        result
+    }
+
+    fn tcp(addr: &str) -> SocketAddress {
+        SocketAddress::Tcp(addr.parse().unwrap())
+    }
+
+    #[test]
+    fn headers_from_an_untrusted_peer_are_ignored() {
+        let headers = headers("1.2.3.4");
+        let trusted_proxies = TrustedProxies::new().add_cidr("10.0.0.0/8");
+
+        let ip =
+            RequestIp::new_with_trusted_proxies(&tcp("6.6.6.6:1000"), &headers, &trusted_proxies);
+
+        assert_eq!(ip.get_real_ip(), "6.6.6.6");
+    }
+
+    #[test]
+    fn spoofed_hops_left_of_the_client_are_dropped() {
+        let headers = headers("9.9.9.9, 1.2.3.4, 10.0.0.7");
+        let trusted_proxies = TrustedProxies::new().add_cidr("10.0.0.0/8");
+
+        let ip =
+            RequestIp::new_with_trusted_proxies(&tcp("10.0.0.1:1000"), &headers, &trusted_proxies);
+
+        assert_eq!(ip.get_real_ip(), "1.2.3.4");
+        assert_eq!(ip.to_string(), "1.2.3.4, 10.0.0.7");
+    }
+
+    /// A proxy that appends to `X-Forwarded-For` passes a `Forwarded` the client wrote through.
+    #[test]
+    fn client_sent_forwarded_does_not_override_the_proxy_hop() {
+        let mut headers = headers("6.6.6.6");
+        headers.insert("forwarded", "for=1.2.3.4".parse().unwrap());
+        let trusted_proxies = TrustedProxies::new().add_cidr("10.0.0.0/8");
+
+        let ip =
+            RequestIp::new_with_trusted_proxies(&tcp("10.0.0.1:1000"), &headers, &trusted_proxies);
+
+        assert_eq!(ip.get_real_ip(), "6.6.6.6");
+    }
+
+    #[test]
+    fn unix_peer_is_not_trusted_unless_asked() {
+        let headers = headers("1.2.3.4");
+        let addr = SocketAddress::Unix(std::sync::Arc::new("/run/app.sock".to_string()));
+
+        let ip = RequestIp::new_with_trusted_proxies(&addr, &headers, &TrustedProxies::new());
+        assert_ne!(ip.get_real_ip(), "1.2.3.4");
+
+        let trusted_proxies = TrustedProxies::new().trust_unix_peers();
+        let ip = RequestIp::new_with_trusted_proxies(&addr, &headers, &trusted_proxies);
+        assert_eq!(ip.get_real_ip(), "1.2.3.4");
+    }
+}
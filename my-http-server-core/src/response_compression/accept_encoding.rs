@@ -0,0 +1,150 @@
+use crate::ResponseEncoding;
+
+/// Picks the encoding to compress a response with from the `Accept-Encoding` of the request —
+/// the one the client weights highest, `preferred` deciding between equal weights. `*` stands for
+/// every encoding the client did not name, `q=0` rules one out.
+///
+/// `None` when the client sent no `Accept-Encoding` or accepts none of `preferred`: the response
+/// goes out as it is.
+pub(crate) fn negotiate_response_encoding(
+    accept_encoding: Option<&str>,
+    preferred: &[ResponseEncoding],
+) -> Option<ResponseEncoding> {
+    let accepted = parse_accept_encoding(accept_encoding?);
+
+    let star_weight = accepted
+        .iter()
+        .find(|(coding, _)| *coding == "*")
+        .map(|(_, weight)| *weight);
+
+    let mut result: Option<(ResponseEncoding, f32)> = None;
+
+    for encoding in preferred {
+        let weight = accepted
+            .iter()
+            .find(|(coding, _)| encoding.is_named(coding))
+            .map(|(_, weight)| *weight)
+            .or(star_weight)
+            .unwrap_or(0.0);
+
+        if weight <= 0.0 {
+            continue;
+        }
+
+        match result {
+            Some((_, best_weight)) if best_weight >= weight => {}
+            _ => result = Some((*encoding, weight)),
+        }
+    }
+
+    result.map(|(encoding, _)| encoding)
+}
+
+/// `gzip;q=0.8, br, *;q=0` → `[("gzip", 0.8), ("br", 1.0), ("*", 0.0)]`. An entry with a weight
+/// that does not parse is dropped.
+fn parse_accept_encoding(value: &str) -> Vec<(&str, f32)> {
+    let mut result = Vec::new();
+
+    for item in value.split(',') {
+        let mut parts = item.split(';');
+
+        let coding = parts.next().unwrap_or_default().trim();
+
+        if coding.is_empty() {
+            continue;
+        }
+
+        let mut weight = Some(1.0);
+
+        for param in parts {
+            let Some((key, value)) = param.split_once('=') else {
+                continue;
+            };
+
+            if key.trim().eq_ignore_ascii_case("q") {
+                weight = value
+                    .trim()
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|itm| (0.0..=1.0).contains(itm));
+            }
+        }
+
+        if let Some(weight) = weight {
+            result.push((coding, weight));
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PREFERRED: [ResponseEncoding; 3] = [
+        ResponseEncoding::Br,
+        ResponseEncoding::Zstd,
+        ResponseEncoding::GZip,
+    ];
+
+    fn negotiate(accept_encoding: &str) -> Option<ResponseEncoding> {
+        negotiate_response_encoding(Some(accept_encoding), &PREFERRED)
+    }
+
+    #[test]
+    fn equal_weights_follow_the_server_preference() {
+        assert_eq!(negotiate("gzip, deflate, br"), Some(ResponseEncoding::Br));
+        assert_eq!(negotiate("gzip, zstd"), Some(ResponseEncoding::Zstd));
+        assert_eq!(negotiate("gzip"), Some(ResponseEncoding::GZip));
+    }
+
+    #[test]
+    fn higher_weight_wins() {
+        assert_eq!(
+            negotiate("br;q=0.5, gzip;q=0.9"),
+            Some(ResponseEncoding::GZip)
+        );
+        assert_eq!(negotiate("br;q=0.5, gzip"), Some(ResponseEncoding::GZip));
+    }
+
+    #[test]
+    fn zero_weight_rules_an_encoding_out() {
+        assert_eq!(negotiate("br;q=0, gzip"), Some(ResponseEncoding::GZip));
+        assert_eq!(negotiate("gzip;q=0"), None);
+    }
+
+    #[test]
+    fn star_covers_the_encodings_not_named() {
+        assert_eq!(negotiate("*"), Some(ResponseEncoding::Br));
+        assert_eq!(negotiate("br;q=0, *"), Some(ResponseEncoding::Zstd));
+        assert_eq!(negotiate("gzip, *;q=0"), Some(ResponseEncoding::GZip));
+    }
+
+    #[test]
+    fn nothing_acceptable() {
+        assert_eq!(negotiate_response_encoding(None, &PREFERRED), None);
+        assert_eq!(negotiate(""), None);
+        assert_eq!(negotiate("identity"), None);
+        assert_eq!(negotiate("deflate, compress"), None);
+    }
+
+    #[test]
+    fn names_and_parameters_are_case_insensitive() {
+        assert_eq!(negotiate("GZIP;Q=1"), Some(ResponseEncoding::GZip));
+        assert_eq!(negotiate("x-gzip"), Some(ResponseEncoding::GZip));
+    }
+
+    #[test]
+    fn malformed_weight_is_ignored() {
+        assert_eq!(negotiate("br;q=abc, gzip"), Some(ResponseEncoding::GZip));
+        assert_eq!(negotiate("br;q=2, gzip"), Some(ResponseEncoding::GZip));
+    }
+
+    #[test]
+    fn only_encodings_the_server_offers() {
+        let result = negotiate_response_encoding(Some("br, gzip"), &[ResponseEncoding::GZip]);
+
+        assert_eq!(result, Some(ResponseEncoding::GZip));
+    }
+}
@@ -0,0 +1,332 @@
+use std::io::Write;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use flate2::write::GzEncoder;
+use http_body_util::combinators::BoxBody;
+use hyper::body::{Body, Frame};
+
+use crate::ResponseEncoding;
+
+/// Dynamic responses are compressed on every request — the levels are the balanced ones, not the
+/// smallest output.
+const GZIP_LEVEL: flate2::Compression = flate2::Compression::new(6);
+const BROTLI_QUALITY: u32 = 5;
+const BROTLI_WINDOW: u32 = 22;
+const BROTLI_BUFFER_SIZE: usize = 4096;
+const ZSTD_LEVEL: i32 = 3;
+
+pub(crate) enum ResponseEncoder {
+    GZip(GzEncoder<Vec<u8>>),
+    Br(Box<brotli::CompressorWriter<Vec<u8>>>),
+    Zstd(zstd::stream::write::Encoder<'static, Vec<u8>>),
+}
+
+impl ResponseEncoder {
+    pub fn new(encoding: ResponseEncoding) -> std::io::Result<Self> {
+        let result = match encoding {
+            ResponseEncoding::GZip => Self::GZip(GzEncoder::new(Vec::new(), GZIP_LEVEL)),
+            ResponseEncoding::Br => Self::Br(Box::new(brotli::CompressorWriter::new(
+                Vec::new(),
+                BROTLI_BUFFER_SIZE,
+                BROTLI_QUALITY,
+                BROTLI_WINDOW,
+            ))),
+            ResponseEncoding::Zstd => {
+                Self::Zstd(zstd::stream::write::Encoder::new(Vec::new(), ZSTD_LEVEL)?)
+            }
+        };
+
+        Ok(result)
+    }
+
+    /// Compresses `data` and flushes, so what the client gets can be decoded up to this point —
+    /// a streamed response is not held back until the encoder fills a block.
+    fn compress(&mut self, data: &[u8]) -> std::io::Result<Bytes> {
+        let output = match self {
+            Self::GZip(encoder) => {
+                encoder.write_all(data)?;
+                encoder.flush()?;
+                encoder.get_mut()
+            }
+            Self::Br(encoder) => {
+                encoder.write_all(data)?;
+                encoder.flush()?;
+                encoder.get_mut()
+            }
+            Self::Zstd(encoder) => {
+                encoder.write_all(data)?;
+                encoder.flush()?;
+                encoder.get_mut()
+            }
+        };
+
+        Ok(std::mem::take(output).into())
+    }
+
+    /// Compresses the last of the data and closes the stream.
+    fn finish(self, data: &[u8]) -> std::io::Result<Bytes> {
+        let output = match self {
+            Self::GZip(mut encoder) => {
+                encoder.write_all(data)?;
+                encoder.finish()?
+            }
+            Self::Br(mut encoder) => {
+                encoder.write_all(data)?;
+                encoder.into_inner()
+            }
+            Self::Zstd(mut encoder) => {
+                encoder.write_all(data)?;
+                encoder.finish()?
+            }
+        };
+
+        Ok(output.into())
+    }
+}
+
+/// A response body compressed frame by frame as the inner body yields them. Trailers are passed
+/// through once the compressed data is complete.
+pub(crate) struct CompressedBody {
+    inner: BoxBody<Bytes, String>,
+    encoder: Option<ResponseEncoder>,
+    trailers: Option<hyper::HeaderMap>,
+}
+
+impl CompressedBody {
+    pub fn new(inner: BoxBody<Bytes, String>, encoder: ResponseEncoder) -> Self {
+        Self {
+            inner,
+            encoder: Some(encoder),
+            trailers: None,
+        }
+    }
+
+    fn compress(&mut self, data: &[u8], last: bool) -> Result<Bytes, String> {
+        let result = if last {
+            match self.encoder.take() {
+                Some(encoder) => encoder.finish(data),
+                None => return Ok(Bytes::new()),
+            }
+        } else {
+            match self.encoder.as_mut() {
+                Some(encoder) => encoder.compress(data),
+                None => return Ok(Bytes::new()),
+            }
+        };
+
+        result.map_err(|err| {
+            self.encoder = None;
+            format!("Can not compress response body: {}", err)
+        })
+    }
+}
+
+impl Body for CompressedBody {
+    type Data = Bytes;
+    type Error = String;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+
+        loop {
+            if this.encoder.is_none() {
+                return Poll::Ready(this.trailers.take().map(|itm| Ok(Frame::trailers(itm))));
+            }
+
+            let compressed = match Pin::new(&mut this.inner).poll_frame(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Some(Ok(frame))) => match frame.into_data() {
+                    Ok(data) => {
+                        let last = this.inner.is_end_stream();
+                        this.compress(&data, last)
+                    }
+                    Err(frame) => {
+                        if let Ok(trailers) = frame.into_trailers() {
+                            this.trailers = Some(trailers);
+                        }
+
+                        this.compress(&[], true)
+                    }
+                },
+                Poll::Ready(Some(Err(err))) => {
+                    this.encoder = None;
+                    return Poll::Ready(Some(Err(err)));
+                }
+                Poll::Ready(None) => this.compress(&[], true),
+            };
+
+            match compressed {
+                Ok(data) => {
+                    if !data.is_empty() {
+                        return Poll::Ready(Some(Ok(Frame::data(data))));
+                    }
+                }
+                Err(err) => return Poll::Ready(Some(Err(err))),
+            }
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.encoder.is_none() && self.trailers.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use http_body_util::{BodyExt, Full, StreamBody};
+
+    use super::*;
+
+    fn decompress(encoding: ResponseEncoding, data: &[u8]) -> Vec<u8> {
+        let mut result = Vec::new();
+
+        match encoding {
+            ResponseEncoding::GZip => {
+                flate2::read::GzDecoder::new(data)
+                    .read_to_end(&mut result)
+                    .unwrap();
+            }
+            ResponseEncoding::Br => {
+                brotli_decompressor::Decompressor::new(data, 4096)
+                    .read_to_end(&mut result)
+                    .unwrap();
+            }
+            ResponseEncoding::Zstd => {
+                result = zstd::stream::decode_all(data).unwrap();
+            }
+        }
+
+        result
+    }
+
+    fn compressed(
+        rx: futures::channel::mpsc::Receiver<Result<Frame<Bytes>, String>>,
+        encoding: ResponseEncoding,
+    ) -> CompressedBody {
+        CompressedBody::new(
+            StreamBody::new(rx).boxed(),
+            ResponseEncoder::new(encoding).unwrap(),
+        )
+    }
+
+    fn plaintext() -> Vec<u8> {
+        (0..50_000).map(|i| b"abcdefgh"[i % 8]).collect()
+    }
+
+    const ENCODINGS: [ResponseEncoding; 3] = [
+        ResponseEncoding::Br,
+        ResponseEncoding::Zstd,
+        ResponseEncoding::GZip,
+    ];
+
+    #[tokio::test]
+    async fn full_body_round_trip() {
+        for encoding in ENCODINGS {
+            let inner = Full::new(Bytes::from(plaintext()))
+                .map_err(|err| match err {})
+                .boxed();
+
+            let body = CompressedBody::new(inner, ResponseEncoder::new(encoding).unwrap());
+            let compressed = body.collect().await.unwrap().to_bytes();
+
+            assert!(compressed.len() < plaintext().len() / 10);
+            assert_eq!(decompress(encoding, &compressed), plaintext());
+        }
+    }
+
+    #[tokio::test]
+    async fn every_frame_can_be_decoded_as_it_comes() {
+        for encoding in ENCODINGS {
+            let (mut tx, rx) = futures::channel::mpsc::channel::<Result<Frame<Bytes>, String>>(1);
+            let mut body = compressed(rx, encoding);
+
+            let mut received = Vec::new();
+
+            for chunk in [&b"first chunk;"[..], b"second chunk;"] {
+                tx.try_send(Ok(Frame::data(Bytes::copy_from_slice(chunk))))
+                    .unwrap();
+
+                let frame = body.frame().await.unwrap().unwrap();
+                received.extend_from_slice(&frame.into_data().unwrap());
+
+                let mut decoded = Vec::new();
+                match encoding {
+                    ResponseEncoding::GZip => {
+                        let mut decoder = flate2::write::GzDecoder::new(&mut decoded);
+                        decoder.write_all(&received).unwrap();
+                        decoder.flush().unwrap();
+                    }
+                    ResponseEncoding::Br => {
+                        let mut decoder =
+                            brotli_decompressor::DecompressorWriter::new(&mut decoded, 4096);
+                        decoder.write_all(&received).unwrap();
+                        decoder.flush().unwrap();
+                    }
+                    ResponseEncoding::Zstd => {
+                        let mut decoder = zstd::stream::write::Decoder::new(&mut decoded).unwrap();
+                        decoder.write_all(&received).unwrap();
+                        decoder.flush().unwrap();
+                    }
+                }
+
+                assert!(decoded.ends_with(chunk), "{:?}", encoding);
+            }
+
+            drop(tx);
+
+            while let Some(frame) = body.frame().await {
+                received.extend_from_slice(&frame.unwrap().into_data().unwrap());
+            }
+
+            assert_eq!(
+                decompress(encoding, &received),
+                b"first chunk;second chunk;"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn trailers_follow_the_compressed_data() {
+        let mut trailers = hyper::HeaderMap::new();
+        trailers.insert("x-checksum", "42".parse().unwrap());
+
+        let (mut tx, rx) = futures::channel::mpsc::channel::<Result<Frame<Bytes>, String>>(2);
+        tx.try_send(Ok(Frame::data(Bytes::from_static(b"payload"))))
+            .unwrap();
+        tx.try_send(Ok(Frame::trailers(trailers))).unwrap();
+        drop(tx);
+
+        let body = compressed(rx, ResponseEncoding::GZip);
+        let collected = body.collect().await.unwrap();
+
+        assert_eq!(
+            collected.trailers().unwrap().get("x-checksum").unwrap(),
+            "42"
+        );
+        assert_eq!(
+            decompress(ResponseEncoding::GZip, &collected.to_bytes()),
+            b"payload"
+        );
+    }
+
+    #[tokio::test]
+    async fn error_of_the_inner_body_is_passed_on() {
+        let (mut tx, rx) = futures::channel::mpsc::channel::<Result<Frame<Bytes>, String>>(1);
+        tx.try_send(Err("connection lost".to_string())).unwrap();
+
+        let mut body = compressed(rx, ResponseEncoding::Zstd);
+
+        assert_eq!(
+            body.frame().await.unwrap().unwrap_err(),
+            "connection lost".to_string()
+        );
+        assert!(body.frame().await.is_none());
+    }
+}
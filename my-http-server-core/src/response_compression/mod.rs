@@ -0,0 +1,6 @@
+mod response_compression;
+pub use response_compression::*;
+mod accept_encoding;
+pub(crate) use accept_encoding::*;
+mod compressed_body;
+pub(crate) use compressed_body::*;
@@ -0,0 +1,376 @@
+use http_body_util::BodyExt;
+use hyper::body::Body;
+use hyper::header::{CACHE_CONTROL, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, VARY};
+use my_hyper_utils::MyHttpResponse;
+
+use crate::{CompressedBody, ResponseEncoder};
+
+/// Responses with fewer bytes than this are not worth the CPU — and can come out bigger.
+pub const DEFAULT_RESPONSE_COMPRESSION_MIN_SIZE: u64 = 1024;
+
+/// Types that are compressed already — by format — and would only get bigger.
+const ALREADY_COMPRESSED_CONTENT_TYPES: &[&str] = &[
+    "image/",
+    "video/",
+    "audio/",
+    "font/woff",
+    "application/zip",
+    "application/gzip",
+    "application/x-gzip",
+    "application/zstd",
+    "application/x-7z-compressed",
+    "application/x-rar-compressed",
+    "application/vnd.rar",
+    "application/x-bzip2",
+    "application/pdf",
+];
+
+/// An SVG is XML, whatever `image/` says.
+const SVG_CONTENT_TYPE: &str = "image/svg+xml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseEncoding {
+    Br,
+    Zstd,
+    GZip,
+}
+
+impl ResponseEncoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Br => "br",
+            Self::Zstd => "zstd",
+            Self::GZip => "gzip",
+        }
+    }
+
+    pub(crate) fn is_named(&self, coding: &str) -> bool {
+        if coding.eq_ignore_ascii_case(self.as_str()) {
+            return true;
+        }
+
+        matches!(self, Self::GZip) && coding.eq_ignore_ascii_case("x-gzip")
+    }
+}
+
+/// Compression of the responses a [`MyHttpServer`](crate::MyHttpServer) gives. Set through
+/// [`MyHttpServer::set_response_compression`](crate::MyHttpServer::set_response_compression);
+/// responses go out as the actions built them by default.
+///
+/// The encoding is negotiated from the `Accept-Encoding` of every request. Streamed responses —
+/// [`HttpOutputAsStream`](crate::HttpOutputAsStream) — are compressed frame by frame, so every
+/// frame still reaches the client as soon as it is sent.
+#[derive(Debug, Clone)]
+pub struct ResponseCompression {
+    encodings: Vec<ResponseEncoding>,
+    min_size: u64,
+    skip_content_types: Vec<String>,
+}
+
+impl Default for ResponseCompression {
+    fn default() -> Self {
+        Self {
+            encodings: vec![
+                ResponseEncoding::Br,
+                ResponseEncoding::Zstd,
+                ResponseEncoding::GZip,
+            ],
+            min_size: DEFAULT_RESPONSE_COMPRESSION_MIN_SIZE,
+            skip_content_types: ALREADY_COMPRESSED_CONTENT_TYPES
+                .iter()
+                .map(|itm| itm.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl ResponseCompression {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Responses with a body smaller than this go out uncompressed. A streamed body has no size
+    /// known up front and is always compressed. Defaults to
+    /// [`DEFAULT_RESPONSE_COMPRESSION_MIN_SIZE`].
+    pub fn with_min_size(mut self, min_size: u64) -> Self {
+        self.min_size = min_size;
+        self
+    }
+
+    /// The encodings the server offers, the preferred first — it decides between the ones the
+    /// client weights equally. `br`, `zstd`, `gzip` by default.
+    pub fn with_encodings(mut self, encodings: impl IntoIterator<Item = ResponseEncoding>) -> Self {
+        self.encodings = encodings.into_iter().collect();
+
+        if self.encodings.is_empty() {
+            panic!("Response compression needs at least one encoding");
+        }
+
+        self
+    }
+
+    /// Leaves responses whose `Content-Type` starts with `content_type` uncompressed, on top of
+    /// the types which are compressed already — images, video, audio, archives.
+    pub fn skip_content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.skip_content_types.push(content_type.into());
+        self
+    }
+
+    pub fn get_encodings(&self) -> &[ResponseEncoding] {
+        &self.encodings
+    }
+
+    fn is_content_type_skipped(&self, content_type: &str) -> bool {
+        let content_type = content_type.split(';').next().unwrap_or_default().trim();
+
+        if content_type.eq_ignore_ascii_case(SVG_CONTENT_TYPE) {
+            return false;
+        }
+
+        self.skip_content_types.iter().any(|prefix| {
+            content_type.len() >= prefix.len()
+                && content_type.as_bytes()[..prefix.len()].eq_ignore_ascii_case(prefix.as_bytes())
+        })
+    }
+
+    fn can_be_compressed(&self, response: &MyHttpResponse) -> bool {
+        let status = response.status();
+
+        if status.is_informational()
+            || status == hyper::StatusCode::NO_CONTENT
+            || status == hyper::StatusCode::NOT_MODIFIED
+            || status == hyper::StatusCode::PARTIAL_CONTENT
+        {
+            return false;
+        }
+
+        let headers = response.headers();
+
+        if headers.contains_key(CONTENT_ENCODING) {
+            return false;
+        }
+
+        let no_transform = headers
+            .get_all(CACHE_CONTROL)
+            .iter()
+            .filter_map(|itm| itm.to_str().ok())
+            .flat_map(|itm| itm.split(','))
+            .any(|itm| itm.trim().eq_ignore_ascii_case("no-transform"));
+
+        if no_transform {
+            return false;
+        }
+
+        if let Some(content_type) = headers.get(CONTENT_TYPE) {
+            if let Ok(content_type) = content_type.to_str() {
+                if self.is_content_type_skipped(content_type) {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Compresses `response` with `encoding` — what
+    /// [`negotiate_response_encoding`](crate::negotiate_response_encoding) picked for the request
+    /// — if it is worth it. A response that could be compressed gets `Vary: Accept-Encoding`
+    /// either way, so caches keep the variants apart.
+    pub(crate) fn compress(
+        &self,
+        response: MyHttpResponse,
+        encoding: Option<ResponseEncoding>,
+    ) -> MyHttpResponse {
+        if !self.can_be_compressed(&response) {
+            return response;
+        }
+
+        let mut response = response;
+        add_vary_accept_encoding(response.headers_mut());
+
+        let Some(encoding) = encoding else {
+            return response;
+        };
+
+        let body = response.body();
+
+        if body.is_end_stream() {
+            return response;
+        }
+
+        if let Some(size) = body.size_hint().exact() {
+            if size < self.min_size {
+                return response;
+            }
+        }
+
+        let Ok(encoder) = ResponseEncoder::new(encoding) else {
+            return response;
+        };
+
+        let (mut parts, body) = response.into_parts();
+        let body = CompressedBody::new(body, encoder).boxed();
+
+        parts.headers.remove(CONTENT_LENGTH);
+        parts.headers.insert(
+            CONTENT_ENCODING,
+            hyper::header::HeaderValue::from_static(encoding.as_str()),
+        );
+
+        hyper::Response::from_parts(parts, body)
+    }
+}
+
+fn add_vary_accept_encoding(headers: &mut hyper::HeaderMap) {
+    let already_there = headers
+        .get_all(VARY)
+        .iter()
+        .filter_map(|itm| itm.to_str().ok())
+        .flat_map(|itm| itm.split(','))
+        .map(|itm| itm.trim())
+        .any(|itm| itm == "*" || itm.eq_ignore_ascii_case("accept-encoding"));
+
+    if !already_there {
+        headers.append(
+            VARY,
+            hyper::header::HeaderValue::from_static("Accept-Encoding"),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use http_body_util::Full;
+
+    use super::*;
+
+    fn response(status: u16, headers: &[(&str, &str)], body: Vec<u8>) -> MyHttpResponse {
+        let mut builder = hyper::Response::builder().status(status);
+
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+
+        builder
+            .body(
+                Full::new(Bytes::from(body))
+                    .map_err(|err| match err {})
+                    .boxed(),
+            )
+            .unwrap()
+    }
+
+    fn text(headers: &[(&str, &str)]) -> MyHttpResponse {
+        response(200, headers, vec![b'a'; 4096])
+    }
+
+    fn content_encoding(response: &MyHttpResponse) -> Option<&str> {
+        response
+            .headers()
+            .get(CONTENT_ENCODING)
+            .map(|itm| itm.to_str().unwrap())
+    }
+
+    fn vary(response: &MyHttpResponse) -> Vec<&str> {
+        response
+            .headers()
+            .get_all(VARY)
+            .iter()
+            .map(|itm| itm.to_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn text_is_compressed() {
+        let compressed = ResponseCompression::new().compress(
+            text(&[("content-type", "text/plain"), ("content-length", "4096")]),
+            Some(ResponseEncoding::Br),
+        );
+
+        assert_eq!(content_encoding(&compressed), Some("br"));
+        assert_eq!(vary(&compressed), vec!["Accept-Encoding"]);
+        assert!(compressed.headers().get(CONTENT_LENGTH).is_none());
+    }
+
+    #[test]
+    fn nothing_negotiated_still_varies() {
+        let response = ResponseCompression::new().compress(text(&[]), None);
+
+        assert_eq!(content_encoding(&response), None);
+        assert_eq!(vary(&response), vec!["Accept-Encoding"]);
+    }
+
+    #[test]
+    fn vary_is_not_repeated() {
+        let response = ResponseCompression::new().compress(
+            text(&[("vary", "Origin, accept-encoding")]),
+            Some(ResponseEncoding::GZip),
+        );
+
+        assert_eq!(vary(&response), vec!["Origin, accept-encoding"]);
+    }
+
+    #[test]
+    fn small_body_is_left_as_is() {
+        let response = ResponseCompression::new().compress(
+            response(200, &[], vec![b'a'; 100]),
+            Some(ResponseEncoding::GZip),
+        );
+
+        assert_eq!(content_encoding(&response), None);
+
+        let response = ResponseCompression::new().with_min_size(10).compress(
+            self::response(200, &[], vec![b'a'; 100]),
+            Some(ResponseEncoding::GZip),
+        );
+
+        assert_eq!(content_encoding(&response), Some("gzip"));
+    }
+
+    #[test]
+    fn compressed_content_types_are_skipped() {
+        let compression = ResponseCompression::new().skip_content_type("application/x-custom");
+
+        for content_type in [
+            "image/png",
+            "video/mp4",
+            "font/woff2",
+            "application/zip",
+            "application/x-custom; charset=utf-8",
+        ] {
+            let response = compression.compress(
+                text(&[("content-type", content_type)]),
+                Some(ResponseEncoding::GZip),
+            );
+
+            assert_eq!(content_encoding(&response), None, "{}", content_type);
+            assert!(vary(&response).is_empty(), "{}", content_type);
+        }
+
+        let response = compression.compress(
+            text(&[("content-type", "image/svg+xml")]),
+            Some(ResponseEncoding::GZip),
+        );
+
+        assert_eq!(content_encoding(&response), Some("gzip"));
+    }
+
+    #[test]
+    fn responses_that_must_not_be_transformed() {
+        let compression = ResponseCompression::new();
+
+        for response in [
+            text(&[("content-encoding", "gzip")]),
+            text(&[("cache-control", "public, no-transform")]),
+            response(206, &[], vec![b'a'; 4096]),
+            response(304, &[], Vec::new()),
+            response(204, &[], Vec::new()),
+        ] {
+            let status = response.status();
+            let response = compression.compress(response, Some(ResponseEncoding::Zstd));
+
+            assert_ne!(content_encoding(&response), Some("zstd"), "{}", status);
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use std::sync::Arc;
+
+use rust_extensions::Logger;
+
+use crate::{
+    HttpConnectionInfo, HttpServerMiddlewares, HttpServerShutdown, HttpTestRequest,
+    HttpTestResponse, SocketAddress,
+};
+
+/// The listener name the requests of an [`HttpTestClient`] report in
+/// [`HttpConnectionInfo::listener_name`].
+pub const TEST_LISTENER_NAME: &str = "test";
+
+/// Sends requests to a [`MyHttpServer`](crate::MyHttpServer) in process — through the same code a
+/// request off a socket goes through, minus the socket. Middlewares, limits, timeouts, request
+/// ids, compression and the tech middlewares all see it the way they see a real one, and a panic
+/// is caught and answered the same way.
+///
+/// Nothing is bound, so tests need no free ports and run in parallel. Behind the `test-client`
+/// feature, which is meant for the `[dev-dependencies]` of an application.
+///
+/// ```ignore
+/// let mut server = MyHttpServer::new(addr);
+/// server.add_middleware(Arc::new(controllers));
+/// let client = server.create_test_client(app_states, logger);
+///
+/// let response = client.send(HttpTestRequest::get("/api/users/1")).await;
+/// assert_eq!(response.status_code, 200);
+/// ```
+pub struct HttpTestClient {
+    http_server_middlewares: Arc<HttpServerMiddlewares>,
+    logger: Arc<dyn Logger + Send + Sync + 'static>,
+    shutdown: Arc<HttpServerShutdown>,
+    listener_name: Arc<String>,
+}
+
+impl HttpTestClient {
+    pub(crate) fn new(
+        http_server_middlewares: Arc<HttpServerMiddlewares>,
+        logger: Arc<dyn Logger + Send + Sync + 'static>,
+        shutdown: Arc<HttpServerShutdown>,
+    ) -> Self {
+        Self {
+            http_server_middlewares,
+            logger,
+            shutdown,
+            listener_name: Arc::new(TEST_LISTENER_NAME.to_string()),
+        }
+    }
+
+    /// Every request comes on a connection of its own.
+    pub async fn send(&self, request: HttpTestRequest) -> HttpTestResponse {
+        let connection = HttpConnectionInfo::new(
+            self.listener_name.clone(),
+            SocketAddress::Tcp(request.get_remote_addr()),
+        );
+
+        let response = crate::handle_requests(
+            request.into_hyper_request(),
+            self.http_server_middlewares.clone(),
+            Arc::new(connection),
+            self.logger.clone(),
+            self.shutdown.is_force_closing(),
+        )
+        .await;
+
+        match response {
+            Ok(response) => HttpTestResponse::from_response(response).await,
+            Err(err) => panic!("Test request failed: {}", err),
+        }
+    }
+
+    pub async fn get(&self, uri: &str) -> HttpTestResponse {
+        self.send(HttpTestRequest::get(uri)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use rust_extensions::ApplicationStates;
+
+    use super::*;
+    use crate::test_logger::NoLogger;
+    use crate::{
+        HttpContext, HttpFailResult, HttpOkResult, HttpOutput, HttpRequestHeaders,
+        HttpServerMiddleware, MyHttpServer, DEFAULT_TEST_REMOTE_ADDR,
+    };
+
+    struct InitializedAppStates;
+
+    impl ApplicationStates for InitializedAppStates {
+        fn is_initialized(&self) -> bool {
+            true
+        }
+
+        fn is_shutting_down(&self) -> bool {
+            false
+        }
+    }
+
+    /// Answers `/echo` with what it saw of the request, `/hyper` with the uri of the request it
+    /// takes as hyper has it, panics on `/panic`.
+    struct EchoMiddleware;
+
+    #[async_trait::async_trait]
+    impl HttpServerMiddleware for EchoMiddleware {
+        async fn handle_request(
+            &self,
+            ctx: &mut HttpContext,
+        ) -> Option<Result<HttpOkResult, HttpFailResult>> {
+            match ctx.request.get_path().as_str() {
+                "/echo" => {}
+                "/hyper" => {
+                    return Some(match ctx.request.take_my_hyper_http_request() {
+                        Ok(req) => HttpOutput::as_text(req.uri().to_string()).into_ok_result(false),
+                        Err(err) => Err(err),
+                    });
+                }
+                "/panic" => panic!("Test panic"),
+                _ => return None,
+            }
+
+            let body = match ctx.request.receive_body().await {
+                Ok(body) => body.get_body(),
+                Err(err) => return Some(Err(err)),
+            };
+
+            let echo = format!(
+                "{} {} {} {} {}",
+                ctx.request.method,
+                ctx.request.get_path_and_query(),
+                ctx.request.get_ip().get_real_ip(),
+                ctx.request
+                    .get_headers()
+                    .try_get_case_insensitive("x-test")
+                    .map(|itm| itm.as_str().unwrap_or_default().to_string())
+                    .unwrap_or_default(),
+                String::from_utf8_lossy(&body)
+            );
+
+            Some(HttpOutput::as_text(echo).into_ok_result(false))
+        }
+    }
+
+    fn create_client() -> HttpTestClient {
+        let mut server = MyHttpServer::new(DEFAULT_TEST_REMOTE_ADDR.parse().unwrap());
+        server.add_middleware(Arc::new(EchoMiddleware));
+        server.create_test_client(Arc::new(InitializedAppStates), Arc::new(NoLogger))
+    }
+
+    #[tokio::test]
+    async fn test_request_goes_through_the_middlewares() {
+        let client = create_client();
+
+        let response = client
+            .send(
+                HttpTestRequest::post("/echo?a=1")
+                    .with_header("x-test", "value")
+                    .with_remote_addr("10.0.0.1:1234".parse().unwrap())
+                    .with_body("payload"),
+            )
+            .await;
+
+        assert_eq!(response.status_code, 200);
+        assert_eq!(
+            response.body_as_str(),
+            "POST /echo?a=1 10.0.0.1 value payload"
+        );
+        assert!(response.get_header("x-request-id").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_streamed_body() {
+        let client = create_client();
+
+        let chunks = ["str", "ea", "med"].map(|itm| Bytes::from_static(itm.as_bytes()));
+
+        let response = client
+            .send(
+                HttpTestRequest::post("/echo")
+                    .with_body_stream(futures::stream::iter(chunks.clone())),
+            )
+            .await;
+
+        assert_eq!(response.status_code, 200);
+        assert!(response.body_as_str().ends_with(" streamed"));
+
+        // Ends short of what it announced — an abandoned upload.
+        let response = client
+            .send(
+                HttpTestRequest::post("/echo")
+                    .with_header("content-length", "100")
+                    .with_body_stream(futures::stream::iter(chunks)),
+            )
+            .await;
+
+        assert_eq!(response.status_code, 400);
+    }
+
+    /// A streamed test body has no hyper request to go in — an error, not a panic.
+    #[tokio::test]
+    async fn test_take_my_hyper_http_request() {
+        let client = create_client();
+
+        let response = client
+            .send(HttpTestRequest::post("/hyper").with_body("payload"))
+            .await;
+        assert_eq!(response.status_code, 200);
+        assert_eq!(response.body_as_str(), "/hyper");
+
+        let chunks = ["str", "ea", "med"].map(|itm| Bytes::from_static(itm.as_bytes()));
+
+        let response = client
+            .send(HttpTestRequest::post("/hyper").with_body_stream(futures::stream::iter(chunks)))
+            .await;
+        assert_eq!(response.status_code, 500);
+    }
+
+    #[tokio::test]
+    async fn test_unmatched_request_and_panic() {
+        let client = create_client();
+
+        let response = client.get("/missing").await;
+        assert_eq!(response.status_code, 404);
+
+        let response = client.get("/panic").await;
+        assert_eq!(response.status_code, 500);
+        assert_eq!(response.body_as_str(), "Internal server error");
+    }
+}
@@ -0,0 +1,151 @@
+use std::net::SocketAddr;
+
+use bytes::Bytes;
+use futures::Stream;
+use hyper::{header::HeaderValue, HeaderMap, Method, Version};
+use serde::Serialize;
+
+use crate::HttpRequestBodySource;
+
+/// The address an [`HttpTestRequest`] comes from unless it says otherwise.
+pub const DEFAULT_TEST_REMOTE_ADDR: &str = "127.0.0.1:50000";
+
+/// A request for [`HttpTestClient`](crate::HttpTestClient), built in memory. Panics on a URI or a
+/// header that would not parse — a test that gets one wrong should fail where it is written.
+///
+/// ```ignore
+/// let response = client
+///     .send(
+///         HttpTestRequest::post("/api/users")
+///             .with_header("authorization", "Bearer token")
+///             .with_json(&NewUser { name: "John" }),
+///     )
+///     .await;
+/// ```
+pub struct HttpTestRequest {
+    method: Method,
+    uri: hyper::Uri,
+    version: Version,
+    headers: HeaderMap,
+    body: HttpRequestBodySource,
+    remote_addr: SocketAddr,
+}
+
+impl HttpTestRequest {
+    pub fn new(method: Method, uri: &str) -> Self {
+        let uri = match uri.parse() {
+            Ok(uri) => uri,
+            Err(err) => panic!("Invalid test request uri '{}': {}", uri, err),
+        };
+
+        Self {
+            method,
+            uri,
+            version: Version::HTTP_11,
+            headers: HeaderMap::new(),
+            body: HttpRequestBodySource::empty(),
+            remote_addr: DEFAULT_TEST_REMOTE_ADDR.parse().unwrap(),
+        }
+    }
+
+    pub fn get(uri: &str) -> Self {
+        Self::new(Method::GET, uri)
+    }
+
+    pub fn post(uri: &str) -> Self {
+        Self::new(Method::POST, uri)
+    }
+
+    pub fn put(uri: &str) -> Self {
+        Self::new(Method::PUT, uri)
+    }
+
+    pub fn delete(uri: &str) -> Self {
+        Self::new(Method::DELETE, uri)
+    }
+
+    /// Added to the values the header already has.
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        let name = match hyper::header::HeaderName::try_from(name) {
+            Ok(name) => name,
+            Err(err) => panic!("Invalid test request header name '{}': {}", name, err),
+        };
+
+        let value = match HeaderValue::try_from(value) {
+            Ok(value) => value,
+            Err(err) => panic!("Invalid value of test request header '{}': {}", name, err),
+        };
+
+        self.headers.append(name, value);
+        self
+    }
+
+    /// The whole body at once, with its `Content-Length`.
+    pub fn with_body(mut self, body: impl Into<Bytes>) -> Self {
+        let body = body.into();
+
+        self.headers
+            .insert(hyper::header::CONTENT_LENGTH, HeaderValue::from(body.len()));
+        self.body = HttpRequestBodySource::from_bytes(body);
+        self
+    }
+
+    /// `model` as a JSON body, with its `Content-Type`.
+    pub fn with_json(mut self, model: &impl Serialize) -> Self {
+        self.headers.insert(
+            hyper::header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        );
+        self.with_body(serde_json::to_vec(model).unwrap())
+    }
+
+    /// A body that comes in chunks, as `stream` yields them — without a `Content-Length` unless
+    /// one is set with [`with_header`](Self::with_header). A stream that ends before the length it
+    /// was announced with is read as an upload the client abandoned.
+    pub fn with_body_stream(
+        mut self,
+        stream: impl Stream<Item = Bytes> + Send + Sync + 'static,
+    ) -> Self {
+        self.body = HttpRequestBodySource::from_stream(stream);
+        self
+    }
+
+    /// `HTTP/1.1` by default.
+    pub fn with_version(mut self, version: Version) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// The peer the request comes from — what [`HttpRequest::get_ip`](crate::HttpRequest::get_ip)
+    /// sees before any forwarding header.
+    pub fn with_remote_addr(mut self, remote_addr: SocketAddr) -> Self {
+        self.remote_addr = remote_addr;
+        self
+    }
+
+    pub(crate) fn get_remote_addr(&self) -> SocketAddr {
+        self.remote_addr
+    }
+
+    /// `Host` is added the way every HTTP/1.1 client sends it, if the test did not set one.
+    pub(crate) fn into_hyper_request(mut self) -> hyper::Request<HttpRequestBodySource> {
+        if !self.headers.contains_key(hyper::header::HOST) {
+            let host = self
+                .uri
+                .authority()
+                .map(|itm| itm.as_str())
+                .unwrap_or("localhost");
+
+            if let Ok(host) = HeaderValue::try_from(host) {
+                self.headers.insert(hyper::header::HOST, host);
+            }
+        }
+
+        let mut result = hyper::Request::new(self.body);
+        *result.method_mut() = self.method;
+        *result.uri_mut() = self.uri;
+        *result.version_mut() = self.version;
+        *result.headers_mut() = self.headers;
+        result
+    }
+}
@@ -0,0 +1,55 @@
+use hyper::HeaderMap;
+use serde::de::DeserializeOwned;
+
+/// What [`HttpTestClient`](crate::HttpTestClient) got back, with the body read to the end. The
+/// body is as it would go on the wire — one compressed for `Accept-Encoding` stays compressed.
+#[derive(Debug)]
+pub struct HttpTestResponse {
+    pub status_code: u16,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+impl HttpTestResponse {
+    pub(crate) async fn from_response(response: my_hyper_utils::MyHttpResponse) -> Self {
+        use http_body_util::BodyExt;
+
+        let (parts, body) = response.into_parts();
+
+        let body = match body.collect().await {
+            Ok(body) => body.to_bytes().to_vec(),
+            Err(err) => panic!("Can not read the test response body: {}", err),
+        };
+
+        Self {
+            status_code: parts.status.as_u16(),
+            headers: parts.headers,
+            body,
+        }
+    }
+
+    /// The first value of the header, if it is there and is text.
+    pub fn get_header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)?.to_str().ok()
+    }
+
+    /// Panics if the body is not UTF-8.
+    pub fn body_as_str(&self) -> &str {
+        match std::str::from_utf8(&self.body) {
+            Ok(body) => body,
+            Err(err) => panic!("Test response body is not UTF-8: {}", err),
+        }
+    }
+
+    /// Panics if the body is not `T` as JSON.
+    pub fn body_as_json<T: DeserializeOwned>(&self) -> T {
+        match serde_json::from_slice(&self.body) {
+            Ok(result) => result,
+            Err(err) => panic!(
+                "Test response body is not the expected JSON: {}. Body: {}",
+                err,
+                String::from_utf8_lossy(&self.body)
+            ),
+        }
+    }
+}
@@ -0,0 +1,6 @@
+mod http_test_client;
+pub use http_test_client::*;
+mod http_test_request;
+pub use http_test_request::*;
+mod http_test_response;
+pub use http_test_response::*;
@@ -0,0 +1,15 @@
+//! A logger that drops everything, for the unit tests that need one to build a server part.
+
+use std::collections::HashMap;
+
+use rust_extensions::Logger;
+
+pub(crate) struct NoLogger;
+
+impl Logger for NoLogger {
+    fn write_info(&self, _: String, _: String, _: Option<HashMap<String, String>>) {}
+    fn write_warning(&self, _: String, _: String, _: Option<HashMap<String, String>>) {}
+    fn write_error(&self, _: String, _: String, _: Option<HashMap<String, String>>) {}
+    fn write_fatal_error(&self, _: String, _: String, _: Option<HashMap<String, String>>) {}
+    fn write_debug_info(&self, _: String, _: String, _: Option<HashMap<String, String>>) {}
+}
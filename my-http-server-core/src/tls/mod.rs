@@ -0,0 +1,12 @@
+mod tls_settings;
+pub use tls_settings::*;
+mod sni_certificate_resolver;
+pub use sni_certificate_resolver::*;
+mod pem_loader;
+pub use pem_loader::*;
+mod tls_certificates_handle;
+pub use tls_certificates_handle::*;
+mod tls_files_watcher;
+pub use tls_files_watcher::*;
+#[cfg(test)]
+mod test_certificates;
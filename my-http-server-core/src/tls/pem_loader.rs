@@ -0,0 +1,68 @@
+use std::sync::Arc;
+
+use tokio_rustls::rustls::{
+    crypto::CryptoProvider,
+    pki_types::{CertificateDer, PrivateKeyDer},
+    sign::CertifiedKey,
+};
+
+use crate::TlsCertificate;
+
+/// Reads a certificate chain and its private key from the PEM files of [`TlsCertificate`] and
+/// turns them into something rustls can sign handshakes with.
+pub fn load_certified_key(
+    certificate: &TlsCertificate,
+    provider: &CryptoProvider,
+) -> Result<Arc<CertifiedKey>, String> {
+    let cert_chain = load_cert_chain(certificate.cert_chain_path.as_str())?;
+    let private_key = load_private_key(certificate.private_key_path.as_str())?;
+
+    let signing_key = provider
+        .key_provider
+        .load_private_key(private_key)
+        .map_err(|err| {
+            format!(
+                "Can not use private key {}. Err: {}",
+                certificate.private_key_path, err
+            )
+        })?;
+
+    Ok(Arc::new(CertifiedKey::new(cert_chain, signing_key)))
+}
+
+fn load_cert_chain(path: &str) -> Result<Vec<CertificateDer<'static>>, String> {
+    let file = std::fs::File::open(path)
+        .map_err(|err| format!("Can not open certificate file {}. Err: {}", path, err))?;
+
+    let mut reader = std::io::BufReader::new(file);
+
+    let mut result = Vec::new();
+
+    for cert in rustls_pemfile::certs(&mut reader) {
+        let cert =
+            cert.map_err(|err| format!("Can not read certificate file {}. Err: {}", path, err))?;
+        result.push(cert);
+    }
+
+    if result.is_empty() {
+        return Err(format!("No certificates found in file {}", path));
+    }
+
+    Ok(result)
+}
+
+fn load_private_key(path: &str) -> Result<PrivateKeyDer<'static>, String> {
+    let file = std::fs::File::open(path)
+        .map_err(|err| format!("Can not open private key file {}. Err: {}", path, err))?;
+
+    let mut reader = std::io::BufReader::new(file);
+
+    match rustls_pemfile::private_key(&mut reader) {
+        Ok(Some(private_key)) => Ok(private_key),
+        Ok(None) => Err(format!("No private key found in file {}", path)),
+        Err(err) => Err(format!(
+            "Can not read private key file {}. Err: {}",
+            path, err
+        )),
+    }
+}
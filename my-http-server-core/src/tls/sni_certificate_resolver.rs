@@ -0,0 +1,191 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use tokio_rustls::rustls::{
+    crypto::CryptoProvider,
+    server::{ClientHello, ResolvesServerCert},
+    sign::CertifiedKey,
+};
+
+use crate::TlsSettings;
+
+/// Every configured certificate, loaded and indexed by the host names it is served for.
+#[derive(Debug)]
+pub struct TlsCertificates {
+    by_server_name: HashMap<String, Arc<CertifiedKey>>,
+    default: Arc<CertifiedKey>,
+}
+
+impl TlsCertificates {
+    pub fn load(settings: &TlsSettings, provider: &CryptoProvider) -> Result<Self, String> {
+        let mut by_server_name = HashMap::new();
+        let mut default = None;
+
+        for certificate in settings.certificates.iter() {
+            let certified_key = crate::load_certified_key(certificate, provider)?;
+
+            for server_name in certificate.server_names.iter() {
+                by_server_name.insert(server_name.to_lowercase(), certified_key.clone());
+            }
+
+            if default.is_none() {
+                default = Some(certified_key);
+            }
+        }
+
+        let Some(default) = default else {
+            return Err("TLS is enabled but no certificate is configured".to_string());
+        };
+
+        Ok(Self {
+            by_server_name,
+            default,
+        })
+    }
+
+    pub fn resolve(&self, server_name: Option<&str>) -> Arc<CertifiedKey> {
+        let Some(server_name) = server_name else {
+            return self.default.clone();
+        };
+
+        let server_name = server_name.to_lowercase();
+
+        if let Some(result) = self.by_server_name.get(server_name.as_str()) {
+            return result.clone();
+        }
+
+        if let Some(index) = server_name.find('.') {
+            let wildcard = format!("*{}", &server_name[index..]);
+            if let Some(result) = self.by_server_name.get(wildcard.as_str()) {
+                return result.clone();
+            }
+        }
+
+        self.default.clone()
+    }
+}
+
+/// Picks the certificate for a handshake by the SNI host name the client sent.
+///
+/// The certificate set can be swapped while the server runs. A swap only affects handshakes that
+/// start after it — connections already established keep the certificate they were opened with.
+#[derive(Debug)]
+pub struct SniCertificateResolver {
+    certificates: RwLock<Arc<TlsCertificates>>,
+}
+
+impl SniCertificateResolver {
+    pub fn new(certificates: TlsCertificates) -> Self {
+        Self {
+            certificates: RwLock::new(Arc::new(certificates)),
+        }
+    }
+
+    pub fn swap(&self, certificates: TlsCertificates) {
+        let mut write_access = self.certificates.write().unwrap();
+        *write_access = Arc::new(certificates);
+    }
+
+    pub(crate) fn get_certificates(&self) -> Arc<TlsCertificates> {
+        self.certificates.read().unwrap().clone()
+    }
+}
+
+impl ResolvesServerCert for SniCertificateResolver {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.get_certificates().resolve(client_hello.server_name()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio_rustls::rustls::crypto::aws_lc_rs;
+
+    use super::*;
+    use crate::tls::test_certificates::*;
+
+    struct TestSet {
+        certificates: TlsCertificates,
+        default: TestCertificate,
+        api: TestCertificate,
+        wildcard: TestCertificate,
+    }
+
+    fn load_test_set(test_name: &str) -> TestSet {
+        let default = TestCertificate::generate(test_name, "default", &["default.test"]);
+        let api = TestCertificate::generate(test_name, "api", &["api.example.com"]);
+        let wildcard = TestCertificate::generate(test_name, "wildcard", &["*.example.com"]);
+
+        let settings = TlsSettings::new()
+            .add_certificate(&default.cert_chain_path, &default.private_key_path, &[])
+            .add_certificate(
+                &api.cert_chain_path,
+                &api.private_key_path,
+                &["API.example.com"],
+            )
+            .add_certificate(
+                &wildcard.cert_chain_path,
+                &wildcard.private_key_path,
+                &["*.example.com"],
+            );
+
+        let certificates =
+            TlsCertificates::load(&settings, &aws_lc_rs::default_provider()).unwrap();
+        remove_test_dir(test_name);
+
+        TestSet {
+            certificates,
+            default,
+            api,
+            wildcard,
+        }
+    }
+
+    #[test]
+    fn test_exact_name_wins_over_wildcard() {
+        let set = load_test_set("exact");
+
+        assert!(set
+            .api
+            .is(&set.certificates.resolve(Some("api.example.com"))));
+        assert!(set
+            .wildcard
+            .is(&set.certificates.resolve(Some("www.example.com"))));
+    }
+
+    #[test]
+    fn test_wildcard_matches_one_label() {
+        let set = load_test_set("wildcard");
+
+        assert!(set
+            .default
+            .is(&set.certificates.resolve(Some("a.www.example.com"))));
+        assert!(set
+            .default
+            .is(&set.certificates.resolve(Some("example.com"))));
+    }
+
+    #[test]
+    fn test_names_are_case_insensitive() {
+        let set = load_test_set("case");
+
+        assert!(set
+            .api
+            .is(&set.certificates.resolve(Some("Api.Example.COM"))));
+        assert!(set
+            .wildcard
+            .is(&set.certificates.resolve(Some("WWW.EXAMPLE.COM"))));
+    }
+
+    #[test]
+    fn test_first_certificate_is_the_fallback() {
+        let set = load_test_set("fallback");
+
+        assert!(set.default.is(&set.certificates.resolve(None)));
+        assert!(set
+            .default
+            .is(&set.certificates.resolve(Some("other.test"))));
+    }
+}
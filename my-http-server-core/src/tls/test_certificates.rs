@@ -0,0 +1,89 @@
+//! Self-signed certificates written to PEM files, for the TLS tests.
+
+use std::{path::PathBuf, sync::Arc};
+
+use tokio_rustls::rustls::{crypto::aws_lc_rs, sign::CertifiedKey};
+
+use crate::{SniCertificateResolver, TlsCertificates, TlsCertificatesHandle, TlsSettings};
+
+pub(crate) struct TestCertificate {
+    pub cert_chain_path: String,
+    pub private_key_path: String,
+    der: Vec<u8>,
+}
+
+impl TestCertificate {
+    /// Writes `<name>.crt` and `<name>.key` into a directory of the test's own.
+    pub fn generate(test_name: &str, name: &str, server_names: &[&str]) -> Self {
+        let dir = get_test_dir(test_name);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut result = Self {
+            cert_chain_path: dir
+                .join(format!("{}.crt", name))
+                .to_str()
+                .unwrap()
+                .to_string(),
+            private_key_path: dir
+                .join(format!("{}.key", name))
+                .to_str()
+                .unwrap()
+                .to_string(),
+            der: Vec::new(),
+        };
+
+        result.regenerate(server_names);
+        result
+    }
+
+    /// Overwrites the files with a new certificate — a renewal in place.
+    pub fn regenerate(&mut self, server_names: &[&str]) {
+        let server_names: Vec<String> = server_names.iter().map(|itm| itm.to_string()).collect();
+        let certified_key = rcgen::generate_simple_self_signed(server_names).unwrap();
+
+        std::fs::write(&self.cert_chain_path, certified_key.cert.pem()).unwrap();
+        std::fs::write(
+            &self.private_key_path,
+            certified_key.signing_key.serialize_pem(),
+        )
+        .unwrap();
+
+        self.der = certified_key.cert.der().to_vec();
+    }
+
+    pub fn is(&self, certified_key: &CertifiedKey) -> bool {
+        certified_key.cert[0].as_ref() == self.der.as_slice()
+    }
+}
+
+/// A handle serving `certificate`, and the resolver it swaps the certificates of.
+pub(crate) fn create_handle(
+    certificate: &TestCertificate,
+) -> (TlsCertificatesHandle, Arc<SniCertificateResolver>) {
+    let provider = Arc::new(aws_lc_rs::default_provider());
+
+    let settings = TlsSettings::new().add_certificate(
+        &certificate.cert_chain_path,
+        &certificate.private_key_path,
+        &[],
+    );
+
+    let certificates = TlsCertificates::load(&settings, provider.as_ref()).unwrap();
+    let resolver = Arc::new(SniCertificateResolver::new(certificates));
+
+    let handle = TlsCertificatesHandle::new(resolver.clone(), provider, settings);
+
+    (handle, resolver)
+}
+
+pub(crate) fn remove_test_dir(test_name: &str) {
+    let _ = std::fs::remove_dir_all(get_test_dir(test_name));
+}
+
+fn get_test_dir(test_name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "my-http-server-tls-{}-{}",
+        test_name,
+        std::process::id()
+    ))
+}
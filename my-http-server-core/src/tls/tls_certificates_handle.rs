@@ -0,0 +1,117 @@
+use std::sync::{Arc, Mutex};
+
+use tokio_rustls::rustls::crypto::CryptoProvider;
+
+use crate::{SniCertificateResolver, TlsCertificates, TlsSettings};
+
+/// Swaps the certificates a running TLS listener serves. Returned from
+/// [`MyHttpServer::start`](crate::MyHttpServer::start) through
+/// [`HttpServerHandle::get_tls_certificates`](crate::HttpServerHandle::get_tls_certificates).
+///
+/// New certificates are used for handshakes that start after the swap; open connections —
+/// WebSockets and SignalR included — are not touched. A set that fails to load is never applied:
+/// the listener keeps serving the previous one.
+#[derive(Clone)]
+pub struct TlsCertificatesHandle {
+    resolver: Arc<SniCertificateResolver>,
+    provider: Arc<CryptoProvider>,
+    settings: Arc<Mutex<TlsSettings>>,
+}
+
+impl TlsCertificatesHandle {
+    pub fn new(
+        resolver: Arc<SniCertificateResolver>,
+        provider: Arc<CryptoProvider>,
+        settings: TlsSettings,
+    ) -> Self {
+        Self {
+            resolver,
+            provider,
+            settings: Arc::new(Mutex::new(settings)),
+        }
+    }
+
+    /// Re-reads the PEM files of the current settings — for a certificate renewed in place.
+    ///
+    /// The settings stay locked from the load to the swap, so a [`set_certificates`] racing it
+    /// can not be overwritten by the set it replaced.
+    ///
+    /// [`set_certificates`]: Self::set_certificates
+    pub fn reload(&self) -> Result<(), String> {
+        let settings = self.settings.lock().unwrap();
+        let certificates = TlsCertificates::load(&settings, self.provider.as_ref())?;
+        self.resolver.swap(certificates);
+        Ok(())
+    }
+
+    /// Replaces the whole certificate set — for certificates that moved or host names that changed.
+    pub fn set_certificates(&self, settings: TlsSettings) -> Result<(), String> {
+        let mut write_access = self.settings.lock().unwrap();
+
+        let certificates = TlsCertificates::load(&settings, self.provider.as_ref())?;
+        self.resolver.swap(certificates);
+        *write_access = settings;
+
+        Ok(())
+    }
+
+    pub fn get_settings(&self) -> TlsSettings {
+        self.settings.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tls::test_certificates::*;
+
+    #[test]
+    fn test_reload_serves_the_renewed_certificate() {
+        let mut certificate = TestCertificate::generate("reload", "server", &["server.test"]);
+        let (handle, resolver) = create_handle(&certificate);
+
+        certificate.regenerate(&["server.test"]);
+        handle.reload().unwrap();
+
+        assert!(certificate.is(&resolver.get_certificates().resolve(None)));
+
+        remove_test_dir("reload");
+    }
+
+    #[test]
+    fn test_failed_reload_keeps_the_previous_certificate() {
+        let certificate = TestCertificate::generate("bad-pem", "server", &["server.test"]);
+        let (handle, resolver) = create_handle(&certificate);
+
+        std::fs::write(&certificate.cert_chain_path, "not a certificate").unwrap();
+
+        let err = handle.reload().unwrap_err();
+        assert!(err.contains(&certificate.cert_chain_path), "{}", err);
+
+        assert!(certificate.is(&resolver.get_certificates().resolve(None)));
+
+        remove_test_dir("bad-pem");
+    }
+
+    #[test]
+    fn test_failed_set_certificates_keeps_the_settings() {
+        let certificate = TestCertificate::generate("bad-set", "server", &["server.test"]);
+        let (handle, resolver) = create_handle(&certificate);
+
+        let err = handle
+            .set_certificates(crate::TlsSettings::new().add_certificate(
+                "/missing.crt",
+                "/missing.key",
+                &[],
+            ))
+            .unwrap_err();
+        assert!(err.contains("/missing.crt"), "{}", err);
+
+        assert_eq!(
+            handle.get_settings().certificates[0].cert_chain_path,
+            certificate.cert_chain_path
+        );
+        assert!(certificate.is(&resolver.get_certificates().resolve(None)));
+
+        remove_test_dir("bad-set");
+    }
+}
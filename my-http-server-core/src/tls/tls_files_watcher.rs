@@ -0,0 +1,139 @@
+use std::{collections::HashMap, sync::Arc, time::SystemTime};
+
+use rust_extensions::{ApplicationStates, Logger};
+
+use crate::TlsCertificatesHandle;
+
+const PROCESS_NAME: &str = "TlsCertificatesWatcher";
+
+/// Polls the PEM files of the current certificate set and reloads it when any of them changes.
+///
+/// A change is applied only once the files have stopped changing between two polls, so a
+/// certificate and its key written one after the other are not picked up half-way.
+pub async fn watch_tls_files(
+    tls_certificates: TlsCertificatesHandle,
+    interval: std::time::Duration,
+    app_states: Arc<dyn ApplicationStates + Send + Sync + 'static>,
+    logger: Arc<dyn Logger + Send + Sync + 'static>,
+) {
+    let mut applied = get_files_modified(&tls_certificates);
+    let mut last_seen = applied.clone();
+
+    while !app_states.is_shutting_down() {
+        tokio::time::sleep(interval).await;
+
+        let now = get_files_modified(&tls_certificates);
+
+        if now == applied {
+            last_seen = now;
+            continue;
+        }
+
+        if now != last_seen {
+            last_seen = now;
+            continue;
+        }
+
+        applied = now;
+
+        match tls_certificates.reload() {
+            Ok(_) => {
+                logger.write_info(
+                    PROCESS_NAME.to_string(),
+                    "Certificate files changed. TLS certificates are reloaded".to_string(),
+                    None,
+                );
+            }
+            Err(err) => {
+                logger.write_error(
+                    PROCESS_NAME.to_string(),
+                    format!(
+                        "Certificate files changed but can not be loaded. Previous certificates are kept. Err: {}",
+                        err
+                    ),
+                    None,
+                );
+            }
+        }
+    }
+}
+
+fn get_files_modified(
+    tls_certificates: &TlsCertificatesHandle,
+) -> HashMap<String, Option<SystemTime>> {
+    let mut result = HashMap::new();
+
+    for certificate in tls_certificates.get_settings().certificates {
+        for path in [certificate.cert_chain_path, certificate.private_key_path] {
+            let modified = std::fs::metadata(path.as_str())
+                .and_then(|metadata| metadata.modified())
+                .ok();
+            result.insert(path, modified);
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicBool, Ordering},
+        time::Duration,
+    };
+
+    use super::*;
+    use crate::test_logger::NoLogger;
+    use crate::tls::test_certificates::*;
+
+    #[derive(Default)]
+    struct TestAppStates {
+        shutting_down: AtomicBool,
+    }
+
+    impl ApplicationStates for TestAppStates {
+        fn is_initialized(&self) -> bool {
+            true
+        }
+
+        fn is_shutting_down(&self) -> bool {
+            self.shutting_down.load(Ordering::SeqCst)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_changed_files_are_reloaded() {
+        let mut certificate = TestCertificate::generate("watch", "server", &["server.test"]);
+        let (handle, resolver) = create_handle(&certificate);
+
+        let app_states = Arc::new(TestAppStates::default());
+
+        let watcher = tokio::spawn(watch_tls_files(
+            handle,
+            Duration::from_millis(20),
+            app_states.clone(),
+            Arc::new(NoLogger),
+        ));
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        certificate.regenerate(&["server.test"]);
+
+        let mut reloaded = false;
+
+        for _ in 0..250 {
+            if certificate.is(&resolver.get_certificates().resolve(None)) {
+                reloaded = true;
+                break;
+            }
+
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        app_states.shutting_down.store(true, Ordering::SeqCst);
+        watcher.await.unwrap();
+
+        assert!(reloaded);
+
+        remove_test_dir("watch");
+    }
+}
@@ -0,0 +1,101 @@
+use std::sync::Arc;
+
+use tokio_rustls::rustls::crypto::CryptoProvider;
+
+use crate::{HttpProtocol, SniCertificateResolver, TlsCertificates, TlsCertificatesHandle};
+
+/// One certificate chain and its private key — both PEM files on disk — and the host names it is
+/// served for.
+#[derive(Debug, Clone)]
+pub struct TlsCertificate {
+    pub cert_chain_path: String,
+    pub private_key_path: String,
+    /// SNI host names this certificate answers for. `*.example.com` matches exactly one label.
+    pub server_names: Vec<String>,
+}
+
+/// Makes [`MyHttpServer`](crate::MyHttpServer) terminate TLS itself instead of relying on a
+/// terminator in front of it.
+///
+/// The certificate is picked by the SNI host name the client sends. The first certificate added
+/// is the fallback: it is served to a client that sends no SNI at all, or a name nothing matches.
+///
+/// Handshakes use the rustls crypto provider the application installed as the process default
+/// (`CryptoProvider::install_default`), or aws-lc-rs when none is installed.
+#[derive(Debug, Clone, Default)]
+pub struct TlsSettings {
+    pub certificates: Vec<TlsCertificate>,
+    /// When set, the PEM files are checked for changes this often and the certificates are
+    /// reloaded without restarting the server.
+    pub watch_files_interval: Option<std::time::Duration>,
+}
+
+impl TlsSettings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_certificate(
+        mut self,
+        cert_chain_path: impl Into<String>,
+        private_key_path: impl Into<String>,
+        server_names: &[&str],
+    ) -> Self {
+        self.certificates.push(TlsCertificate {
+            cert_chain_path: cert_chain_path.into(),
+            private_key_path: private_key_path.into(),
+            server_names: server_names.iter().map(|itm| itm.to_string()).collect(),
+        });
+        self
+    }
+
+    /// Reload the certificates whenever their PEM files change on disk — for certificates renewed
+    /// by an external tool. The outcome of every reload is written to the server's logger.
+    pub fn watch_files(mut self, interval: std::time::Duration) -> Self {
+        self.watch_files_interval = Some(interval);
+        self
+    }
+
+    /// Loads every certificate and builds the rustls config a listener accepts with. ALPN offers
+    /// only what `protocol` can serve, so a client never agrees on h2 with an h1-only listener.
+    ///
+    /// The returned handle swaps the certificates of that config while it is in use.
+    pub fn build_server_config(
+        &self,
+        protocol: HttpProtocol,
+    ) -> Result<
+        (
+            Arc<tokio_rustls::rustls::ServerConfig>,
+            TlsCertificatesHandle,
+        ),
+        String,
+    > {
+        let provider = get_crypto_provider();
+
+        let builder = tokio_rustls::rustls::ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(|err| format!("Can not use the TLS crypto provider. Err: {}", err))?
+            .with_no_client_auth();
+
+        let certificates = TlsCertificates::load(self, provider.as_ref())?;
+
+        let resolver = Arc::new(SniCertificateResolver::new(certificates));
+
+        let mut config = builder.with_cert_resolver(resolver.clone());
+
+        config.alpn_protocols = protocol.get_alpn_protocols();
+
+        let tls_certificates = TlsCertificatesHandle::new(resolver, provider, self.clone());
+
+        Ok((Arc::new(config), tls_certificates))
+    }
+}
+
+/// Picked explicitly: `ServerConfig::builder` panics when the dependency tree enables both the ring
+/// and the aws-lc-rs providers of rustls and the application installed neither as the default.
+fn get_crypto_provider() -> Arc<CryptoProvider> {
+    match CryptoProvider::get_default() {
+        Some(provider) => provider.clone(),
+        None => Arc::new(tokio_rustls::rustls::crypto::aws_lc_rs::default_provider()),
+    }
+}
@@ -0,0 +1,244 @@
+use std::{net::IpAddr, str::FromStr};
+
+use crate::SocketAddress;
+
+/// An IP range in CIDR notation: `10.0.0.0/8`, `fd00::/8`. A bare address is a range of one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpCidr {
+    pub addr: IpAddr,
+    pub prefix_len: u8,
+}
+
+impl IpCidr {
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.addr, to_canonical(ip)) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                prefix_matches(&network.octets(), &ip.octets(), self.prefix_len)
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                prefix_matches(&network.octets(), &ip.octets(), self.prefix_len)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpCidr {
+    type Err = String;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix_len) = match src.split_once('/') {
+            Some((addr, prefix_len)) => (addr, Some(prefix_len)),
+            None => (src, None),
+        };
+
+        let addr: IpAddr = addr
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid IP range '{}'", src))?;
+
+        let max_prefix_len = if addr.is_ipv4() { 32 } else { 128 };
+
+        let prefix_len = match prefix_len {
+            Some(prefix_len) => prefix_len
+                .trim()
+                .parse::<u8>()
+                .ok()
+                .filter(|itm| *itm <= max_prefix_len)
+                .ok_or_else(|| format!("Invalid prefix length in IP range '{}'", src))?,
+            None => max_prefix_len,
+        };
+
+        Ok(Self { addr, prefix_len })
+    }
+}
+
+fn prefix_matches(network: &[u8], ip: &[u8], prefix_len: u8) -> bool {
+    let full_bytes = (prefix_len / 8) as usize;
+
+    if network[..full_bytes] != ip[..full_bytes] {
+        return false;
+    }
+
+    let rest_bits = prefix_len % 8;
+
+    if rest_bits == 0 {
+        return true;
+    }
+
+    let mask = 0xFFu8 << (8 - rest_bits);
+
+    network[full_bytes] & mask == ip[full_bytes] & mask
+}
+
+/// An IPv4 client on a dual-stack socket shows up as `::ffff:a.b.c.d` — compare it as IPv4.
+fn to_canonical(ip: &IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => IpAddr::V4(v4),
+            None => *ip,
+        },
+        IpAddr::V4(_) => *ip,
+    }
+}
+
+/// Which forwarding headers the trusted proxies write. Only those are read: the others come from
+/// the client as they are, since a proxy passes through whatever it does not write itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ForwardingHeaders {
+    /// `X-Forwarded-For`, `X-Forwarded-Proto` and `X-Forwarded-Host` — what nginx, HAProxy and the
+    /// cloud load balancers append to.
+    #[default]
+    XForwarded,
+    /// `Forwarded` of RFC 7239.
+    Forwarded,
+}
+
+/// The reverse proxies a server takes forwarding headers from. Set through
+/// [`MyHttpServer::set_trusted_proxies`](crate::MyHttpServer::set_trusted_proxies).
+///
+/// The headers — the `X-Forwarded-*` family, or `Forwarded` when the proxies write that instead —
+/// are only read when the connection comes from one of these ranges, or over a Unix socket once
+/// [`trust_unix_peers`](Self::trust_unix_peers) is set. The client is then the first hop, walking
+/// the chain from the right, that is not a trusted proxy itself: whatever a client wrote into the
+/// headers before it is ignored.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies {
+    ranges: Vec<IpCidr>,
+    forwarding_headers: ForwardingHeaders,
+    trust_unix_peers: bool,
+}
+
+impl TrustedProxies {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a range in CIDR notation. Panics on a range that does not parse — the same way a
+    /// listener that can not bind stops the start of the server.
+    pub fn add_cidr(mut self, cidr: &str) -> Self {
+        match cidr.parse() {
+            Ok(range) => self.ranges.push(range),
+            Err(err) => panic!("Invalid trusted proxy range. Err: {}", err),
+        }
+        self
+    }
+
+    pub fn add_range(mut self, range: IpCidr) -> Self {
+        self.ranges.push(range);
+        self
+    }
+
+    pub fn get_ranges(&self) -> &[IpCidr] {
+        &self.ranges
+    }
+
+    /// The headers the proxies write — [`ForwardingHeaders::XForwarded`] unless set.
+    pub fn with_forwarding_headers(mut self, forwarding_headers: ForwardingHeaders) -> Self {
+        self.forwarding_headers = forwarding_headers;
+        self
+    }
+
+    pub fn get_forwarding_headers(&self) -> ForwardingHeaders {
+        self.forwarding_headers
+    }
+
+    /// Treats every peer of a Unix socket listener as a trusted proxy — for a proxy on the same
+    /// host, when the socket's mode and owner keep everybody else out. Off by default.
+    pub fn trust_unix_peers(mut self) -> Self {
+        self.trust_unix_peers = true;
+        self
+    }
+
+    pub fn is_trusted_ip(&self, ip: &IpAddr) -> bool {
+        self.ranges.iter().any(|range| range.contains(ip))
+    }
+
+    /// A hop of a forwarding header. `unknown` and obfuscated identifiers are never trusted.
+    pub fn is_trusted_hop(&self, hop: &str) -> bool {
+        match hop.parse::<IpAddr>() {
+            Ok(ip) => self.is_trusted_ip(&ip),
+            Err(_) => false,
+        }
+    }
+
+    pub fn is_trusted_peer(&self, addr: &SocketAddress) -> bool {
+        match addr {
+            SocketAddress::Tcp(socket_addr) => self.is_trusted_ip(&socket_addr.ip()),
+            SocketAddress::Unix(_) => self.trust_unix_peers,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(src: &str) -> IpAddr {
+        src.parse().unwrap()
+    }
+
+    #[test]
+    fn v4_range() {
+        let range: IpCidr = "10.1.0.0/16".parse().unwrap();
+
+        assert!(range.contains(&ip("10.1.255.3")));
+        assert!(!range.contains(&ip("10.2.0.1")));
+    }
+
+    #[test]
+    fn range_with_partial_byte_prefix() {
+        let range: IpCidr = "192.168.0.0/20".parse().unwrap();
+
+        assert!(range.contains(&ip("192.168.15.255")));
+        assert!(!range.contains(&ip("192.168.16.0")));
+    }
+
+    #[test]
+    fn v6_range() {
+        let range: IpCidr = "fd00::/8".parse().unwrap();
+
+        assert!(range.contains(&ip("fd12:3456::1")));
+        assert!(!range.contains(&ip("fe80::1")));
+        assert!(!range.contains(&ip("10.0.0.1")));
+    }
+
+    #[test]
+    fn bare_address_is_a_single_ip() {
+        let range: IpCidr = "10.0.0.1".parse().unwrap();
+
+        assert!(range.contains(&ip("10.0.0.1")));
+        assert!(!range.contains(&ip("10.0.0.2")));
+    }
+
+    #[test]
+    fn v4_mapped_v6_address_matches_v4_range() {
+        let range: IpCidr = "10.0.0.0/8".parse().unwrap();
+
+        assert!(range.contains(&ip("::ffff:10.1.2.3")));
+    }
+
+    #[test]
+    fn zero_prefix_matches_everything_of_its_family() {
+        let range: IpCidr = "0.0.0.0/0".parse().unwrap();
+
+        assert!(range.contains(&ip("8.8.8.8")));
+    }
+
+    #[test]
+    fn unix_peers_are_trusted_only_when_asked() {
+        let addr = SocketAddress::Unix(std::sync::Arc::new("/run/app.sock".to_string()));
+
+        assert!(!TrustedProxies::new().is_trusted_peer(&addr));
+        assert!(TrustedProxies::new()
+            .trust_unix_peers()
+            .is_trusted_peer(&addr));
+    }
+
+    #[test]
+    fn invalid_ranges_are_rejected() {
+        assert!("10.0.0.0/33".parse::<IpCidr>().is_err());
+        assert!("10.0.0/8".parse::<IpCidr>().is_err());
+        assert!("proxy.local".parse::<IpCidr>().is_err());
+    }
+}
@@ -0,0 +1,298 @@
+/// How a Unix socket listener sets up its socket file. Set through
+/// [`MyHttpServer::set_unix_socket_options`](crate::MyHttpServer::set_unix_socket_options) or
+/// [`HttpListener::with_unix_socket_options`](crate::HttpListener::with_unix_socket_options);
+/// ignored by TCP listeners.
+///
+/// An address that starts with `@` is bound in the Linux abstract namespace (`@my-service` is the
+/// abstract name `my-service`). Such a socket has no file, so none of the file options apply to it.
+#[derive(Debug, Clone)]
+pub struct UnixSocketOptions {
+    /// Mode the socket file is given once bound, e.g. `0o660` to let a group connect.
+    pub permissions: Option<u32>,
+    pub owner_uid: Option<u32>,
+    pub owner_gid: Option<u32>,
+    /// A socket file left behind by a process that is gone makes the bind fail. With this set the
+    /// file is removed first — but only if it is a socket nothing accepts connections on, so a
+    /// second instance can not steal the socket of one that is still running, and a mistyped path
+    /// can not delete a regular file.
+    pub remove_stale_file: bool,
+}
+
+impl Default for UnixSocketOptions {
+    fn default() -> Self {
+        Self {
+            permissions: None,
+            owner_uid: None,
+            owner_gid: None,
+            remove_stale_file: true,
+        }
+    }
+}
+
+impl UnixSocketOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_permissions(mut self, mode: u32) -> Self {
+        self.permissions = Some(mode);
+        self
+    }
+
+    /// Changes the owner of the socket file. `None` leaves that id as is.
+    pub fn with_owner(mut self, uid: Option<u32>, gid: Option<u32>) -> Self {
+        self.owner_uid = uid;
+        self.owner_gid = gid;
+        self
+    }
+
+    pub fn with_remove_stale_file(mut self, remove_stale_file: bool) -> Self {
+        self.remove_stale_file = remove_stale_file;
+        self
+    }
+}
+
+#[cfg(unix)]
+pub(crate) fn bind_unix_listener(
+    unix_socket_addr: &str,
+    options: &UnixSocketOptions,
+) -> std::io::Result<tokio::net::UnixListener> {
+    if let Some(abstract_name) = unix_socket_addr.strip_prefix('@') {
+        return bind_abstract(abstract_name);
+    }
+
+    if options.remove_stale_file {
+        remove_stale_file(unix_socket_addr)?;
+    }
+
+    if options.permissions.is_none() && options.owner_uid.is_none() && options.owner_gid.is_none() {
+        return tokio::net::UnixListener::bind(unix_socket_addr);
+    }
+
+    bind_with_access(std::path::Path::new(unix_socket_addr), options)
+}
+
+/// A bound socket accepts connections at once, with the mode the umask gives it. So it is bound in
+/// a directory only this process can enter, given its mode and owner there, and only then linked
+/// to its path — which fails if the path is taken, as `bind` would.
+#[cfg(unix)]
+fn bind_with_access(
+    path: &std::path::Path,
+    options: &UnixSocketOptions,
+) -> std::io::Result<tokio::net::UnixListener> {
+    use std::os::unix::fs::DirBuilderExt;
+
+    static BIND_ID: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+    let bind_id = BIND_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    let private_dir = path.with_file_name(format!(".{}-{}.tmp", std::process::id(), bind_id));
+
+    std::fs::DirBuilder::new()
+        .mode(0o700)
+        .create(&private_dir)?;
+
+    let result = bind_in_private_dir(&private_dir, path, options);
+
+    let _ = std::fs::remove_dir_all(&private_dir);
+
+    result
+}
+
+#[cfg(unix)]
+fn bind_in_private_dir(
+    private_dir: &std::path::Path,
+    path: &std::path::Path,
+    options: &UnixSocketOptions,
+) -> std::io::Result<tokio::net::UnixListener> {
+    let private_path = private_dir.join("socket");
+
+    let listener = tokio::net::UnixListener::bind(&private_path)?;
+
+    if let Some(mode) = options.permissions {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&private_path, std::fs::Permissions::from_mode(mode))?;
+    }
+
+    if options.owner_uid.is_some() || options.owner_gid.is_some() {
+        std::os::unix::fs::chown(&private_path, options.owner_uid, options.owner_gid)?;
+    }
+
+    std::fs::hard_link(&private_path, path).map_err(|err| {
+        if err.kind() == std::io::ErrorKind::AlreadyExists {
+            std::io::Error::from(std::io::ErrorKind::AddrInUse)
+        } else {
+            err
+        }
+    })?;
+
+    Ok(listener)
+}
+
+#[cfg(unix)]
+fn remove_stale_file(unix_socket_addr: &str) -> std::io::Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+
+    let metadata = match std::fs::symlink_metadata(unix_socket_addr) {
+        Ok(metadata) => metadata,
+        Err(_) => return Ok(()),
+    };
+
+    // Not a socket — nothing of ours to clean up; the bind fails on it and says so.
+    if !metadata.file_type().is_socket() {
+        return Ok(());
+    }
+
+    // Somebody is still listening — leave the file to them and let the bind report it.
+    if std::os::unix::net::UnixStream::connect(unix_socket_addr).is_ok() {
+        return Ok(());
+    }
+
+    std::fs::remove_file(unix_socket_addr)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn bind_abstract(abstract_name: &str) -> std::io::Result<tokio::net::UnixListener> {
+    #[cfg(target_os = "android")]
+    use std::os::android::net::SocketAddrExt;
+    #[cfg(target_os = "linux")]
+    use std::os::linux::net::SocketAddrExt;
+
+    let addr = std::os::unix::net::SocketAddr::from_abstract_name(abstract_name.as_bytes())?;
+    let listener = std::os::unix::net::UnixListener::bind_addr(&addr)?;
+    listener.set_nonblocking(true)?;
+
+    tokio::net::UnixListener::from_std(listener)
+}
+
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+fn bind_abstract(_abstract_name: &str) -> std::io::Result<tokio::net::UnixListener> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "Abstract namespace Unix sockets are supported on Linux only",
+    ))
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    fn socket_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("{}-{}.sock", name, std::process::id()))
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn stale_socket_file_is_removed() {
+        let path = socket_path("stale");
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+
+        let listener = bind_unix_listener(&path, &UnixSocketOptions::new());
+        assert!(listener.is_ok());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn socket_in_use_is_not_taken_over() {
+        let path = socket_path("in-use");
+        let _ = std::fs::remove_file(&path);
+
+        let _first = bind_unix_listener(&path, &UnixSocketOptions::new()).unwrap();
+        let second = bind_unix_listener(&path, &UnixSocketOptions::new());
+
+        assert_eq!(second.err().unwrap().kind(), std::io::ErrorKind::AddrInUse);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn stale_file_is_kept_when_asked() {
+        let path = socket_path("kept");
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+
+        let options = UnixSocketOptions::new().with_remove_stale_file(false);
+        assert!(bind_unix_listener(&path, &options).is_err());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn regular_file_is_not_removed() {
+        let path = socket_path("regular");
+        std::fs::write(&path, b"data").unwrap();
+
+        assert!(bind_unix_listener(&path, &UnixSocketOptions::new()).is_err());
+        assert_eq!(std::fs::read(&path).unwrap(), b"data");
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn permissions_are_applied() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = socket_path("permissions");
+
+        let options = UnixSocketOptions::new().with_permissions(0o660);
+        let _listener = bind_unix_listener(&path, &options).unwrap();
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o660);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn socket_in_use_is_not_taken_over_with_permissions() {
+        let path = socket_path("in-use-permissions");
+        let _ = std::fs::remove_file(&path);
+
+        let options = UnixSocketOptions::new().with_permissions(0o660);
+
+        let _first = bind_unix_listener(&path, &options).unwrap();
+        let second = bind_unix_listener(&path, &options);
+
+        assert_eq!(second.err().unwrap().kind(), std::io::ErrorKind::AddrInUse);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn socket_with_permissions_accepts_connections_at_its_path() {
+        let path = socket_path("linked");
+
+        let options = UnixSocketOptions::new().with_permissions(0o600);
+        let listener = bind_unix_listener(&path, &options).unwrap();
+
+        let (accepted, connected) =
+            tokio::join!(listener.accept(), tokio::net::UnixStream::connect(&path));
+        assert!(accepted.is_ok());
+        assert!(connected.is_ok());
+
+        let dir = std::path::Path::new(&path).parent().unwrap().to_path_buf();
+        let private_dir_prefix = format!(".{}-", std::process::id());
+        let left_behind = std::fs::read_dir(dir)
+            .unwrap()
+            .filter_map(|itm| itm.ok())
+            .any(|itm| {
+                let name = itm.file_name().to_string_lossy().to_string();
+                name.starts_with(private_dir_prefix.as_str()) && name.ends_with(".tmp")
+            });
+        assert!(!left_behind);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn abstract_name_has_no_file() {
+        let name = format!("@my-http-server-test-{}", std::process::id());
+
+        let _listener = bind_unix_listener(&name, &UnixSocketOptions::new()).unwrap();
+
+        assert!(std::fs::symlink_metadata(&name).is_err());
+        assert!(std::fs::symlink_metadata(&name[1..]).is_err());
+    }
+}
@@ -71,8 +71,13 @@ pub struct ActionParameters<'s>{
     pub controller: Option<&'s str>,
     #[allow_ident]
     pub input_data: Option<&'s str>,
+    #[allow_ident]
+    pub extension: Option<&'s str>,
     pub authorized: Option<ShouldBeAuthorized>,
     pub result: Option<Vec<HttpActionResult<'s>>>,
+    pub request_timeout_ms: Option<u64>,
+    pub max_body_size: Option<u64>,
+    pub compress_response: Option<bool>,
 }
 
 
@@ -199,7 +204,10 @@ pub fn build_action(attr: TokenStream, input: TokenStream) -> Result<TokenStream
 
     let http_fail_result = crate::consts::get_http_fail_result();
 
-    let handle_request = super::generate_handle_request_fn(action_parameters.input_data);
+    let handle_request = super::generate_handle_request_fn(
+        action_parameters.input_data,
+        action_parameters.extension,
+    );
 
     let model_routes: proc_macro2::TokenStream = if let Some(input_data) = &action_parameters.input_data{
         let input_data = proc_macro2::TokenStream::from_str(input_data).unwrap();
@@ -221,6 +229,36 @@ pub fn build_action(attr: TokenStream, input: TokenStream) -> Result<TokenStream
         quote::quote!(None)
     };
 
+    let request_timeout = if let Some(request_timeout_ms) = action_parameters.request_timeout_ms{
+        quote::quote!{
+            fn get_request_timeout(&self) -> Option<std::time::Duration>{
+                Some(std::time::Duration::from_millis(#request_timeout_ms))
+            }
+        }
+    }else{
+        quote::quote!()
+    };
+
+    let max_body_size = if let Some(max_body_size) = action_parameters.max_body_size{
+        quote::quote!{
+            fn get_max_body_size(&self) -> Option<u64>{
+                Some(#max_body_size)
+            }
+        }
+    }else{
+        quote::quote!()
+    };
+
+    let compress_response = if let Some(compress_response) = action_parameters.compress_response{
+        quote::quote!{
+            fn get_compress_response(&self) -> Option<bool>{
+                Some(#compress_response)
+            }
+        }
+    }else{
+        quote::quote!()
+    };
+
     let result = quote::quote! {
         #[derive(Clone)]
         #ast
@@ -257,6 +295,12 @@ pub fn build_action(attr: TokenStream, input: TokenStream) -> Result<TokenStream
                 
                 #handle_request
             }
+
+            #request_timeout
+
+            #max_body_size
+
+            #compress_response
         }
   
     }
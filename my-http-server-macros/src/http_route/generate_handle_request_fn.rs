@@ -2,7 +2,23 @@ use std::str::FromStr;
 
 use proc_macro2::TokenStream;
 
-pub fn generate_handle_request_fn(input_data: Option<&str>) -> TokenStream {
+pub fn generate_handle_request_fn(
+    input_data: Option<&str>,
+    extension: Option<&str>,
+) -> TokenStream {
+    // `extension: T` hands the action a copy of the value a middleware put into `ctx.extensions`,
+    // so the action owns it while still having `ctx` mutably — and the value stays there for an
+    // around middleware that runs the chain again.
+    let (take_extension, extension_arg) = if let Some(extension) = extension {
+        let extension = TokenStream::from_str(extension).unwrap();
+        (
+            quote::quote!(let __extension = ctx.get_required_extension::<#extension>()?;),
+            quote::quote!(__extension,),
+        )
+    } else {
+        (quote::quote!(), quote::quote!())
+    };
+
     if let Some(input_data) = input_data {
         let input_data = TokenStream::from_str(input_data).unwrap();
         quote::quote! {
@@ -11,6 +27,8 @@ pub fn generate_handle_request_fn(input_data: Option<&str>) -> TokenStream {
             // the model's own consts say which: materialized whole (READS_BODY) or taken as a
             // stream of chunks (STREAMS_BODY). Never both.
 
+            #take_extension
+
             // Taken first: it needs `&mut ctx.request`, and the reader below borrows it shared.
             let __body_stream = if #input_data::STREAMS_BODY {
                 Some(ctx.request.take_body_stream()?)
@@ -38,9 +56,12 @@ pub fn generate_handle_request_fn(input_data: Option<&str>) -> TokenStream {
                 #input_data::parse(&__reader)?
             };
 
-            handle_request(self, input_data, ctx).await
+            handle_request(self, input_data, #extension_arg ctx).await
         }
     } else {
-        quote::quote!(handle_request(self, ctx).await)
+        quote::quote! {
+            #take_extension
+            handle_request(self, #extension_arg ctx).await
+        }
     }
 }
@@ -58,6 +58,9 @@ impl<
                         "payload".to_string(),
                         String::from_utf8_lossy(data).to_string(),
                     );
+                    if let Some(request_id) = connection.get_request_id().await {
+                        ctx.insert("request_id".to_string(), request_id);
+                    }
                     self.logger.write_fatal_error(
                         "SignalR payload handler".to_string(),
                         format!("Can read parameters payloads. Err: {:?}", err),
@@ -86,6 +89,9 @@ impl<
                     "payload".to_string(),
                     String::from_utf8_lossy(data).to_string(),
                 );
+                if let Some(request_id) = connection.get_request_id().await {
+                    ctx.insert("request_id".to_string(), request_id);
+                }
                 self.logger.write_fatal_error(
                     "SignalR payload handler".to_string(),
                     format!("Can not deserialize payload. Err: {}", err),
@@ -84,6 +84,12 @@ impl<TCtx: Send + Sync + Default + 'static> MySignalRConnection<TCtx> {
         read_access.web_socket.clone()
     }
 
+    /// The id of the request the current web socket of the connection was upgraded from.
+    pub async fn get_request_id(&self) -> Option<String> {
+        let read_access = self.single_threaded.lock().await;
+        read_access.web_socket.as_ref()?.request_id.clone()
+    }
+
     pub fn update_incoming_activity(&self) {
         let now = DateTimeAsMicroseconds::now();
         self.last_incoming_moment.update(now);
@@ -1,5 +1,6 @@
 use std::{collections::HashMap, sync::Arc};
 
+use my_http_server_core::HttpMetricsGauge;
 use my_http_server_web_sockets::MyWebSocket;
 use rust_extensions::lazy::LazyVec;
 use tokio::sync::RwLock;
@@ -14,6 +15,7 @@ struct SignalRListInner<TCtx: Send + Sync + 'static> {
 
 pub struct SignalRConnectionsList<TCtx: Send + Sync + Default + 'static> {
     sockets: RwLock<SignalRListInner<TCtx>>,
+    connections: HttpMetricsGauge,
 }
 
 impl<TCtx: Send + Sync + Default + 'static> SignalRConnectionsList<TCtx> {
@@ -24,20 +26,31 @@ impl<TCtx: Send + Sync + Default + 'static> SignalRConnectionsList<TCtx> {
                 sockets_by_connection_token: HashMap::new(),
                 tags: crate::Tags::new(),
             }),
+            connections: HttpMetricsGauge::new(),
         }
     }
 
+    /// The SignalR connections in the list — for
+    /// [`HttpServerMetrics::add_connections_gauge`](my_http_server_core::HttpServerMetrics::add_connections_gauge).
+    pub fn get_connections_gauge(&self) -> HttpMetricsGauge {
+        self.connections.clone()
+    }
+
     pub async fn add_signal_r_connection(
         &self,
         signal_r_connection: Arc<MySignalRConnection<TCtx>>,
     ) {
         let web_socket = signal_r_connection.get_web_socket().await;
         let mut write_access = self.sockets.write().await;
-        write_access.sockets_by_connection_token.insert(
+        let replaced = write_access.sockets_by_connection_token.insert(
             signal_r_connection.get_list_index().to_string(),
             signal_r_connection.clone(),
         );
 
+        if replaced.is_none() {
+            self.connections.inc();
+        }
+
         if let Some(web_socket) = web_socket {
             write_access
                 .sockets_by_web_socket_id
@@ -157,6 +170,8 @@ impl<TCtx: Send + Sync + Default + 'static> SignalRConnectionsList<TCtx> {
                 .remove(connection_token);
 
             if let Some(removed) = &removed {
+                self.connections.dec();
+
                 write_access
                     .tags
                     .remove_connection(removed.connection_id.as_ref_of_string());
@@ -179,27 +179,36 @@ impl<TCtx: Send + Sync + Default + 'static> my_http_server_web_sockets::MyWebSoc
                         match _result {
                             Ok(my_telemetry) => {
                                 if my_telemetry.get_write_telemetry() {
+                                    let mut tags =
+                                        my_telemetry.tags.add_ip(my_web_socket.addr.ip_as_string());
+
+                                    if let Some(request_id) = my_web_socket.request_id.as_ref() {
+                                        tags = tags.add("request_id", request_id.to_string());
+                                    }
+
                                     my_telemetry::TELEMETRY_INTERFACE.write_success(
                                         &ctx,
                                         started,
                                         format!("[SignalR] {}", target),
                                         format!("Executed Ok",),
-                                        my_telemetry
-                                            .tags
-                                            .add_ip(my_web_socket.addr.ip_as_string())
-                                            .build(),
+                                        tags.build(),
                                     );
                                 }
                             }
                             Err(err) => {
+                                let mut tags = TelemetryEventTagsBuilder::new()
+                                    .add_ip(my_web_socket.addr.ip_as_string());
+
+                                if let Some(request_id) = my_web_socket.request_id.as_ref() {
+                                    tags = tags.add("request_id", request_id.to_string());
+                                }
+
                                 my_telemetry::TELEMETRY_INTERFACE.write_fail(
                                     &ctx,
                                     started,
                                     target,
                                     format!("{:?}", err),
-                                    TelemetryEventTagsBuilder::new()
-                                        .add_ip(my_web_socket.addr.ip_as_string())
-                                        .build(),
+                                    tags.build(),
                                 );
                             }
                         }
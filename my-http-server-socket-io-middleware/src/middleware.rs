@@ -55,6 +55,12 @@ impl MySocketIoEngineMiddleware {
         self.registered_sockets.add(socket_io).await;
     }
 
+    /// The Socket.IO connections open right now — for
+    /// [`HttpServerMetrics::add_connections_gauge`].
+    pub fn get_connections_gauge(&self) -> HttpMetricsGauge {
+        self.socket_io_list.get_connections_gauge()
+    }
+
     async fn get_socket_id(&self) -> i64 {
         let mut socket_no = self.socket_id.lock().await;
         *socket_no += 1;
@@ -1,5 +1,6 @@
 use std::{collections::HashMap, sync::Arc};
 
+use my_http_server_core::HttpMetricsGauge;
 use my_http_server_web_sockets::MyWebSocket;
 use tokio::sync::RwLock;
 
@@ -12,6 +13,7 @@ struct SocketIdListInner {
 
 pub struct SocketIoList {
     sockets: RwLock<SocketIdListInner>,
+    connections: HttpMetricsGauge,
 }
 
 impl SocketIoList {
@@ -21,17 +23,28 @@ impl SocketIoList {
                 sockets_by_web_socket_id: HashMap::new(),
                 sockets_by_my_socket_io_id: HashMap::new(),
             }),
+            connections: HttpMetricsGauge::new(),
         }
     }
 
+    /// The Socket.IO connections in the list — for
+    /// [`HttpServerMetrics::add_connections_gauge`](my_http_server_core::HttpServerMetrics::add_connections_gauge).
+    pub fn get_connections_gauge(&self) -> HttpMetricsGauge {
+        self.connections.clone()
+    }
+
     pub async fn add_socket_io(&self, socket_io_connection: Arc<MySocketIoConnection>) {
         let web_socket = socket_io_connection.get_web_socket().await;
         let mut write_access = self.sockets.write().await;
-        write_access.sockets_by_my_socket_io_id.insert(
+        let replaced = write_access.sockets_by_my_socket_io_id.insert(
             socket_io_connection.id.clone(),
             socket_io_connection.clone(),
         );
 
+        if replaced.is_none() {
+            self.connections.inc();
+        }
+
         if let Some(web_socket) = web_socket {
             write_access
                 .sockets_by_web_socket_id
@@ -98,6 +111,8 @@ impl SocketIoList {
         };
 
         if let Some(removed_socket_io) = &removed_socket_io {
+            self.connections.dec();
+
             let web_socket = removed_socket_io.disconnect().await;
             if let Some(web_socket) = web_socket {
                 let mut write_access = self.sockets.write().await;
@@ -22,12 +22,14 @@ pub async fn handle_web_socket_upgrade<
     };
 
     let addr = req.addr.clone();
+    let trusted_proxies = req.get_trusted_proxies().cloned();
 
-    let req = req.take_my_hyper_http_request();
+    let req = req.take_my_hyper_http_request()?;
 
     let upgrade_result = crate::web_sockets_upgrade::upgrade(
         id,
         addr,
+        trusted_proxies,
         query_string,
         req,
         callback.clone(),
@@ -19,6 +19,9 @@ pub struct MyWebSocket {
     pub write_stream: Mutex<Option<SplitSink<HyperWebsocketStream, Message>>>,
     pub addr: SocketAddress,
     pub id: i64,
+    /// The id of the request the web socket was upgraded from — see
+    /// [`HttpContext::get_request_id`](my_http_server_core::HttpContext::get_request_id).
+    pub request_id: Option<String>,
     callbacks: Arc<dyn MyWebSocketCallback + Send + Sync + 'static>,
     query_string: Option<String>,
     connected: AtomicBool,
@@ -30,6 +33,7 @@ impl MyWebSocket {
     pub fn new(
         id: i64,
         addr: SocketAddress,
+        request_id: Option<String>,
         write_stream: SplitSink<HyperWebsocketStream, Message>,
         query_string: Option<String>,
         callbacks: Arc<dyn MyWebSocketCallback + Send + Sync + 'static>,
@@ -39,6 +43,7 @@ impl MyWebSocket {
             write_stream: Mutex::new(write_stream.into()),
             addr,
             id,
+            request_id,
             query_string,
             connected: AtomicBool::new(true),
             callbacks,
@@ -74,6 +79,9 @@ impl MyWebSocket {
             ctx.insert("Ip".to_string(), self.addr.to_string());
             ctx.insert("Id".to_string(), self.id.to_string());
             ctx.insert("ConnectedAt".to_string(), self.connected_at.to_rfc3339());
+            if let Some(request_id) = self.request_id.as_ref() {
+                ctx.insert("RequestId".to_string(), request_id.to_string());
+            }
             self.logs.write_warning(
                 "Error sending message to websocket".to_string(),
                 format!("{err}"),
@@ -95,6 +103,9 @@ impl MyWebSocket {
                 ctx.insert("Ip".to_string(), self.addr.to_string());
                 ctx.insert("Id".to_string(), self.id.to_string());
                 ctx.insert("ConnectedAt".to_string(), self.connected_at.to_rfc3339());
+                if let Some(request_id) = self.request_id.as_ref() {
+                    ctx.insert("RequestId".to_string(), request_id.to_string());
+                }
                 self.logs.write_warning(
                     "WebSocket parsing query string".to_string(),
                     format!("Invalid query string {}", str.to_string()),
@@ -1,6 +1,10 @@
+use std::sync::Arc;
+
 use hyper::{header::*, *};
 use hyper_tungstenite::tungstenite::http::Extensions;
-use my_http_server_core::{CookiesReader, MyHyperHttpRequest, RequestIp, SocketAddress};
+use my_http_server_core::{
+    CookiesReader, MyHyperHttpRequest, RequestIp, SocketAddress, TrustedProxies,
+};
 
 pub struct MyWebSocketHttpRequest {
     uri: Uri,
@@ -8,16 +12,24 @@ pub struct MyWebSocketHttpRequest {
     version: Version,
     extensions: Extensions,
     addr: SocketAddress,
+    trusted_proxies: Option<Arc<TrustedProxies>>,
 }
 
 impl<'s> MyWebSocketHttpRequest {
-    pub fn new(req: &MyHyperHttpRequest, addr: SocketAddress) -> Self {
+    /// `trusted_proxies` are the server's — see
+    /// [`HttpRequest::get_trusted_proxies`](my_http_server_core::HttpRequest::get_trusted_proxies).
+    pub fn new(
+        req: &MyHyperHttpRequest,
+        addr: SocketAddress,
+        trusted_proxies: Option<Arc<TrustedProxies>>,
+    ) -> Self {
         Self {
             uri: req.uri().clone(),
             headers: req.headers().clone(),
             version: req.version(),
             extensions: req.extensions().clone(),
             addr,
+            trusted_proxies,
         }
     }
 
@@ -38,7 +50,12 @@ impl<'s> MyWebSocketHttpRequest {
     }
 
     pub fn get_ip(&'s self) -> RequestIp<'s> {
-        RequestIp::new(&self.addr, self.get_headers())
+        match self.trusted_proxies.as_ref() {
+            Some(trusted_proxies) => {
+                RequestIp::new_with_trusted_proxies(&self.addr, self.get_headers(), trusted_proxies)
+            }
+            None => RequestIp::new(&self.addr, self.get_headers()),
+        }
     }
 
     pub fn get_cookies(&'s self) -> CookiesReader<'s> {
@@ -1,19 +1,23 @@
 use std::{
+    collections::HashSet,
     sync::{atomic::AtomicI64, Arc},
     time::Duration,
 };
 
 use my_http_server_core::{
-    HttpContext, HttpFailResult, HttpOkResult, HttpRequestHeaders, HttpServerMiddleware,
+    HttpContext, HttpFailResult, HttpMetricsGauge, HttpOkResult, HttpRequestHeaders,
+    HttpServerMiddleware,
 };
 use rust_extensions::{Logger, StrOrString};
 
-use crate::MyWebSocketCallback;
+use crate::{
+    MyWebSocket, MyWebSocketCallback, MyWebSocketHttpRequest, WebSocketConnectedFail, WsMessage,
+};
 
 pub struct MyWebsocketMiddleware<TMyWebSocketCallback: MyWebSocketCallback + Send + Sync + 'static>
 {
     path: StrOrString<'static>,
-    callbacks: Arc<TMyWebSocketCallback>,
+    callbacks: Arc<CountedWebSocketCallback<TMyWebSocketCallback>>,
     socket_id: AtomicI64,
     disconnect_timeout: Duration,
     logger: Arc<dyn Logger + Send + Sync + 'static>,
@@ -29,13 +33,23 @@ impl<TMyWebSocketCallback: MyWebSocketCallback + Send + Sync + 'static>
     ) -> Self {
         Self {
             path: path.into(),
-            callbacks,
+            callbacks: Arc::new(CountedWebSocketCallback {
+                inner: callbacks,
+                connected: std::sync::Mutex::new(HashSet::new()),
+                connections: HttpMetricsGauge::new(),
+            }),
             socket_id: AtomicI64::new(0),
             disconnect_timeout: Duration::from_secs(60),
             logger,
         }
     }
 
+    /// The web sockets connected right now — for
+    /// [`HttpServerMetrics::add_connections_gauge`](my_http_server_core::HttpServerMetrics::add_connections_gauge).
+    pub fn get_connections_gauge(&self) -> HttpMetricsGauge {
+        self.callbacks.connections.clone()
+    }
+
     fn get_socket_id(&self) -> i64 {
         self.socket_id
             .fetch_add(1, std::sync::atomic::Ordering::Relaxed)
@@ -83,3 +97,60 @@ impl<TMyWebSocketCallback: MyWebSocketCallback + Send + Sync + 'static> HttpServ
         Some(result)
     }
 }
+
+/// Counts the web sockets the callbacks were told are connected and not yet disconnected. A web
+/// socket `connected` refused is never told it disconnected, and one may get disconnected while
+/// `connected` still runs — hence the ids, not only the number.
+struct CountedWebSocketCallback<TMyWebSocketCallback: MyWebSocketCallback + Send + Sync + 'static> {
+    inner: Arc<TMyWebSocketCallback>,
+    connected: std::sync::Mutex<HashSet<i64>>,
+    connections: HttpMetricsGauge,
+}
+
+impl<TMyWebSocketCallback: MyWebSocketCallback + Send + Sync + 'static>
+    CountedWebSocketCallback<TMyWebSocketCallback>
+{
+    fn remove(&self, id: i64) {
+        if self.connected.lock().unwrap().remove(&id) {
+            self.connections.dec();
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<TMyWebSocketCallback: MyWebSocketCallback + Send + Sync + 'static> MyWebSocketCallback
+    for CountedWebSocketCallback<TMyWebSocketCallback>
+{
+    async fn connected(
+        &self,
+        my_web_socket: Arc<MyWebSocket>,
+        http_request: MyWebSocketHttpRequest,
+        disconnect_timeout: Duration,
+    ) -> Result<(), WebSocketConnectedFail> {
+        let id = my_web_socket.id;
+
+        if self.connected.lock().unwrap().insert(id) {
+            self.connections.inc();
+        }
+
+        let result = self
+            .inner
+            .connected(my_web_socket, http_request, disconnect_timeout)
+            .await;
+
+        if result.is_err() {
+            self.remove(id);
+        }
+
+        result
+    }
+
+    async fn disconnected(&self, my_web_socket: &MyWebSocket) {
+        self.remove(my_web_socket.id);
+        self.inner.disconnected(my_web_socket).await;
+    }
+
+    async fn on_message(&self, my_web_socket: Arc<MyWebSocket>, message: WsMessage) {
+        self.inner.on_message(my_web_socket, message).await;
+    }
+}
@@ -21,7 +21,9 @@ type Error = Box<dyn std::error::Error + Send + Sync + 'static>;
 type WsStreamFuture =
     Pin<Box<dyn std::future::Future<Output = Result<HyperWebsocketStream, Error>> + Send>>;
 
-use my_http_server_core::{my_hyper_utils::*, MyHyperHttpRequest, SocketAddress};
+use my_http_server_core::{
+    my_hyper_utils::*, HttpRequestId, MyHyperHttpRequest, SocketAddress, TrustedProxies,
+};
 
 fn is_h2_ws_connect_request(req: &MyHyperHttpRequest) -> bool {
     let method = match req {
@@ -78,13 +80,18 @@ fn h1_websocket_upgrade(req: MyHyperHttpRequest) -> Result<(MyHttpResponse, WsSt
 pub async fn upgrade<TMyWebSocketCallback: MyWebSocketCallback + Send + Sync + 'static>(
     id: i64,
     addr: SocketAddress,
+    trusted_proxies: Option<Arc<TrustedProxies>>,
     query_string: Option<String>,
     req: MyHyperHttpRequest,
     callback: Arc<TMyWebSocketCallback>,
     disconnect_timeout: Duration,
     logs: Arc<dyn Logger + Send + Sync + 'static>,
 ) -> Result<MyHttpResponse, Error> {
-    let http_request = MyWebSocketHttpRequest::new(&req, addr.clone());
+    let http_request = MyWebSocketHttpRequest::new(&req, addr.clone(), trusted_proxies);
+    let request_id = req
+        .extensions()
+        .get::<HttpRequestId>()
+        .map(|itm| itm.as_str().to_string());
 
     let (response, websocket) = if is_h2_ws_connect_request(&req) {
         h2_ext_connect_upgrade(req)?
@@ -120,6 +127,7 @@ pub async fn upgrade<TMyWebSocketCallback: MyWebSocketCallback + Send + Sync + '
         let my_web_socket = MyWebSocket::new(
             id,
             addr,
+            request_id,
             ws_sender,
             query_string.clone(),
             callback.clone(),
@@ -1,3 +1,6 @@
+#[cfg(test)]
+pub mod test_common;
+
 #[cfg(test)]
 pub mod test_http_input;
 
@@ -10,5 +13,65 @@ pub mod test_parse_e2e;
 #[cfg(test)]
 pub mod test_body_as_stream_e2e;
 
+#[cfg(test)]
+pub mod test_graceful_shutdown_e2e;
+
+#[cfg(test)]
+pub mod test_listeners_e2e;
+
+#[cfg(test)]
+pub mod test_tls_e2e;
+
+#[cfg(all(test, unix))]
+pub mod test_unix_socket_e2e;
+
+#[cfg(test)]
+pub mod test_proxy_protocol_e2e;
+
+#[cfg(test)]
+pub mod test_request_timeout_e2e;
+
+#[cfg(test)]
+pub mod test_request_decompression_e2e;
+
+#[cfg(test)]
+pub mod test_response_compression_e2e;
+
+#[cfg(test)]
+pub mod test_around_middleware_e2e;
+
+#[cfg(test)]
+pub mod test_middleware_groups_e2e;
+
+#[cfg(test)]
+pub mod test_access_log_e2e;
+
+#[cfg(test)]
+pub mod test_metrics_e2e;
+
+#[cfg(test)]
+pub mod test_request_id_e2e;
+
+#[cfg(test)]
+pub mod test_context_extensions_e2e;
+
+#[cfg(test)]
+pub mod test_fallback_results_e2e;
+
+#[cfg(test)]
+pub mod test_health_e2e;
+
+#[cfg(test)]
+pub mod test_in_process_client_e2e;
+
+#[cfg(test)]
+pub mod test_sse_e2e;
+
+#[cfg(test)]
+pub mod test_stream_output_e2e;
+
+#[cfg(test)]
+pub mod test_web_socket_ip_e2e;
+
 #[cfg(test)]
 pub mod test_pkg_compile_date_time;
@@ -0,0 +1,144 @@
+//! End-to-end coverage of `HttpAccessLogMiddleware`: a line per request with the route the
+//! controllers matched, the user agent and the size of the request — and the secrets redacted.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use my_http_server::controllers::ControllersMiddleware;
+use my_http_server::*;
+use rust_extensions::Logger;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::test_common::*;
+
+pub mod user_action {
+    use my_http_server::macros::*;
+    use my_http_server::*;
+
+    #[http_route(
+        method: "POST",
+        route: "/users/{id}",
+        controller: "Test",
+        summary: "User",
+        description: "Answers with a user",
+        result: [
+            { status_code: 200, description: "Ok" },
+        ]
+    )]
+    pub struct UserAction;
+
+    async fn handle_request(
+        _action: &UserAction,
+        _ctx: &mut HttpContext,
+    ) -> Result<HttpOkResult, HttpFailResult> {
+        HttpOutput::as_text("user").into_ok_result(true).into()
+    }
+}
+
+/// Keeps the access log lines — and nothing else.
+#[derive(Default)]
+struct AccessLogLines(Mutex<Vec<String>>);
+
+impl Logger for AccessLogLines {
+    fn write_info(&self, process: String, message: String, _c: Option<HashMap<String, String>>) {
+        if process == "HttpAccessLog" {
+            self.0.lock().unwrap().push(message);
+        }
+    }
+    fn write_warning(&self, _p: String, _m: String, _c: Option<HashMap<String, String>>) {}
+    fn write_error(&self, _p: String, _m: String, _c: Option<HashMap<String, String>>) {}
+    fn write_fatal_error(&self, _p: String, _m: String, _c: Option<HashMap<String, String>>) {}
+    fn write_debug_info(&self, _p: String, _m: String, _c: Option<HashMap<String, String>>) {}
+}
+
+async fn start_server(format: HttpAccessLogFormat) -> (u16, HttpServerHandle, Arc<AccessLogLines>) {
+    let (listener, port) = bind_local_port();
+
+    let mut controllers = ControllersMiddleware::new(None, None);
+    controllers.register_post_action(Arc::new(user_action::UserAction));
+
+    let lines = Arc::new(AccessLogLines::default());
+
+    let mut server = MyHttpServer::new_from_std_tcp_listener(listener);
+    server.add_middleware(Arc::new(controllers));
+    server.add_tech_middleware(Arc::new(
+        HttpAccessLogMiddleware::new(lines.clone(), format).with_headers(["authorization"]),
+    ));
+
+    let app_states = app_states();
+
+    let handle = server.start_h1(app_states, lines.clone());
+
+    (port, handle, lines)
+}
+
+async fn post(port: u16, path: &str) -> String {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+
+    let request = format!(
+        "POST {} HTTP/1.1\r\nHost: localhost\r\nUser-Agent: e2e\r\nAuthorization: Bearer secret\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{{}}",
+        path
+    );
+
+    stream.write_all(request.as_bytes()).await.unwrap();
+
+    let mut buf = Vec::new();
+    let _ = tokio::time::timeout(Duration::from_secs(10), stream.read_to_end(&mut buf)).await;
+    String::from_utf8_lossy(&buf).to_string()
+}
+
+/// The line is written after the response went out.
+async fn wait_for_line(lines: &AccessLogLines) -> String {
+    for _ in 0..100 {
+        if let Some(line) = lines.0.lock().unwrap().first() {
+            return line.clone();
+        }
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    panic!("No access log line was written");
+}
+
+#[tokio::test]
+async fn json_line_has_the_route_and_the_request() {
+    let (port, handle, lines) = start_server(HttpAccessLogFormat::Json).await;
+
+    let response = post(port, "/users/42").await;
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+
+    let line = wait_for_line(&lines).await;
+    let json: serde_json::Value = serde_json::from_str(&line).unwrap();
+
+    assert_eq!(json["path"], "/users/42", "{}", line);
+    assert_eq!(json["process_name"], "/users/{id}", "{}", line);
+    assert_eq!(json["status_code"], 200, "{}", line);
+    assert_eq!(json["user_agent"], "e2e", "{}", line);
+    assert_eq!(json["request_size"], 2, "{}", line);
+    assert_eq!(json["version"], "HTTP/1.1", "{}", line);
+    assert_eq!(json["headers"]["authorization"], "[REDACTED]", "{}", line);
+    assert!(!line.contains("secret"), "{}", line);
+
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn combined_line() {
+    let (port, handle, lines) = start_server(HttpAccessLogFormat::Combined).await;
+
+    let response = post(port, "/users/42").await;
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+
+    let line = wait_for_line(&lines).await;
+
+    assert!(line.starts_with("127.0.0.1 - - ["), "{}", line);
+    assert!(
+        line.ends_with("] \"POST /users/42 HTTP/1.1\" 200 4 \"-\" \"e2e\" \"[REDACTED]\""),
+        "{}",
+        line
+    );
+
+    handle.shutdown().await;
+}
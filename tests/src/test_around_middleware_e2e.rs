@@ -0,0 +1,249 @@
+//! End-to-end coverage of `HttpServerAroundMiddleware`: it wraps the middlewares added after it
+//! — controllers included — sees what they answer, can change it, and can call them more than
+//! once. A plain `HttpServerMiddleware` in front of it still short-circuits the way it always did.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use my_http_server::controllers::ControllersMiddleware;
+use my_http_server::*;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::test_common::*;
+
+pub mod hello_action {
+    use my_http_server::macros::*;
+    use my_http_server::*;
+
+    #[http_route(
+        method: "GET",
+        route: "/hello",
+        controller: "Test",
+        summary: "Hello",
+        description: "Answers with a greeting",
+        result: [
+            { status_code: 200, description: "Ok" },
+        ]
+    )]
+    pub struct HelloAction;
+
+    async fn handle_request(
+        _action: &HelloAction,
+        _ctx: &mut HttpContext,
+    ) -> Result<HttpOkResult, HttpFailResult> {
+        HttpOutput::as_text("hello").into_ok_result(true).into()
+    }
+}
+
+pub mod flaky_action {
+    use std::sync::atomic::Ordering;
+
+    use my_http_server::macros::*;
+    use my_http_server::*;
+
+    #[http_route(
+        method: "GET",
+        route: "/flaky",
+        controller: "Test",
+        summary: "Flaky",
+        description: "Fails the first time it is called",
+        result: [
+            { status_code: 200, description: "Ok" },
+        ]
+    )]
+    pub struct FlakyAction;
+
+    async fn handle_request(
+        _action: &FlakyAction,
+        _ctx: &mut HttpContext,
+    ) -> Result<HttpOkResult, HttpFailResult> {
+        if super::FLAKY_CALLS.fetch_add(1, Ordering::SeqCst) == 0 {
+            return Err(HttpFailResult::as_fatal_error("try again".to_string()));
+        }
+
+        HttpOutput::as_text("second time lucky")
+            .into_ok_result(true)
+            .into()
+    }
+}
+
+static FLAKY_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+/// Stamps every response the chain gives — the errors too.
+struct StampMiddleware;
+
+#[async_trait::async_trait]
+impl HttpServerAroundMiddleware for StampMiddleware {
+    async fn handle_request(
+        &self,
+        ctx: &mut HttpContext,
+        next: HttpServerMiddlewareNext<'_>,
+    ) -> Result<HttpOkResult, HttpFailResult> {
+        match next.run(ctx).await {
+            Ok(mut ok_result) => {
+                stamp(&mut ok_result.output);
+                Ok(ok_result)
+            }
+            Err(mut fail_result) => {
+                stamp(&mut fail_result.output);
+                Err(fail_result)
+            }
+        }
+    }
+}
+
+fn stamp(output: &mut HttpOutput) {
+    if let HttpOutput::Content { headers, .. } = output {
+        headers.add_header("x-stamp".into(), "around".to_string());
+    }
+}
+
+/// Answers a request nobody else did with JSON instead of the plain text `404`.
+struct NotFoundAsJsonMiddleware;
+
+#[async_trait::async_trait]
+impl HttpServerAroundMiddleware for NotFoundAsJsonMiddleware {
+    async fn handle_request(
+        &self,
+        ctx: &mut HttpContext,
+        next: HttpServerMiddlewareNext<'_>,
+    ) -> Result<HttpOkResult, HttpFailResult> {
+        let result = next.run(ctx).await;
+
+        match result {
+            Err(fail_result) if fail_result.output.get_status_code() == 404 => {
+                HttpOutput::as_json(serde_json::json!({ "error": "not found" }))
+                    .set_status_code(404)
+                    .into_err(false, false)
+            }
+            result => result,
+        }
+    }
+}
+
+/// Tries once more when the rest of the chain fails with a `500`.
+struct RetryMiddleware;
+
+#[async_trait::async_trait]
+impl HttpServerAroundMiddleware for RetryMiddleware {
+    async fn handle_request(
+        &self,
+        ctx: &mut HttpContext,
+        next: HttpServerMiddlewareNext<'_>,
+    ) -> Result<HttpOkResult, HttpFailResult> {
+        match next.run(ctx).await {
+            Err(fail_result) if fail_result.output.get_status_code() == 500 => next.run(ctx).await,
+            result => result,
+        }
+    }
+}
+
+/// Answers `/blocked` itself — the middlewares after it never see the request.
+struct BlockMiddleware;
+
+#[async_trait::async_trait]
+impl HttpServerMiddleware for BlockMiddleware {
+    async fn handle_request(
+        &self,
+        ctx: &mut HttpContext,
+    ) -> Option<Result<HttpOkResult, HttpFailResult>> {
+        if ctx.request.get_path().to_string() == "/blocked" {
+            return Some(HttpFailResult::as_forbidden(Some("blocked")).into_err());
+        }
+
+        None
+    }
+}
+
+async fn start_server() -> (u16, HttpServerHandle) {
+    let (listener, port) = bind_local_port();
+
+    let mut controllers = ControllersMiddleware::new(None, None);
+    controllers.register_get_action(Arc::new(hello_action::HelloAction));
+    controllers.register_get_action(Arc::new(flaky_action::FlakyAction));
+
+    let mut server = MyHttpServer::new_from_std_tcp_listener(listener);
+    server.add_middleware(Arc::new(BlockMiddleware));
+    server.add_around_middleware(Arc::new(StampMiddleware));
+    server.add_around_middleware(Arc::new(NotFoundAsJsonMiddleware));
+    server.add_around_middleware(Arc::new(RetryMiddleware));
+    server.add_middleware(Arc::new(controllers));
+
+    let app_states = app_states();
+
+    let handle = server.start_h1(app_states, Arc::new(NoLogger));
+
+    (port, handle)
+}
+
+async fn get(port: u16, path: &str) -> String {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+        path
+    );
+
+    stream.write_all(request.as_bytes()).await.unwrap();
+
+    let mut buf = Vec::new();
+    let _ = tokio::time::timeout(Duration::from_secs(10), stream.read_to_end(&mut buf)).await;
+    String::from_utf8_lossy(&buf).to_string()
+}
+
+#[tokio::test]
+async fn around_middleware_changes_the_answer_of_an_action() {
+    let (port, handle) = start_server().await;
+
+    let response = get(port, "/hello").await;
+
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(response.contains("x-stamp: around"), "{}", response);
+    assert!(response.ends_with("hello"), "{}", response);
+
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn around_middleware_translates_errors() {
+    let (port, handle) = start_server().await;
+
+    let response = get(port, "/missing").await;
+
+    assert!(response.starts_with("HTTP/1.1 404"), "{}", response);
+    assert!(response.contains("x-stamp: around"), "{}", response);
+    assert!(
+        response.ends_with(r#"{"error":"not found"}"#),
+        "{}",
+        response
+    );
+
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn around_middleware_can_run_the_rest_of_the_chain_again() {
+    let (port, handle) = start_server().await;
+
+    let response = get(port, "/flaky").await;
+
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(response.ends_with("second time lucky"), "{}", response);
+    assert_eq!(FLAKY_CALLS.load(Ordering::SeqCst), 2);
+
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn middleware_in_front_still_short_circuits() {
+    let (port, handle) = start_server().await;
+
+    let response = get(port, "/blocked").await;
+
+    assert!(response.starts_with("HTTP/1.1 403"), "{}", response);
+    assert!(!response.contains("x-stamp"), "{}", response);
+
+    handle.shutdown().await;
+}
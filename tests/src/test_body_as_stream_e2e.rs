@@ -368,17 +368,23 @@ async fn start_server_h2() -> (u16, HttpConnectionsCounter) {
 }
 
 async fn start_server_with(h2: bool) -> (u16, HttpConnectionsCounter) {
-    start_server_full(h2, None).await
+    start_server_full(h2, None, None).await
 }
 
 /// Same, with a body-read idle timeout in force.
 async fn start_server_with_read_timeout(timeout: Duration) -> (u16, HttpConnectionsCounter) {
-    start_server_full(false, Some(timeout)).await
+    start_server_full(false, Some(timeout), None).await
+}
+
+/// Same, with a server-wide body limit in force.
+async fn start_server_with_max_body_size(h2: bool, max_body_size: u64) -> u16 {
+    start_server_full(h2, None, Some(max_body_size)).await.0
 }
 
 async fn start_server_full(
     h2: bool,
     body_read_timeout: Option<Duration>,
+    max_body_size: Option<u64>,
 ) -> (u16, HttpConnectionsCounter) {
     let port = free_port();
 
@@ -388,6 +394,7 @@ async fn start_server_full(
     controllers.register_post_action(Arc::new(upload_raw::UploadRawAction));
     controllers.register_post_action(Arc::new(upload_slow::UploadSlowAction));
     controllers.register_post_action(Arc::new(reject::RejectAction));
+    controllers.register_post_action(Arc::new(upload_big::UploadBigAction));
 
     let mut server = MyHttpServer::new(SocketAddr::from(([127, 0, 0, 1], port)));
     server.add_middleware(Arc::new(controllers));
@@ -396,6 +403,10 @@ async fn start_server_full(
         server.set_body_read_timeout(body_read_timeout);
     }
 
+    if let Some(max_body_size) = max_body_size {
+        server.set_max_body_size(max_body_size);
+    }
+
     let counter = server.get_http_connections_counter();
 
     let app_states = Arc::new(AppStates::create_initialized());
@@ -910,6 +921,50 @@ fn the_model_consts_say_which_path_the_body_takes() {
     assert!(crate::test_macros_split::UpdateUserRequest::READS_BODY);
 }
 
+/// The materializing action again, with a body limit of its own above the server-wide one.
+pub mod upload_big {
+    use my_http_server::macros::*;
+    use my_http_server::*;
+
+    #[derive(MyHttpInput)]
+    pub struct UploadBigHttpInput {
+        #[http_header(name = "X-Test-Id", description = "Test id")]
+        pub test_id: String,
+
+        #[http_body_raw(description = "File content")]
+        pub body: RawData,
+    }
+
+    #[http_route(
+        method: "POST",
+        route: "/upload-big",
+        controller: "Test",
+        summary: "Upload big",
+        description: "Materializes a body larger than the server-wide limit",
+        input_data: "UploadBigHttpInput",
+        max_body_size: 1000000,
+        result: [
+            { status_code: 200, description: "Ok" },
+        ]
+    )]
+    pub struct UploadBigAction;
+
+    async fn handle_request(
+        _action: &UploadBigAction,
+        input_data: UploadBigHttpInput,
+        _ctx: &mut HttpContext,
+    ) -> Result<HttpOkResult, HttpFailResult> {
+        super::set_outcome(
+            input_data.test_id,
+            format!("ok:total={}", input_data.body.as_slice().len()),
+        );
+
+        HttpOutput::as_text("ok".to_string())
+            .into_ok_result(true)
+            .into()
+    }
+}
+
 // ───────────────────── body-read idle timeout ─────────────────────
 
 /// Sends request headers, part of the announced body, and then goes quiet — the shape that used to
@@ -1083,3 +1138,179 @@ async fn a_slow_consumer_does_not_trigger_the_timeout() {
         "a slow handler was mistaken for a stalled client"
     );
 }
+
+// ─────────────────────────── body size limit ───────────────────────────
+
+/// Sends request headers and `body` as is, then reads what the server answers without sending
+/// anything more — the server must not need the rest of the body to turn the request down.
+async fn send_and_read_answer(port: u16, head: String, body: &[u8]) -> String {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    stream.write_all(head.as_bytes()).await.unwrap();
+    stream.write_all(body).await.unwrap();
+    stream.flush().await.unwrap();
+
+    let mut buf = vec![0u8; 4096];
+    let read = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut buf))
+        .await
+        .expect("the server did not answer within 5s");
+
+    String::from_utf8_lossy(&buf[..read.unwrap()]).to_string()
+}
+
+fn chunked(body: &[u8]) -> Vec<u8> {
+    let mut result = Vec::new();
+    for part in body.chunks(4096) {
+        result.extend_from_slice(format!("{:x}\r\n", part.len()).as_bytes());
+        result.extend_from_slice(part);
+        result.extend_from_slice(b"\r\n");
+    }
+    result
+}
+
+/// A `Content-Length` above the limit is refused before a byte of the body is sent — and the
+/// action is never run.
+#[tokio::test]
+async fn an_announced_body_over_the_limit_is_refused_up_front() {
+    let port = start_server_with_max_body_size(false, 10_000).await;
+    let id = test_id("limit-announced");
+
+    let response = send_and_read_answer(
+        port,
+        format!(
+            "POST /upload-raw HTTP/1.1\r\nHost: localhost\r\nX-Test-Id: {}\r\nContent-Length: 1000000\r\nConnection: close\r\n\r\n",
+            id
+        ),
+        &[],
+    )
+    .await;
+
+    assert!(response.starts_with("HTTP/1.1 413"), "response: {}", response);
+    assert!(take_outcome(&id).is_none());
+}
+
+/// The same on the streamed path: the action can not take a reader for a body it may not read.
+#[tokio::test]
+async fn an_announced_body_over_the_limit_is_refused_on_the_streamed_path() {
+    let port = start_server_with_max_body_size(false, 10_000).await;
+    let id = test_id("limit-announced-stream");
+
+    let response = send_and_read_answer(
+        port,
+        format!(
+            "POST /upload HTTP/1.1\r\nHost: localhost\r\nX-Test-Id: {}\r\nContent-Length: 1000000\r\nConnection: close\r\n\r\n",
+            id
+        ),
+        &[],
+    )
+    .await;
+
+    assert!(response.starts_with("HTTP/1.1 413"), "response: {}", response);
+    assert!(take_outcome(&id).is_none());
+}
+
+/// A chunked body announces nothing, so it is cut off once it goes over the limit.
+#[tokio::test]
+async fn a_chunked_body_over_the_limit_is_cut_off_on_the_materialize_path() {
+    let port = start_server_with_max_body_size(false, 10_000).await;
+    let id = test_id("limit-chunked-raw");
+
+    let response = send_and_read_answer(
+        port,
+        format!(
+            "POST /upload-raw HTTP/1.1\r\nHost: localhost\r\nX-Test-Id: {}\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n",
+            id
+        ),
+        &chunked(&payload(20_000)),
+    )
+    .await;
+
+    assert!(response.starts_with("HTTP/1.1 413"), "response: {}", response);
+    assert!(take_outcome(&id).is_none());
+}
+
+/// ...and the reader of a streamed body gets an error, not a body that ended at the limit.
+#[tokio::test]
+async fn a_chunked_body_over_the_limit_is_an_error_on_the_streamed_path() {
+    let port = start_server_with_max_body_size(false, 10_000).await;
+    let id = test_id("limit-chunked-stream");
+
+    let response = send_and_read_answer(
+        port,
+        format!(
+            "POST /upload HTTP/1.1\r\nHost: localhost\r\nX-Test-Id: {}\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n",
+            id
+        ),
+        &chunked(&payload(20_000)),
+    )
+    .await;
+
+    assert!(response.starts_with("HTTP/1.1 413"), "response: {}", response);
+
+    let outcome = take_outcome(&id).unwrap();
+    assert!(
+        outcome.starts_with("err:") && outcome.contains("exceeds the limit"),
+        "outcome: {}",
+        outcome
+    );
+}
+
+/// An h2 upload of unknown size is held to the limit the same way.
+#[tokio::test]
+async fn an_h2_upload_over_the_limit_is_an_error() {
+    let port = start_server_with_max_body_size(true, 10_000).await;
+    let id = test_id("limit-h2");
+
+    let outcome = h2_upload_with(port, "/upload", &id, None, 40_000, false).await;
+
+    assert!(
+        outcome.starts_with("err:") && outcome.contains("exceeds the limit"),
+        "outcome: {}",
+        outcome
+    );
+}
+
+/// A body within the limit is not affected by it.
+#[tokio::test]
+async fn a_body_within_the_limit_is_accepted() {
+    let port = start_server_with_max_body_size(false, 100_000).await;
+    let id = test_id("limit-within");
+
+    let body = payload(40_000);
+
+    let mut request = format!(
+        "POST /upload HTTP/1.1\r\nHost: localhost\r\nX-Test-Id: {}\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n",
+        id
+    )
+    .into_bytes();
+    request.extend_from_slice(&chunked(&body));
+    request.extend_from_slice(b"0\r\n\r\n");
+
+    let response = send_raw(port, request).await;
+
+    assert!(response.starts_with("HTTP/1.1 200"), "response: {}", response);
+
+    let outcome = take_outcome(&id).unwrap();
+    assert!(outcome.starts_with("ok:total=40000,"), "outcome: {}", outcome);
+}
+
+/// `max_body_size` of the route wins over the server-wide limit.
+#[tokio::test]
+async fn the_route_limit_overrides_the_server_one() {
+    let port = start_server_with_max_body_size(false, 10_000).await;
+    let id = test_id("limit-route");
+
+    let body = payload(40_000);
+
+    let mut request = format!(
+        "POST /upload-big HTTP/1.1\r\nHost: localhost\r\nX-Test-Id: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        id,
+        body.len()
+    )
+    .into_bytes();
+    request.extend_from_slice(&body);
+
+    let response = send_raw(port, request).await;
+
+    assert!(response.starts_with("HTTP/1.1 200"), "response: {}", response);
+    assert_eq!(take_outcome(&id).unwrap(), "ok:total=40000");
+}
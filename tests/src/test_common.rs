@@ -0,0 +1,34 @@
+//! What the end-to-end tests share: a logger that drops everything, a local port to serve on and
+//! the application states to start with.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use rust_extensions::{AppStates, Logger};
+
+pub struct NoLogger;
+
+impl Logger for NoLogger {
+    fn write_info(&self, _p: String, _m: String, _c: Option<HashMap<String, String>>) {}
+    fn write_warning(&self, _p: String, _m: String, _c: Option<HashMap<String, String>>) {}
+    fn write_error(&self, _p: String, _m: String, _c: Option<HashMap<String, String>>) {}
+    fn write_fatal_error(&self, _p: String, _m: String, _c: Option<HashMap<String, String>>) {}
+    fn write_debug_info(&self, _p: String, _m: String, _c: Option<HashMap<String, String>>) {}
+}
+
+/// A listener on a free local port, for `MyHttpServer::new_from_std_tcp_listener` — the port is
+/// held from the start, so a test running alongside can not take it.
+pub fn bind_local_port() -> (std::net::TcpListener, u16) {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    (listener, port)
+}
+
+/// A port that was free a moment ago — for a server that has to bind the address itself.
+pub fn free_port() -> u16 {
+    bind_local_port().1
+}
+
+pub fn app_states() -> Arc<AppStates> {
+    Arc::new(AppStates::create_initialized())
+}
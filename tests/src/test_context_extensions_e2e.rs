@@ -0,0 +1,292 @@
+//! End-to-end coverage of `HttpContext::extensions`: a middleware hands a typed value to the
+//! action, which takes a copy of it with `extension:` of `#[http_route]` — with and without
+//! `input_data`, and again when an around middleware runs the chain once more — or reads it from
+//! the context.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use my_http_server::controllers::ControllersMiddleware;
+use my_http_server::*;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::test_common::*;
+
+/// What an auth middleware would decode from a token.
+#[derive(Clone)]
+pub struct UserProfile {
+    pub id: String,
+}
+
+/// Puts the profile of `X-User` into the context — and nothing for a request without one.
+struct AuthMiddleware;
+
+#[async_trait::async_trait]
+impl HttpServerMiddleware for AuthMiddleware {
+    async fn handle_request(
+        &self,
+        ctx: &mut HttpContext,
+    ) -> Option<Result<HttpOkResult, HttpFailResult>> {
+        let user = ctx
+            .request
+            .data
+            .headers()
+            .get("x-user")
+            .and_then(|itm| itm.to_str().ok())
+            .map(|itm| itm.to_string());
+
+        if let Some(id) = user {
+            ctx.extensions.insert(UserProfile { id });
+        }
+
+        None
+    }
+}
+
+// One `handle_request` per module: `#[http_route]` generates a call to a free function of that
+// name, so two actions can not share a module.
+
+pub mod profile_action {
+    use my_http_server::macros::*;
+    use my_http_server::*;
+
+    use super::UserProfile;
+
+    #[http_route(
+        method: "GET",
+        route: "/profile",
+        controller: "Test",
+        summary: "Profile",
+        description: "Answers with the id of the user",
+        extension: "UserProfile",
+        result: [
+            { status_code: 200, description: "Ok" },
+        ]
+    )]
+    pub struct ProfileAction;
+
+    async fn handle_request(
+        _action: &ProfileAction,
+        profile: UserProfile,
+        ctx: &mut HttpContext,
+    ) -> Result<HttpOkResult, HttpFailResult> {
+        // A copy — the value stays in the context.
+        assert!(ctx.extensions.contains::<UserProfile>());
+
+        HttpOutput::as_text(profile.id).into_ok_result(true).into()
+    }
+}
+
+pub mod greeting_action {
+    use my_http_server::macros::*;
+    use my_http_server::*;
+
+    use super::UserProfile;
+
+    #[derive(MyHttpInput)]
+    pub struct GreetingHttpInput {
+        #[http_path(name = "greeting", description = "Greeting")]
+        pub greeting: String,
+    }
+
+    #[http_route(
+        method: "GET",
+        route: "/greeting/{greeting}",
+        controller: "Test",
+        summary: "Greeting",
+        description: "Greets the user",
+        input_data: "GreetingHttpInput",
+        extension: "UserProfile",
+        result: [
+            { status_code: 200, description: "Ok" },
+        ]
+    )]
+    pub struct GreetingAction;
+
+    async fn handle_request(
+        _action: &GreetingAction,
+        input_data: GreetingHttpInput,
+        profile: UserProfile,
+        _ctx: &mut HttpContext,
+    ) -> Result<HttpOkResult, HttpFailResult> {
+        HttpOutput::as_text(format!("{} {}", input_data.greeting, profile.id))
+            .into_ok_result(true)
+            .into()
+    }
+}
+
+pub mod flaky_action {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use my_http_server::macros::*;
+    use my_http_server::*;
+
+    use super::UserProfile;
+
+    static ATTEMPTS: AtomicUsize = AtomicUsize::new(0);
+
+    #[http_route(
+        method: "GET",
+        route: "/flaky",
+        controller: "Test",
+        summary: "Flaky",
+        description: "Fails the first time it is called",
+        extension: "UserProfile",
+        result: [
+            { status_code: 200, description: "Ok" },
+        ]
+    )]
+    pub struct FlakyAction;
+
+    async fn handle_request(
+        _action: &FlakyAction,
+        profile: UserProfile,
+        _ctx: &mut HttpContext,
+    ) -> Result<HttpOkResult, HttpFailResult> {
+        if ATTEMPTS.fetch_add(1, Ordering::SeqCst) == 0 {
+            return Err(HttpFailResult::as_fatal_error("Try again".to_string()));
+        }
+
+        HttpOutput::as_text(profile.id).into_ok_result(true).into()
+    }
+}
+
+pub mod whoami_action {
+    use my_http_server::macros::*;
+    use my_http_server::*;
+
+    use super::UserProfile;
+
+    #[http_route(
+        method: "GET",
+        route: "/whoami",
+        controller: "Test",
+        summary: "Who am I",
+        description: "Answers with the id of the user, if there is one",
+        result: [
+            { status_code: 200, description: "Ok" },
+        ]
+    )]
+    pub struct WhoAmIAction;
+
+    async fn handle_request(
+        _action: &WhoAmIAction,
+        ctx: &mut HttpContext,
+    ) -> Result<HttpOkResult, HttpFailResult> {
+        let id = match ctx.extensions.get::<UserProfile>() {
+            Some(profile) => profile.id.clone(),
+            None => "anonymous".to_string(),
+        };
+
+        HttpOutput::as_text(id).into_ok_result(true).into()
+    }
+}
+
+/// Runs the rest of the chain once more when it fails with a `500`.
+struct RetryMiddleware;
+
+#[async_trait::async_trait]
+impl HttpServerAroundMiddleware for RetryMiddleware {
+    async fn handle_request(
+        &self,
+        ctx: &mut HttpContext,
+        next: HttpServerMiddlewareNext<'_>,
+    ) -> Result<HttpOkResult, HttpFailResult> {
+        match next.run(ctx).await {
+            Err(fail_result) if fail_result.output.get_status_code() == 500 => next.run(ctx).await,
+            result => result,
+        }
+    }
+}
+
+async fn start_server() -> (u16, HttpServerHandle) {
+    let (listener, port) = bind_local_port();
+
+    let mut controllers = ControllersMiddleware::new(None, None);
+    controllers.register_get_action(Arc::new(profile_action::ProfileAction));
+    controllers.register_get_action(Arc::new(greeting_action::GreetingAction));
+    controllers.register_get_action(Arc::new(whoami_action::WhoAmIAction));
+    controllers.register_get_action(Arc::new(flaky_action::FlakyAction));
+
+    let mut server = MyHttpServer::new_from_std_tcp_listener(listener);
+    server.add_middleware(Arc::new(AuthMiddleware));
+    server.add_around_middleware(Arc::new(RetryMiddleware));
+    server.add_middleware(Arc::new(controllers));
+
+    let app_states = app_states();
+
+    let handle = server.start_h1(app_states, Arc::new(NoLogger));
+
+    (port, handle)
+}
+
+async fn get(port: u16, path: &str, headers: &str) -> String {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: localhost\r\n{}Connection: close\r\n\r\n",
+        path, headers
+    );
+
+    stream.write_all(request.as_bytes()).await.unwrap();
+
+    let mut buf = Vec::new();
+    let _ = tokio::time::timeout(Duration::from_secs(10), stream.read_to_end(&mut buf)).await;
+    String::from_utf8_lossy(&buf).to_string()
+}
+
+fn get_body(response: &str) -> &str {
+    response.split("\r\n\r\n").nth(1).unwrap_or_default()
+}
+
+#[tokio::test]
+async fn action_takes_the_extension_as_an_argument() {
+    let (port, handle) = start_server().await;
+
+    let response = get(port, "/profile", "X-User: user-1\r\n").await;
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert_eq!(get_body(&response), "user-1");
+
+    let response = get(port, "/greeting/hello", "X-User: user-2\r\n").await;
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert_eq!(get_body(&response), "hello user-2");
+
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn missing_extension_is_a_server_error() {
+    let (port, handle) = start_server().await;
+
+    let response = get(port, "/profile", "").await;
+    assert!(response.starts_with("HTTP/1.1 500"), "{}", response);
+
+    handle.shutdown().await;
+}
+
+/// The auth middleware runs once, before the retry: the second attempt has to find the extension
+/// still in the context.
+#[tokio::test]
+async fn retried_action_gets_the_extension_again() {
+    let (port, handle) = start_server().await;
+
+    let response = get(port, "/flaky", "X-User: user-4\r\n").await;
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert_eq!(get_body(&response), "user-4");
+
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn action_reads_the_extension_from_the_context() {
+    let (port, handle) = start_server().await;
+
+    let response = get(port, "/whoami", "X-User: user-3\r\n").await;
+    assert_eq!(get_body(&response), "user-3");
+
+    let response = get(port, "/whoami", "").await;
+    assert_eq!(get_body(&response), "anonymous");
+
+    handle.shutdown().await;
+}
@@ -0,0 +1,179 @@
+//! End-to-end coverage of the results the server answers with on its own: a panicked handler and
+//! a request no middleware answered — with the defaults and with the factories set on
+//! `MyHttpServer`.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use my_http_server::*;
+use serde::Serialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::test_common::*;
+
+/// Panics on `/panic`, answers `/ok`, passes everything else on.
+struct TestMiddleware;
+
+#[async_trait::async_trait]
+impl HttpServerMiddleware for TestMiddleware {
+    async fn handle_request(
+        &self,
+        ctx: &mut HttpContext,
+    ) -> Option<Result<HttpOkResult, HttpFailResult>> {
+        match ctx.request.get_path().as_str() {
+            "/panic" => panic!("Something went wrong"),
+            "/ok" => Some(HttpOutput::as_text("ok").into_ok_result(true)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorEnvelope {
+    code: &'static str,
+    path: String,
+    request_id: String,
+    /// Read off the request headers — given to the factories without any tech middleware.
+    host: Option<String>,
+}
+
+fn get_host(request: &HttpRequestData) -> Option<String> {
+    request
+        .headers
+        .get("host")
+        .and_then(|itm| itm.to_str().ok())
+        .map(|itm| itm.to_string())
+}
+
+struct JsonPanicResult;
+
+impl HttpPanicResultFactory for JsonPanicResult {
+    fn get_panic_result(&self, request: &HttpRequestData, panic_message: &str) -> HttpFailResult {
+        assert!(panic_message.contains("Something went wrong"));
+
+        HttpOutput::as_json(ErrorEnvelope {
+            code: "InternalError",
+            path: request.path.clone(),
+            request_id: request.request_id.clone(),
+            host: get_host(request),
+        })
+        .set_status_code(500)
+        .into_http_fail_result(false, false)
+    }
+}
+
+struct JsonNotFoundResult;
+
+impl HttpNotFoundResultFactory for JsonNotFoundResult {
+    fn get_not_found_result(&self, request: &HttpRequestData) -> HttpFailResult {
+        HttpOutput::as_json(ErrorEnvelope {
+            code: "NotFound",
+            path: request.path.clone(),
+            request_id: request.request_id.clone(),
+            host: get_host(request),
+        })
+        .set_status_code(404)
+        .into_http_fail_result(false, false)
+    }
+}
+
+async fn start_server(with_factories: bool) -> (u16, HttpServerHandle) {
+    let (listener, port) = bind_local_port();
+
+    let mut server = MyHttpServer::new_from_std_tcp_listener(listener);
+    server.add_middleware(Arc::new(TestMiddleware));
+
+    if with_factories {
+        server.set_panic_result_factory(Arc::new(JsonPanicResult));
+        server.set_not_found_result_factory(Arc::new(JsonNotFoundResult));
+    }
+
+    let app_states = app_states();
+
+    let handle = server.start_h1(app_states, Arc::new(NoLogger));
+
+    (port, handle)
+}
+
+async fn get(port: u16, path: &str) -> String {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: localhost\r\nX-Request-Id: req-1\r\nConnection: close\r\n\r\n",
+        path
+    );
+
+    stream.write_all(request.as_bytes()).await.unwrap();
+
+    let mut buf = Vec::new();
+    let _ = tokio::time::timeout(Duration::from_secs(10), stream.read_to_end(&mut buf)).await;
+    String::from_utf8_lossy(&buf).to_string()
+}
+
+fn get_body(response: &str) -> &str {
+    response.split("\r\n\r\n").nth(1).unwrap_or_default()
+}
+
+fn has_header(response: &str, header: &str) -> bool {
+    response
+        .split("\r\n\r\n")
+        .next()
+        .unwrap_or_default()
+        .to_lowercase()
+        .contains(&header.to_lowercase())
+}
+
+#[tokio::test]
+async fn defaults_are_plain_text() {
+    let (port, handle) = start_server(false).await;
+
+    let response = get(port, "/panic").await;
+    assert!(response.starts_with("HTTP/1.1 500"), "{}", response);
+    assert_eq!(get_body(&response), "Internal server error");
+
+    let response = get(port, "/missing").await;
+    assert!(response.starts_with("HTTP/1.1 404"), "{}", response);
+    assert_eq!(get_body(&response), "404 - Not Found");
+
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn panic_is_answered_by_the_factory() {
+    let (port, handle) = start_server(true).await;
+
+    let response = get(port, "/panic").await;
+    assert!(response.starts_with("HTTP/1.1 500"), "{}", response);
+    assert!(has_header(&response, "content-type: application/json"));
+    assert!(has_header(&response, "x-request-id: req-1"));
+
+    let body: serde_json::Value = serde_json::from_str(get_body(&response)).unwrap();
+    assert_eq!(body["code"], "InternalError");
+    assert_eq!(body["path"], "/panic");
+    assert_eq!(body["request_id"], "req-1");
+    assert_eq!(body["host"], "localhost");
+
+    // The server goes on serving after a panic.
+    let response = get(port, "/ok").await;
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn not_found_is_answered_by_the_factory() {
+    let (port, handle) = start_server(true).await;
+
+    let response = get(port, "/missing").await;
+    assert!(response.starts_with("HTTP/1.1 404"), "{}", response);
+    assert!(has_header(&response, "content-type: application/json"));
+
+    let body: serde_json::Value = serde_json::from_str(get_body(&response)).unwrap();
+    assert_eq!(body["code"], "NotFound");
+    assert_eq!(body["path"], "/missing");
+    assert_eq!(body["request_id"], "req-1");
+    assert_eq!(body["host"], "localhost");
+
+    handle.shutdown().await;
+}
@@ -0,0 +1,182 @@
+//! End-to-end coverage of `HttpServerHandle::shutdown` against a real server: a request already in
+//! flight must be answered, the listener must stop accepting at once, and a request that outlives
+//! the drain timeout must not hold the shutdown up.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use my_http_server::controllers::ControllersMiddleware;
+use my_http_server::{HttpServerHandle, MyHttpServer};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::test_common::*;
+
+pub mod slow {
+    use my_http_server::macros::*;
+    use my_http_server::*;
+
+    #[http_route(
+        method: "GET",
+        route: "/slow",
+        controller: "Test",
+        summary: "Slow",
+        description: "Answers after half a second",
+        result: [
+            { status_code: 200, description: "Ok" },
+        ]
+    )]
+    pub struct SlowAction;
+
+    async fn handle_request(
+        _action: &SlowAction,
+        _ctx: &mut HttpContext,
+    ) -> Result<HttpOkResult, HttpFailResult> {
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+
+        HttpOutput::as_text("done".to_string())
+            .into_ok_result(true)
+            .into()
+    }
+}
+
+pub mod hanging {
+    use my_http_server::macros::*;
+    use my_http_server::*;
+
+    #[http_route(
+        method: "GET",
+        route: "/hanging",
+        controller: "Test",
+        summary: "Hanging",
+        description: "Takes longer than any drain timeout in these tests",
+        result: [
+            { status_code: 200, description: "Ok" },
+        ]
+    )]
+    pub struct HangingAction;
+
+    async fn handle_request(
+        _action: &HangingAction,
+        _ctx: &mut HttpContext,
+    ) -> Result<HttpOkResult, HttpFailResult> {
+        tokio::time::sleep(std::time::Duration::from_secs(30)).await;
+
+        HttpOutput::as_text("too late".to_string())
+            .into_ok_result(true)
+            .into()
+    }
+}
+
+async fn start_server(graceful_shutdown_timeout: Duration) -> (u16, HttpServerHandle) {
+    let port = free_port();
+
+    let mut controllers = ControllersMiddleware::new(None, None);
+    controllers.register_get_action(Arc::new(slow::SlowAction));
+    controllers.register_get_action(Arc::new(hanging::HangingAction));
+
+    let mut server = MyHttpServer::new(SocketAddr::from(([127, 0, 0, 1], port)));
+    server.add_middleware(Arc::new(controllers));
+    server.set_graceful_shutdown_timeout(graceful_shutdown_timeout);
+
+    let app_states = app_states();
+
+    let handle = server.start_h1(app_states, Arc::new(NoLogger));
+
+    for _ in 0..100 {
+        if TcpStream::connect(("127.0.0.1", port)).await.is_ok() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    (port, handle)
+}
+
+/// Sends the request and hands back the connection, so the response can be read after the
+/// shutdown has started.
+async fn send_get(port: u16, path: &str) -> TcpStream {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path);
+    stream.write_all(request.as_bytes()).await.unwrap();
+    stream.flush().await.unwrap();
+    stream
+}
+
+async fn read_response(mut stream: TcpStream) -> String {
+    let mut buf = Vec::new();
+    let _ = tokio::time::timeout(Duration::from_secs(10), stream.read_to_end(&mut buf)).await;
+    String::from_utf8_lossy(&buf).to_string()
+}
+
+/// A request the server is working on when the shutdown starts is answered, and the keep-alive
+/// connection it came on is closed right after rather than left for the next request.
+#[tokio::test]
+async fn a_request_in_flight_is_answered() {
+    let (port, handle) = start_server(Duration::from_secs(5)).await;
+
+    let stream = send_get(port, "/slow").await;
+
+    // Let the request reach the action before shutting down.
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let shutdown = tokio::spawn(async move { handle.shutdown().await });
+
+    let response = read_response(stream).await;
+
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(response.ends_with("done"), "{}", response);
+
+    tokio::time::timeout(Duration::from_secs(5), shutdown)
+        .await
+        .expect("shutdown must complete once the connection is drained")
+        .unwrap();
+}
+
+/// Once the shutdown has started, the listener is gone.
+#[tokio::test]
+async fn no_connections_are_accepted_after_shutdown() {
+    let (port, handle) = start_server(Duration::from_secs(5)).await;
+
+    handle.shutdown().await;
+
+    // The accept loop drops the listener once it sees the shutdown — give its task a moment.
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    assert!(TcpStream::connect(("127.0.0.1", port)).await.is_err());
+}
+
+/// A request that is still running when the drain timeout is up is cut off: the shutdown
+/// completes on time and the client gets the connection closed, not a response.
+#[tokio::test]
+async fn a_request_past_the_deadline_is_force_closed() {
+    let (port, handle) = start_server(Duration::from_millis(200)).await;
+
+    let stream = send_get(port, "/hanging").await;
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    tokio::time::timeout(Duration::from_secs(5), handle.shutdown())
+        .await
+        .expect("shutdown must not wait for a request past the deadline");
+
+    let response = read_response(stream).await;
+
+    assert_eq!(response, "");
+}
+
+/// Calling shutdown twice is fine — the second call waits for the sequence the first started.
+#[tokio::test]
+async fn shutdown_can_be_awaited_more_than_once() {
+    let (_, handle) = start_server(Duration::from_secs(5)).await;
+
+    let first = handle.clone();
+    let first = tokio::spawn(async move { first.shutdown().await });
+
+    tokio::time::timeout(Duration::from_secs(5), handle.shutdown())
+        .await
+        .unwrap();
+
+    first.await.unwrap();
+}
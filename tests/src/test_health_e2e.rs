@@ -0,0 +1,170 @@
+//! End-to-end coverage of `MyHttpServer::set_health`: liveness answers whatever the state,
+//! readiness follows the application states and the checks, and with a shutdown delay the server
+//! goes on answering — unready — after the application starts shutting down.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use my_http_server::*;
+use rust_extensions::ApplicationStates;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::test_common::*;
+
+#[derive(Default)]
+struct TestAppStates {
+    initialized: AtomicBool,
+    shutting_down: AtomicBool,
+}
+
+impl ApplicationStates for TestAppStates {
+    fn is_initialized(&self) -> bool {
+        self.initialized.load(Ordering::SeqCst)
+    }
+
+    fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::SeqCst)
+    }
+}
+
+/// Healthy unless told to fail.
+#[derive(Default)]
+struct DbCheck {
+    fail: AtomicBool,
+}
+
+#[async_trait::async_trait]
+impl HttpHealthCheck for DbCheck {
+    async fn check(&self) -> Result<(), String> {
+        if self.fail.load(Ordering::SeqCst) {
+            return Err("Connection refused".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+struct AuthMiddleware;
+
+#[async_trait::async_trait]
+impl HttpServerMiddleware for AuthMiddleware {
+    async fn handle_request(
+        &self,
+        _ctx: &mut HttpContext,
+    ) -> Option<Result<HttpOkResult, HttpFailResult>> {
+        Some(Err(HttpFailResult::as_unauthorized(None)))
+    }
+}
+
+async fn start_server(
+    app_states: Arc<TestAppStates>,
+    db_check: Arc<DbCheck>,
+    shutdown_delay: Duration,
+) -> (u16, HttpServerHandle) {
+    let (listener, port) = bind_local_port();
+
+    let mut server = MyHttpServer::new_from_std_tcp_listener(listener);
+    server.set_health(HttpHealth::new().with_check("db", db_check));
+    server.set_shutdown_delay(shutdown_delay);
+    server.add_middleware(Arc::new(AuthMiddleware));
+
+    let handle = server.start_h1(app_states, Arc::new(NoLogger));
+
+    (port, handle)
+}
+
+async fn get(port: u16, path: &str) -> (u16, serde_json::Value) {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+        path
+    );
+
+    stream.write_all(request.as_bytes()).await.unwrap();
+
+    let mut buf = Vec::new();
+    let _ = tokio::time::timeout(Duration::from_secs(10), stream.read_to_end(&mut buf)).await;
+    let response = String::from_utf8_lossy(&buf).to_string();
+
+    let status_code = response[9..12].parse().unwrap();
+    let body = response.split("\r\n\r\n").nth(1).unwrap_or_default();
+
+    (status_code, serde_json::from_str(body).unwrap_or_default())
+}
+
+#[tokio::test]
+async fn readiness_follows_the_initialization_and_the_checks() {
+    let app_states = Arc::new(TestAppStates::default());
+    let db_check = Arc::new(DbCheck::default());
+    let (port, handle) = start_server(app_states.clone(), db_check.clone(), Duration::ZERO).await;
+
+    // Reachable past the middleware that rejects everything else.
+    let (status_code, body) = get(port, "/health/live").await;
+    assert_eq!(status_code, 200);
+    assert_eq!(body["status"], "Healthy");
+
+    let (status_code, body) = get(port, "/health/ready").await;
+    assert_eq!(status_code, 503);
+    assert_eq!(body["initialized"], false);
+
+    app_states.initialized.store(true, Ordering::SeqCst);
+
+    let (status_code, body) = get(port, "/health/ready").await;
+    assert_eq!(status_code, 200);
+    assert_eq!(body["status"], "Healthy");
+    assert_eq!(body["checks"][0]["name"], "db");
+    assert_eq!(body["checks"][0]["status"], "Healthy");
+    assert!(body["checks"][0]["latency_ms"].is_number());
+
+    db_check.fail.store(true, Ordering::SeqCst);
+
+    let (status_code, body) = get(port, "/health/ready").await;
+    assert_eq!(status_code, 503);
+    assert_eq!(body["checks"][0]["status"], "Unhealthy");
+    assert_eq!(body["checks"][0]["error"], "Connection refused");
+
+    let (status_code, _) = get(port, "/health/live").await;
+    assert_eq!(status_code, 200);
+
+    let (status_code, _) = get(port, "/api/users").await;
+    assert_eq!(status_code, 401);
+
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn readiness_turns_unhealthy_before_the_listener_closes() {
+    let app_states = Arc::new(TestAppStates::default());
+    app_states.initialized.store(true, Ordering::SeqCst);
+
+    let (port, handle) = start_server(
+        app_states.clone(),
+        Arc::new(DbCheck::default()),
+        Duration::from_secs(1),
+    )
+    .await;
+
+    let (status_code, _) = get(port, "/health/ready").await;
+    assert_eq!(status_code, 200);
+
+    app_states.shutting_down.store(true, Ordering::SeqCst);
+
+    // Let the server notice the application states.
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let (status_code, body) = get(port, "/health/ready").await;
+    assert_eq!(status_code, 503);
+    assert_eq!(body["shutting_down"], true);
+
+    let (status_code, _) = get(port, "/health/live").await;
+    assert_eq!(status_code, 200);
+
+    // Once the delay is up, the server stops accepting.
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    assert!(TcpStream::connect(("127.0.0.1", port)).await.is_err());
+
+    handle.shutdown().await;
+}
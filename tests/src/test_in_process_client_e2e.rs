@@ -0,0 +1,201 @@
+//! End-to-end coverage of `HttpTestClient`: requests built in memory go through the middlewares
+//! and the controllers of a server that is never started — `#[http_route]` actions, their input
+//! models and streamed bodies included.
+
+use std::sync::Arc;
+
+use my_http_server::controllers::ControllersMiddleware;
+use my_http_server::*;
+
+use crate::test_common::*;
+
+// One `handle_request` per module: `#[http_route]` generates a call to a free function of that
+// name, so two actions can not share a module.
+
+pub mod rename_action {
+    use my_http_server::macros::*;
+    use my_http_server::*;
+    use serde::Serialize;
+
+    #[derive(MyHttpInput)]
+    pub struct RenameHttpInput {
+        #[http_path(name = "id", description = "Id of the user")]
+        pub id: String,
+
+        #[http_body(name = "name", description = "New name")]
+        pub name: String,
+    }
+
+    #[derive(Serialize, MyHttpObjectStructure)]
+    pub struct RenameHttpResponse {
+        pub id: String,
+        pub name: String,
+    }
+
+    #[http_route(
+        method: "PUT",
+        route: "/users/{id}",
+        controller: "Test",
+        summary: "Rename",
+        description: "Renames the user",
+        input_data: "RenameHttpInput",
+        result: [
+            { status_code: 200, description: "Ok", model: "RenameHttpResponse" },
+        ]
+    )]
+    pub struct RenameAction;
+
+    async fn handle_request(
+        _action: &RenameAction,
+        input_data: RenameHttpInput,
+        _ctx: &mut HttpContext,
+    ) -> Result<HttpOkResult, HttpFailResult> {
+        let response = RenameHttpResponse {
+            id: input_data.id,
+            name: input_data.name,
+        };
+
+        HttpOutput::as_json(response).into_ok_result(true).into()
+    }
+}
+
+pub mod upload_action {
+    use my_http_server::macros::*;
+    use my_http_server::*;
+
+    #[derive(MyHttpInput)]
+    pub struct UploadHttpInput {
+        #[http_body_as_stream(description = "File content")]
+        pub body: HttpBodyAsStream,
+    }
+
+    #[http_route(
+        method: "POST",
+        route: "/upload",
+        controller: "Test",
+        summary: "Upload",
+        description: "Reads the body as a stream",
+        input_data: "UploadHttpInput",
+        result: [
+            { status_code: 200, description: "Ok" },
+        ]
+    )]
+    pub struct UploadAction;
+
+    /// Answers with how much of the body arrived, or with the error reading it ended in.
+    async fn handle_request(
+        _action: &UploadAction,
+        input_data: UploadHttpInput,
+        _ctx: &mut HttpContext,
+    ) -> Result<HttpOkResult, HttpFailResult> {
+        let reader = input_data.body.get_body_reader()?;
+
+        let mut total = 0usize;
+        let mut chunks = 0usize;
+
+        loop {
+            match reader.get_next_chunk().await {
+                Ok(Some(chunk)) => {
+                    chunks += 1;
+                    total += chunk.len();
+                }
+                Ok(None) => break,
+                Err(err) => {
+                    return HttpOutput::as_text(format!("err:{}", err))
+                        .into_ok_result(true)
+                        .into();
+                }
+            }
+        }
+
+        HttpOutput::as_text(format!("total={},chunks={}", total, chunks))
+            .into_ok_result(true)
+            .into()
+    }
+}
+
+/// Turns away everything under `/admin`, so the test can tell the middlewares ran.
+struct AdminGuardMiddleware;
+
+#[async_trait::async_trait]
+impl HttpServerMiddleware for AdminGuardMiddleware {
+    async fn handle_request(
+        &self,
+        ctx: &mut HttpContext,
+    ) -> Option<Result<HttpOkResult, HttpFailResult>> {
+        if ctx.request.get_path().as_str().starts_with("/admin") {
+            return Some(Err(HttpFailResult::as_forbidden(Some("Admins only"))));
+        }
+
+        None
+    }
+}
+
+fn create_client() -> HttpTestClient {
+    let mut controllers = ControllersMiddleware::new(None, None);
+    controllers.register_put_action(Arc::new(rename_action::RenameAction));
+    controllers.register_post_action(Arc::new(upload_action::UploadAction));
+
+    // Never bound: the address is only what the server would listen on.
+    let mut server = MyHttpServer::new("127.0.0.1:0".parse().unwrap());
+    server.add_middleware(Arc::new(AdminGuardMiddleware));
+    server.add_middleware(Arc::new(controllers));
+
+    server.create_test_client(app_states(), Arc::new(NoLogger))
+}
+
+fn chunks(count: usize, size: usize) -> impl futures::Stream<Item = bytes::Bytes> {
+    futures::stream::iter((0..count).map(move |_| bytes::Bytes::from(vec![b'a'; size])))
+}
+
+#[tokio::test]
+async fn request_reaches_the_action() {
+    let client = create_client();
+
+    let response = client
+        .send(HttpTestRequest::put("/users/42").with_json(&serde_json::json!({ "name": "John" })))
+        .await;
+
+    assert_eq!(response.status_code, 200, "{}", response.body_as_str());
+
+    let body: serde_json::Value = response.body_as_json();
+    assert_eq!(body["id"], "42");
+    assert_eq!(body["name"], "John");
+
+    let response = client.get("/admin/users").await;
+    assert_eq!(response.status_code, 403);
+
+    let response = client.get("/missing").await;
+    assert_eq!(response.status_code, 404);
+}
+
+#[tokio::test]
+async fn streamed_body_reaches_the_action_in_chunks() {
+    let client = create_client();
+
+    let response = client
+        .send(HttpTestRequest::post("/upload").with_body_stream(chunks(4, 1000)))
+        .await;
+
+    assert_eq!(response.status_code, 200);
+    assert_eq!(response.body_as_str(), "total=4000,chunks=4");
+}
+
+#[tokio::test]
+async fn stream_shorter_than_its_content_length_is_an_error() {
+    let client = create_client();
+
+    let response = client
+        .send(
+            HttpTestRequest::post("/upload")
+                .with_header("content-length", "10000")
+                .with_body_stream(chunks(4, 1000)),
+        )
+        .await;
+
+    assert!(
+        response.body_as_str().starts_with("err:"),
+        "{}",
+        response.body_as_str()
+    );
+}
@@ -0,0 +1,150 @@
+//! End-to-end coverage of `MyHttpServer::add_listener`: every listener is served by the same
+//! middlewares, and a request knows which listener it came in on.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use my_http_server::controllers::ControllersMiddleware;
+use my_http_server::{HttpListener, HttpServerHandle, MyHttpServer};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::test_common::*;
+
+pub mod listener_name {
+    use my_http_server::macros::*;
+    use my_http_server::*;
+
+    #[http_route(
+        method: "GET",
+        route: "/listener",
+        controller: "Test",
+        summary: "Listener",
+        description: "Answers with the name of the listener the request came in on",
+        result: [
+            { status_code: 200, description: "Ok" },
+        ]
+    )]
+    pub struct ListenerNameAction;
+
+    async fn handle_request(
+        _action: &ListenerNameAction,
+        ctx: &mut HttpContext,
+    ) -> Result<HttpOkResult, HttpFailResult> {
+        HttpOutput::as_text(ctx.request.get_listener_name().to_string())
+            .into_ok_result(true)
+            .into()
+    }
+}
+
+async fn wait_for_port(port: u16) {
+    for _ in 0..100 {
+        if TcpStream::connect(("127.0.0.1", port)).await.is_ok() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+/// Starts a server on a public and an admin port.
+async fn start_server() -> (u16, u16, HttpServerHandle) {
+    let public_port = free_port();
+    let admin_port = free_port();
+
+    let mut controllers = ControllersMiddleware::new(None, None);
+    controllers.register_get_action(Arc::new(listener_name::ListenerNameAction));
+
+    let mut server = MyHttpServer::new(SocketAddr::from(([127, 0, 0, 1], public_port)));
+    server.add_listener(HttpListener::tcp(
+        "admin",
+        SocketAddr::from(([127, 0, 0, 1], admin_port)),
+    ));
+    server.add_middleware(Arc::new(controllers));
+
+    let app_states = app_states();
+
+    let handle = server.start_h1(app_states, Arc::new(NoLogger));
+
+    wait_for_port(public_port).await;
+    wait_for_port(admin_port).await;
+
+    (public_port, admin_port, handle)
+}
+
+async fn get(port: u16, path: &str) -> String {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+        path
+    );
+    stream.write_all(request.as_bytes()).await.unwrap();
+
+    let mut buf = Vec::new();
+    let _ = tokio::time::timeout(Duration::from_secs(10), stream.read_to_end(&mut buf)).await;
+    String::from_utf8_lossy(&buf).to_string()
+}
+
+#[tokio::test]
+async fn each_listener_is_served_and_reports_its_name() {
+    let (public_port, admin_port, handle) = start_server().await;
+
+    let response = get(public_port, "/listener").await;
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(
+        response.ends_with(my_http_server::DEFAULT_LISTENER_NAME),
+        "{}",
+        response
+    );
+
+    let response = get(admin_port, "/listener").await;
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(response.ends_with("admin"), "{}", response);
+
+    handle.shutdown().await;
+}
+
+/// One shutdown closes every listener of the server.
+#[tokio::test]
+async fn shutdown_stops_every_listener() {
+    let (public_port, admin_port, handle) = start_server().await;
+
+    handle.shutdown().await;
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    assert!(TcpStream::connect(("127.0.0.1", public_port))
+        .await
+        .is_err());
+    assert!(TcpStream::connect(("127.0.0.1", admin_port)).await.is_err());
+}
+
+/// A listener bound before the server starts — the way one inherited from systemd or from the
+/// previous version of the binary arrives — is served, and handed out again for the next one.
+#[cfg(unix)]
+#[tokio::test]
+async fn pre_bound_listener_is_served_and_handed_over() {
+    let (std_listener, port) = bind_local_port();
+
+    let mut controllers = ControllersMiddleware::new(None, None);
+    controllers.register_get_action(Arc::new(listener_name::ListenerNameAction));
+
+    let mut server = MyHttpServer::new_from_std_tcp_listener(std_listener);
+    server.add_middleware(Arc::new(controllers));
+
+    let app_states = app_states();
+
+    let handle = server.start_h1(app_states, Arc::new(NoLogger));
+
+    let response = get(port, "/listener").await;
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+
+    let listen_fds = handle.get_listen_fds();
+    assert_eq!(listen_fds.len(), 1);
+    assert_eq!(
+        listen_fds[0].name.as_deref(),
+        Some(my_http_server::DEFAULT_LISTENER_NAME)
+    );
+
+    handle.shutdown().await;
+}
@@ -0,0 +1,147 @@
+//! End-to-end coverage of `MyHttpServer::set_metrics`: requests are counted per route template of
+//! the action — not per path — and status class, and the metrics are served in the Prometheus
+//! text format on the path they are given.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use my_http_server::controllers::ControllersMiddleware;
+use my_http_server::*;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::test_common::*;
+
+pub mod user_action {
+    use my_http_server::macros::*;
+    use my_http_server::*;
+
+    #[http_route(
+        method: "GET",
+        route: "/users/{id}",
+        controller: "Test",
+        summary: "User",
+        description: "Answers with a user",
+        result: [
+            { status_code: 200, description: "Ok" },
+        ]
+    )]
+    pub struct UserAction;
+
+    async fn handle_request(
+        _action: &UserAction,
+        _ctx: &mut HttpContext,
+    ) -> Result<HttpOkResult, HttpFailResult> {
+        HttpOutput::as_text("user").into_ok_result(true).into()
+    }
+}
+
+async fn start_server(metrics: HttpServerMetrics) -> (u16, HttpServerHandle) {
+    let (listener, port) = bind_local_port();
+
+    let mut controllers = ControllersMiddleware::new(None, None);
+    controllers.register_get_action(Arc::new(user_action::UserAction));
+
+    let mut server = MyHttpServer::new_from_std_tcp_listener(listener);
+    server.set_metrics(metrics);
+    server.add_middleware(Arc::new(controllers));
+
+    let app_states = app_states();
+
+    let handle = server.start_h1(app_states, Arc::new(NoLogger));
+
+    (port, handle)
+}
+
+async fn get(port: u16, path: &str) -> String {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+        path
+    );
+
+    stream.write_all(request.as_bytes()).await.unwrap();
+
+    let mut buf = Vec::new();
+    let _ = tokio::time::timeout(Duration::from_secs(10), stream.read_to_end(&mut buf)).await;
+    String::from_utf8_lossy(&buf).to_string()
+}
+
+/// The requests are counted after they are answered.
+async fn wait_for_metrics(port: u16, path: &str, expected: &str) -> String {
+    for _ in 0..100 {
+        let response = get(port, path).await;
+
+        if response.contains(expected) {
+            return response;
+        }
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    panic!("Metrics never had {}", expected);
+}
+
+#[tokio::test]
+async fn requests_are_counted_per_route_template() {
+    let (port, handle) = start_server(HttpServerMetrics::new()).await;
+
+    for path in ["/users/1", "/users/2", "/missing"] {
+        get(port, path).await;
+    }
+
+    let response = wait_for_metrics(
+        port,
+        "/metrics",
+        "http_server_requests_total{method=\"GET\",route=\"/users/{id}\",status=\"2xx\"} 2\n",
+    )
+    .await;
+
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(
+        response.contains("text/plain; version=0.0.4"),
+        "{}",
+        response
+    );
+    assert!(
+        response.contains("route=\"unmatched\",status=\"4xx\"} 1\n"),
+        "{}",
+        response
+    );
+    assert!(!response.contains("/users/1"), "{}", response);
+    assert!(
+        response.contains("# TYPE http_server_open_connections gauge\n"),
+        "{}",
+        response
+    );
+    assert!(
+        response.contains("http_server_requests_in_flight 1\n"),
+        "{}",
+        response
+    );
+
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn metrics_path_is_configurable() {
+    let metrics = HttpServerMetrics::new().with_path("/internal/metrics");
+
+    let (port, handle) = start_server(metrics.clone()).await;
+
+    let response = get(port, "/metrics").await;
+    assert!(response.starts_with("HTTP/1.1 404"), "{}", response);
+
+    let response = wait_for_metrics(
+        port,
+        "/internal/metrics",
+        "route=\"unmatched\",status=\"4xx\"} 1\n",
+    )
+    .await;
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+
+    assert!(metrics.render().contains("http_server_panics_total 0\n"));
+
+    handle.shutdown().await;
+}
@@ -0,0 +1,204 @@
+//! End-to-end coverage of `HttpMiddlewareGroup`: middlewares of a group only see the requests
+//! under its prefix — and of its methods — a nested group is under its parent, and a request the
+//! group lets through goes on to the controllers added after it.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use my_http_server::controllers::ControllersMiddleware;
+use my_http_server::*;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::test_common::*;
+
+pub mod admin_users_action {
+    use my_http_server::macros::*;
+    use my_http_server::*;
+
+    #[http_route(
+        method: "POST",
+        route: "/api/admin/users",
+        controller: "Test",
+        summary: "Admin",
+        description: "Only for admins",
+        result: [
+            { status_code: 200, description: "Ok" },
+        ]
+    )]
+    pub struct AdminUsersAction;
+
+    async fn handle_request(
+        _action: &AdminUsersAction,
+        _ctx: &mut HttpContext,
+    ) -> Result<HttpOkResult, HttpFailResult> {
+        HttpOutput::as_text("admin").into_ok_result(true).into()
+    }
+}
+
+pub mod admin_users_list_action {
+    use my_http_server::macros::*;
+    use my_http_server::*;
+
+    #[http_route(
+        method: "GET",
+        route: "/api/admin/users",
+        controller: "Test",
+        summary: "Admin",
+        description: "Readable by anybody",
+        result: [
+            { status_code: 200, description: "Ok" },
+        ]
+    )]
+    pub struct AdminUsersListAction;
+
+    async fn handle_request(
+        _action: &AdminUsersListAction,
+        _ctx: &mut HttpContext,
+    ) -> Result<HttpOkResult, HttpFailResult> {
+        HttpOutput::as_text("list").into_ok_result(true).into()
+    }
+}
+
+pub mod public_action {
+    use my_http_server::macros::*;
+    use my_http_server::*;
+
+    #[http_route(
+        method: "POST",
+        route: "/public",
+        controller: "Test",
+        summary: "Public",
+        description: "For everybody",
+        result: [
+            { status_code: 200, description: "Ok" },
+        ]
+    )]
+    pub struct PublicAction;
+
+    async fn handle_request(
+        _action: &PublicAction,
+        _ctx: &mut HttpContext,
+    ) -> Result<HttpOkResult, HttpFailResult> {
+        HttpOutput::as_text("public").into_ok_result(true).into()
+    }
+}
+
+/// Lets a request through only with the `X-Admin` header.
+struct AdminOnlyMiddleware;
+
+#[async_trait::async_trait]
+impl HttpServerMiddleware for AdminOnlyMiddleware {
+    async fn handle_request(
+        &self,
+        ctx: &mut HttpContext,
+    ) -> Option<Result<HttpOkResult, HttpFailResult>> {
+        let headers = ctx.request.get_headers();
+
+        if headers.try_get_case_insensitive("x-admin").is_some() {
+            return None;
+        }
+
+        Some(HttpFailResult::as_forbidden(Some("admins only")).into_err())
+    }
+}
+
+/// Marks the responses of the group it is in.
+struct ApiHeaderMiddleware;
+
+#[async_trait::async_trait]
+impl HttpServerAroundMiddleware for ApiHeaderMiddleware {
+    async fn handle_request(
+        &self,
+        ctx: &mut HttpContext,
+        next: HttpServerMiddlewareNext<'_>,
+    ) -> Result<HttpOkResult, HttpFailResult> {
+        let mut result = next.run(ctx).await;
+
+        let output = match &mut result {
+            Ok(ok_result) => &mut ok_result.output,
+            Err(fail_result) => &mut fail_result.output,
+        };
+
+        if let HttpOutput::Content { headers, .. } = output {
+            headers.add_header("x-api".into(), "yes".to_string());
+        }
+
+        result
+    }
+}
+
+async fn start_server() -> (u16, HttpServerHandle) {
+    let (listener, port) = bind_local_port();
+
+    let mut controllers = ControllersMiddleware::new(None, None);
+    controllers.register_post_action(Arc::new(admin_users_action::AdminUsersAction));
+    controllers.register_get_action(Arc::new(admin_users_list_action::AdminUsersListAction));
+    controllers.register_post_action(Arc::new(public_action::PublicAction));
+
+    let mut admin = HttpMiddlewareGroup::new("/admin").with_methods([hyper::Method::POST]);
+    admin.add_middleware(Arc::new(AdminOnlyMiddleware));
+
+    let mut api = HttpMiddlewareGroup::new("/api");
+    api.add_around_middleware(Arc::new(ApiHeaderMiddleware));
+    api.add_group(admin);
+
+    let mut server = MyHttpServer::new_from_std_tcp_listener(listener);
+    server.add_middleware_group(api);
+    server.add_middleware(Arc::new(controllers));
+
+    let app_states = app_states();
+
+    let handle = server.start_h1(app_states, Arc::new(NoLogger));
+
+    (port, handle)
+}
+
+async fn send(port: u16, method: &str, path: &str, admin: bool) -> String {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+
+    let admin_header = if admin { "X-Admin: 1\r\n" } else { "" };
+
+    let request = format!(
+        "{} {} HTTP/1.1\r\nHost: localhost\r\n{}Content-Length: 0\r\nConnection: close\r\n\r\n",
+        method, path, admin_header
+    );
+
+    stream.write_all(request.as_bytes()).await.unwrap();
+
+    let mut buf = Vec::new();
+    let _ = tokio::time::timeout(Duration::from_secs(10), stream.read_to_end(&mut buf)).await;
+    String::from_utf8_lossy(&buf).to_string()
+}
+
+#[tokio::test]
+async fn nested_group_guards_its_prefix_and_methods() {
+    let (port, handle) = start_server().await;
+
+    let response = send(port, "POST", "/api/admin/users", false).await;
+    assert!(response.starts_with("HTTP/1.1 403"), "{}", response);
+    assert!(response.contains("x-api: yes"), "{}", response);
+
+    let response = send(port, "POST", "/api/admin/users", true).await;
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(response.ends_with("admin"), "{}", response);
+
+    let response = send(port, "GET", "/api/admin/users", false).await;
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(response.ends_with("list"), "{}", response);
+
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn requests_outside_of_the_group_skip_it() {
+    let (port, handle) = start_server().await;
+
+    let response = send(port, "POST", "/public", false).await;
+
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(!response.contains("x-api"), "{}", response);
+    assert!(response.ends_with("public"), "{}", response);
+
+    handle.shutdown().await;
+}
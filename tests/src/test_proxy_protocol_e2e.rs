@@ -0,0 +1,121 @@
+//! End-to-end coverage of `MyHttpServer::set_proxy_protocol`: the client address and the TLVs of a
+//! v1 or v2 header reach the middlewares, and a connection that does not start with a header is
+//! closed without an answer.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use my_http_server::*;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::test_common::*;
+
+const PP2_TYPE_AUTHORITY: u8 = 0x02;
+
+/// Answers with the address of the request and the authority TLV of its PROXY header.
+struct ProxiedMiddleware;
+
+#[async_trait::async_trait]
+impl HttpServerMiddleware for ProxiedMiddleware {
+    async fn handle_request(
+        &self,
+        ctx: &mut HttpContext,
+    ) -> Option<Result<HttpOkResult, HttpFailResult>> {
+        let authority = ctx
+            .request
+            .connection
+            .proxy_protocol
+            .as_ref()
+            .and_then(|header| header.get_authority())
+            .unwrap_or("-")
+            .to_string();
+
+        let answer = format!("{} {}", ctx.request.addr.to_string(), authority);
+        Some(HttpOutput::as_text(answer).into_ok_result(false))
+    }
+}
+
+fn start_server() -> (u16, HttpServerHandle) {
+    let (listener, port) = bind_local_port();
+
+    let mut server = MyHttpServer::new_from_std_tcp_listener(listener);
+    server.set_proxy_protocol(true);
+    server.add_middleware(Arc::new(ProxiedMiddleware));
+
+    let handle = server.start_h1(app_states(), Arc::new(NoLogger));
+
+    (port, handle)
+}
+
+/// Sends `header` and a request after it, and returns what came back.
+async fn send(port: u16, header: &[u8]) -> String {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+
+    stream.write_all(header).await.unwrap();
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .await
+        .unwrap();
+
+    let mut buf = Vec::new();
+    let _ = tokio::time::timeout(Duration::from_secs(10), stream.read_to_end(&mut buf)).await;
+    String::from_utf8_lossy(&buf).to_string()
+}
+
+fn get_body(response: &str) -> &str {
+    response.split("\r\n\r\n").nth(1).unwrap_or_default()
+}
+
+/// A v2 header of a TCP over IPv4 connection from `1.2.3.4:1111`, with an authority TLV.
+fn v2_header(authority: &str) -> Vec<u8> {
+    let mut result = b"\r\n\r\n\0\r\nQUIT\n".to_vec();
+    result.push(0x21);
+    result.push(0x11);
+
+    let mut payload = Vec::new();
+    payload.extend_from_slice(&[1, 2, 3, 4]);
+    payload.extend_from_slice(&[5, 6, 7, 8]);
+    payload.extend_from_slice(&1111u16.to_be_bytes());
+    payload.extend_from_slice(&80u16.to_be_bytes());
+
+    payload.push(PP2_TYPE_AUTHORITY);
+    payload.extend_from_slice(&(authority.len() as u16).to_be_bytes());
+    payload.extend_from_slice(authority.as_bytes());
+
+    result.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+    result.extend_from_slice(&payload);
+    result
+}
+
+#[tokio::test]
+async fn v1_header_gives_the_client_address() {
+    let (port, handle) = start_server();
+
+    let response = send(port, b"PROXY TCP4 1.2.3.4 5.6.7.8 1111 80\r\n").await;
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert_eq!(get_body(&response), "1.2.3.4:1111 -");
+
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn v2_header_gives_the_client_address_and_the_tlvs() {
+    let (port, handle) = start_server();
+
+    let response = send(port, &v2_header("api.example.test")).await;
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert_eq!(get_body(&response), "1.2.3.4:1111 api.example.test");
+
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn connection_without_header_is_closed() {
+    let (port, handle) = start_server();
+
+    let response = send(port, b"").await;
+    assert_eq!(response, "");
+
+    handle.shutdown().await;
+}
@@ -0,0 +1,228 @@
+//! End-to-end coverage of request bodies sent with `Content-Encoding`: actions get the plaintext,
+//! whether they materialize the body or stream it, and a body that inflates past
+//! `MyHttpServer::set_max_decompressed_body_size` is refused.
+
+use std::io::Write;
+use std::sync::Arc;
+use std::time::Duration;
+
+use my_http_server::controllers::ControllersMiddleware;
+use my_http_server::{HttpServerHandle, MyHttpServer};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::test_common::*;
+
+pub mod upload_raw_action {
+    use my_http_server::macros::*;
+    use my_http_server::*;
+
+    #[derive(MyHttpInput)]
+    pub struct UploadRawHttpInput {
+        #[http_body_raw(description = "File content")]
+        pub body: RawData,
+    }
+
+    #[http_route(
+        method: "POST",
+        route: "/upload-raw",
+        controller: "Test",
+        summary: "Upload raw",
+        description: "Materializes the whole body",
+        input_data: "UploadRawHttpInput",
+        result: [
+            { status_code: 200, description: "Ok" },
+        ]
+    )]
+    pub struct UploadRawAction;
+
+    async fn handle_request(
+        _action: &UploadRawAction,
+        input_data: UploadRawHttpInput,
+        _ctx: &mut HttpContext,
+    ) -> Result<HttpOkResult, HttpFailResult> {
+        HttpOutput::as_text(super::describe(input_data.body.as_slice()))
+            .into_ok_result(true)
+            .into()
+    }
+}
+
+pub mod upload_stream_action {
+    use my_http_server::macros::*;
+    use my_http_server::*;
+
+    #[derive(MyHttpInput)]
+    pub struct UploadStreamHttpInput {
+        #[http_body_as_stream(description = "File content")]
+        pub body: HttpBodyAsStream,
+    }
+
+    #[http_route(
+        method: "POST",
+        route: "/upload-stream",
+        controller: "Test",
+        summary: "Upload stream",
+        description: "Reads the body as a stream",
+        input_data: "UploadStreamHttpInput",
+        result: [
+            { status_code: 200, description: "Ok" },
+        ]
+    )]
+    pub struct UploadStreamAction;
+
+    async fn handle_request(
+        _action: &UploadStreamAction,
+        input_data: UploadStreamHttpInput,
+        _ctx: &mut HttpContext,
+    ) -> Result<HttpOkResult, HttpFailResult> {
+        let reader = input_data.body.get_body_reader()?;
+
+        let mut body = Vec::new();
+
+        while let Some(chunk) = reader.get_next_chunk().await? {
+            body.extend_from_slice(&chunk);
+        }
+
+        HttpOutput::as_text(super::describe(&body))
+            .into_ok_result(true)
+            .into()
+    }
+}
+
+fn describe(body: &[u8]) -> String {
+    format!(
+        "total={},sum={}",
+        body.len(),
+        body.iter().map(|b| *b as u64).sum::<u64>()
+    )
+}
+
+async fn start_server() -> (u16, HttpServerHandle) {
+    let (listener, port) = bind_local_port();
+
+    let mut controllers = ControllersMiddleware::new(None, None);
+    controllers.register_post_action(Arc::new(upload_raw_action::UploadRawAction));
+    controllers.register_post_action(Arc::new(upload_stream_action::UploadStreamAction));
+
+    let mut server = MyHttpServer::new_from_std_tcp_listener(listener);
+    server.set_max_decompressed_body_size(1024 * 1024);
+    server.add_middleware(Arc::new(controllers));
+
+    let app_states = app_states();
+
+    let handle = server.start_h1(app_states, Arc::new(NoLogger));
+
+    (port, handle)
+}
+
+async fn post(port: u16, path: &str, content_encoding: &str, body: &[u8]) -> String {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+
+    let mut request = format!(
+        "POST {} HTTP/1.1\r\nHost: localhost\r\nContent-Encoding: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        path,
+        content_encoding,
+        body.len()
+    )
+    .into_bytes();
+    request.extend_from_slice(body);
+
+    stream.write_all(&request).await.unwrap();
+
+    let mut buf = Vec::new();
+    let _ = tokio::time::timeout(Duration::from_secs(10), stream.read_to_end(&mut buf)).await;
+    String::from_utf8_lossy(&buf).to_string()
+}
+
+fn plaintext() -> Vec<u8> {
+    (0..200_000).map(|i| (i % 251) as u8).collect()
+}
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn zstd(data: &[u8]) -> Vec<u8> {
+    zstd::stream::encode_all(data, 3).unwrap()
+}
+
+#[tokio::test]
+async fn compressed_body_reaches_a_materializing_action_as_plaintext() {
+    let (port, handle) = start_server().await;
+    let body = plaintext();
+
+    for (content_encoding, compressed) in [
+        ("gzip", gzip(&body)),
+        ("deflate", deflate(&body)),
+        ("zstd", zstd(&body)),
+    ] {
+        let response = post(port, "/upload-raw", content_encoding, &compressed).await;
+
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(
+            response.ends_with(&describe(&body)),
+            "{}: {}",
+            content_encoding,
+            response
+        );
+    }
+
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn compressed_body_is_streamed_as_plaintext() {
+    let (port, handle) = start_server().await;
+    let body = plaintext();
+
+    for (content_encoding, compressed) in [
+        ("gzip", gzip(&body)),
+        ("deflate", deflate(&body)),
+        ("zstd", zstd(&body)),
+    ] {
+        let response = post(port, "/upload-stream", content_encoding, &compressed).await;
+
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(
+            response.ends_with(&describe(&body)),
+            "{}: {}",
+            content_encoding,
+            response
+        );
+    }
+
+    handle.shutdown().await;
+}
+
+/// A few kilobytes that inflate to 10 MB — over the 1 MB the server allows.
+#[tokio::test]
+async fn decompression_bomb_is_refused_with_413() {
+    let (port, handle) = start_server().await;
+    let bomb = gzip(&vec![0u8; 10 * 1024 * 1024]);
+
+    let response = post(port, "/upload-raw", "gzip", &bomb).await;
+    assert!(response.starts_with("HTTP/1.1 413"), "{}", response);
+
+    let response = post(port, "/upload-stream", "gzip", &bomb).await;
+    assert!(response.starts_with("HTTP/1.1 413"), "{}", response);
+
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn unsupported_encoding_is_refused_with_415() {
+    let (port, handle) = start_server().await;
+
+    let response = post(port, "/upload-raw", "compress", b"whatever").await;
+    assert!(response.starts_with("HTTP/1.1 415"), "{}", response);
+
+    handle.shutdown().await;
+}
@@ -0,0 +1,176 @@
+//! End-to-end coverage of the request id: taken from `X-Request-Id` or generated, seen by the
+//! handler, echoed back in the response and written into the log lines of the request.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use my_http_server::*;
+use rust_extensions::Logger;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::test_common::*;
+
+/// Answers with the id of the request — and panics on `/panic`.
+struct RequestIdMiddleware;
+
+#[async_trait::async_trait]
+impl HttpServerMiddleware for RequestIdMiddleware {
+    async fn handle_request(
+        &self,
+        ctx: &mut HttpContext,
+    ) -> Option<Result<HttpOkResult, HttpFailResult>> {
+        if ctx.request.http_path.as_str() == "/panic" {
+            panic!("boom");
+        }
+
+        Some(HttpOutput::as_text(ctx.get_request_id().to_string()).into_ok_result(false))
+    }
+}
+
+/// Keeps the contexts of the errors written.
+#[derive(Default)]
+struct ErrorContexts(Mutex<Vec<HashMap<String, String>>>);
+
+impl Logger for ErrorContexts {
+    fn write_info(&self, _p: String, _m: String, _c: Option<HashMap<String, String>>) {}
+    fn write_warning(&self, _p: String, _m: String, _c: Option<HashMap<String, String>>) {}
+    fn write_error(&self, _p: String, _m: String, ctx: Option<HashMap<String, String>>) {
+        if let Some(ctx) = ctx {
+            self.0.lock().unwrap().push(ctx);
+        }
+    }
+    fn write_fatal_error(&self, _p: String, _m: String, _c: Option<HashMap<String, String>>) {}
+    fn write_debug_info(&self, _p: String, _m: String, _c: Option<HashMap<String, String>>) {}
+}
+
+async fn start_server(
+    request_id_header: Option<&str>,
+) -> (u16, HttpServerHandle, Arc<ErrorContexts>) {
+    let (listener, port) = bind_local_port();
+
+    let logger = Arc::new(ErrorContexts::default());
+
+    let mut server = MyHttpServer::new_from_std_tcp_listener(listener);
+    if let Some(request_id_header) = request_id_header {
+        server.set_request_id_header(request_id_header);
+    }
+    server.add_middleware(Arc::new(RequestIdMiddleware));
+
+    let app_states = app_states();
+
+    let handle = server.start_h1(app_states, logger.clone());
+
+    (port, handle, logger)
+}
+
+async fn get(port: u16, path: &str, headers: &str) -> String {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: localhost\r\n{}Connection: close\r\n\r\n",
+        path, headers
+    );
+
+    stream.write_all(request.as_bytes()).await.unwrap();
+
+    let mut buf = Vec::new();
+    let _ = tokio::time::timeout(Duration::from_secs(10), stream.read_to_end(&mut buf)).await;
+    String::from_utf8_lossy(&buf).to_string()
+}
+
+fn get_header<'s>(response: &'s str, name: &str) -> Option<&'s str> {
+    let head = response.split("\r\n\r\n").next()?;
+
+    head.lines().skip(1).find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.eq_ignore_ascii_case(name).then(|| value.trim())
+    })
+}
+
+fn get_body(response: &str) -> &str {
+    response.split("\r\n\r\n").nth(1).unwrap_or_default()
+}
+
+#[tokio::test]
+async fn incoming_id_is_seen_by_the_handler_and_echoed_back() {
+    let (port, handle, _) = start_server(None).await;
+
+    let response = get(port, "/", "X-Request-Id: abc-123\r\n").await;
+
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert_eq!(
+        get_header(&response, "x-request-id"),
+        Some("abc-123"),
+        "{}",
+        response
+    );
+    assert_eq!(get_body(&response), "abc-123");
+
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn id_is_generated_when_the_request_has_none() {
+    let (port, handle, _) = start_server(None).await;
+
+    let response = get(port, "/", "X-Request-Id: has spaces\r\n").await;
+
+    let request_id = get_header(&response, "x-request-id").unwrap();
+    assert_ne!(request_id, "has spaces");
+    assert_eq!(request_id.len(), 36, "{}", response);
+    assert_eq!(get_body(&response), request_id);
+
+    let other = get(port, "/", "").await;
+    assert_ne!(get_header(&other, "x-request-id"), Some(request_id));
+
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn id_is_taken_from_the_configured_header() {
+    let (port, handle, _) = start_server(Some("X-Correlation-Id")).await;
+
+    let response = get(
+        port,
+        "/",
+        "X-Correlation-Id: corr-1\r\nX-Request-Id: other\r\n",
+    )
+    .await;
+
+    assert_eq!(
+        get_header(&response, "x-correlation-id"),
+        Some("corr-1"),
+        "{}",
+        response
+    );
+    assert_eq!(get_header(&response, "x-request-id"), None, "{}", response);
+    assert_eq!(get_body(&response), "corr-1");
+
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn panic_log_and_response_carry_the_id() {
+    let (port, handle, logger) = start_server(None).await;
+
+    let response = get(port, "/panic", "X-Request-Id: panic-1\r\n").await;
+
+    assert!(response.starts_with("HTTP/1.1 500"), "{}", response);
+    assert_eq!(
+        get_header(&response, "x-request-id"),
+        Some("panic-1"),
+        "{}",
+        response
+    );
+
+    let contexts = logger.0.lock().unwrap().clone();
+    assert_eq!(contexts.len(), 1);
+    assert_eq!(
+        contexts[0].get("request_id").map(|itm| itm.as_str()),
+        Some("panic-1")
+    );
+
+    handle.shutdown().await;
+}
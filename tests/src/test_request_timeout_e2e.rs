@@ -0,0 +1,123 @@
+//! End-to-end coverage of `MyHttpServer::set_request_timeout`: a handler that does not answer in
+//! time is cancelled and the client gets a 504, unless its route has a timeout of its own.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use my_http_server::controllers::ControllersMiddleware;
+use my_http_server::{HttpServerHandle, MyHttpServer};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::test_common::*;
+
+pub mod slow_action {
+    use std::time::Duration;
+
+    use my_http_server::macros::*;
+    use my_http_server::*;
+
+    #[http_route(
+        method: "GET",
+        route: "/slow",
+        controller: "Test",
+        summary: "Slow",
+        description: "Answers later than the server-wide request timeout",
+        result: [
+            { status_code: 200, description: "Ok" },
+        ]
+    )]
+    pub struct SlowAction;
+
+    async fn handle_request(
+        _action: &SlowAction,
+        _ctx: &mut HttpContext,
+    ) -> Result<HttpOkResult, HttpFailResult> {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        HttpOutput::as_text("done".to_string())
+            .into_ok_result(true)
+            .into()
+    }
+}
+
+pub mod slow_but_allowed_action {
+    use std::time::Duration;
+
+    use my_http_server::macros::*;
+    use my_http_server::*;
+
+    #[http_route(
+        method: "GET",
+        route: "/slow-but-allowed",
+        controller: "Test",
+        summary: "Slow but allowed",
+        description: "Answers later than the server-wide request timeout, within its own",
+        request_timeout_ms: 5000,
+        result: [
+            { status_code: 200, description: "Ok" },
+        ]
+    )]
+    pub struct SlowButAllowedAction;
+
+    async fn handle_request(
+        _action: &SlowButAllowedAction,
+        _ctx: &mut HttpContext,
+    ) -> Result<HttpOkResult, HttpFailResult> {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        HttpOutput::as_text("done".to_string())
+            .into_ok_result(true)
+            .into()
+    }
+}
+
+async fn start_server() -> (u16, HttpServerHandle) {
+    let (listener, port) = bind_local_port();
+
+    let mut controllers = ControllersMiddleware::new(None, None);
+    controllers.register_get_action(Arc::new(slow_action::SlowAction));
+    controllers.register_get_action(Arc::new(slow_but_allowed_action::SlowButAllowedAction));
+
+    let mut server = MyHttpServer::new_from_std_tcp_listener(listener);
+    server.set_request_timeout(Duration::from_millis(100));
+    server.add_middleware(Arc::new(controllers));
+
+    let app_states = app_states();
+
+    let handle = server.start_h1(app_states, Arc::new(NoLogger));
+
+    (port, handle)
+}
+
+async fn get(port: u16, path: &str) -> String {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+        path
+    );
+    stream.write_all(request.as_bytes()).await.unwrap();
+
+    let mut buf = Vec::new();
+    let _ = tokio::time::timeout(Duration::from_secs(10), stream.read_to_end(&mut buf)).await;
+    String::from_utf8_lossy(&buf).to_string()
+}
+
+#[tokio::test]
+async fn slow_handler_is_answered_with_504() {
+    let (port, handle) = start_server().await;
+
+    let response = get(port, "/slow").await;
+    assert!(response.starts_with("HTTP/1.1 504"), "{}", response);
+
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn route_timeout_overrides_the_server_one() {
+    let (port, handle) = start_server().await;
+
+    let response = get(port, "/slow-but-allowed").await;
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(response.ends_with("done"), "{}", response);
+
+    handle.shutdown().await;
+}
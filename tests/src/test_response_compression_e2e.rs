@@ -0,0 +1,307 @@
+//! End-to-end coverage of `MyHttpServer::set_response_compression`: the encoding follows the
+//! `Accept-Encoding` of the request, a streamed response is compressed too, and a route with
+//! `compress_response: false` is left alone.
+
+use std::io::Read;
+use std::sync::Arc;
+use std::time::Duration;
+
+use my_http_server::controllers::ControllersMiddleware;
+use my_http_server::{HttpServerHandle, MyHttpServer, ResponseCompression};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::test_common::*;
+
+pub mod text_action {
+    use my_http_server::macros::*;
+    use my_http_server::*;
+
+    #[http_route(
+        method: "GET",
+        route: "/text",
+        controller: "Test",
+        summary: "Text",
+        description: "Answers with text worth compressing",
+        result: [
+            { status_code: 200, description: "Ok" },
+        ]
+    )]
+    pub struct TextAction;
+
+    async fn handle_request(
+        _action: &TextAction,
+        _ctx: &mut HttpContext,
+    ) -> Result<HttpOkResult, HttpFailResult> {
+        HttpOutput::as_text(super::text())
+            .into_ok_result(true)
+            .into()
+    }
+}
+
+pub mod uncompressed_text_action {
+    use my_http_server::macros::*;
+    use my_http_server::*;
+
+    #[http_route(
+        method: "GET",
+        route: "/text-uncompressed",
+        controller: "Test",
+        summary: "Text",
+        description: "Answers with text the route does not want compressed",
+        compress_response: false,
+        result: [
+            { status_code: 200, description: "Ok" },
+        ]
+    )]
+    pub struct UncompressedTextAction;
+
+    async fn handle_request(
+        _action: &UncompressedTextAction,
+        _ctx: &mut HttpContext,
+    ) -> Result<HttpOkResult, HttpFailResult> {
+        HttpOutput::as_text(super::text())
+            .into_ok_result(true)
+            .into()
+    }
+}
+
+pub mod stream_action {
+    use my_http_server::macros::*;
+    use my_http_server::*;
+
+    #[http_route(
+        method: "GET",
+        route: "/stream",
+        controller: "Test",
+        summary: "Stream",
+        description: "Answers with a streamed body",
+        result: [
+            { status_code: 200, description: "Ok" },
+        ]
+    )]
+    pub struct StreamAction;
+
+    async fn handle_request(
+        _action: &StreamAction,
+        _ctx: &mut HttpContext,
+    ) -> Result<HttpOkResult, HttpFailResult> {
+        let (stream, mut producer) = HttpOutput::as_stream(10);
+
+        tokio::spawn(async move {
+            for chunk in super::stream_chunks() {
+                if producer.send(chunk.into_bytes()).await.is_err() {
+                    return;
+                }
+
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            }
+        });
+
+        stream.get_result()
+    }
+}
+
+fn text() -> String {
+    "hello world ".repeat(2000)
+}
+
+fn stream_chunks() -> Vec<String> {
+    (0..3)
+        .map(|i| format!("chunk-{};", i).repeat(100))
+        .collect()
+}
+
+async fn start_server() -> (u16, HttpServerHandle) {
+    let (listener, port) = bind_local_port();
+
+    let mut controllers = ControllersMiddleware::new(None, None);
+    controllers.register_get_action(Arc::new(text_action::TextAction));
+    controllers.register_get_action(Arc::new(uncompressed_text_action::UncompressedTextAction));
+    controllers.register_get_action(Arc::new(stream_action::StreamAction));
+
+    let mut server = MyHttpServer::new_from_std_tcp_listener(listener);
+    server.set_response_compression(ResponseCompression::new());
+    server.add_middleware(Arc::new(controllers));
+
+    let app_states = app_states();
+
+    let handle = server.start_h1(app_states, Arc::new(NoLogger));
+
+    (port, handle)
+}
+
+struct Response {
+    head: String,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn get_header(&self, name: &str) -> Option<&str> {
+        self.head.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            if key.trim().eq_ignore_ascii_case(name) {
+                Some(value.trim())
+            } else {
+                None
+            }
+        })
+    }
+
+    fn decode(&self) -> Vec<u8> {
+        let mut result = Vec::new();
+
+        match self.get_header("content-encoding") {
+            None => result = self.body.clone(),
+            Some("gzip") => {
+                flate2::read::GzDecoder::new(self.body.as_slice())
+                    .read_to_end(&mut result)
+                    .unwrap();
+            }
+            Some("br") => {
+                brotli_decompressor::Decompressor::new(self.body.as_slice(), 4096)
+                    .read_to_end(&mut result)
+                    .unwrap();
+            }
+            Some("zstd") => result = zstd::stream::decode_all(self.body.as_slice()).unwrap(),
+            Some(other) => panic!("unexpected Content-Encoding: {}", other),
+        }
+
+        result
+    }
+}
+
+async fn get(port: u16, path: &str, accept_encoding: Option<&str>) -> Response {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+
+    let accept_encoding = match accept_encoding {
+        Some(value) => format!("Accept-Encoding: {}\r\n", value),
+        None => String::new(),
+    };
+
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: localhost\r\n{}Connection: close\r\n\r\n",
+        path, accept_encoding
+    );
+
+    stream.write_all(request.as_bytes()).await.unwrap();
+
+    let mut buf = Vec::new();
+    let _ = tokio::time::timeout(Duration::from_secs(10), stream.read_to_end(&mut buf)).await;
+
+    let head_end = buf.windows(4).position(|itm| itm == b"\r\n\r\n").unwrap();
+    let head = String::from_utf8_lossy(&buf[..head_end]).to_string();
+    let mut response = Response {
+        head,
+        body: buf[head_end + 4..].to_vec(),
+    };
+
+    if response.get_header("transfer-encoding") == Some("chunked") {
+        response.body = dechunk(&response.body);
+    }
+
+    response
+}
+
+fn dechunk(mut data: &[u8]) -> Vec<u8> {
+    let mut result = Vec::new();
+
+    loop {
+        let line_end = data.windows(2).position(|itm| itm == b"\r\n").unwrap();
+        let size = std::str::from_utf8(&data[..line_end]).unwrap();
+        let size = usize::from_str_radix(size.split(';').next().unwrap().trim(), 16).unwrap();
+
+        if size == 0 {
+            return result;
+        }
+
+        let chunk_start = line_end + 2;
+        result.extend_from_slice(&data[chunk_start..chunk_start + size]);
+        data = &data[chunk_start + size + 2..];
+    }
+}
+
+#[tokio::test]
+async fn encoding_follows_accept_encoding() {
+    let (port, handle) = start_server().await;
+
+    for (accept_encoding, expected) in [
+        ("gzip", "gzip"),
+        ("gzip, deflate, br", "br"),
+        ("br;q=0.5, zstd", "zstd"),
+        ("br;q=0, *", "zstd"),
+    ] {
+        let response = get(port, "/text", Some(accept_encoding)).await;
+
+        assert!(
+            response.head.starts_with("HTTP/1.1 200"),
+            "{}",
+            response.head
+        );
+        assert_eq!(
+            response.get_header("content-encoding"),
+            Some(expected),
+            "{}",
+            accept_encoding
+        );
+        assert_eq!(response.get_header("vary"), Some("Accept-Encoding"));
+        assert!(response.body.len() < text().len() / 10);
+        assert_eq!(response.decode(), text().into_bytes());
+    }
+
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn nothing_accepted_goes_out_as_is() {
+    let (port, handle) = start_server().await;
+
+    for accept_encoding in [None, Some("identity"), Some("gzip;q=0, deflate")] {
+        let response = get(port, "/text", accept_encoding).await;
+
+        assert_eq!(response.get_header("content-encoding"), None);
+        assert_eq!(response.get_header("vary"), Some("Accept-Encoding"));
+        assert_eq!(response.body, text().into_bytes());
+    }
+
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn route_can_opt_out() {
+    let (port, handle) = start_server().await;
+
+    let response = get(port, "/text-uncompressed", Some("gzip, br")).await;
+
+    assert!(
+        response.head.starts_with("HTTP/1.1 200"),
+        "{}",
+        response.head
+    );
+    assert_eq!(response.get_header("content-encoding"), None);
+    assert_eq!(response.body, text().into_bytes());
+
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn streamed_response_is_compressed() {
+    let (port, handle) = start_server().await;
+
+    for accept_encoding in ["gzip", "br", "zstd"] {
+        let response = get(port, "/stream", Some(accept_encoding)).await;
+
+        assert!(
+            response.head.starts_with("HTTP/1.1 200"),
+            "{}",
+            response.head
+        );
+        assert_eq!(
+            response.get_header("content-encoding"),
+            Some(accept_encoding)
+        );
+        assert_eq!(response.decode(), stream_chunks().concat().into_bytes());
+    }
+
+    handle.shutdown().await;
+}
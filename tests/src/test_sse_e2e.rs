@@ -0,0 +1,181 @@
+//! End-to-end coverage of `HttpOutput::as_sse` over a raw TCP socket: the `text/event-stream`
+//! framing as it reaches the client, resuming after `Last-Event-ID`, keep-alive comments while
+//! idle, and the producer noticing the client is gone.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use my_http_server::*;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::test_common::*;
+
+/// `/events` sends the events after `Last-Event-ID` — up to id 3 — and ends; `/idle` sends none
+/// and stays open until the client goes away.
+struct EventsMiddleware {
+    idle_disconnected: Arc<AtomicBool>,
+}
+
+#[async_trait::async_trait]
+impl HttpServerMiddleware for EventsMiddleware {
+    async fn handle_request(
+        &self,
+        ctx: &mut HttpContext,
+    ) -> Option<Result<HttpOkResult, HttpFailResult>> {
+        match ctx.request.get_path().as_str() {
+            "/events" => {
+                let from_id: u32 = ctx
+                    .request
+                    .get_last_event_id()
+                    .and_then(|id| id.parse().ok())
+                    .unwrap_or(0);
+
+                let (output, producer) = HttpOutput::as_sse(4);
+
+                tokio::spawn(async move {
+                    for id in from_id + 1..=3 {
+                        let event = HttpSseEvent::new(format!("line {}\nend", id))
+                            .with_event("tick")
+                            .with_id(id.to_string());
+
+                        producer.send(event).await.unwrap();
+                    }
+                });
+
+                Some(output.get_result())
+            }
+            "/idle" => {
+                let (output, producer) = HttpOutput::as_sse(4);
+
+                let idle_disconnected = self.idle_disconnected.clone();
+
+                tokio::spawn(async move {
+                    producer
+                        .send(HttpSseEvent::new("").with_retry(Duration::from_secs(3)))
+                        .await
+                        .unwrap();
+
+                    producer.disconnected().await;
+                    idle_disconnected.store(true, Ordering::SeqCst);
+                });
+
+                Some(
+                    output
+                        .with_keep_alive(Duration::from_millis(100))
+                        .get_result(),
+                )
+            }
+            _ => None,
+        }
+    }
+}
+
+async fn start_server(idle_disconnected: Arc<AtomicBool>) -> (u16, HttpServerHandle) {
+    let (listener, port) = bind_local_port();
+
+    let mut server = MyHttpServer::new_from_std_tcp_listener(listener);
+    server.add_middleware(Arc::new(EventsMiddleware { idle_disconnected }));
+
+    let app_states = app_states();
+
+    let handle = server.start_h1(app_states, Arc::new(NoLogger));
+
+    (port, handle)
+}
+
+async fn send_get(port: u16, path: &str, headers: &str) -> TcpStream {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: localhost\r\nAccept: text/event-stream\r\n{}\r\n",
+        path, headers
+    );
+
+    stream.write_all(request.as_bytes()).await.unwrap();
+    stream
+}
+
+/// Reads until `expected` has arrived, or gives up after a few seconds.
+async fn read_until(stream: &mut TcpStream, expected: &str) -> String {
+    let mut buf = Vec::new();
+
+    let _ = tokio::time::timeout(Duration::from_secs(5), async {
+        let mut chunk = [0u8; 4096];
+
+        while !String::from_utf8_lossy(&buf).contains(expected) {
+            let read = stream.read(&mut chunk).await.unwrap();
+
+            if read == 0 {
+                break;
+            }
+
+            buf.extend_from_slice(&chunk[..read]);
+        }
+    })
+    .await;
+
+    String::from_utf8_lossy(&buf).to_string()
+}
+
+#[tokio::test]
+async fn events_are_framed_and_resume_after_last_event_id() {
+    let (port, handle) = start_server(Arc::new(AtomicBool::new(false))).await;
+
+    let mut stream = send_get(port, "/events", "Connection: close\r\n").await;
+    let response = read_until(&mut stream, "id: 3\n").await;
+
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    let lower = response.to_lowercase();
+    assert!(
+        lower.contains("content-type: text/event-stream"),
+        "{}",
+        response
+    );
+    assert!(lower.contains("cache-control: no-cache"), "{}", response);
+
+    for id in 1..=3 {
+        let expected = format!("event: tick\nid: {}\ndata: line {}\ndata: end\n\n", id, id);
+        assert!(response.contains(&expected), "{}", response);
+    }
+
+    let mut stream = send_get(port, "/events", "Connection: close\r\nLast-Event-ID: 2\r\n").await;
+    let response = read_until(&mut stream, "id: 3\n").await;
+
+    assert!(!response.contains("id: 1\n"), "{}", response);
+    assert!(!response.contains("id: 2\n"), "{}", response);
+    assert!(response.contains("id: 3\ndata: line 3"), "{}", response);
+
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn idle_stream_keeps_alive_and_notices_the_client_leaving() {
+    let idle_disconnected = Arc::new(AtomicBool::new(false));
+    let (port, handle) = start_server(idle_disconnected.clone()).await;
+
+    let mut stream = send_get(port, "/idle", "").await;
+
+    let response = read_until(&mut stream, "retry: 3000\ndata:\n\n").await;
+    assert!(response.contains("retry: 3000\ndata:\n\n"), "{}", response);
+
+    let response = read_until(&mut stream, ":\n\n").await;
+    assert!(response.contains(":\n\n"), "{}", response);
+    assert!(!idle_disconnected.load(Ordering::SeqCst));
+
+    drop(stream);
+
+    // The next keep-alive finds the connection closed.
+    for _ in 0..50 {
+        if idle_disconnected.load(Ordering::SeqCst) {
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    assert!(idle_disconnected.load(Ordering::SeqCst));
+
+    handle.shutdown().await;
+}
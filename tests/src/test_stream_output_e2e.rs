@@ -0,0 +1,206 @@
+//! End-to-end coverage of how a streamed response ends on the wire: with trailers, with an abort
+//! the client sees as an error — a connection closed before the final chunk on HTTP/1.1, a reset
+//! stream on HTTP/2 — and as newline-delimited JSON.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use my_http_server::*;
+use serde::Serialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::test_common::*;
+
+#[derive(Serialize)]
+struct Item {
+    id: u32,
+}
+
+/// `/export` ends with an `X-Checksum` trailer, `/export-failed` aborts halfway and `/items` is
+/// NDJSON.
+struct ExportMiddleware;
+
+#[async_trait::async_trait]
+impl HttpServerMiddleware for ExportMiddleware {
+    async fn handle_request(
+        &self,
+        ctx: &mut HttpContext,
+    ) -> Option<Result<HttpOkResult, HttpFailResult>> {
+        match ctx.request.get_path().as_str() {
+            "/export" => {
+                let (output, mut producer) = HttpOutput::as_stream(4);
+
+                tokio::spawn(async move {
+                    producer.send(b"first,".to_vec()).await.unwrap();
+                    producer.send(b"second".to_vec()).await.unwrap();
+
+                    let mut trailers = my_http_server::hyper::HeaderMap::new();
+                    trailers.insert("x-checksum", "abc".parse().unwrap());
+                    producer.send_trailers(trailers).await.unwrap();
+                });
+
+                Some(output.with_trailer("x-checksum").get_result())
+            }
+            "/export-failed" => {
+                let (output, mut producer) = HttpOutput::as_stream(4);
+
+                tokio::spawn(async move {
+                    producer.send(b"first,".to_vec()).await.unwrap();
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    producer.abort("Export failed").await;
+                });
+
+                Some(output.get_result())
+            }
+            "/items" => {
+                let (output, mut producer) = HttpOutput::as_ndjson::<Item>(4);
+
+                tokio::spawn(async move {
+                    for id in 1..=3 {
+                        producer.send(&Item { id }).await.unwrap();
+                    }
+                });
+
+                Some(output.get_result())
+            }
+            _ => None,
+        }
+    }
+}
+
+async fn start_server(h2: bool) -> (u16, HttpServerHandle) {
+    let (listener, port) = bind_local_port();
+
+    let mut server = MyHttpServer::new_from_std_tcp_listener(listener);
+    server.add_middleware(Arc::new(ExportMiddleware));
+
+    let app_states = app_states();
+
+    let handle = if h2 {
+        server.start_h2(app_states, Arc::new(NoLogger))
+    } else {
+        server.start_h1(app_states, Arc::new(NoLogger))
+    };
+
+    (port, handle)
+}
+
+async fn h1_get(port: u16, path: &str, headers: &str) -> String {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: localhost\r\n{}Connection: close\r\n\r\n",
+        path, headers
+    );
+
+    stream.write_all(request.as_bytes()).await.unwrap();
+
+    let mut buf = Vec::new();
+    let _ = tokio::time::timeout(Duration::from_secs(10), stream.read_to_end(&mut buf)).await;
+    String::from_utf8_lossy(&buf).to_string()
+}
+
+#[tokio::test]
+async fn h1_trailers_go_to_a_client_that_asks_for_them() {
+    let (port, handle) = start_server(false).await;
+
+    let response = h1_get(port, "/export", "TE: trailers\r\n").await;
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(
+        response.to_lowercase().contains("trailer: x-checksum"),
+        "{}",
+        response
+    );
+    assert!(
+        response.ends_with("0\r\nx-checksum: abc\r\n\r\n"),
+        "{}",
+        response
+    );
+
+    // Without `TE: trailers` the body still ends cleanly, just without them.
+    let response = h1_get(port, "/export", "").await;
+    assert!(response.ends_with("\r\n0\r\n\r\n"), "{}", response);
+    assert!(!response.contains("x-checksum: abc"), "{}", response);
+
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn h1_abort_closes_the_connection_before_the_final_chunk() {
+    let (port, handle) = start_server(false).await;
+
+    let response = h1_get(port, "/export-failed", "").await;
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(response.contains("first,"), "{}", response);
+    assert!(!response.ends_with("0\r\n\r\n"), "{}", response);
+
+    handle.shutdown().await;
+}
+
+async fn h2_get(port: u16, path: &str) -> h2::RecvStream {
+    let tcp = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+
+    let (mut client, connection) = h2::client::handshake(tcp).await.unwrap();
+    tokio::spawn(async move {
+        let _ = connection.await;
+    });
+
+    let request = http::Request::builder()
+        .method("GET")
+        .uri(format!("http://localhost{}", path))
+        .body(())
+        .unwrap();
+
+    let (response, _) = client.send_request(request, true).unwrap();
+    let response = response.await.unwrap();
+    assert_eq!(response.status(), 200);
+
+    response.into_body()
+}
+
+#[tokio::test]
+async fn h2_trailers_and_abort() {
+    let (port, handle) = start_server(true).await;
+
+    let mut body = h2_get(port, "/export").await;
+
+    let mut data = Vec::new();
+    while let Some(chunk) = body.data().await {
+        data.extend_from_slice(&chunk.unwrap());
+    }
+    assert_eq!(data, b"first,second");
+
+    let trailers = body.trailers().await.unwrap().unwrap();
+    assert_eq!(trailers["x-checksum"], "abc");
+
+    let mut body = h2_get(port, "/export-failed").await;
+
+    assert_eq!(body.data().await.unwrap().unwrap().as_ref(), b"first,");
+
+    let err = body.data().await.unwrap().unwrap_err();
+    assert!(err.is_reset(), "{}", err);
+
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn ndjson_is_an_item_per_line() {
+    let (port, handle) = start_server(false).await;
+
+    let response = h1_get(port, "/items", "").await;
+    assert!(
+        response
+            .to_lowercase()
+            .contains("content-type: application/x-ndjson"),
+        "{}",
+        response
+    );
+
+    for id in 1..=3 {
+        let line = format!("{{\"id\":{}}}\n", id);
+        assert!(response.contains(&line), "{}", response);
+    }
+
+    handle.shutdown().await;
+}
@@ -0,0 +1,226 @@
+//! End-to-end coverage of `MyHttpServer::set_tls`: the certificate is picked by the SNI host name
+//! the client sends, ALPN agrees on h2, the request knows it came in over https, and a reloaded
+//! certificate is served from the next handshake on.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use my_http_server::*;
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::crypto::aws_lc_rs;
+use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName};
+use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+use tokio_rustls::TlsConnector;
+
+use crate::test_common::*;
+
+/// Answers with the scheme of the request.
+struct SchemeMiddleware;
+
+#[async_trait::async_trait]
+impl HttpServerMiddleware for SchemeMiddleware {
+    async fn handle_request(
+        &self,
+        ctx: &mut HttpContext,
+    ) -> Option<Result<HttpOkResult, HttpFailResult>> {
+        let scheme = ctx.request.get_scheme().to_string();
+        Some(HttpOutput::as_text(scheme).into_ok_result(false))
+    }
+}
+
+/// A self-signed certificate, written as PEM files into a directory of the test's own.
+struct TestCertificate {
+    cert_chain_path: String,
+    private_key_path: String,
+    der: CertificateDer<'static>,
+}
+
+impl TestCertificate {
+    fn generate(test_name: &str, name: &str, server_name: &str) -> Self {
+        let dir = get_test_dir(test_name);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut result = Self {
+            cert_chain_path: dir
+                .join(format!("{}.crt", name))
+                .to_str()
+                .unwrap()
+                .to_string(),
+            private_key_path: dir
+                .join(format!("{}.key", name))
+                .to_str()
+                .unwrap()
+                .to_string(),
+            der: CertificateDer::from(Vec::new()),
+        };
+
+        result.regenerate(server_name);
+        result
+    }
+
+    /// Overwrites the files with a new certificate — a renewal in place.
+    fn regenerate(&mut self, server_name: &str) {
+        let certified_key =
+            rcgen::generate_simple_self_signed(vec![server_name.to_string()]).unwrap();
+
+        std::fs::write(&self.cert_chain_path, certified_key.cert.pem()).unwrap();
+        std::fs::write(
+            &self.private_key_path,
+            certified_key.signing_key.serialize_pem(),
+        )
+        .unwrap();
+
+        self.der = certified_key.cert.der().clone();
+    }
+}
+
+fn get_test_dir(test_name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "my-http-server-tls-e2e-{}-{}",
+        test_name,
+        std::process::id()
+    ))
+}
+
+fn start_server(tls: TlsSettings) -> (u16, HttpServerHandle) {
+    let (listener, port) = bind_local_port();
+
+    let mut server = MyHttpServer::new_from_std_tcp_listener(listener);
+    server.set_tls(tls);
+    server.add_middleware(Arc::new(SchemeMiddleware));
+
+    let handle = server.start(app_states(), Arc::new(NoLogger));
+
+    (port, handle)
+}
+
+/// Trusts `trusted` only, so the handshake succeeds only if the server picked that certificate.
+async fn connect(
+    port: u16,
+    server_name: &str,
+    trusted: &TestCertificate,
+    alpn_protocols: &[&[u8]],
+) -> std::io::Result<TlsStream<TcpStream>> {
+    let mut roots = RootCertStore::empty();
+    roots.add(trusted.der.clone()).unwrap();
+
+    let mut config = ClientConfig::builder_with_provider(Arc::new(aws_lc_rs::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    config.alpn_protocols = alpn_protocols.iter().map(|itm| itm.to_vec()).collect();
+
+    let stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    let server_name = ServerName::try_from(server_name.to_string()).unwrap();
+
+    TlsConnector::from(Arc::new(config))
+        .connect(server_name, stream)
+        .await
+}
+
+async fn h2_get(stream: TlsStream<TcpStream>, uri: &str) -> String {
+    let (mut client, connection) = h2::client::handshake(stream).await.unwrap();
+    tokio::spawn(connection);
+
+    let request = http::Request::get(uri).body(()).unwrap();
+    let (response, _) = client.send_request(request, true).unwrap();
+
+    let response = response.await.unwrap();
+    assert_eq!(response.status(), 200);
+
+    let mut body = response.into_body();
+    let mut result = Vec::new();
+
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.unwrap();
+        body.flow_control().release_capacity(chunk.len()).unwrap();
+        result.extend_from_slice(&chunk);
+    }
+
+    String::from_utf8(result).unwrap()
+}
+
+#[tokio::test]
+async fn h2_request_over_tls_is_https() {
+    let default = TestCertificate::generate("h2", "default", "default.test");
+    let api = TestCertificate::generate("h2", "api", "api.example.test");
+
+    let (port, handle) = start_server(
+        TlsSettings::new()
+            .add_certificate(&default.cert_chain_path, &default.private_key_path, &[])
+            .add_certificate(
+                &api.cert_chain_path,
+                &api.private_key_path,
+                &["api.example.test"],
+            ),
+    );
+
+    let stream = connect(port, "api.example.test", &api, &[b"h2", b"http/1.1"])
+        .await
+        .unwrap();
+
+    assert_eq!(stream.get_ref().1.alpn_protocol(), Some(&b"h2"[..]));
+    assert_eq!(h2_get(stream, "https://api.example.test/").await, "https");
+
+    handle.shutdown().await;
+    let _ = std::fs::remove_dir_all(get_test_dir("h2"));
+}
+
+#[tokio::test]
+async fn unknown_server_name_gets_the_first_certificate() {
+    let default = TestCertificate::generate("sni", "default", "default.test");
+    let api = TestCertificate::generate("sni", "api", "api.example.test");
+
+    let (port, handle) = start_server(
+        TlsSettings::new()
+            .add_certificate(&default.cert_chain_path, &default.private_key_path, &[])
+            .add_certificate(
+                &api.cert_chain_path,
+                &api.private_key_path,
+                &["api.example.test"],
+            ),
+    );
+
+    assert!(connect(port, "default.test", &api, &[b"h2"]).await.is_err());
+    assert!(connect(port, "default.test", &default, &[b"h2"])
+        .await
+        .is_ok());
+
+    handle.shutdown().await;
+    let _ = std::fs::remove_dir_all(get_test_dir("sni"));
+}
+
+/// A certificate renewed in place is served to the handshakes after `reload`; a connection opened
+/// before goes on with the one it was opened with.
+#[tokio::test]
+async fn reload_serves_the_renewed_certificate_to_new_handshakes() {
+    let mut certificate = TestCertificate::generate("reload", "server", "server.test");
+
+    let (port, handle) = start_server(TlsSettings::new().add_certificate(
+        &certificate.cert_chain_path,
+        &certificate.private_key_path,
+        &[],
+    ));
+
+    let opened_before = connect(port, "server.test", &certificate, &[b"h2"])
+        .await
+        .unwrap();
+
+    certificate.regenerate("server.test");
+    assert!(connect(port, "server.test", &certificate, &[b"h2"])
+        .await
+        .is_err());
+
+    handle.get_tls_certificates().unwrap().reload().unwrap();
+
+    assert!(connect(port, "server.test", &certificate, &[b"h2"])
+        .await
+        .is_ok());
+    assert_eq!(h2_get(opened_before, "https://server.test/").await, "https");
+
+    handle.shutdown().await;
+    let _ = std::fs::remove_dir_all(get_test_dir("reload"));
+}
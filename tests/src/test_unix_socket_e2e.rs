@@ -0,0 +1,84 @@
+//! End-to-end coverage of a Unix socket listener: `start_h2` serves HTTP/2 with prior knowledge
+//! on it, and the socket file gets the mode of `UnixSocketOptions`.
+
+use std::os::unix::fs::PermissionsExt;
+use std::sync::Arc;
+
+use my_http_server::*;
+use tokio::net::UnixStream;
+
+use crate::test_common::*;
+
+/// Answers with the path of the request.
+struct PathMiddleware;
+
+#[async_trait::async_trait]
+impl HttpServerMiddleware for PathMiddleware {
+    async fn handle_request(
+        &self,
+        ctx: &mut HttpContext,
+    ) -> Option<Result<HttpOkResult, HttpFailResult>> {
+        let path = ctx.request.get_path().as_str().to_string();
+        Some(HttpOutput::as_text(path).into_ok_result(false))
+    }
+}
+
+async fn h2_get(stream: UnixStream) -> String {
+    let (mut client, connection) = h2::client::handshake(stream).await.unwrap();
+    tokio::spawn(connection);
+
+    let request = http::Request::get("http://localhost/over-h2")
+        .body(())
+        .unwrap();
+    let (response, _) = client.send_request(request, true).unwrap();
+
+    let response = response.await.unwrap();
+    assert_eq!(response.status(), 200);
+
+    let mut body = response.into_body();
+    let mut result = Vec::new();
+
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.unwrap();
+        body.flow_control().release_capacity(chunk.len()).unwrap();
+        result.extend_from_slice(&chunk);
+    }
+
+    String::from_utf8(result).unwrap()
+}
+
+#[tokio::test]
+async fn h2_with_prior_knowledge_over_unix_socket() {
+    let path = std::env::temp_dir()
+        .join(format!("my-http-server-h2-{}.sock", std::process::id()))
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    let mut server = MyHttpServer::new_as_unix_socket(path.as_str());
+    server.set_unix_socket_options(UnixSocketOptions::new().with_permissions(0o600));
+    server.add_middleware(Arc::new(PathMiddleware));
+
+    let handle = server.start_h2(app_states(), Arc::new(NoLogger));
+
+    let mut stream = None;
+
+    for _ in 0..100 {
+        if let Ok(connected) = UnixStream::connect(path.as_str()).await {
+            stream = Some(connected);
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+
+    let mode = std::fs::metadata(path.as_str())
+        .unwrap()
+        .permissions()
+        .mode();
+    assert_eq!(mode & 0o777, 0o600);
+
+    assert_eq!(h2_get(stream.unwrap()).await, "/over-h2");
+
+    handle.shutdown().await;
+    let _ = std::fs::remove_file(path.as_str());
+}
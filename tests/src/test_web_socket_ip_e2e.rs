@@ -0,0 +1,95 @@
+//! End-to-end coverage of the client IP a web socket callback sees: the forwarding headers of the
+//! upgrade request are believed only from a trusted proxy, as they are for ordinary requests.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use my_http_server::web_sockets::*;
+use my_http_server::*;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::{channel, Receiver, Sender};
+
+use crate::test_common::*;
+
+/// Sends the IP of every web socket that connects.
+struct IpCallback(Sender<String>);
+
+#[async_trait::async_trait]
+impl MyWebSocketCallback for IpCallback {
+    async fn connected(
+        &self,
+        _my_web_socket: Arc<MyWebSocket>,
+        http_request: MyWebSocketHttpRequest,
+        _disconnect_timeout: Duration,
+    ) -> Result<(), WebSocketConnectedFail> {
+        let ip = http_request.get_ip().get_real_ip_as_string();
+        self.0.send(ip).await.unwrap();
+        Ok(())
+    }
+
+    async fn disconnected(&self, _my_web_socket: &MyWebSocket) {}
+
+    async fn on_message(&self, _my_web_socket: Arc<MyWebSocket>, _message: WsMessage) {}
+}
+
+async fn start_server(trusted_cidr: &str) -> (u16, HttpServerHandle, Receiver<String>) {
+    let (listener, port) = bind_local_port();
+
+    let (tx, rx) = channel(1);
+
+    let mut server = MyHttpServer::new_from_std_tcp_listener(listener);
+    server.set_trusted_proxies(TrustedProxies::new().add_cidr(trusted_cidr));
+    server.add_middleware(Arc::new(MyWebsocketMiddleware::new(
+        "/ws",
+        Arc::new(IpCallback(tx)),
+        Arc::new(NoLogger),
+    )));
+
+    let handle = server.start_h1(app_states(), Arc::new(NoLogger));
+
+    (port, handle, rx)
+}
+
+/// Upgrades with `X-Forwarded-For: 6.6.6.6` and returns the IP the callback saw.
+async fn connect_as_spoofed(port: u16, rx: &mut Receiver<String>) -> String {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+
+    let request = "GET /ws HTTP/1.1\r\n\
+        Host: localhost\r\n\
+        Connection: Upgrade\r\n\
+        Upgrade: websocket\r\n\
+        Sec-WebSocket-Version: 13\r\n\
+        Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+        X-Forwarded-For: 6.6.6.6\r\n\r\n";
+
+    stream.write_all(request.as_bytes()).await.unwrap();
+
+    let mut buf = [0u8; 1024];
+    let read = stream.read(&mut buf).await.unwrap();
+    let response = String::from_utf8_lossy(&buf[..read]).to_string();
+    assert!(response.starts_with("HTTP/1.1 101"), "{}", response);
+
+    tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn forwarded_header_from_an_untrusted_peer_is_ignored() {
+    let (port, handle, mut rx) = start_server("10.0.0.0/8").await;
+
+    assert_eq!(connect_as_spoofed(port, &mut rx).await, "127.0.0.1");
+
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn forwarded_header_from_a_trusted_proxy_is_believed() {
+    let (port, handle, mut rx) = start_server("127.0.0.0/8").await;
+
+    assert_eq!(connect_as_spoofed(port, &mut rx).await, "6.6.6.6");
+
+    handle.shutdown().await;
+}